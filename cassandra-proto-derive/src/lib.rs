@@ -0,0 +1,184 @@
+//! Derive macros for **cassandra-proto** traits.
+//!
+//! `#[derive(TryFromRow)]` maps columns of a `Row` into fields of a struct with
+//! the same names. A column name could be overridden with
+//! `#[cassandra(rename = "column_name")]` field attribute. `Option<T>` fields
+//! are `None` if a column value is null, other fields require non-null values.
+//!
+//! `#[derive(IntoQueryValues)]` converts a struct into `QueryValues::NamedValues`
+//! where field names (or `rename`d ones) are used as value names. With
+//! `#[cassandra(positional)]` struct attribute values are converted into
+//! `QueryValues::SimpleValues` in an order of fields declaration.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, GenericArgument, LitStr,
+          PathArguments, Type};
+
+#[proc_macro_derive(TryFromRow, attributes(cassandra))]
+pub fn derive_try_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    try_from_row(&input).unwrap_or_else(|err| err.to_compile_error())
+                        .into()
+}
+
+fn try_from_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(input)?;
+
+    let conversions = fields.iter()
+                            .map(|field| {
+                                let ident = field.ident.as_ref().unwrap();
+                                let column = column_name(field)?;
+                                let conversion = field_conversion(field, &column);
+                                Ok(quote! { #ident: #conversion })
+                            })
+                            .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::cassandra_proto::frame::TryFromRow for #name #ty_generics
+            #where_clause
+        {
+            fn try_from_row(row: ::cassandra_proto::types::rows::Row)
+                            -> ::cassandra_proto::Result<Self> {
+                Ok(#name { #(#conversions,)* })
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(IntoQueryValues, attributes(cassandra))]
+pub fn derive_into_query_values(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_query_values(&input).unwrap_or_else(|err| err.to_compile_error())
+                             .into()
+}
+
+fn into_query_values(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(input)?;
+    let idents = fields.iter()
+                       .map(|field| field.ident.as_ref().unwrap())
+                       .collect::<Vec<_>>();
+    let len = fields.len();
+
+    let body = if is_positional(input)? {
+        quote! {
+            let mut values = ::std::vec::Vec::with_capacity(#len);
+            #(values.push(::std::convert::Into::<::cassandra_proto::types::value::Value>::into(
+                self.#idents
+            ));)*
+            ::cassandra_proto::query::QueryValues::SimpleValues(values)
+        }
+    } else {
+        let columns = fields.iter()
+                            .map(|field| column_name(field))
+                            .collect::<syn::Result<Vec<_>>>()?;
+        quote! {
+            let mut values = ::std::collections::HashMap::with_capacity(#len);
+            #(values.insert(
+                ::std::string::String::from(#columns),
+                ::std::convert::Into::<::cassandra_proto::types::value::Value>::into(self.#idents)
+            );)*
+            ::cassandra_proto::query::QueryValues::NamedValues(values)
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::cassandra_proto::frame::IntoQueryValues for #name #ty_generics
+            #where_clause
+        {
+            fn into_query_values(self) -> ::cassandra_proto::query::QueryValues {
+                #body
+            }
+        }
+    })
+}
+
+/// Checks if a struct has `#[cassandra(positional)]` attribute.
+fn is_positional(input: &DeriveInput) -> syn::Result<bool> {
+    let mut positional = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("cassandra")) {
+        attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("positional") {
+                    positional = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported cassandra attribute"))
+                }
+            })?;
+    }
+
+    Ok(positional)
+}
+
+/// Generates an expression which takes a column by name and converts it into field's type.
+/// Conversion errors are returned as they are, e.g. `ColumnTypeMismatch` or `NoSuchColumn`
+/// which name the column.
+fn field_conversion(field: &Field, column: &str) -> TokenStream2 {
+    match option_inner_type(&field.ty) {
+        Some(inner) => quote! {
+            ::cassandra_proto::types::IntoRustByName::<#inner>::get_by_name(&row, #column)?
+        },
+        None => {
+            let ty = &field.ty;
+            quote! {
+                ::cassandra_proto::types::IntoRustByName::<#ty>::get_r_by_name(&row, #column)?
+            }
+        }
+    }
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<&Field>> {
+    match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => Ok(fields.named.iter().collect()),
+            _ => Err(syn::Error::new_spanned(input,
+                                             "only structs with named fields are supported")),
+        },
+        _ => Err(syn::Error::new_spanned(input, "only structs are supported")),
+    }
+}
+
+/// Returns a column name of a field taking `#[cassandra(rename = "...")]` into account.
+fn column_name(field: &Field) -> syn::Result<String> {
+    let mut column = field.ident.as_ref().unwrap().to_string();
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("cassandra")) {
+        attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    column = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported cassandra attribute"))
+                }
+            })?;
+    }
+
+    Ok(column)
+}
+
+/// Returns `T` if a given type is `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let path = match *ty {
+        Type::Path(ref type_path) if type_path.qself.is_none() => &type_path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => {
+            match args.args[0] {
+                GenericArgument::Type(ref inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,51 @@
+use cassandra_proto::frame::{IntoBytes, IntoQueryValues};
+use cassandra_proto::query::QueryValues;
+use cassandra_proto::types::value::Value;
+use cassandra_proto::IntoQueryValues;
+
+#[derive(IntoQueryValues)]
+struct NamedUser {
+    id: i32,
+    #[cassandra(rename = "user_name")]
+    name: String,
+    email: Option<String>,
+}
+
+#[derive(IntoQueryValues)]
+#[cassandra(positional)]
+struct PositionalUser {
+    id: i32,
+    name: String,
+}
+
+#[test]
+fn into_named_values() {
+    let user = NamedUser { id: 1,
+                           name: "John".into(),
+                           email: None };
+
+    match user.into_query_values() {
+        QueryValues::NamedValues(values) => {
+            assert_eq!(values.len(), 3);
+            assert_eq!(values["id"].into_cbytes(), Value::from(1).into_cbytes());
+            assert_eq!(values["user_name"].into_cbytes(), Value::from("John").into_cbytes());
+            assert_eq!(values["email"].into_cbytes(), Value::new_null().into_cbytes());
+        }
+        _ => panic!("named values are expected"),
+    }
+}
+
+#[test]
+fn into_positional_values() {
+    let user = PositionalUser { id: 1,
+                                name: "John".into() };
+
+    match user.into_query_values() {
+        QueryValues::SimpleValues(values) => {
+            let bytes = values.iter().map(|v| v.into_cbytes()).collect::<Vec<_>>();
+            assert_eq!(bytes,
+                       vec![Value::from(1).into_cbytes(), Value::from("John").into_cbytes()]);
+        }
+        _ => panic!("simple values are expected"),
+    }
+}
@@ -0,0 +1,90 @@
+use cassandra_proto::frame::frame_result::{BodyResResultRows, ColSpec, ColType, ColTypeOption,
+                                           RowsMetadata};
+use cassandra_proto::error::Error;
+use cassandra_proto::types::prelude::*;
+use cassandra_proto::types::{CBytes, CString};
+
+#[derive(Debug, PartialEq, TryFromRow)]
+struct User {
+    id: i32,
+    #[cassandra(rename = "user_name")]
+    name: String,
+    email: Option<String>,
+}
+
+fn rows(columns: Vec<(&str, ColType)>, rows_content: Vec<Vec<CBytes>>) -> Vec<Row> {
+    let col_specs = columns.into_iter()
+                           .map(|(name, id)| ColSpec { ksname: None,
+                                                       tablename: None,
+                                                       name: CString::new(name.into()),
+                                                       col_type: ColTypeOption { id,
+                                                                                 value: None } })
+                           .collect::<Vec<_>>();
+    let metadata = RowsMetadata { flags: 0,
+                                  columns_count: col_specs.len() as i32,
+                                  paging_state: None,
+                                  global_table_space: None,
+                                  col_specs,
+                                  new_metadata_id: None,
+                                  continuous_page: None };
+
+    Row::from_frame_body(BodyResResultRows { metadata,
+                                             rows_count: rows_content.len() as i32,
+                                             rows_content })
+}
+
+fn columns() -> Vec<(&'static str, ColType)> {
+    vec![("id", ColType::Int), ("user_name", ColType::Varchar), ("email", ColType::Varchar)]
+}
+
+#[test]
+fn try_from_row() {
+    let rows = rows(columns(),
+                    vec![vec![CBytes::new(vec![0, 0, 0, 1]),
+                              CBytes::new(b"John".to_vec()),
+                              CBytes::new_empty()]]);
+    let user = User::try_from_row(rows[0].clone()).unwrap();
+
+    assert_eq!(user,
+               User { id: 1,
+                      name: "John".into(),
+                      email: None });
+}
+
+#[test]
+fn try_from_row_null_required_field() {
+    let rows = rows(columns(),
+                    vec![vec![CBytes::new(vec![0, 0, 0, 1]),
+                              CBytes::new_empty(),
+                              CBytes::new_empty()]]);
+    let err = User::try_from_row(rows[0].clone()).unwrap_err();
+
+    assert!(err.to_string().contains("'user_name'"));
+}
+
+#[test]
+fn try_from_row_wrong_type() {
+    let rows = rows(vec![("id", ColType::Varchar),
+                         ("user_name", ColType::Varchar),
+                         ("email", ColType::Varchar)],
+                    vec![vec![CBytes::new(b"1".to_vec()),
+                              CBytes::new(b"John".to_vec()),
+                              CBytes::new(b"john@example.com".to_vec())]]);
+    let err = User::try_from_row(rows[0].clone()).unwrap_err();
+
+    match err {
+        Error::ColumnTypeMismatch { column, col_type: ColType::Varchar, .. } => {
+            assert_eq!(column, "id")
+        }
+        err => panic!("Unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn try_from_row_missing_column() {
+    let rows = rows(vec![("id", ColType::Int), ("email", ColType::Varchar)],
+                    vec![vec![CBytes::new(vec![0, 0, 0, 1]), CBytes::new_empty()]]);
+    let err = User::try_from_row(rows[0].clone()).unwrap_err();
+
+    assert!(matches!(err, Error::NoSuchColumn { ref name, .. } if name == "user_name"));
+}
@@ -0,0 +1,184 @@
+//! Authentication exchange which doesn't own any socket, so drivers don't repeat
+//! multi-round logic of SASL mechanisms. A caller sends produced `AUTH_RESPONSE` frames
+//! and passes received frames to `AuthFlow::on_frame`.
+use std::fmt;
+use std::io::Cursor;
+
+use super::Authenticator;
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::{Frame, FromCursor};
+use crate::types::CBytes;
+
+/// What a connection should do next during authentication.
+#[derive(Debug)]
+pub enum AuthAction {
+    /// The frame should be sent and a response to it should be passed to `AuthFlow::on_frame`.
+    Send(Frame),
+    /// A server has accepted credentials.
+    Authenticated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Created,
+    Responded,
+    Authenticated,
+    Failed,
+}
+
+/// State machine of an authentication exchange:
+/// `AUTHENTICATE` -> `AUTH_RESPONSE` -> (`AUTH_CHALLENGE` -> `AUTH_RESPONSE`)*
+/// -> `AUTH_SUCCESS`. Tokens of `AUTH_RESPONSE` frames are produced by an authenticator.
+pub struct AuthFlow {
+    authenticator: Box<dyn Authenticator>,
+    state: State,
+    // stream id of a request which waits for a response
+    stream: Option<i16>,
+}
+
+impl AuthFlow {
+    pub fn new(authenticator: Box<dyn Authenticator>) -> AuthFlow {
+        AuthFlow { authenticator,
+                   state: State::Created,
+                   stream: None }
+    }
+
+    /// Should be called with `AUTHENTICATE` response to `STARTUP` and then with every
+    /// response to produced frames. A server error or an unexpected frame fails
+    /// the authentication.
+    pub fn on_frame(&mut self, frame: &Frame) -> error::Result<AuthAction> {
+        match self.handle(frame) {
+            Ok(action) => Ok(action),
+            Err(err) => {
+                self.state = State::Failed;
+                Err(err)
+            }
+        }
+    }
+
+    /// Shows if a server has accepted credentials.
+    pub fn is_authenticated(&self) -> bool {
+        self.state == State::Authenticated
+    }
+
+    /// Shows if the authentication has failed.
+    pub fn is_failed(&self) -> bool {
+        self.state == State::Failed
+    }
+
+    fn handle(&mut self, frame: &Frame) -> error::Result<AuthAction> {
+        if self.state == State::Responded && self.stream != Some(frame.stream) {
+            return Err(error::Error::Authentication(format!("Unexpected frame on stream {} \
+                                                             during authentication",
+                                                            frame.stream)));
+        }
+
+        match (self.state, frame.get_body()?) {
+            (_, ResponseBody::Error(err)) => Err(err.into()),
+            (State::Created, ResponseBody::Authenticate(authenticate)) => {
+                let token = self.authenticator
+                                .initial_response(authenticate.data.as_str())?;
+                Ok(self.respond(token))
+            }
+            (State::Responded, ResponseBody::AuthChallenge(challenge)) => {
+                let token = self.authenticator
+                                .evaluate_challenge(challenge.data.as_slice())?;
+                Ok(self.respond(token))
+            }
+            (State::Responded, ResponseBody::AuthSuccess(_)) => {
+                // `BodyReqAuthSuccess` doesn't keep a token, so it's decoded from the body
+                let token = CBytes::from_cursor(&mut Cursor::new(frame.body.as_ref()))?;
+                self.authenticator.on_success(token.as_slice())?;
+                self.state = State::Authenticated;
+                self.stream = None;
+                Ok(AuthAction::Authenticated)
+            }
+            (state, _) => {
+                Err(error::Error::Authentication(format!("Unexpected {:?} frame in {:?} \
+                                                          authentication state",
+                                                         frame.opcode, state)))
+            }
+        }
+    }
+
+    fn respond(&mut self, token: Vec<u8>) -> AuthAction {
+        let frame = Frame::new_req_auth_response(token);
+        self.state = State::Responded;
+        self.stream = Some(frame.stream);
+        AuthAction::Send(frame)
+    }
+}
+
+impl fmt::Debug for AuthFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AuthFlow")
+         .field("state", &self.state)
+         .field("stream", &self.stream)
+         .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticator::PlainTextAuthenticator;
+    use crate::frame::{Flags, IntoBytes, Opcode, Version};
+    use crate::types::CString;
+
+    fn response(stream: i16, opcode: Opcode, body: Vec<u8>) -> Frame {
+        Frame { version: Version::Response,
+                flags: Flags::empty(),
+                opcode,
+                stream,
+                body: body.into(),
+                tracing_id: None,
+                warnings: vec![],
+                custom_payload: Default::default() }
+    }
+
+    fn send(action: AuthAction) -> Frame {
+        match action {
+            AuthAction::Send(frame) => frame,
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn dse_plain() {
+        let mut flow = AuthFlow::new(Box::new(PlainTextAuthenticator::new("user", "pass")));
+        let authenticator = "com.datastax.bdp.cassandra.auth.DseAuthenticator".to_string();
+        let authenticator = CString::new(authenticator).into_cbytes();
+        let authenticate = response(1, Opcode::Authenticate, authenticator);
+        let auth_response = send(flow.on_frame(&authenticate).unwrap());
+        assert_eq!(auth_response.opcode, Opcode::AuthResponse);
+        assert_eq!(auth_response.body, CBytes::new(b"PLAIN".to_vec()).into_cbytes());
+
+        let challenge = CBytes::new(b"PLAIN-START".to_vec()).into_cbytes();
+        let challenge = response(auth_response.stream, Opcode::AuthChallenge, challenge);
+        let auth_response = send(flow.on_frame(&challenge).unwrap());
+        assert_eq!(auth_response.body, CBytes::new(b"\0user\0pass".to_vec()).into_cbytes());
+        assert!(!flow.is_authenticated());
+
+        let success = response(auth_response.stream, Opcode::AuthSuccess, vec![0xff; 4]);
+        assert!(matches!(flow.on_frame(&success).unwrap(), AuthAction::Authenticated));
+        assert!(flow.is_authenticated());
+        assert!(flow.on_frame(&success).is_err());
+        assert!(flow.is_failed());
+    }
+
+    #[test]
+    fn unexpected_frames() {
+        let mut flow = AuthFlow::new(Box::new(PlainTextAuthenticator::new("user", "pass")));
+        let challenge = CBytes::new(vec![]).into_cbytes();
+        assert!(flow.on_frame(&response(1, Opcode::AuthChallenge, challenge)).is_err());
+        assert!(flow.is_failed());
+
+        let mut flow = AuthFlow::new(Box::new(PlainTextAuthenticator::new("user", "pass")));
+        let authenticator = CString::new("PasswordAuthenticator".into()).into_cbytes();
+        let authenticate = response(1, Opcode::Authenticate, authenticator);
+        let auth_response = send(flow.on_frame(&authenticate).unwrap());
+        let stream = auth_response.stream.wrapping_add(1);
+        assert!(flow.on_frame(&response(stream, Opcode::AuthSuccess, vec![0xff; 4])).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Kerberos authentication with SASL GSSAPI mechanism (RFC 4752). The crate doesn't link
+//! any GSSAPI library, a security context is provided by a caller, e.g. a wrapper
+//! of `libgssapi` or SSPI initiated for `<service>/<host>@<REALM>` principal.
+use super::{is_dse_authenticator, Authenticator};
+use crate::error;
+
+/// Mechanism name sent to `DseAuthenticator` in the first `AUTH_RESPONSE`.
+const DSE_GSSAPI: &[u8] = b"GSSAPI";
+/// Challenge sent by `DseAuthenticator` once a client has chosen GSSAPI mechanism.
+const DSE_GSSAPI_START: &[u8] = b"GSSAPI-START";
+/// Security layer bit of SASL GSSAPI negotiation which means no protection of messages.
+const NO_SECURITY_LAYER: u8 = 0x01;
+
+/// Client side of a GSSAPI security context.
+pub trait GssapiContext: Send {
+    /// Calls `gss_init_sec_context` with a token of a server, which is `None` for the first
+    /// call, and returns a token for the server, which may be empty.
+    fn step(&mut self, token: Option<&[u8]>) -> error::Result<Vec<u8>>;
+
+    /// Shows if the context has been established.
+    fn is_complete(&self) -> bool;
+
+    /// Verifies and decodes a message of a server (`gss_unwrap`).
+    fn unwrap(&mut self, message: &[u8]) -> error::Result<Vec<u8>>;
+
+    /// Signs a message for a server without encryption (`gss_wrap`).
+    fn wrap(&mut self, message: &[u8]) -> error::Result<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Created,
+    // `DseAuthenticator` has been asked for GSSAPI mechanism
+    MechanismSelected,
+    Establishing,
+    Negotiated,
+}
+
+/// Authenticator of SASL GSSAPI mechanism which is used by `DseAuthenticator` in Kerberos
+/// mode. Challenges carry context tokens until the context is established, then a server
+/// and a client negotiate a security layer, and only authentication without protection
+/// of messages is supported.
+#[derive(Debug)]
+pub struct GssapiAuthenticator<C> {
+    context: C,
+    authorization_id: Option<String>,
+    state: State,
+}
+
+impl<C: GssapiContext> GssapiAuthenticator<C> {
+    pub fn new(context: C) -> GssapiAuthenticator<C> {
+        GssapiAuthenticator { context,
+                              authorization_id: None,
+                              state: State::Created }
+    }
+
+    /// Sets a user which is proxied by the authenticated principal.
+    pub fn with_authorization_id(mut self, authorization_id: &str) -> Self {
+        self.authorization_id = Some(authorization_id.to_string());
+        self
+    }
+
+    fn step(&mut self, token: Option<&[u8]>) -> error::Result<Vec<u8>> {
+        self.state = State::Establishing;
+        self.context.step(token)
+    }
+
+    // RFC 4752, section 3.1: a server sends supported security layers and a maximum
+    // message size, a client answers with the chosen layer and an authorization id
+    fn negotiate(&mut self, challenge: &[u8]) -> error::Result<Vec<u8>> {
+        let offer = self.context.unwrap(challenge)?;
+        if offer.len() != 4 {
+            return Err(error::Error::Protocol("Invalid GSSAPI security layer offer".into()));
+        }
+        if offer[0] & NO_SECURITY_LAYER == 0 {
+            return Err(error::Error::Protocol("Server requires GSSAPI security layer".into()));
+        }
+
+        let authorization_id = self.authorization_id.as_deref().unwrap_or("");
+        let mut answer = Vec::with_capacity(4 + authorization_id.len());
+        answer.extend_from_slice(&[NO_SECURITY_LAYER, 0, 0, 0]);
+        answer.extend_from_slice(authorization_id.as_bytes());
+        self.state = State::Negotiated;
+        self.context.wrap(&answer)
+    }
+}
+
+impl<C: GssapiContext> Authenticator for GssapiAuthenticator<C> {
+    fn initial_response(&mut self, authenticator: &str) -> error::Result<Vec<u8>> {
+        if is_dse_authenticator(authenticator) {
+            self.state = State::MechanismSelected;
+            return Ok(DSE_GSSAPI.to_vec());
+        }
+        self.step(None)
+    }
+
+    fn evaluate_challenge(&mut self, challenge: Option<&[u8]>) -> error::Result<Vec<u8>> {
+        let challenge = challenge.unwrap_or(&[]);
+        match self.state {
+            State::MechanismSelected if challenge == DSE_GSSAPI_START => self.step(None),
+            State::Establishing if !self.context.is_complete() => self.step(Some(challenge)),
+            State::Establishing => self.negotiate(challenge),
+            state => {
+                let err = format!("Unexpected GSSAPI challenge in {:?} state", state);
+                Err(error::Error::Authentication(err))
+            }
+        }
+    }
+
+    fn on_success(&mut self, _token: Option<&[u8]>) -> error::Result<()> {
+        if self.state != State::Negotiated {
+            let err = "Authentication has succeeded before GSSAPI negotiation";
+            return Err(error::Error::Authentication(err.into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // established after two tokens of a server, wrapping prepends a byte
+    #[derive(Debug, Default)]
+    struct Context {
+        steps: u8,
+    }
+
+    impl GssapiContext for Context {
+        fn step(&mut self, _token: Option<&[u8]>) -> error::Result<Vec<u8>> {
+            self.steps += 1;
+            Ok(vec![self.steps])
+        }
+
+        fn is_complete(&self) -> bool {
+            self.steps == 2
+        }
+
+        fn unwrap(&mut self, message: &[u8]) -> error::Result<Vec<u8>> {
+            Ok(message[1..].to_vec())
+        }
+
+        fn wrap(&mut self, message: &[u8]) -> error::Result<Vec<u8>> {
+            Ok([&[0xff], message].concat())
+        }
+    }
+
+    #[test]
+    fn dse_kerberos() {
+        let mut authenticator = GssapiAuthenticator::new(Context::default())
+            .with_authorization_id("proxied");
+        let dse_authenticator = "com.datastax.bdp.cassandra.auth.DseAuthenticator";
+        assert_eq!(authenticator.initial_response(dse_authenticator).unwrap(), b"GSSAPI");
+        assert!(authenticator.on_success(None).is_err());
+
+        assert_eq!(authenticator.evaluate_challenge(Some(b"GSSAPI-START")).unwrap(), vec![1]);
+        assert_eq!(authenticator.evaluate_challenge(Some(&[1])).unwrap(), vec![2]);
+        let answer = authenticator.evaluate_challenge(Some(&[0xff, 0x07, 0, 0x10, 0]))
+                                  .unwrap();
+        assert_eq!(answer, [&[0xff, NO_SECURITY_LAYER, 0, 0, 0][..], b"proxied"].concat());
+        assert!(authenticator.evaluate_challenge(None).is_err());
+        assert!(authenticator.on_success(None).is_ok());
+    }
+
+    #[test]
+    fn security_layer_required() {
+        let mut authenticator = GssapiAuthenticator::new(Context::default());
+        assert_eq!(authenticator.initial_response("KerberosAuthenticator").unwrap(), vec![1]);
+        assert_eq!(authenticator.evaluate_challenge(Some(&[1])).unwrap(), vec![2]);
+        assert!(authenticator.evaluate_challenge(Some(&[0xff, 0x04, 0, 0x10, 0])).is_err());
+        assert!(authenticator.evaluate_challenge(Some(&[0xff, 0x01])).is_err());
+    }
+}
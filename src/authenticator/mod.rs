@@ -0,0 +1,118 @@
+//! SASL authentication of connections. A server names its authenticator class in
+//! `AUTHENTICATE` response, then a client and a server exchange tokens in `AUTH_RESPONSE`
+//! and `AUTH_CHALLENGE` frames until the server sends `AUTH_SUCCESS`. An `Authenticator`
+//! produces client tokens of a particular SASL mechanism, so multi-round mechanisms
+//! could be plugged into `Handshake`.
+use crate::error;
+use crate::frame::frame_auth_response::plain_token;
+
+mod flow;
+#[cfg(feature = "gssapi")]
+mod gssapi;
+mod secret;
+
+#[cfg(feature = "gssapi")]
+pub use self::gssapi::{GssapiAuthenticator, GssapiContext};
+pub use self::flow::{AuthAction, AuthFlow};
+pub use self::secret::{SecretString, SecretVec};
+
+/// Client side of a SASL mechanism. Returned tokens are zeroed by `AuthFlow` once they are
+/// copied into `AUTH_RESPONSE` frames.
+pub trait Authenticator: Send {
+    /// Returns a token of the first `AUTH_RESPONSE`. `authenticator` is a class name
+    /// of a server authenticator sent in `AUTHENTICATE`, e.g.
+    /// `org.apache.cassandra.auth.PasswordAuthenticator`.
+    fn initial_response(&mut self, authenticator: &str) -> error::Result<Vec<u8>>;
+
+    /// Evaluates a token of `AUTH_CHALLENGE` and returns a token of the next `AUTH_RESPONSE`.
+    fn evaluate_challenge(&mut self, challenge: Option<&[u8]>) -> error::Result<Vec<u8>>;
+
+    /// Is called with a token of `AUTH_SUCCESS`. An error fails authentication, e.g. if
+    /// a mechanism verifies a server.
+    fn on_success(&mut self, _token: Option<&[u8]>) -> error::Result<()> {
+        Ok(())
+    }
+}
+
+/// Shows if a server authenticator is `DseAuthenticator` which negotiates a mechanism
+/// before its SASL exchange.
+fn is_dse_authenticator(authenticator: &str) -> bool {
+    authenticator.ends_with("DseAuthenticator")
+}
+
+/// Challenge sent by `DseAuthenticator` once a client has chosen PLAIN mechanism.
+const DSE_PLAIN_START: &[u8] = b"PLAIN-START";
+
+/// Authenticator of SASL PLAIN mechanism which is used by `PasswordAuthenticator`.
+/// `DseAuthenticator` is asked for PLAIN mechanism first and gets credentials
+/// in response to its challenge.
+#[derive(Debug)]
+pub struct PlainTextAuthenticator {
+    username: String,
+    password: SecretString,
+}
+
+impl PlainTextAuthenticator {
+    pub fn new<P: Into<SecretString>>(username: &str, password: P) -> PlainTextAuthenticator {
+        PlainTextAuthenticator { username: username.to_string(),
+                                 password: password.into() }
+    }
+
+    fn token(&self) -> error::Result<Vec<u8>> {
+        // the copy is zeroed by `AuthFlow` along with other tokens
+        plain_token(&self.username, self.password.expose_secret())
+            .map(|token| token.expose_secret().to_vec())
+    }
+}
+
+impl Authenticator for PlainTextAuthenticator {
+    fn initial_response(&mut self, authenticator: &str) -> error::Result<Vec<u8>> {
+        if is_dse_authenticator(authenticator) {
+            return Ok(b"PLAIN".to_vec());
+        }
+        self.token()
+    }
+
+    fn evaluate_challenge(&mut self, challenge: Option<&[u8]>) -> error::Result<Vec<u8>> {
+        match challenge {
+            Some(DSE_PLAIN_START) => self.token(),
+            _ => Err(error::Error::Protocol("Unexpected challenge of PLAIN mechanism".into())),
+        }
+    }
+}
+
+/// Authenticator which sends a given token and doesn't support challenges.
+pub(crate) struct StaticTokenAuthenticator(pub(crate) SecretVec);
+
+impl Authenticator for StaticTokenAuthenticator {
+    fn initial_response(&mut self, _authenticator: &str) -> error::Result<Vec<u8>> {
+        Ok(self.0.expose_secret().to_vec())
+    }
+
+    fn evaluate_challenge(&mut self, _challenge: Option<&[u8]>) -> error::Result<Vec<u8>> {
+        let err = "Multi-round authentication challenges are not supported by a static token";
+        Err(error::Error::Authentication(err.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text() {
+        let mut authenticator = PlainTextAuthenticator::new("user", "secret");
+        let password_authenticator = "org.apache.cassandra.auth.PasswordAuthenticator";
+        assert_eq!(authenticator.initial_response(password_authenticator).unwrap(),
+                   b"\0user\0secret".to_vec());
+        assert!(authenticator.evaluate_challenge(None).is_err());
+        assert!(!format!("{:?}", authenticator).contains("secret"));
+
+        let dse_authenticator = "com.datastax.bdp.cassandra.auth.DseAuthenticator";
+        assert_eq!(authenticator.initial_response(dse_authenticator).unwrap(),
+                   b"PLAIN".to_vec());
+        assert_eq!(authenticator.evaluate_challenge(Some(b"PLAIN-START")).unwrap(),
+                   b"\0user\0secret".to_vec());
+        assert!(authenticator.on_success(None).is_ok());
+    }
+}
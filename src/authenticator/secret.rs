@@ -0,0 +1,119 @@
+//! Containers of credentials which are zeroed when dropped and are redacted in `Debug`
+//! output, so passwords and tokens are less likely to outlive authentication in memory
+//! or to leak into logs.
+//!
+//! Zeroing is best effort: only the current allocation of a container is zeroed.
+//! Copies made before a secret is wrapped, e.g. by reallocation of a growing `String`,
+//! or by the OS, e.g. swap, are out of reach. Tokens produced by an `Authenticator`
+//! are zeroed once they are copied into a frame, however the body of an `AUTH_RESPONSE`
+//! frame is `Bytes` shared with a caller, so it's not zeroed and such frames should be
+//! dropped once they are sent.
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Password or another secret string.
+pub struct SecretString(String);
+
+/// Token or another secret sequence of bytes.
+pub struct SecretVec(Vec<u8>);
+
+impl SecretString {
+    pub fn new(secret: String) -> SecretString {
+        SecretString(secret)
+    }
+
+    /// Returns the secret, it shouldn't be copied into values which are not zeroed.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl SecretVec {
+    pub fn new(secret: Vec<u8>) -> SecretVec {
+        SecretVec(secret)
+    }
+
+    /// Returns the secret, it shouldn't be copied into values which are not zeroed.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> SecretString {
+        SecretString::new(secret)
+    }
+}
+
+impl<'a> From<&'a str> for SecretString {
+    fn from(secret: &'a str) -> SecretString {
+        SecretString::new(secret.to_string())
+    }
+}
+
+impl From<Vec<u8>> for SecretVec {
+    fn from(secret: Vec<u8>) -> SecretVec {
+        SecretVec::new(secret)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl fmt::Debug for SecretVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretVec([REDACTED])")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        // zeroes keep the string valid UTF-8
+        zeroize(unsafe { self.0.as_mut_vec() });
+    }
+}
+
+impl Drop for SecretVec {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+/// Overwrites the whole allocation of a vector with zeroes. Volatile writes are not
+/// removed by the compiler even though the memory is freed right after.
+fn zeroize(bytes: &mut Vec<u8>) {
+    let ptr = bytes.as_mut_ptr();
+    for i in 0..bytes.capacity() {
+        unsafe { ptr::write_volatile(ptr.add(i), 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted() {
+        let password = SecretString::from("password");
+        assert_eq!(password.expose_secret(), "password");
+        assert!(!format!("{:?}", password).contains("password"));
+
+        let token = SecretVec::from(b"token".to_vec());
+        assert_eq!(token.expose_secret(), b"token");
+        assert!(!format!("{:?}", token).contains("116"));
+    }
+
+    #[test]
+    fn zeroed() {
+        let mut bytes = Vec::with_capacity(8);
+        bytes.extend_from_slice(b"secret");
+        zeroize(&mut bytes);
+        assert_eq!(bytes, vec![0; 6]);
+        assert_eq!(bytes.capacity(), 8);
+    }
+}
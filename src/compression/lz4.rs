@@ -0,0 +1,95 @@
+use lz4_flex::block;
+
+use crate::compression::{too_long, write_with, Compressor, CompressorError};
+use crate::frame::MAX_FRAME_LEN;
+use crate::types::{from_bytes, to_int, INT_LEN};
+
+/// LZ4 compressor. Compressed bodies are prefixed with big-endian `[int]` length
+/// of uncompressed data as Cassandra expects.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lz4Compressor;
+
+impl Lz4Compressor {
+    /// Name of the compression as it's used in `SUPPORTED` and `STARTUP` options.
+    pub const NAME: &'static str = "lz4";
+}
+
+impl Compressor for Lz4Compressor {
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        buf.extend_from_slice(&to_int(bytes.len() as i32));
+        write_with(buf, block::get_maximum_output_size(bytes.len()), |output| {
+            block::compress_into(bytes, output).map_err(Into::into)
+        })
+    }
+
+    fn decode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        self.decode_with_max_len(bytes, buf, MAX_FRAME_LEN)
+    }
+
+    fn decode_with_max_len(&self,
+                           bytes: &[u8],
+                           buf: &mut Vec<u8>,
+                           max_len: usize)
+                           -> Result<(), CompressorError> {
+        if bytes.len() < INT_LEN {
+            return Err("LZ4 body is too short".into());
+        }
+
+        let len = from_bytes(&bytes[..INT_LEN]) as usize;
+        if len > max_len {
+            return Err(too_long(len, max_len));
+        }
+        write_with(buf, len, |output| {
+            let written = block::decompress_into(&bytes[INT_LEN..], output)?;
+            if written == len {
+                Ok(written)
+            } else {
+                Err(format!("LZ4 body length is {} while {} is expected", written, len).into())
+            }
+        })
+    }
+
+    fn into_string(&self) -> Option<String> {
+        Some(Lz4Compressor::NAME.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Result<Vec<u8>, CompressorError> {
+        let mut buf = vec![];
+        Lz4Compressor.decode(bytes, &mut buf).map(|_| buf)
+    }
+
+    #[test]
+    fn round_trip() {
+        let body = b"SELECT * FROM system.local WHERE key='local'".repeat(4);
+        let mut encoded = vec![1];
+        Lz4Compressor.encode(&body, &mut encoded).unwrap();
+        assert_eq!(&encoded[1..INT_LEN + 1], &to_int(body.len() as i32)[..]);
+        assert!(encoded.len() < body.len());
+        assert_eq!(decode(&encoded[1..]).unwrap(), body);
+    }
+
+    #[test]
+    fn reference_frame() {
+        // uncompressed length 3 followed by a literal-only LZ4 block "abc"
+        let encoded = [0, 0, 0, 3, 0x30, b'a', b'b', b'c'];
+        assert_eq!(decode(&encoded).unwrap(), b"abc".to_vec());
+        assert!(decode(&[0, 0]).is_err());
+        assert!(decode(&[0, 0, 0, 9, 0x30, b'a']).is_err());
+        assert!(decode(&[0, 0, 0, 9, 0x30, b'a', b'b', b'c']).is_err());
+    }
+
+    #[test]
+    fn length_above_max() {
+        let encoded = [0, 0, 0, 3, 0x30, b'a', b'b', b'c'];
+        let mut buf = vec![];
+        assert!(Lz4Compressor.decode_with_max_len(&encoded, &mut buf, 2).is_err());
+        assert!(buf.is_empty());
+        // 0xFFFFFFFF is above the default limit and must not be allocated
+        assert!(decode(&[0xFF, 0xFF, 0xFF, 0xFF, 0x30, b'a']).is_err());
+    }
+}
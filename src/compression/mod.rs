@@ -0,0 +1,179 @@
+//!CDRS support traffic decompression as it is described in [Apache
+//!Cassandra protocol](
+//!https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L790)
+//!
+//!Before being used, client and server must agree on a compression algorithm to
+//!use, which is done in the STARTUP message. As a consequence, a STARTUP message
+//!must never be compressed.  However, once the STARTUP frame has been received
+//!by the server, messages can be compressed (including the response to the STARTUP
+//!request).
+
+use std::error::Error;
+use std::io::Read;
+
+use crate::error;
+use crate::frame::frame_startup::StartupParams;
+use crate::frame::frame_supported::BodyResSupported;
+
+#[cfg(feature = "compression-lz4")]
+mod lz4;
+#[cfg(feature = "compression-snappy")]
+mod snappy;
+#[cfg(feature = "compression-zstd")]
+mod zstd;
+
+#[cfg(feature = "compression-lz4")]
+pub use self::lz4::Lz4Compressor;
+#[cfg(feature = "compression-snappy")]
+pub use self::snappy::SnappyCompressor;
+#[cfg(feature = "compression-zstd")]
+pub use self::zstd::ZstdCompressor;
+
+/// Names of compression algorithms compiled into the crate in order of preference.
+pub const AVAILABLE_COMPRESSIONS: &[&str] = &[#[cfg(feature = "compression-lz4")]
+                                              Lz4Compressor::NAME,
+                                              #[cfg(feature = "compression-snappy")]
+                                              SnappyCompressor::NAME,
+                                              #[cfg(feature = "compression-zstd")]
+                                              ZstdCompressor::NAME];
+
+/// Error of compression or decompression.
+pub type CompressorError = Box<dyn Error + Send + Sync>;
+
+/// Compressor trait that defines functionality
+/// which should be provided by typical compressor.
+/// The trait is object safe, so compressors could be chosen at runtime.
+pub trait Compressor {
+    /// Encodes given bytes and appends encoded data to `buf`.
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError>;
+    /// Decodes given encoded data and appends decoded bytes to `buf`.
+    fn decode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError>;
+    /// The same as `decode` but fails if decoded data is longer than `max_len` bytes.
+    /// Built-in compressors fail before a buffer is allocated, the default implementation
+    /// checks the length after decoding.
+    fn decode_with_max_len(&self,
+                           bytes: &[u8],
+                           buf: &mut Vec<u8>,
+                           max_len: usize)
+                           -> Result<(), CompressorError> {
+        let start = buf.len();
+        self.decode(bytes, buf)?;
+        let len = buf.len() - start;
+        if len > max_len {
+            buf.truncate(start);
+            return Err(too_long(len, max_len));
+        }
+        Ok(())
+    }
+    /// Returns a reader which decodes data read from `reader` incrementally, so large
+    /// bodies are decoded without reading the whole encoded data into memory.
+    /// Returns `None` if a compressor cannot decode data incrementally. Of built-in
+    /// compressors only zstd does: LZ4 and Snappy bodies are single blocks which are
+    /// decoded once the whole block is read.
+    fn decoding_reader<'a>(&self,
+                           _reader: Box<dyn Read + 'a>)
+                           -> Result<Option<Box<dyn Read + 'a>>, CompressorError> {
+        Ok(None)
+    }
+    /// Returns a string which is a name of a compressor. This name should be
+    /// exactly the same as one which server returns in a response to
+    /// `Options` request.
+    fn into_string(&self) -> Option<String>;
+}
+
+/// Reverses bytes, so a body is unreadable unless it's decoded. Used by tests
+/// of frame encoding and parsing.
+#[cfg(test)]
+pub(crate) struct ReverseCompressor;
+
+#[cfg(test)]
+impl Compressor for ReverseCompressor {
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        buf.extend(bytes.iter().rev());
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        self.encode(bytes, buf)
+    }
+
+    fn into_string(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Error of decoded data which is longer than `max_len`.
+fn too_long(len: usize, max_len: usize) -> CompressorError {
+    format!("Decoded body length {} exceeds maximal length {}", len, max_len).into()
+}
+
+/// Reserves at most `max_len` bytes at the end of a buffer, lets `write` fill them
+/// and truncates the ones which have not been written.
+#[cfg(any(feature = "compression-lz4", feature = "compression-snappy"))]
+fn write_with<F>(buf: &mut Vec<u8>, max_len: usize, write: F) -> Result<(), CompressorError>
+    where F: FnOnce(&mut [u8]) -> Result<usize, CompressorError>
+{
+    let start = buf.len();
+    buf.resize(start + max_len, 0);
+    match write(&mut buf[start..]) {
+        Ok(written) => {
+            buf.truncate(start + written);
+            Ok(())
+        }
+        Err(err) => {
+            buf.truncate(start);
+            Err(err)
+        }
+    }
+}
+
+/// Picks the first of `available` compression algorithms (in order of preference)
+/// which is supported by a server. Names are compared case insensitively.
+pub fn select_compression<'a>(supported: &BodyResSupported,
+                              available: &[&'a str])
+                              -> error::Result<&'a str> {
+    let server_compressions = supported.compression_algorithms();
+    available.iter()
+             .find(|name| {
+                       server_compressions.iter()
+                                          .any(|supported| supported.eq_ignore_ascii_case(name))
+                   })
+             .cloned()
+             .ok_or_else(|| {
+                             error::Error::Compression(format!("None of compressions {:?} is \
+                                                                supported by server, supported \
+                                                                ones are {:?}",
+                                                               available,
+                                                               server_compressions))
+                         })
+}
+
+/// Returns `STARTUP` options which enable the best compression supported both by
+/// the crate and by a server.
+pub fn negotiate_compression(supported: &BodyResSupported) -> error::Result<StartupParams> {
+    select_compression(supported, AVAILABLE_COMPRESSIONS)
+        .map(|name| StartupParams::new().with_compression(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn supported(compressions: &[&str]) -> BodyResSupported {
+        let mut data = HashMap::new();
+        data.insert("COMPRESSION".to_string(),
+                    compressions.iter().map(|name| name.to_string()).collect());
+        BodyResSupported { data }
+    }
+
+    #[test]
+    fn select() {
+        let supported = supported(&["snappy", "LZ4"]);
+        assert_eq!(select_compression(&supported, &["lz4", "snappy"]).unwrap(), "lz4");
+        assert_eq!(select_compression(&supported, &["zstd", "snappy"]).unwrap(), "snappy");
+        assert!(select_compression(&supported, &["zstd"]).is_err());
+        assert!(select_compression(&supported, &[]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use snap::{decompress_len, max_compress_len, Decoder, Encoder};
+
+use crate::compression::{too_long, write_with, Compressor, CompressorError};
+use crate::frame::MAX_FRAME_LEN;
+
+/// Snappy compressor which uses raw (not framed) Snappy format as Cassandra expects.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnappyCompressor;
+
+impl SnappyCompressor {
+    /// Name of the compression as it's used in `SUPPORTED` and `STARTUP` options.
+    pub const NAME: &'static str = "snappy";
+}
+
+impl Compressor for SnappyCompressor {
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        write_with(buf, max_compress_len(bytes.len()), |output| {
+            Encoder::new().compress(bytes, output).map_err(Into::into)
+        })
+    }
+
+    fn decode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        self.decode_with_max_len(bytes, buf, MAX_FRAME_LEN)
+    }
+
+    fn decode_with_max_len(&self,
+                           bytes: &[u8],
+                           buf: &mut Vec<u8>,
+                           max_len: usize)
+                           -> Result<(), CompressorError> {
+        let len = decompress_len(bytes)?;
+        if len > max_len {
+            return Err(too_long(len, max_len));
+        }
+        write_with(buf, len, |output| {
+            Decoder::new().decompress(bytes, output).map_err(Into::into)
+        })
+    }
+
+    fn into_string(&self) -> Option<String> {
+        Some(SnappyCompressor::NAME.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(bytes: &[u8]) -> Vec<u8> {
+        let mut buf = vec![];
+        SnappyCompressor.encode(bytes, &mut buf).unwrap();
+        buf
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Vec<u8>, CompressorError> {
+        let mut buf = vec![];
+        SnappyCompressor.decode(bytes, &mut buf).map(|_| buf)
+    }
+
+    #[test]
+    fn round_trip() {
+        let body = b"SELECT * FROM system.local WHERE key='local'".repeat(4);
+        let encoded = encode(&body);
+        assert!(encoded.len() < body.len());
+        assert_eq!(decode(&encoded).unwrap(), body);
+    }
+
+    #[test]
+    fn reference_frame() {
+        // varint uncompressed length 3 followed by a literal "abc"
+        let encoded = vec![0x03, 0x08, b'a', b'b', b'c'];
+        assert_eq!(encode(b"abc"), encoded);
+        assert_eq!(decode(&encoded).unwrap(), b"abc".to_vec());
+        assert!(decode(&[0x05, 0x08, b'a']).is_err());
+    }
+
+    #[test]
+    fn length_above_max() {
+        let encoded = encode(b"abc");
+        let mut buf = vec![];
+        assert!(SnappyCompressor.decode_with_max_len(&encoded, &mut buf, 2).is_err());
+        assert!(buf.is_empty());
+    }
+}
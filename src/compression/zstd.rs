@@ -0,0 +1,125 @@
+use std::io::Read;
+
+use crate::compression::{too_long, Compressor, CompressorError};
+use crate::frame::MAX_FRAME_LEN;
+
+/// Default compression level of Zstd library.
+const DEFAULT_LEVEL: i32 = 0;
+
+/// Zstd compressor. Compressed bodies are regular Zstd frames. Zstd is not a part of
+/// Apache Cassandra protocol, it's supported by ScyllaDB and DSE.
+#[derive(Debug, Clone, Copy)]
+pub struct ZstdCompressor {
+    level: i32,
+}
+
+impl ZstdCompressor {
+    /// Name of the compression as it's used in `SUPPORTED` and `STARTUP` options.
+    pub const NAME: &'static str = "zstd";
+
+    /// Creates new compressor with a given compression level.
+    pub fn with_level(level: i32) -> ZstdCompressor {
+        ZstdCompressor { level }
+    }
+}
+
+impl Default for ZstdCompressor {
+    fn default() -> ZstdCompressor {
+        ZstdCompressor::with_level(DEFAULT_LEVEL)
+    }
+}
+
+impl Compressor for ZstdCompressor {
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        zstd::stream::copy_encode(bytes, buf, self.level).map_err(Into::into)
+    }
+
+    fn decode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+        self.decode_with_max_len(bytes, buf, MAX_FRAME_LEN)
+    }
+
+    fn decode_with_max_len(&self,
+                           bytes: &[u8],
+                           buf: &mut Vec<u8>,
+                           max_len: usize)
+                           -> Result<(), CompressorError> {
+        // one byte above the limit is read to detect too long data
+        let start = buf.len();
+        let decoder = zstd::stream::read::Decoder::new(bytes)?;
+        match decoder.take(max_len as u64 + 1).read_to_end(buf) {
+            Ok(len) if len <= max_len => Ok(()),
+            Ok(len) => {
+                buf.truncate(start);
+                Err(too_long(len, max_len))
+            }
+            Err(err) => {
+                buf.truncate(start);
+                Err(err.into())
+            }
+        }
+    }
+
+    fn decoding_reader<'a>(&self,
+                           reader: Box<dyn Read + 'a>)
+                           -> Result<Option<Box<dyn Read + 'a>>, CompressorError> {
+        let decoder = zstd::stream::read::Decoder::new(reader)?;
+        Ok(Some(Box::new(decoder)))
+    }
+
+    fn into_string(&self) -> Option<String> {
+        Some(ZstdCompressor::NAME.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::compression::negotiate_compression;
+    use crate::frame::frame_supported::BodyResSupported;
+
+    #[test]
+    fn round_trip() {
+        let compressor = ZstdCompressor::default();
+        let body = b"SELECT * FROM system.local WHERE key='local'".repeat(4);
+        let mut encoded = vec![];
+        compressor.encode(&body, &mut encoded).unwrap();
+        assert_eq!(&encoded[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+        assert!(encoded.len() < body.len());
+
+        let mut decoded = vec![];
+        compressor.decode(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, body);
+        assert!(compressor.decode(&[1, 2, 3], &mut decoded).is_err());
+
+        let mut reader = compressor.decoding_reader(Box::new(encoded.as_slice()))
+                                   .unwrap()
+                                   .unwrap();
+        let mut decoded = vec![];
+        reader.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn length_above_max() {
+        let compressor = ZstdCompressor::default();
+        let body = vec![0; 1024];
+        let mut encoded = vec![];
+        compressor.encode(&body, &mut encoded).unwrap();
+
+        let mut decoded = vec![];
+        assert!(compressor.decode_with_max_len(&encoded, &mut decoded, 1023).is_err());
+        assert!(decoded.is_empty());
+        compressor.decode_with_max_len(&encoded, &mut decoded, 1024).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn negotiation() {
+        let mut data = HashMap::new();
+        data.insert("COMPRESSION".to_string(), vec!["zstd".to_string()]);
+        let params = negotiate_compression(&BodyResSupported { data }).unwrap();
+        assert_eq!(params.compression.as_deref(), Some(ZstdCompressor::NAME));
+    }
+}
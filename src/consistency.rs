@@ -1,8 +1,12 @@
 #![warn(missing_docs)]
 //! The module contains Rust representation of Cassandra consistency levels.
-use std::convert::From;
+use std::convert::{From, TryFrom};
 use std::default::Default;
+use std::fmt;
 use std::io;
+use std::str::FromStr;
+
+use bytes::BufMut;
 
 use crate::error;
 use crate::frame::{FromBytes, FromCursor, IntoBytes};
@@ -71,32 +75,67 @@ pub enum Consistency {
     Unknown,
 }
 
+/// Consistency levels defined by protocol along with their names used by CQL,
+/// e.g. in `CONSISTENCY` command of cqlsh.
+const NAMES: [(Consistency, &str); 11] = [(Consistency::Any, "ANY"),
+                                          (Consistency::One, "ONE"),
+                                          (Consistency::Two, "TWO"),
+                                          (Consistency::Three, "THREE"),
+                                          (Consistency::Quorum, "QUORUM"),
+                                          (Consistency::All, "ALL"),
+                                          (Consistency::LocalQuorum, "LOCAL_QUORUM"),
+                                          (Consistency::EachQuorum, "EACH_QUORUM"),
+                                          (Consistency::Serial, "SERIAL"),
+                                          (Consistency::LocalSerial, "LOCAL_SERIAL"),
+                                          (Consistency::LocalOne, "LOCAL_ONE")];
+
 impl Default for Consistency {
     fn default() -> Consistency {
         Consistency::One
     }
 }
 
-impl IntoBytes for Consistency {
-    fn into_cbytes(&self) -> Vec<u8> {
+impl Consistency {
+    /// Shows if a consistency level could be used as a serial consistency
+    /// of lightweight transactions.
+    pub fn is_serial(&self) -> bool {
+        *self == Consistency::Serial || *self == Consistency::LocalSerial
+    }
+
+    /// Code of a consistency level which is sent as `[short]`.
+    fn as_short(&self) -> i16 {
         match *self {
-            Consistency::Any => to_short(0x0000),
-            Consistency::One => to_short(0x0001),
-            Consistency::Two => to_short(0x0002),
-            Consistency::Three => to_short(0x0003),
-            Consistency::Quorum => to_short(0x0004),
-            Consistency::All => to_short(0x0005),
-            Consistency::LocalQuorum => to_short(0x0006),
-            Consistency::EachQuorum => to_short(0x0007),
-            Consistency::Serial => to_short(0x0008),
-            Consistency::LocalSerial => to_short(0x0009),
-            Consistency::LocalOne => to_short(0x000A),
-            Consistency::Unknown => to_short(0x0063),
+            Consistency::Any => 0x0000,
+            Consistency::One => 0x0001,
+            Consistency::Two => 0x0002,
+            Consistency::Three => 0x0003,
+            Consistency::Quorum => 0x0004,
+            Consistency::All => 0x0005,
+            Consistency::LocalQuorum => 0x0006,
+            Consistency::EachQuorum => 0x0007,
+            Consistency::Serial => 0x0008,
+            Consistency::LocalSerial => 0x0009,
+            Consistency::LocalOne => 0x000A,
+            Consistency::Unknown => 0x0063,
             // giving Unknown a value of 99
         }
     }
 }
 
+impl IntoBytes for Consistency {
+    fn into_cbytes(&self) -> Vec<u8> {
+        to_short(self.as_short())
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_i16(self.as_short());
+    }
+
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN
+    }
+}
+
 impl From<i32> for Consistency {
     fn from(bytes: i32) -> Consistency {
         match bytes {
@@ -116,6 +155,60 @@ impl From<i32> for Consistency {
     }
 }
 
+impl TryFrom<i16> for Consistency {
+    type Error = error::Error;
+
+    /// Unlike `From<i32>` values which are not defined by protocol are rejected
+    /// instead of being converted into `Unknown`.
+    fn try_from(value: i16) -> error::Result<Consistency> {
+        match Consistency::from(i32::from(value)) {
+            Consistency::Unknown => {
+                Err(error::Error::Protocol(format!("Unknown consistency {}", value)))
+            }
+            consistency => Ok(consistency),
+        }
+    }
+}
+
+impl fmt::Display for Consistency {
+    /// Writes a name of a consistency level, e.g. `LOCAL_QUORUM`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = NAMES.iter()
+                        .find(|(consistency, _)| consistency == self)
+                        .map_or("UNKNOWN", |(_, name)| name);
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Consistency {
+    type Err = error::Error;
+
+    /// Parses a name of a consistency level ignoring its case, e.g. `LOCAL_QUORUM`
+    /// or `local_quorum`.
+    fn from_str(s: &str) -> error::Result<Consistency> {
+        NAMES.iter()
+             .find(|(_, name)| name.eq_ignore_ascii_case(s))
+             .map(|(consistency, _)| *consistency)
+             .ok_or_else(|| error::Error::Parse(format!("Unknown consistency {:?}", s)))
+    }
+}
+
+/// Consistency is serialized as its name, so it could be set in configuration files.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Consistency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Consistency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Consistency, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl FromBytes for Consistency {
     fn from_bytes(bytes: &[u8]) -> error::Result<Consistency> {
         try_from_bytes(bytes).map_err(Into::into).map(|b| match b {
@@ -180,6 +273,38 @@ mod tests {
         assert_eq!(Consistency::from(11), Consistency::Unknown);
     }
 
+    #[test]
+    fn test_consistency_try_from() {
+        assert_eq!(Consistency::try_from(0i16).unwrap(), Consistency::Any);
+        assert_eq!(Consistency::try_from(6i16).unwrap(), Consistency::LocalQuorum);
+        assert_eq!(Consistency::try_from(10i16).unwrap(), Consistency::LocalOne);
+        assert!(Consistency::try_from(11i16).is_err());
+        assert!(Consistency::try_from(-1i16).is_err());
+        assert!(Consistency::try_from(0x63_i16).is_err());
+    }
+
+    #[test]
+    fn test_consistency_names() {
+        for (consistency, name) in NAMES.iter() {
+            assert_eq!(consistency.to_string(), *name);
+            assert_eq!(name.parse::<Consistency>().unwrap(), *consistency);
+        }
+        assert_eq!("local_quorum".parse::<Consistency>().unwrap(), Consistency::LocalQuorum);
+        assert_eq!(Consistency::Unknown.to_string(), "UNKNOWN");
+        assert!(matches!("UNKNOWN".parse::<Consistency>(), Err(error::Error::Parse(_))));
+        assert!("QUORUM ".parse::<Consistency>().is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "json"))]
+    fn test_consistency_serde() {
+        let json = serde_json::to_string(&Consistency::EachQuorum).unwrap();
+        assert_eq!(json, "\"EACH_QUORUM\"");
+        let consistency: Consistency = serde_json::from_str("\"local_one\"").unwrap();
+        assert_eq!(consistency, Consistency::LocalOne);
+        assert!(serde_json::from_str::<Consistency>("\"none\"").is_err());
+    }
+
     #[test]
     fn test_consistency_from_bytes() {
         assert_eq!(Consistency::from_bytes(&[0, 0]).unwrap(), Consistency::Any);
@@ -2,11 +2,17 @@ use std::error;
 use std::fmt;
 use std::fmt::Display;
 use std::io;
+use std::io::Cursor;
 use std::result;
 use std::string::FromUtf8Error;
 use uuid::Error as UUIDError;
 
+#[cfg(feature = "arrow")]
+use arrow_schema::ArrowError;
+
 use crate::frame::frame_error::CDRSError;
+use crate::frame::frame_result::ColType;
+use crate::query::QueryValuesError;
 
 pub type Result<T> = result::Result<T, Error>;
 
@@ -15,24 +21,149 @@ pub type Result<T> = result::Result<T, Error>;
 /// is a type that represent internal error because due to implementation IO errors only
 /// can be raised by CDRS driver. `Server` error is an error which are ones returned by
 /// a Server via result error frames.
+///
+/// New variants may be added in the future, so matches on `Error` should have
+/// a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Internal IO error.
     Io(io::Error),
     /// Internal error that may be raised during `uuid::Uuid::from_bytes`
     UUIDParse(UUIDError),
-    /// General error
-    General(String),
     /// Internal error that may be raised during `String::from_utf8`
     FromUtf8(FromUtf8Error),
     /// Internal Compression/Decompression error
     Compression(String),
     /// Server error.
     Server(CDRSError),
+    /// Received bytes violate the protocol, e.g. an unknown result kind, column type
+    /// or error code.
+    Protocol(String),
+    /// A value cannot be converted into a requested type, e.g. a `Varchar` column
+    /// is read as `i32`.
+    TypeMismatch(String),
+    /// A column of a row cannot be read as a requested Rust type, e.g. `Varchar` column
+    /// is read as `i64`. `col_type` is a type declared by a server.
+    ColumnTypeMismatch { column: String, col_type: ColType, rust_type: &'static str },
+    /// A row has no column with a given name. `available` lists names of its columns.
+    NoSuchColumn { name: String, available: Vec<String> },
+    /// Length of a received frame body exceeds the maximal one.
+    FrameTooLarge { length: usize, max: usize },
+    /// Length prefix or a number of items read from a frame is negative or exceeds
+    /// the number of remaining bytes.
+    InvalidLength { length: i64, remaining: usize },
+    /// Opcode of a received frame is not defined by protocol.
+    UnknownOpcode(u8),
+    /// Version byte of a received frame, including the direction bit, doesn't match
+    /// the protocol version in use.
+    UnsupportedVersion(u8),
+    /// Length or a number of items of a serialized value doesn't fit its length prefix,
+    /// e.g. a `[string]` is longer than `u16::MAX` bytes.
+    LengthOverflow { length: usize, max: usize },
+    /// A request cannot be serialized because of a contradicting combination of its
+    /// parameters, e.g. a batch timestamp flag is set without a timestamp.
+    InvalidRequest(String),
+    /// Query values cannot be sent, e.g. named and positional values are mixed.
+    InvalidValues(QueryValuesError),
+    /// A column or a UDT field is requested as a value, while it is NULL or doesn't exist.
+    EmptyColumn(String),
+    /// All stream ids of a pool are in flight.
+    StreamIdsExhausted { capacity: usize },
+    /// Connection handshake cannot proceed, e.g. a frame is received on an unexpected
+    /// stream or in an unexpected state.
+    Handshake(String),
+    /// Authentication cannot proceed on client side, e.g. a challenge is received
+    /// in an unexpected state of an authenticator.
+    Authentication(String),
+    /// A string cannot be parsed into a value, e.g. an unknown consistency name
+    /// or a malformed token.
+    Parse(String),
+    /// A row cannot be deserialized with serde, e.g. a struct field has no column.
+    #[cfg(feature = "serde")]
+    Deserialize(String),
+    /// Rows cannot be converted into an Arrow record batch.
+    #[cfg(feature = "arrow")]
+    Arrow(ArrowError),
+    /// Decoding of a received body failed. `context` lists entities which were being
+    /// decoded from the outermost to the innermost one, `offset` is a position
+    /// of the innermost entity in decoded bytes and `found` holds its first bytes.
+    Decode {
+        context: Vec<String>,
+        offset: u64,
+        found: Vec<u8>,
+        source: Box<Error>,
+    },
+}
+
+/// Maximal number of bytes kept in `Error::Decode::found`.
+const FOUND_BYTES_LIMIT: usize = 16;
+
+impl Error {
+    /// Adds an entity which was being decoded to an error. The first added entity
+    /// is the innermost one, so its `offset` and bytes of `body` starting at the offset
+    /// are kept, while entities added later only extend the context.
+    pub fn in_context<E: Display>(self, entity: E, offset: u64, body: &[u8]) -> Error {
+        match self {
+            Error::Decode { mut context,
+                            offset,
+                            found,
+                            source, } => {
+                context.insert(0, entity.to_string());
+                Error::Decode { context,
+                                offset,
+                                found,
+                                source }
+            }
+            err => {
+                let start = (offset as usize).min(body.len());
+                let end = (start + FOUND_BYTES_LIMIT).min(body.len());
+                Error::Decode { context: vec![entity.to_string()],
+                                offset,
+                                found: body[start..end].to_vec(),
+                                source: Box::new(err) }
+            }
+        }
+    }
+
+    /// Adds a column, its type and a requested Rust type to `Error::TypeMismatch`,
+    /// other errors are returned as is.
+    pub fn in_column(self, column: &str, col_type: &ColType, rust_type: &'static str) -> Error {
+        match self {
+            Error::TypeMismatch(_) => Error::ColumnTypeMismatch { column: column.to_string(),
+                                                                  col_type: col_type.clone(),
+                                                                  rust_type },
+            err => err,
+        }
+    }
+
+    /// Returns the innermost error, e.g. an error which caused `Error::Decode`.
+    pub fn root(&self) -> &Error {
+        match *self {
+            Error::Decode { ref source, .. } => source.root(),
+            ref err => err,
+        }
+    }
+}
+
+/// Decodes a value of `entity` with `decode`. If decoding fails the entity and a position
+/// of the cursor before decoding are added to the error, see `Error::in_context`.
+pub fn decode_context<T, E, F>(cursor: &mut Cursor<&[u8]>, entity: E, decode: F) -> Result<T>
+    where E: Display,
+          F: FnOnce(&mut Cursor<&[u8]>) -> Result<T>
+{
+    let offset = cursor.position();
+    decode(cursor).map_err(|err| err.in_context(entity, offset, cursor.get_ref()))
 }
 
 pub fn column_is_empty_err<T: Display>(column_name: T) -> Error {
-    Error::General(format!("Column or UDT property '{}' is empty", column_name))
+    Error::EmptyColumn(column_name.to_string())
+}
+
+/// Error of a NULL item of a collection converted into a Rust type which cannot hold it,
+/// e.g. a list item read as `Vec<i32>` rather than `Vec<Option<i32>>`.
+pub fn null_item_err<T: Display>(item: T) -> Error {
+    Error::TypeMismatch(format!("{} is null, it could be read as Option", item))
 }
 
 impl fmt::Display for Error {
@@ -43,21 +174,72 @@ impl fmt::Display for Error {
             Error::Server(ref err) => write!(f, "Server error: {:?}", err.message),
             Error::FromUtf8(ref err) => write!(f, "FromUtf8Error error: {:?}", err),
             Error::UUIDParse(ref err) => write!(f, "UUIDParse error: {:?}", err),
-            Error::General(ref err) => write!(f, "GeneralParsing error: {:?}", err),
+            Error::Protocol(ref err) => write!(f, "Protocol violation: {}", err),
+            Error::TypeMismatch(ref err) => write!(f, "Type mismatch: {}", err),
+            Error::ColumnTypeMismatch { ref column,
+                                        ref col_type,
+                                        rust_type, } => {
+                write!(f,
+                       "Type mismatch: cannot read column '{}' of {:?} type as {}",
+                       column, col_type, rust_type)
+            }
+            Error::NoSuchColumn { ref name, ref available } => {
+                write!(f, "No such column '{}', available: [{}]", name, available.join(", "))
+            }
+            Error::FrameTooLarge { length, max } => {
+                write!(f, "Frame body of {} bytes exceeds maximal length {}", length, max)
+            }
+            Error::InvalidLength { length, remaining } => {
+                write!(f, "Invalid length {}, {} bytes remaining", length, remaining)
+            }
+            Error::UnknownOpcode(opcode) => write!(f, "Unknown opcode {:#04x}", opcode),
+            Error::UnsupportedVersion(version) => {
+                let direction = if version & 0x80 == 0 { "request" } else { "response" };
+                write!(f, "Unsupported protocol version {} of {} frame", version & 0x7f, direction)
+            }
+            Error::LengthOverflow { length, max } => {
+                write!(f, "Length {} exceeds maximal length {}", length, max)
+            }
+            Error::InvalidRequest(ref err) => write!(f, "Invalid request: {}", err),
+            Error::InvalidValues(ref err) => write!(f, "Invalid query values: {}", err),
+            Error::EmptyColumn(ref name) => {
+                write!(f, "Column or UDT property '{}' is empty", name)
+            }
+            Error::StreamIdsExhausted { capacity } => {
+                write!(f, "All {} stream ids are in flight", capacity)
+            }
+            Error::Handshake(ref err) => write!(f, "Handshake error: {}", err),
+            Error::Authentication(ref err) => write!(f, "Authentication error: {}", err),
+            Error::Parse(ref err) => write!(f, "Parse error: {}", err),
+            #[cfg(feature = "serde")]
+            Error::Deserialize(ref err) => write!(f, "Deserialization error: {}", err),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(ref err) => write!(f, "Arrow error: {}", err),
+            Error::Decode { ref context,
+                            offset,
+                            ref found,
+                            ref source, } => {
+                write!(f,
+                       "Cannot decode {} at byte {} (found {:02x?}): {}",
+                       context.join(" → "),
+                       offset,
+                       found,
+                       source)
+            }
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Error::Io(ref err) => err.description(),
-            Error::Compression(ref err) => err.as_str(),
-            Error::Server(ref err) => err.message.as_str(),
-            Error::FromUtf8(ref err) => err.description(),
-            // FIXME: err.description not found in current scope, std::error::Error not satisfied
-            Error::UUIDParse(_) => "UUID Parse Error",
-            Error::General(ref err) => err.as_str(),
+            Error::Io(ref err) => Some(err),
+            Error::FromUtf8(ref err) => Some(err),
+            Error::UUIDParse(ref err) => Some(err),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(ref err) => Some(err),
+            Error::Decode { ref source, .. } => Some(source.as_ref()),
+            _ => None,
         }
     }
 }
@@ -86,39 +268,58 @@ impl From<UUIDError> for Error {
     }
 }
 
-impl From<String> for Error {
-    fn from(err: String) -> Error {
-        Error::General(err)
-    }
-}
-
-impl<'a> From<&'a str> for Error {
-    fn from(err: &str) -> Error {
-        Error::General(err.to_string())
-    }
-}
-
-#[cfg(tests)]
+#[cfg(test)]
 mod test {
     use super::*;
+    use std::error::Error as StdError;
 
     #[test]
     fn column_is_empty_err() {
-        let err = column_is_empty_err("some_column");
-
-        if let Error::General(err_description) = err {
-            assert_eq!(
-                err_description,
-                "Column or UDT property 'some_column' is empty"
-            );
-            return;
-        }
+        let err = super::column_is_empty_err("some_column");
 
-        panic!("Unexpected result of column_is_empty_err, {:?}", err);
+        assert!(matches!(err, Error::EmptyColumn(ref name) if name == "some_column"));
+        assert_eq!(err.to_string(), "Column or UDT property 'some_column' is empty");
     }
 
     #[test]
     fn error_display() {
-        // TODO:
+        assert_eq!(Error::Protocol("Unexpected result kind".into()).to_string(),
+                   "Protocol violation: Unexpected result kind");
+        assert_eq!(Error::TypeMismatch("Cannot convert Int into String".into()).to_string(),
+                   "Type mismatch: Cannot convert Int into String");
+        assert_eq!(Error::UnknownOpcode(0x42).to_string(), "Unknown opcode 0x42");
+        let err = Error::TypeMismatch("Cannot convert Varchar into i64".into());
+        assert_eq!(err.in_column("name", &ColType::Varchar, "i64").to_string(),
+                   "Type mismatch: cannot read column 'name' of Varchar type as i64");
+        let err = Error::NoSuchColumn { name: "Name".into(),
+                                        available: vec!["id".into(), "name".into()] };
+        assert_eq!(err.to_string(), "No such column 'Name', available: [id, name]");
+    }
+
+    #[test]
+    fn error_source() {
+        let err: Error = io::Error::new(io::ErrorKind::UnexpectedEof, "eof").into();
+        assert_eq!(err.source().unwrap().to_string(), "eof");
+        assert!(Error::Protocol("Unexpected result kind".into()).source().is_none());
+    }
+
+    #[test]
+    fn decode_context() {
+        let body = [0, 0, 0, 1, 0, 0x42];
+        let mut cursor = Cursor::new(&body[..]);
+        cursor.set_position(4);
+        let err = super::decode_context(&mut cursor, "column 0", |cursor| {
+                      super::decode_context(cursor, "type option", |cursor| {
+                          cursor.set_position(6);
+                          Err::<(), _>(Error::Protocol("Unexpected column type".into()))
+                      })
+                  }).unwrap_err()
+                    .in_context("rows metadata", 0, &body);
+
+        assert_eq!(err.to_string(),
+                   "Cannot decode rows metadata → column 0 → type option at byte 4 \
+                    (found [00, 42]): Protocol violation: Unexpected column type");
+        assert!(matches!(err.root(), Error::Protocol(_)));
+        assert!(err.source().is_some());
     }
 }
@@ -0,0 +1,193 @@
+//! Delivery of server events to interested parts of a client. `EventDispatcher` decodes
+//! `EVENT` frames and invokes listeners registered for an event type. A single DDL
+//! statement makes every node of a cluster push the same schema change, so schema
+//! changes could be debounced: a change is delivered once no identical change has been
+//! received for a quiet period.
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::error;
+use crate::frame::events::{ChangeType, SchemaObject, ServerEvent, SimpleServerEvent};
+use crate::frame::Frame;
+
+/// Callback which is invoked with every dispatched event of a subscribed type.
+pub type EventListener = Box<dyn Fn(&ServerEvent) + Send + Sync>;
+
+/// Registry of event listeners.
+#[derive(Default)]
+pub struct EventDispatcher {
+    listeners: Vec<(SimpleServerEvent, EventListener)>,
+    schema_debounce: Option<Duration>,
+    // schema change -> the last received event and time when it was received
+    pending_schema_changes: HashMap<(ChangeType, SchemaObject), (ServerEvent, Instant)>,
+}
+
+impl EventDispatcher {
+    pub fn new() -> EventDispatcher {
+        Default::default()
+    }
+
+    /// Holds a schema change back until no identical change of the same element has been
+    /// dispatched for `window`. Held changes are delivered by `poll`.
+    pub fn with_schema_debounce(mut self, window: Duration) -> Self {
+        self.schema_debounce = Some(window);
+        self
+    }
+
+    /// Registers a listener of events of a given type.
+    pub fn subscribe(&mut self, event: SimpleServerEvent, listener: EventListener) {
+        self.listeners.push((event, listener));
+    }
+
+    /// Returns event types which have listeners, e.g. for `Frame::new_req_register`.
+    pub fn subscribed_events(&self) -> Vec<SimpleServerEvent> {
+        SimpleServerEvent::ALL.iter()
+                              .filter(|event| self.listeners.iter().any(|(e, _)| e == *event))
+                              .cloned()
+                              .collect()
+    }
+
+    /// Decodes an `EVENT` frame and dispatches its event, see `dispatch`.
+    pub fn on_frame(&mut self, frame: &Frame, now: Instant) -> error::Result<usize> {
+        let event = frame.get_body()?
+                         .into_server_event()
+                         .ok_or_else(|| {
+                             error::Error::Protocol(format!("{:?} frame is not an event",
+                                                            frame.opcode))
+                         })?
+                         .event;
+        Ok(self.dispatch(&event, now))
+    }
+
+    /// Invokes listeners of an event type returning how many of them have been invoked.
+    /// If schema changes are debounced a schema change is held back instead, so nothing
+    /// is invoked until `poll` delivers it.
+    pub fn dispatch(&mut self, event: &ServerEvent, now: Instant) -> usize {
+        if let (Some(_), ServerEvent::SchemaChange(change)) = (self.schema_debounce, event) {
+            let key = (change.change_type, change.object());
+            self.pending_schema_changes.insert(key, (event.clone(), now));
+            return 0;
+        }
+
+        self.invoke(event)
+    }
+
+    /// Delivers held schema changes which haven't been repeated for the debounce window
+    /// returning how many listeners have been invoked. It should be called at
+    /// `next_deadline` at the latest.
+    pub fn poll(&mut self, now: Instant) -> usize {
+        let window = match self.schema_debounce {
+            Some(window) => window,
+            None => return 0,
+        };
+
+        let mut ready: Vec<(ServerEvent, Instant)> = Vec::new();
+        let pending = std::mem::take(&mut self.pending_schema_changes);
+        for (key, (event, received)) in pending {
+            if now >= received + window {
+                ready.push((event, received));
+            } else {
+                self.pending_schema_changes.insert(key, (event, received));
+            }
+        }
+        ready.sort_by_key(|(_, received)| *received);
+
+        ready.iter().map(|(event, _)| self.invoke(event)).sum()
+    }
+
+    /// Returns time when the earliest held schema change should be delivered.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let window = self.schema_debounce?;
+        self.pending_schema_changes
+            .values()
+            .map(|(_, received)| *received + window)
+            .min()
+    }
+
+    fn invoke(&self, event: &ServerEvent) -> usize {
+        let event_type = SimpleServerEvent::from(event);
+        let mut invoked = 0;
+        for (_, listener) in self.listeners.iter().filter(|(e, _)| *e == event_type) {
+            listener(event);
+            invoked += 1;
+        }
+        invoked
+    }
+}
+
+impl fmt::Debug for EventDispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventDispatcher")
+         .field("subscribed_events", &self.subscribed_events())
+         .field("schema_debounce", &self.schema_debounce)
+         .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::frame::events::{ChangeSchemeOptions, SchemaChange, StatusChange, Target};
+
+    fn counter(dispatcher: &mut EventDispatcher, event: SimpleServerEvent) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let listener_count = count.clone();
+        dispatcher.subscribe(event,
+                             Box::new(move |_| {
+                                 listener_count.fetch_add(1, Ordering::SeqCst);
+                             }));
+        count
+    }
+
+    fn table_created(table: &str) -> ServerEvent {
+        let options = ChangeSchemeOptions::TableType(("ks".into(), table.into()));
+        ServerEvent::SchemaChange(SchemaChange { change_type: ChangeType::Created,
+                                                 target: Target::Table,
+                                                 options })
+    }
+
+    #[test]
+    fn dispatch() {
+        let mut dispatcher = EventDispatcher::new();
+        let status = counter(&mut dispatcher, SimpleServerEvent::StatusChange);
+        let schema = counter(&mut dispatcher, SimpleServerEvent::SchemaChange);
+        assert_eq!(dispatcher.subscribed_events(),
+                   vec![SimpleServerEvent::StatusChange, SimpleServerEvent::SchemaChange]);
+
+        let now = Instant::now();
+        let up = ServerEvent::StatusChange(StatusChange::Up("127.0.0.1:9042".parse().unwrap()));
+        assert_eq!(dispatcher.dispatch(&up, now), 1);
+        assert_eq!(dispatcher.dispatch(&table_created("t"), now), 1);
+        assert_eq!(dispatcher.dispatch(&table_created("t"), now), 1);
+        assert_eq!(status.load(Ordering::SeqCst), 1);
+        assert_eq!(schema.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn schema_debounce() {
+        let window = Duration::from_secs(1);
+        let mut dispatcher = EventDispatcher::new().with_schema_debounce(window);
+        let schema = counter(&mut dispatcher, SimpleServerEvent::SchemaChange);
+
+        let now = Instant::now();
+        assert_eq!(dispatcher.dispatch(&table_created("t"), now), 0);
+        assert_eq!(dispatcher.next_deadline(), Some(now + window));
+        assert_eq!(dispatcher.dispatch(&table_created("t"), now + window / 2), 0);
+        assert_eq!(dispatcher.dispatch(&table_created("other"), now + window / 2), 0);
+        // the quiet period of "t" is restarted by the repeated change
+        assert_eq!(dispatcher.poll(now + window), 0);
+        assert_eq!(dispatcher.next_deadline(), Some(now + window + window / 2));
+        assert_eq!(dispatcher.poll(now + window + window / 2), 2);
+        assert_eq!(schema.load(Ordering::SeqCst), 2);
+        assert_eq!(dispatcher.next_deadline(), None);
+
+        // identical changes after the quiet period are delivered again
+        assert_eq!(dispatcher.dispatch(&table_created("t"), now + window * 3), 0);
+        assert_eq!(dispatcher.poll(now + window * 4), 1);
+        assert_eq!(schema.load(Ordering::SeqCst), 3);
+    }
+}
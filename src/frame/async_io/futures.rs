@@ -0,0 +1,205 @@
+//! Asynchronous reading and writing of frames over futures-io `AsyncRead` and `AsyncWrite`,
+//! so async-std and smol sockets could be used without tokio.
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use super::{decoder, encoder, ReadState, WriteState};
+use crate::compression::Compressor;
+use crate::error;
+use crate::frame::encoder::FrameEncoder;
+use crate::frame::warning::WarningHandler;
+use crate::frame::{Frame, MAX_FRAME_LEN};
+
+/// Reads a frame from `reader` waiting until the whole frame is received. Compressed frames
+/// are decompressed with a given compressor. `None` is returned if the reader is closed
+/// before the first byte of a frame, if it's closed in the middle of a frame
+/// `UnexpectedEof` IO error is returned.
+pub async fn parse_frame<R>(reader: &mut R,
+                            compressor: Option<&(dyn Compressor + Send + Sync)>)
+                            -> error::Result<Option<Frame>>
+    where R: AsyncRead + Unpin
+{
+    parse_frame_with_max_len(reader, compressor, MAX_FRAME_LEN).await
+}
+
+/// The same as `parse_frame` but `FrameTooLarge` error is returned without reading
+/// a body if it's longer than `max_len`.
+pub async fn parse_frame_with_max_len<R>(reader: &mut R,
+                                         compressor: Option<&(dyn Compressor + Send + Sync)>,
+                                         max_len: usize)
+                                         -> error::Result<Option<Frame>>
+    where R: AsyncRead + Unpin
+{
+    super::parse_frame(|cx, buf| Pin::new(&mut *reader).poll_read(cx, buf),
+                       compressor,
+                       max_len).await
+}
+
+/// Returns a stream of frames read from `reader`. Partially received frames are buffered
+/// and compressed frames are decompressed with a given compressor.
+pub fn frame_stream<R>(reader: R,
+                       compressor: Option<Box<dyn Compressor + Send + Sync>>)
+                       -> FrameStream<R>
+    where R: AsyncRead + Unpin
+{
+    FrameStream { reader,
+                  state: ReadState::new(decoder(compressor)) }
+}
+
+/// Stream of frames read from `AsyncRead`, see `frame_stream`.
+pub struct FrameStream<R> {
+    reader: R,
+    state: ReadState,
+}
+
+impl<R> FrameStream<R> {
+    /// Sets a callback which is invoked with every warning of received frames.
+    pub fn with_warning_handler(self, handler: WarningHandler) -> Self {
+        FrameStream { reader: self.reader,
+                      state: self.state.with_warning_handler(handler) }
+    }
+
+    /// Sets the maximal length of a frame body, `FrameTooLarge` error is returned
+    /// for longer frames. It's `MAX_FRAME_LEN` by default.
+    pub fn with_max_frame_len(self, max_frame_len: usize) -> Self {
+        FrameStream { reader: self.reader,
+                      state: self.state.with_max_frame_len(max_frame_len) }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for FrameStream<R> {
+    type Item = error::Result<Frame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let reader = &mut this.reader;
+        this.state
+            .poll_next(cx, |cx, buf| Pin::new(&mut *reader).poll_read(cx, buf))
+    }
+}
+
+/// Returns a sink which encodes frames and writes them to `writer`. Request bodies are
+/// compressed with a given compressor.
+pub fn frame_sink<W>(writer: W,
+                     compressor: Option<Box<dyn Compressor + Send + Sync>>)
+                     -> FrameSink<W>
+    where W: AsyncWrite + Unpin
+{
+    FrameSink::new(writer, encoder(compressor))
+}
+
+/// Sink of frames written to `AsyncWrite`, see `frame_sink`. Encoded frames are buffered
+/// and written on flush or once the buffer grows over a limit, so `poll_ready` applies
+/// backpressure if the writer is slow.
+pub struct FrameSink<W> {
+    writer: W,
+    state: WriteState,
+}
+
+impl<W> FrameSink<W> {
+    /// Creates new sink which encodes frames with a given encoder.
+    pub fn new(writer: W, encoder: FrameEncoder) -> FrameSink<W> {
+        FrameSink { writer,
+                    state: WriteState::new(encoder) }
+    }
+
+    /// Returns the underlying writer. Frames which have not been flushed are lost.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<Frame> for FrameSink<W> {
+    type Error = error::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<error::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        this.state
+            .poll_ready(cx, |cx, buf| Pin::new(&mut *writer).poll_write(cx, buf))
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: Frame) -> error::Result<()> {
+        self.get_mut().state.start_send(&frame)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<error::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        ready!(this.state
+                   .poll_flush(cx, |cx, buf| Pin::new(&mut *writer).poll_write(cx, buf)))?;
+        Pin::new(writer).poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<error::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().writer).poll_close(cx)
+                                            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use super::*;
+    use crate::frame::async_io::tests::ready_frame;
+    use crate::frame::decoder::HEADER_LEN;
+    use crate::frame::IntoBytes;
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn frames() {
+        let mut bytes = ready_frame(1);
+        bytes.extend(ready_frame(2));
+
+        let mut frames = frame_stream(bytes.as_slice(), None);
+        assert_eq!(next(&mut frames).await.unwrap().unwrap().stream, 1);
+        assert_eq!(next(&mut frames).await.unwrap().unwrap().stream, 2);
+        assert!(next(&mut frames).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn parse() {
+        let mut bytes = ready_frame(1);
+        bytes.extend(ready_frame(2));
+        let mut reader = &bytes[..bytes.len() - 1];
+        assert_eq!(parse_frame(&mut reader, None).await.unwrap().unwrap().stream, 1);
+        assert!(parse_frame(&mut reader, None).await.is_err());
+
+        let mut reader = &bytes[..HEADER_LEN];
+        assert_eq!(parse_frame(&mut reader, None).await.unwrap().unwrap().stream, 1);
+        assert!(parse_frame(&mut reader, None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn sink() {
+        let frames = vec![Frame::new_req_options(), Frame::new_req_startup(None)];
+        let expected = frames.iter()
+                             .flat_map(|frame| frame.into_cbytes())
+                             .collect::<Vec<u8>>();
+
+        let mut sink = frame_sink(vec![], None);
+        for frame in frames {
+            poll_fn(|cx| Pin::new(&mut sink).poll_ready(cx)).await
+                                                            .unwrap();
+            Pin::new(&mut sink).start_send(frame).unwrap();
+        }
+        assert!(sink.writer.is_empty());
+        poll_fn(|cx| Pin::new(&mut sink).poll_close(cx)).await
+                                                        .unwrap();
+        assert_eq!(sink.into_inner(), expected);
+    }
+}
@@ -0,0 +1,252 @@
+//! Asynchronous reading and writing of frames. Frames are read from and written to
+//! tokio `AsyncRead` and `AsyncWrite` (`tokio` feature, see `async_io::tokio`) or
+//! futures-io `AsyncRead` and `AsyncWrite` (`futures-io` feature, see `async_io::futures`)
+//! which are used by async-std and smol. Both flavours share the same framing logic.
+use std::convert::TryFrom;
+use std::future::poll_fn;
+use std::io;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::ready;
+
+use crate::compression::Compressor;
+use crate::error;
+use crate::frame::decoder::{check_frame_len, frame_from_body, missing_compressor, FrameDecoder,
+                            HEADER_LEN};
+use crate::frame::encoder::FrameEncoder;
+use crate::frame::warning::WarningHandler;
+use crate::frame::{Flags, Frame, Opcode, Version, LENGTH_LEN};
+use crate::types::{from_bytes, from_i16_bytes};
+
+#[cfg(feature = "futures-io")]
+pub mod futures;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+/// Size of a chunk which is read from a reader at once.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+/// Number of buffered bytes after which a sink writes them before accepting new frames.
+const BACKPRESSURE_BOUNDARY: usize = 64 * 1024;
+
+/// Reads a frame with a given function which polls a reader for bytes.
+/// See `tokio::parse_frame` and `futures::parse_frame`.
+async fn parse_frame<F>(mut poll_read: F,
+                        compressor: Option<&(dyn Compressor + Send + Sync)>,
+                        max_len: usize)
+                        -> error::Result<Option<Frame>>
+    where F: FnMut(&mut Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>
+{
+    let mut header = [0; HEADER_LEN];
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match poll_fn(|cx| poll_read(cx, &mut header[filled..])).await? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(unexpected_eof().into()),
+            read => filled += read,
+        }
+    }
+
+    let version = Version::try_from(&header[..Version::BYTE_LENGTH])?;
+    let flags = Flags::from_bits_truncate(header[1]);
+    let stream = from_i16_bytes(&header[2..4]);
+    let opcode = Opcode::try_from(header[4])?;
+    let length = from_bytes(&header[HEADER_LEN - LENGTH_LEN..]) as usize;
+    check_frame_len(length, max_len)?;
+
+    let mut body = vec![0; length];
+    let mut filled = 0;
+    while filled < length {
+        match poll_fn(|cx| poll_read(cx, &mut body[filled..])).await? {
+            0 => return Err(unexpected_eof().into()),
+            read => filled += read,
+        }
+    }
+
+    if flags.contains(Flags::COMPRESSION) {
+        let compressor = compressor.ok_or_else(missing_compressor)?;
+        let mut decoded = vec![];
+        compressor.decode_with_max_len(&body, &mut decoded, max_len)
+                  .map_err(|err| error::Error::Compression(err.to_string()))?;
+        body = decoded;
+    }
+
+    frame_from_body(version, flags, stream, opcode, Bytes::from(body)).map(Some)
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Frame is incomplete")
+}
+
+fn decoder(compressor: Option<Box<dyn Compressor + Send + Sync>>) -> FrameDecoder {
+    match compressor {
+        Some(compressor) => FrameDecoder::new().with_compressor(compressor),
+        None => FrameDecoder::new(),
+    }
+}
+
+fn encoder(compressor: Option<Box<dyn Compressor + Send + Sync>>) -> FrameEncoder {
+    match compressor {
+        Some(compressor) => FrameEncoder::new().with_compressor(compressor),
+        None => FrameEncoder::new(),
+    }
+}
+
+/// Reading side of a frame stream which doesn't depend on a flavour of `AsyncRead`.
+struct ReadState {
+    decoder: FrameDecoder,
+    done: bool,
+}
+
+impl ReadState {
+    fn new(decoder: FrameDecoder) -> ReadState {
+        ReadState { decoder,
+                    done: false }
+    }
+
+    fn with_warning_handler(self, handler: WarningHandler) -> ReadState {
+        ReadState { decoder: self.decoder.with_warning_handler(handler),
+                    done: self.done }
+    }
+
+    fn with_max_frame_len(self, max_frame_len: usize) -> ReadState {
+        ReadState { decoder: self.decoder.with_max_frame_len(max_frame_len),
+                    done: self.done }
+    }
+
+    /// Returns the next frame reading bytes with a given function when buffered ones
+    /// don't contain a whole frame.
+    fn poll_next<F>(&mut self,
+                    cx: &mut Context<'_>,
+                    mut poll_read: F)
+                    -> Poll<Option<error::Result<Frame>>>
+        where F: FnMut(&mut Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>
+    {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let mut chunk = [0; READ_CHUNK_LEN];
+        loop {
+            match self.decoder.decode() {
+                Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
+                Ok(None) => {}
+                Err(err) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+
+            match poll_read(cx, &mut chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Ready(Ok(0)) => {
+                    self.done = true;
+                    if self.decoder.buffered_len() == 0 {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Err(unexpected_eof().into())));
+                }
+                Poll::Ready(Ok(read)) => self.decoder.extend(&chunk[..read]),
+            }
+        }
+    }
+}
+
+/// Writing side of a frame sink which doesn't depend on a flavour of `AsyncWrite`.
+struct WriteState {
+    encoder: FrameEncoder,
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl WriteState {
+    fn new(encoder: FrameEncoder) -> WriteState {
+        WriteState { encoder,
+                     buf: vec![],
+                     written: 0 }
+    }
+
+    fn start_send(&mut self, frame: &Frame) -> error::Result<()> {
+        self.encoder.encode(frame, &mut self.buf)
+    }
+
+    /// Writes buffered bytes with a given function until the number of unwritten ones
+    /// is not greater than `limit`.
+    fn poll_write_buf<F>(&mut self,
+                         cx: &mut Context<'_>,
+                         limit: usize,
+                         mut poll_write: F)
+                         -> Poll<io::Result<()>>
+        where F: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>
+    {
+        while self.buf.len() - self.written > limit {
+            let written = ready!(poll_write(cx, &self.buf[self.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += written;
+        }
+
+        if self.written == self.buf.len() {
+            self.buf.clear();
+            self.written = 0;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_ready<F>(&mut self, cx: &mut Context<'_>, poll_write: F) -> Poll<error::Result<()>>
+        where F: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>
+    {
+        self.poll_write_buf(cx, BACKPRESSURE_BOUNDARY - 1, poll_write)
+            .map_err(Into::into)
+    }
+
+    fn poll_flush<F>(&mut self, cx: &mut Context<'_>, poll_write: F) -> Poll<error::Result<()>>
+        where F: FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>
+    {
+        self.poll_write_buf(cx, 0, poll_write).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::IntoBytes;
+
+    pub(super) fn ready_frame(stream: i16) -> Vec<u8> {
+        Frame { version: Version::Response,
+                flags: Flags::empty(),
+                opcode: Opcode::Ready,
+                stream,
+                body: Bytes::new(),
+                tracing_id: None,
+                warnings: vec![],
+                custom_payload: Default::default() }.into_cbytes()
+    }
+
+    #[test]
+    fn backpressure() {
+        let mut state = WriteState::new(FrameEncoder::new());
+        let frame = Frame::new_req_options();
+        let len = frame.into_cbytes().len();
+        while state.buf.len() < BACKPRESSURE_BOUNDARY {
+            state.start_send(&frame).unwrap();
+        }
+
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut written = vec![];
+        let poll = state.poll_ready(&mut cx, |_, buf| {
+                            let len = buf.len().min(len);
+                            written.extend_from_slice(&buf[..len]);
+                            Poll::Ready(Ok(len))
+                        });
+        assert!(matches!(poll, Poll::Ready(Ok(()))));
+        assert_eq!(written.len(), len);
+        assert!(matches!(state.poll_ready(&mut cx, |_, _| Poll::Pending), Poll::Ready(Ok(()))));
+    }
+}
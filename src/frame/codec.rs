@@ -0,0 +1,110 @@
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::compression::Compressor;
+use crate::error;
+use crate::frame::decoder::{decode_frame_with_max_len, notify_warnings};
+use crate::frame::encoder::FrameEncoder;
+use crate::frame::warning::WarningHandler;
+use crate::frame::{Frame, MAX_FRAME_LEN};
+
+/// tokio-util codec of frames, e.g. `Framed::new(stream, CassandraCodec::new(None))`
+/// turns a stream into `Stream` of response frames and `Sink` of request frames.
+pub struct CassandraCodec {
+    encoder: FrameEncoder,
+    max_frame_len: usize,
+    warning_handler: Option<WarningHandler>,
+}
+
+impl CassandraCodec {
+    /// Creates new codec which compresses and decompresses frames with a given compressor.
+    pub fn new(compressor: Option<Box<dyn Compressor + Send + Sync>>) -> CassandraCodec {
+        let encoder = match compressor {
+            Some(compressor) => FrameEncoder::new().with_compressor(compressor),
+            None => FrameEncoder::new(),
+        };
+
+        CassandraCodec { encoder,
+                         max_frame_len: MAX_FRAME_LEN,
+                         warning_handler: None }
+    }
+
+    /// Sets minimal size of a request body which is compressed.
+    pub fn with_min_compress_size(mut self, min_compress_size: usize) -> Self {
+        self.encoder = self.encoder.with_min_compress_size(min_compress_size);
+        self
+    }
+
+    /// Sets maximal length of a response body, `MAX_FRAME_LEN` by default.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Sets a callback which is invoked with every warning of decoded responses.
+    pub fn with_warning_handler(mut self, handler: WarningHandler) -> Self {
+        self.warning_handler = Some(handler);
+        self
+    }
+}
+
+impl Decoder for CassandraCodec {
+    type Item = Frame;
+    type Error = error::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> error::Result<Option<Frame>> {
+        let compressor = self.encoder.compressor().map(|c| c as &dyn Compressor);
+        let frame = decode_frame_with_max_len(src, compressor, self.max_frame_len)?;
+        notify_warnings(self.warning_handler.as_ref(), frame.as_ref());
+        Ok(frame)
+    }
+}
+
+impl Encoder<Frame> for CassandraCodec {
+    type Error = error::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> error::Result<()> {
+        let mut buf = vec![];
+        self.encoder.encode(&frame, &mut buf)?;
+        dst.extend_from_slice(&buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::frame::{Flags, IntoBytes, Opcode, Version};
+
+    #[test]
+    fn decode_partial_frame() {
+        let bytes = Frame { version: Version::Response,
+                            flags: Flags::empty(),
+                            opcode: Opcode::Ready,
+                            stream: 4,
+                            body: Bytes::new(),
+                            tracing_id: None,
+                            warnings: vec![],
+                            custom_payload: Default::default() }.into_cbytes();
+
+        let mut codec = CassandraCodec::new(None);
+        let mut src = BytesMut::from(&bytes[..4]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&bytes[4..]);
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap().stream, 4);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn encode() {
+        let frame = Frame::new_req_options();
+        let expected = frame.into_cbytes();
+
+        let mut dst = BytesMut::new();
+        CassandraCodec::new(None).encode(frame, &mut dst).unwrap();
+        assert_eq!(&dst[..], &expected[..]);
+        assert_eq!(dst[1] & Flags::COMPRESSION.bits(), 0);
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, Cursor, Read};
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::compression::Compressor;
+use crate::error;
+use crate::frame::{Flags, Frame, FromCursor, Opcode, Version, LENGTH_LEN, MAX_FRAME_LEN,
+                   STREAM_LEN};
+use crate::frame::parse_mode::{apply_mode, check_frame, DeviationHandler, ParseMode};
+use crate::frame::warning::{Warning, WarningHandler};
+use crate::types::data_serialization_types::decode_timeuuid;
+use crate::types::{from_bytes, from_i16_bytes, CBytesMap, CStringList, UUID_LEN};
+
+/// Length of a frame header in accordance to protocol.
+pub const HEADER_LEN: usize = Version::BYTE_LENGTH
+                              + Flags::BYTE_LENGTH
+                              + STREAM_LEN
+                              + Opcode::BYTE_LENGTH
+                              + LENGTH_LEN;
+
+/// Size of a chunk which is read at once by `FrameDecoder::read_from`.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// Stateful decoder of response frames. Received bytes are buffered until a whole frame
+/// is available, so a partially received header or body is kept across calls
+/// and decoding resumes when the rest arrives. It makes the decoder suitable
+/// for non-blocking sockets.
+pub struct FrameDecoder {
+    buf: BytesMut,
+    compressor: Option<Box<dyn Compressor + Send + Sync>>,
+    max_frame_len: usize,
+    warning_handler: Option<WarningHandler>,
+    parse_mode: Option<ParseMode>,
+    deviation_handler: Option<DeviationHandler>,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> FrameDecoder {
+        FrameDecoder { buf: BytesMut::new(),
+                       compressor: None,
+                       max_frame_len: MAX_FRAME_LEN,
+                       warning_handler: None,
+                       parse_mode: None,
+                       deviation_handler: None }
+    }
+}
+
+impl FrameDecoder {
+    /// Creates new decoder which doesn't expect compressed frames.
+    pub fn new() -> FrameDecoder {
+        Default::default()
+    }
+
+    /// Sets a compressor agreed in `STARTUP` request.
+    pub fn with_compressor(mut self, compressor: Box<dyn Compressor + Send + Sync>) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    /// Sets maximal length of a frame body, `MAX_FRAME_LEN` by default.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Sets a callback which is invoked with every warning of decoded frames, so warnings
+    /// could be logged in one place instead of checking `Frame::warnings` of every response.
+    pub fn with_warning_handler(mut self, handler: WarningHandler) -> Self {
+        self.warning_handler = Some(handler);
+        self
+    }
+
+    /// Validates decoded frames against the protocol specification. Validation decodes
+    /// a whole body of every frame, so frames are not validated by default.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = Some(mode);
+        self
+    }
+
+    /// Sets a callback which is invoked with every deviation found in `ParseMode::Lenient`.
+    /// Deviations are logged as warnings if the handler is not set.
+    pub fn with_deviation_handler(mut self, handler: DeviationHandler) -> Self {
+        self.deviation_handler = Some(handler);
+        self
+    }
+
+    /// Number of received bytes which have not been decoded yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Appends received bytes.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Reads all the bytes which are currently available in a non-blocking reader.
+    /// It returns a number of read bytes, `0` means that the reader is closed.
+    /// `WouldBlock` error is returned only if nothing has been read.
+    pub fn read_from<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        let mut chunk = [0; READ_CHUNK_LEN];
+        let mut total = 0;
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return Ok(total),
+                Ok(read) => {
+                    self.buf.extend_from_slice(&chunk[..read]);
+                    total += read;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock && total > 0 => {
+                    return Ok(total)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Returns the next frame if it has been received completely and `None` otherwise.
+    pub fn decode(&mut self) -> error::Result<Option<Frame>> {
+        let compressor = self.compressor.as_deref().map(|c| c as &dyn Compressor);
+        let frame = match decode_raw_frame(&mut self.buf, compressor, self.max_frame_len)? {
+            Some((frame, raw_flags)) => {
+                if let Some(mode) = self.parse_mode {
+                    let deviations = check_frame(&frame, raw_flags);
+                    apply_mode(mode, deviations, self.deviation_handler.as_ref())?;
+                }
+                Some(frame)
+            }
+            None => None,
+        };
+        notify_warnings(self.warning_handler.as_ref(), frame.as_ref());
+        Ok(frame)
+    }
+}
+
+/// Passes warnings of a frame to a handler if both are present.
+pub(crate) fn notify_warnings(handler: Option<&WarningHandler>, frame: Option<&Frame>) {
+    if let (Some(handler), Some(frame)) = (handler, frame) {
+        frame.warnings.iter().for_each(handler);
+    }
+}
+
+/// Decodes a frame from the beginning of a buffer and removes its bytes from the buffer.
+/// If the buffer doesn't contain a whole frame yet `None` is returned and the buffer
+/// is left intact.
+pub fn decode_frame(buf: &mut BytesMut,
+                    compressor: Option<&dyn Compressor>)
+                    -> error::Result<Option<Frame>> {
+    decode_frame_with_max_len(buf, compressor, MAX_FRAME_LEN)
+}
+
+/// The same as `decode_frame` but `FrameTooLarge` error is returned if a body
+/// is longer than `max_len`.
+pub fn decode_frame_with_max_len(buf: &mut BytesMut,
+                                 compressor: Option<&dyn Compressor>,
+                                 max_len: usize)
+                                 -> error::Result<Option<Frame>> {
+    decode_raw_frame(buf, compressor, max_len).map(|frame| frame.map(|(frame, _)| frame))
+}
+
+/// The same as `decode_frame_with_max_len` but header flags are returned along with
+/// a frame as they were received, including bits unknown to `Flags`.
+fn decode_raw_frame(buf: &mut BytesMut,
+                    compressor: Option<&dyn Compressor>,
+                    max_len: usize)
+                    -> error::Result<Option<(Frame, u8)>> {
+    if buf.len() < HEADER_LEN {
+        return Ok(None);
+    }
+
+    let length = from_bytes(&buf[HEADER_LEN - LENGTH_LEN..HEADER_LEN]) as usize;
+    check_frame_len(length, max_len)?;
+    if buf.len() < HEADER_LEN + length {
+        buf.reserve(HEADER_LEN + length - buf.len());
+        return Ok(None);
+    }
+
+    let header = buf.split_to(HEADER_LEN);
+    let body = buf.split_to(length).freeze();
+
+    let version = Version::try_from(&header[..Version::BYTE_LENGTH])?;
+    let flags = Flags::from_bits_truncate(header[1]);
+    let stream = from_i16_bytes(&header[2..4]);
+    let opcode = Opcode::try_from(header[4])?;
+
+    let body = if flags.contains(Flags::COMPRESSION) {
+        let compressor = compressor.ok_or_else(missing_compressor)?;
+        let mut decoded = vec![];
+        compressor.decode_with_max_len(&body, &mut decoded, max_len)
+                  .map_err(|err| error::Error::Compression(err.to_string()))?;
+        Bytes::from(decoded)
+    } else {
+        body
+    };
+
+    frame_from_body(version, flags, stream, opcode, body).map(|frame| Some((frame, header[1])))
+}
+
+/// Checks a body length taken from a frame header before a body buffer is allocated.
+pub(crate) fn check_frame_len(length: usize, max: usize) -> error::Result<()> {
+    if length > max {
+        return Err(error::Error::FrameTooLarge { length, max });
+    }
+
+    Ok(())
+}
+
+pub(crate) fn missing_compressor() -> error::Error {
+    error::Error::Compression("Frame is compressed but compressor is not set".into())
+}
+
+/// Builds a frame out of a decompressed body which may start with tracing id, warnings
+/// and custom payload.
+pub(crate) fn frame_from_body(version: Version,
+                              flags: Flags,
+                              stream: i16,
+                              opcode: Opcode,
+                              mut body: Bytes)
+                              -> error::Result<Frame> {
+    let mut cursor = Cursor::new(body.as_ref());
+
+    let tracing_id = if flags.contains(Flags::TRACING) {
+        let mut tracing_bytes = [0; UUID_LEN];
+        cursor.read_exact(&mut tracing_bytes)?;
+        decode_timeuuid(&tracing_bytes).ok()
+    } else {
+        None
+    };
+
+    let warnings = if flags.contains(Flags::WARNING) {
+        CStringList::from_cursor(&mut cursor)?
+            .into_plain()
+            .into_iter()
+            .map(|message| Warning::new(message, opcode.clone(), stream))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+        CBytesMap::from_cursor(&mut cursor)?.into_plain()
+    } else {
+        HashMap::new()
+    };
+
+    let position = cursor.position() as usize;
+    body.advance(position);
+
+    Ok(Frame { version,
+               flags,
+               opcode,
+               stream,
+               body,
+               tracing_id,
+               warnings,
+               custom_payload })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::frame::parse_mode::{Deviation, DeviationKind};
+    use crate::frame::{IntoBytes, EVENT_STREAM};
+
+    fn ready_frame(stream: i16) -> Vec<u8> {
+        Frame { version: Version::Response,
+                flags: Flags::WARNING,
+                opcode: Opcode::Ready,
+                stream,
+                body: Bytes::from(vec![0, 1, 0, 1, b'w']),
+                tracing_id: None,
+                warnings: vec![],
+                custom_payload: Default::default() }.into_cbytes()
+    }
+
+    #[test]
+    fn partial_frames() {
+        let mut bytes = ready_frame(1);
+        bytes.extend(ready_frame(2));
+
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&bytes[..3]);
+        assert!(decoder.decode().unwrap().is_none());
+        decoder.extend(&bytes[3..HEADER_LEN + 2]);
+        assert!(decoder.decode().unwrap().is_none());
+        assert_eq!(decoder.buffered_len(), HEADER_LEN + 2);
+
+        decoder.extend(&bytes[HEADER_LEN + 2..bytes.len() - 1]);
+        let frame = decoder.decode().unwrap().unwrap();
+        assert_eq!(frame.stream, 1);
+        assert_eq!(frame.opcode, Opcode::Ready);
+        assert_eq!(frame.warnings, vec![Warning::new("w".into(), Opcode::Ready, 1)]);
+        assert!(frame.body.is_empty());
+        assert!(decoder.decode().unwrap().is_none());
+
+        decoder.extend(&bytes[bytes.len() - 1..]);
+        assert_eq!(decoder.decode().unwrap().unwrap().stream, 2);
+        assert_eq!(decoder.buffered_len(), 0);
+    }
+
+    #[test]
+    fn warning_handler() {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let handled = warnings.clone();
+        let handler = move |warning: &Warning| handled.lock().unwrap().push(warning.clone());
+        let mut decoder = FrameDecoder::new().with_warning_handler(Box::new(handler));
+
+        decoder.extend(&ready_frame(1));
+        decoder.extend(&ready_frame(2));
+        let frame = decoder.decode().unwrap().unwrap();
+        assert_eq!(*warnings.lock().unwrap(), frame.warnings);
+        decoder.decode().unwrap().unwrap();
+        let streams = warnings.lock().unwrap().iter().map(|w| w.stream).collect::<Vec<_>>();
+        assert_eq!(streams, vec![1, 2]);
+        assert_eq!(warnings.lock().unwrap()[1].to_string(), "w (stream 2)");
+    }
+
+    #[test]
+    fn event_stream() {
+        let mut bytes = ready_frame(EVENT_STREAM);
+        bytes.extend(ready_frame(i16::MAX));
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&bytes);
+        let event = decoder.decode().unwrap().unwrap();
+        assert_eq!(event.stream, EVENT_STREAM);
+        assert!(event.is_event());
+        let response = decoder.decode().unwrap().unwrap();
+        assert_eq!(response.stream, i16::MAX);
+        assert!(!response.is_event());
+    }
+
+    #[test]
+    fn frame_too_large() {
+        let bytes = ready_frame(1);
+        let mut decoder = FrameDecoder::new().with_max_frame_len(4);
+        decoder.extend(&bytes[..HEADER_LEN]);
+        match decoder.decode() {
+            Err(error::Error::FrameTooLarge { length: 5, max: 4 }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let mut decoder = FrameDecoder::new().with_max_frame_len(5);
+        decoder.extend(&bytes);
+        assert!(decoder.decode().unwrap().is_some());
+    }
+
+    #[test]
+    fn read_from() {
+        let bytes = ready_frame(3);
+        let mut decoder = FrameDecoder::new();
+        assert_eq!(decoder.read_from(&mut bytes.as_slice()).unwrap(), bytes.len());
+        assert_eq!(decoder.decode().unwrap().unwrap().stream, 3);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut bytes = ready_frame(1);
+        bytes[4] = 0xff;
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&bytes);
+        assert!(matches!(decoder.decode(), Err(error::Error::UnknownOpcode(0xff))));
+    }
+
+    #[test]
+    fn custom_payload() {
+        let mut body = vec![0, 1, 0, 1, b'w'];
+        body.extend(vec![0, 1, 0, 1, b'k', 0, 0, 0, 2, 1, 2]);
+        body.extend(vec![0, 0, 0, 1]);
+        let bytes = Frame { version: Version::Response,
+                            flags: Flags::WARNING | Flags::CUSTOM_PAYLOAD,
+                            opcode: Opcode::Result,
+                            stream: 1,
+                            body: Bytes::from(body),
+                            tracing_id: None,
+                            warnings: vec![],
+                            custom_payload: Default::default() }.into_cbytes();
+        assert_eq!(bytes[1], 0x0c);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&bytes);
+        let frame = decoder.decode().unwrap().unwrap();
+        assert!(frame.flags.contains(Flags::CUSTOM_PAYLOAD));
+        assert_eq!(frame.warnings[0].message, "w");
+        assert_eq!(frame.custom_payload().get("k"), Some(&vec![1, 2]));
+        assert_eq!(frame.body.as_ref(), &[0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn parse_modes() {
+        let valid = ready_frame(1);
+        let mut deviating = ready_frame(2);
+        deviating[1] |= 0x80;
+        deviating[8] += 1;
+        deviating.push(0);
+
+        let mut decoder = FrameDecoder::new().with_parse_mode(ParseMode::Strict);
+        decoder.extend(&valid);
+        decoder.extend(&deviating);
+        assert_eq!(decoder.decode().unwrap().unwrap().stream, 1);
+        assert!(matches!(decoder.decode(), Err(error::Error::Protocol(_))));
+
+        let kinds = Arc::new(Mutex::new(vec![]));
+        let handler_kinds = kinds.clone();
+        let handler = move |deviation: &Deviation| {
+            handler_kinds.lock().unwrap().push(deviation.kind.clone())
+        };
+        let mut decoder = FrameDecoder::new().with_parse_mode(ParseMode::Lenient)
+                                             .with_deviation_handler(Box::new(handler));
+        decoder.extend(&deviating);
+        assert_eq!(decoder.decode().unwrap().unwrap().stream, 2);
+        assert_eq!(*kinds.lock().unwrap(),
+                   vec![DeviationKind::UnknownFlags(0x80), DeviationKind::TrailingBytes(1)]);
+
+        let unexpected = |_: &Deviation| panic!("Frames are not validated by default");
+        let mut decoder = FrameDecoder::new().with_deviation_handler(Box::new(unexpected));
+        decoder.extend(&deviating);
+        assert!(decoder.decode().unwrap().is_some());
+    }
+
+    #[test]
+    fn compressed_frame_without_compressor() {
+        let mut bytes = ready_frame(1);
+        bytes[1] |= Flags::COMPRESSION.bits();
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&bytes);
+        assert!(decoder.decode().is_err());
+    }
+}
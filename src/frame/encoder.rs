@@ -0,0 +1,128 @@
+use crate::compression::Compressor;
+use crate::error;
+use crate::frame::{AsByte, Flags, Frame, IntoBytes, Opcode, LENGTH_LEN};
+use crate::types::{to_n_bytes, to_short};
+
+/// Encoder of request frames which compresses frame bodies if a compressor is set.
+/// `STARTUP` and `OPTIONS` frames are never compressed as they are sent before
+/// a compression is agreed. Bodies smaller than `min_compress_size` are sent
+/// uncompressed since compressing tiny bodies costs more than it saves.
+#[derive(Default)]
+pub struct FrameEncoder {
+    compressor: Option<Box<dyn Compressor + Send + Sync>>,
+    min_compress_size: usize,
+}
+
+impl FrameEncoder {
+    /// Creates new encoder which doesn't compress frames.
+    pub fn new() -> FrameEncoder {
+        Default::default()
+    }
+
+    /// Sets a compressor agreed in `STARTUP` request.
+    pub fn with_compressor(mut self, compressor: Box<dyn Compressor + Send + Sync>) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    /// Sets minimal size of a body which is compressed.
+    pub fn with_min_compress_size(mut self, min_compress_size: usize) -> Self {
+        self.min_compress_size = min_compress_size;
+        self
+    }
+
+    pub fn compressor(&self) -> Option<&(dyn Compressor + Send + Sync)> {
+        self.compressor.as_deref()
+    }
+
+    pub fn min_compress_size(&self) -> usize {
+        self.min_compress_size
+    }
+
+    /// Encodes a frame and appends it to `buf`.
+    pub fn encode(&self, frame: &Frame, buf: &mut Vec<u8>) -> error::Result<()> {
+        let compressor = match self.compressor {
+            Some(ref compressor) if self.should_compress(frame) => compressor,
+            _ => {
+                buf.reserve(frame.serialized_len());
+                frame.serialize_into(buf);
+                return Ok(());
+            }
+        };
+
+        let start = buf.len();
+        let flags = (frame.flags | Flags::COMPRESSION).bits();
+        encode_header(frame, flags, 0, buf);
+        let body_start = buf.len();
+        if let Err(err) = compressor.encode(&frame.body, buf) {
+            buf.truncate(start);
+            return Err(error::Error::Compression(err.to_string()));
+        }
+
+        let body_len = to_n_bytes((buf.len() - body_start) as u64, LENGTH_LEN);
+        buf[body_start - LENGTH_LEN..body_start].copy_from_slice(&body_len);
+        Ok(())
+    }
+
+    fn should_compress(&self, frame: &Frame) -> bool {
+        frame.opcode != Opcode::Startup
+            && frame.opcode != Opcode::Options
+            && frame.body.len() >= self.min_compress_size
+    }
+}
+
+fn encode_header(frame: &Frame, flags: u8, body_len: usize, buf: &mut Vec<u8>) {
+    buf.push(frame.version.as_byte());
+    buf.push(flags);
+    buf.extend_from_slice(&to_short(frame.stream));
+    buf.push(frame.opcode.as_byte());
+    buf.extend_from_slice(&to_n_bytes(body_len as u64, LENGTH_LEN));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::ReverseCompressor;
+    use crate::consistency::Consistency;
+
+    fn encode(encoder: &FrameEncoder, frame: &Frame) -> Vec<u8> {
+        let mut buf = vec![0xff];
+        encoder.encode(frame, &mut buf).unwrap();
+        buf.remove(0);
+        buf
+    }
+
+    #[test]
+    fn compression_threshold() {
+        let frame = Frame::new_req_query("SELECT * FROM ks.table".to_string(),
+                                         Consistency::One,
+                                         None,
+                                         None,
+                                         None,
+                                         None,
+                                         None,
+                                         None,
+                                         Flags::TRACING);
+        let plain = frame.into_cbytes();
+        let header_len = plain.len() - frame.body.len();
+
+        let encoder = FrameEncoder::new();
+        assert_eq!(encode(&encoder, &frame), plain);
+
+        let encoder = FrameEncoder::new().with_compressor(Box::new(ReverseCompressor));
+        let compressed = encode(&encoder, &frame);
+        assert_eq!(compressed[1], 0x01 | 0x02);
+        assert_eq!(&compressed[header_len - LENGTH_LEN..header_len],
+                   &plain[header_len - LENGTH_LEN..header_len]);
+        let mut body = compressed[header_len..].to_vec();
+        body.reverse();
+        assert_eq!(body, frame.body);
+
+        let encoder = encoder.with_min_compress_size(frame.body.len() + 1);
+        assert_eq!(encode(&encoder, &frame), plain);
+
+        let encoder = encoder.with_min_compress_size(0);
+        let startup = Frame::new_req_startup(Some("lz4"));
+        assert_eq!(encode(&encoder, &startup), startup.into_cbytes());
+    }
+}
@@ -1,8 +1,10 @@
 use std::io::Cursor;
 use std::cmp::PartialEq;
+use std::net::SocketAddr;
 
 use crate::frame::traits::FromCursor;
 use crate::error;
+use crate::error::decode_context;
 use crate::types::{CInet, CString, CStringList};
 
 // Event types
@@ -33,7 +35,7 @@ const AGGREGATE: &'static str = "AGGREGATE";
 /// Simplified `ServerEvent` that does not contain details
 /// about a concrete change. It may be useful for subscription
 /// when you need only string representation of an event.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SimpleServerEvent {
     TopologyChange,
     StatusChange,
@@ -41,13 +43,23 @@ pub enum SimpleServerEvent {
 }
 
 impl SimpleServerEvent {
-    pub fn as_string(&self) -> String {
+    /// All the events a client can register for.
+    pub const ALL: [SimpleServerEvent; 3] = [SimpleServerEvent::TopologyChange,
+                                             SimpleServerEvent::StatusChange,
+                                             SimpleServerEvent::SchemaChange];
+
+    /// Returns event type name as it's sent in `REGISTER` request.
+    pub fn as_str(&self) -> &'static str {
         match *self {
-            SimpleServerEvent::TopologyChange => String::from(TOPOLOGY_CHANGE),
-            SimpleServerEvent::StatusChange => String::from(STATUS_CHANGE),
-            SimpleServerEvent::SchemaChange => String::from(SCHEMA_CHANGE),
+            SimpleServerEvent::TopologyChange => TOPOLOGY_CHANGE,
+            SimpleServerEvent::StatusChange => STATUS_CHANGE,
+            SimpleServerEvent::SchemaChange => SCHEMA_CHANGE,
         }
     }
+
+    pub fn as_string(&self) -> String {
+        String::from(self.as_str())
+    }
 }
 
 impl From<ServerEvent> for SimpleServerEvent {
@@ -77,7 +89,7 @@ impl PartialEq<ServerEvent> for SimpleServerEvent {
 }
 
 /// Full server event that contains all details about a concreate change.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ServerEvent {
     /// Events related to change in the cluster topology
     TopologyChange(TopologyChange),
@@ -96,35 +108,62 @@ impl PartialEq<SimpleServerEvent> for ServerEvent {
 impl FromCursor for ServerEvent {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<ServerEvent> {
         let event_type = CString::from_cursor(&mut cursor)?;
-        match event_type.as_str() {
+        decode_context(cursor, format_args!("{} event", event_type.as_str()), |cursor| {
+            ServerEvent::decode(cursor, event_type.as_str())
+        })
+    }
+}
+
+impl ServerEvent {
+    fn decode(mut cursor: &mut Cursor<&[u8]>, event_type: &str) -> error::Result<ServerEvent> {
+        match event_type {
             TOPOLOGY_CHANGE => {
                 Ok(ServerEvent::TopologyChange(TopologyChange::from_cursor(&mut cursor)?))
             }
             STATUS_CHANGE => Ok(ServerEvent::StatusChange(StatusChange::from_cursor(&mut cursor)?)),
             SCHEMA_CHANGE => Ok(ServerEvent::SchemaChange(SchemaChange::from_cursor(&mut cursor)?)),
-            _ => Err("Unexpected server event".into()),
+            _ => Err(error::Error::Protocol("Unexpected server event".into())),
         }
     }
 }
 
-/// Events related to change in the cluster topology
-#[derive(Debug)]
-pub struct TopologyChange {
-    pub change_type: TopologyChangeType,
-    pub addr: CInet,
+/// Events related to change in the cluster topology. Contains an address of a node
+/// at which it accepts client connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TopologyChange {
+    NewNode(SocketAddr),
+    RemovedNode(SocketAddr),
+}
+
+impl TopologyChange {
+    pub fn change_type(&self) -> TopologyChangeType {
+        match *self {
+            TopologyChange::NewNode(_) => TopologyChangeType::NewNode,
+            TopologyChange::RemovedNode(_) => TopologyChangeType::RemovedNode,
+        }
+    }
+
+    /// Returns an address of a node which has been added or removed.
+    pub fn addr(&self) -> SocketAddr {
+        match *self {
+            TopologyChange::NewNode(addr) | TopologyChange::RemovedNode(addr) => addr,
+        }
+    }
 }
 
 impl FromCursor for TopologyChange {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<TopologyChange> {
         let change_type = TopologyChangeType::from_cursor(&mut cursor)?;
-        let addr = CInet::from_cursor(&mut cursor)?;
+        let addr = CInet::from_cursor(cursor)?.addr;
 
-        Ok(TopologyChange { change_type: change_type,
-                            addr: addr, })
+        Ok(match change_type {
+            TopologyChangeType::NewNode => TopologyChange::NewNode(addr),
+            TopologyChangeType::RemovedNode => TopologyChange::RemovedNode(addr),
+        })
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TopologyChangeType {
     NewNode,
     RemovedNode,
@@ -135,29 +174,51 @@ impl FromCursor for TopologyChangeType {
         CString::from_cursor(&mut cursor).and_then(|tc| match tc.as_str() {
             NEW_NODE => Ok(TopologyChangeType::NewNode),
             REMOVED_NODE => Ok(TopologyChangeType::RemovedNode),
-            _ => Err("Unexpected topology change type received from Cluster".into()),
+            _ => {
+                let msg = "Unexpected topology change type received from Cluster";
+                Err(error::Error::Protocol(msg.into()))
+            }
         })
     }
 }
 
-/// Events related to change of node status.
-#[derive(Debug)]
-pub struct StatusChange {
-    pub change_type: StatusChangeType,
-    pub addr: CInet,
+/// Events related to change of node status. Contains an address of a node
+/// at which it accepts client connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusChange {
+    Up(SocketAddr),
+    Down(SocketAddr),
+}
+
+impl StatusChange {
+    pub fn change_type(&self) -> StatusChangeType {
+        match *self {
+            StatusChange::Up(_) => StatusChangeType::Up,
+            StatusChange::Down(_) => StatusChangeType::Down,
+        }
+    }
+
+    /// Returns an address of a node which has gone up or down.
+    pub fn addr(&self) -> SocketAddr {
+        match *self {
+            StatusChange::Up(addr) | StatusChange::Down(addr) => addr,
+        }
+    }
 }
 
 impl FromCursor for StatusChange {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<StatusChange> {
         let change_type = StatusChangeType::from_cursor(&mut cursor)?;
-        let addr = CInet::from_cursor(&mut cursor)?;
+        let addr = CInet::from_cursor(cursor)?.addr;
 
-        Ok(StatusChange { change_type: change_type,
-                          addr: addr, })
+        Ok(match change_type {
+            StatusChangeType::Up => StatusChange::Up(addr),
+            StatusChangeType::Down => StatusChange::Down(addr),
+        })
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StatusChangeType {
     Up,
     Down,
@@ -168,13 +229,14 @@ impl FromCursor for StatusChangeType {
         CString::from_cursor(&mut cursor).and_then(|sct| match sct.as_str() {
             UP => Ok(StatusChangeType::Up),
             DOWN => Ok(StatusChangeType::Down),
-            _ => Err("Unexpected status change type".into()),
+            _ => Err(error::Error::Protocol("Unexpected status change type".into())),
         })
     }
 }
 
-/// Events related to schema change.
-#[derive(Debug, PartialEq)]
+/// Events related to schema change. The same body is returned in `SCHEMA_CHANGE` result
+/// of a query which has changed a schema.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SchemaChange {
     pub change_type: ChangeType,
     pub target: Target,
@@ -193,9 +255,80 @@ impl FromCursor for SchemaChange {
     }
 }
 
+impl SchemaChange {
+    /// Returns a changed schema element with its identifiers.
+    pub fn object(&self) -> SchemaObject {
+        match self.options {
+            ChangeSchemeOptions::Keyspace(ref keyspace) => {
+                SchemaObject::Keyspace { keyspace: keyspace.clone() }
+            }
+            ChangeSchemeOptions::TableType((ref keyspace, ref name)) => {
+                let (keyspace, name) = (keyspace.clone(), name.clone());
+                match self.target {
+                    Target::Type => SchemaObject::Type { keyspace, name },
+                    _ => SchemaObject::Table { keyspace, name },
+                }
+            }
+            ChangeSchemeOptions::FunctionAggregate((ref keyspace, ref name, ref types)) => {
+                let (keyspace, name, argument_types) =
+                    (keyspace.clone(), name.clone(), types.clone());
+                match self.target {
+                    Target::Aggregate => SchemaObject::Aggregate { keyspace,
+                                                                   name,
+                                                                   argument_types },
+                    _ => SchemaObject::Function { keyspace,
+                                                  name,
+                                                  argument_types },
+                }
+            }
+        }
+    }
+}
+
+/// Schema element which has been changed. Argument types of functions and aggregates
+/// are CQL types, e.g. `frozen<list<int>>`, since overloads differ only in them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SchemaObject {
+    Keyspace {
+        keyspace: String,
+    },
+    Table {
+        keyspace: String,
+        name: String,
+    },
+    /// User defined type.
+    Type {
+        keyspace: String,
+        name: String,
+    },
+    Function {
+        keyspace: String,
+        name: String,
+        argument_types: Vec<String>,
+    },
+    Aggregate {
+        keyspace: String,
+        name: String,
+        argument_types: Vec<String>,
+    },
+}
+
+impl SchemaObject {
+    /// Returns a keyspace of the element.
+    pub fn keyspace(&self) -> &str {
+        match *self {
+            SchemaObject::Keyspace { ref keyspace }
+            | SchemaObject::Table { ref keyspace, .. }
+            | SchemaObject::Type { ref keyspace, .. }
+            | SchemaObject::Function { ref keyspace, .. }
+            | SchemaObject::Aggregate { ref keyspace, .. } => keyspace,
+        }
+    }
+}
+
 /// Represents type of changes.
 // TODO: rename to SchemaChangeType
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ChangeType {
     Created,
     Updated,
@@ -208,14 +341,14 @@ impl FromCursor for ChangeType {
             CREATED => Ok(ChangeType::Created),
             UPDATED => Ok(ChangeType::Updated),
             DROPPED => Ok(ChangeType::Dropped),
-            _ => Err("Unexpected schema change type".into()),
+            _ => Err(error::Error::Protocol("Unexpected schema change type".into())),
         })
     }
 }
 
 /// Refers to a target of changes were made.
 // TODO: rename to SchemaChangeTarget
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Target {
     Keyspace,
     Table,
@@ -232,13 +365,13 @@ impl FromCursor for Target {
             TYPE => Ok(Target::Type),
             FUNCTION => Ok(Target::Function),
             AGGREGATE => Ok(Target::Aggregate),
-            _ => Err("Unexpected schema change target".into()),
+            _ => Err(error::Error::Protocol("Unexpected schema change target".into())),
         })
     }
 }
 
 /// Option that contains an information about changes were made.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ChangeSchemeOptions {
     /// Changes related to keyspaces. Contains keyspace name.
     Keyspace(String),
@@ -485,8 +618,9 @@ mod server_event {
         let event = ServerEvent::from_cursor(&mut c).unwrap();
         match event {
             ServerEvent::TopologyChange(ref tc) => {
-                assert_eq!(tc.change_type, TopologyChangeType::NewNode);
-                assert_eq!(tc.addr.addr, "127.0.0.1:1".parse().unwrap());
+                assert_eq!(*tc, TopologyChange::NewNode("127.0.0.1:1".parse().unwrap()));
+                assert_eq!(tc.change_type(), TopologyChangeType::NewNode);
+                assert_eq!(tc.addr(), "127.0.0.1:1".parse().unwrap());
             }
             _ => panic!("should be topology change event"),
         }
@@ -542,8 +676,8 @@ mod server_event {
         let event = ServerEvent::from_cursor(&mut c).unwrap();
         match event {
             ServerEvent::TopologyChange(ref tc) => {
-                assert_eq!(tc.change_type, TopologyChangeType::RemovedNode);
-                assert_eq!(tc.addr.addr, "127.0.0.1:1".parse().unwrap());
+                assert_eq!(*tc, TopologyChange::RemovedNode("127.0.0.1:1".parse().unwrap()));
+                assert_eq!(tc.change_type(), TopologyChangeType::RemovedNode);
             }
             _ => panic!("should be topology change event"),
         }
@@ -587,8 +721,8 @@ mod server_event {
         let event = ServerEvent::from_cursor(&mut c).unwrap();
         match event {
             ServerEvent::StatusChange(ref tc) => {
-                assert_eq!(tc.change_type, StatusChangeType::Up);
-                assert_eq!(tc.addr.addr, "127.0.0.1:1".parse().unwrap());
+                assert_eq!(*tc, StatusChange::Up("127.0.0.1:1".parse().unwrap()));
+                assert_eq!(tc.change_type(), StatusChangeType::Up);
             }
             _ => panic!("should be status change up"),
         }
@@ -634,8 +768,9 @@ mod server_event {
         let event = ServerEvent::from_cursor(&mut c).unwrap();
         match event {
             ServerEvent::StatusChange(ref tc) => {
-                assert_eq!(tc.change_type, StatusChangeType::Down);
-                assert_eq!(tc.addr.addr, "127.0.0.1:1".parse().unwrap());
+                assert_eq!(*tc, StatusChange::Down("127.0.0.1:1".parse().unwrap()));
+                assert_eq!(tc.change_type(), StatusChangeType::Down);
+                assert_eq!(tc.addr(), "127.0.0.1:1".parse().unwrap());
             }
             _ => panic!("should be status change down"),
         }
@@ -1669,4 +1804,37 @@ mod server_event {
             _ => panic!("should be schema change"),
         }
     }
+
+    #[test]
+    fn schema_object() {
+        use crate::frame::IntoBytes;
+        use crate::types::CStringList;
+
+        let mut body = vec![];
+        for string in &["SCHEMA_CHANGE", "DROPPED", "AGGREGATE", "my_ks", "average"] {
+            body.extend(CString::new(string.to_string()).into_cbytes());
+        }
+        let types = vec!["int".to_string(), "frozen<list<text>>".to_string()];
+        body.extend(CStringList { list: types.iter().cloned().map(CString::new).collect() }
+                        .into_cbytes());
+
+        let change = match ServerEvent::from_cursor(&mut Cursor::new(&body[..])).unwrap() {
+            ServerEvent::SchemaChange(change) => change,
+            event => panic!("Unexpected {:?}", event),
+        };
+        let object = change.object();
+        assert_eq!(object,
+                   SchemaObject::Aggregate { keyspace: "my_ks".into(),
+                                             name: "average".into(),
+                                             argument_types: types });
+        assert_eq!(object.keyspace(), "my_ks");
+
+        let change = SchemaChange { change_type: ChangeType::Created,
+                                    target: Target::Type,
+                                    options: ChangeSchemeOptions::TableType(("ks".into(),
+                                                                             "address".into())) };
+        assert_eq!(change.object(),
+                   SchemaObject::Type { keyspace: "ks".into(),
+                                        name: "address".into() });
+    }
 }
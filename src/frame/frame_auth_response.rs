@@ -1,44 +1,107 @@
-use rand;
+use bytes::BufMut;
 
-use crate::types::CBytes;
+use crate::authenticator::SecretVec;
+use crate::error;
+use crate::types::{CBytes, INT_LEN};
 use crate::frame::*;
 
-#[derive(Debug)]
 pub struct BodyReqAuthResponse {
     data: CBytes,
 }
 
+impl fmt::Debug for BodyReqAuthResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BodyReqAuthResponse { data: [REDACTED] }")
+    }
+}
+
+pub type AuthResponse = BodyReqAuthResponse;
+
 impl BodyReqAuthResponse {
     pub fn new(data: CBytes) -> BodyReqAuthResponse {
         BodyReqAuthResponse { data: data }
     }
+
+    /// Creates a body with SASL PLAIN token `\0username\0password` which is expected
+    /// by `PasswordAuthenticator`. Credentials cannot contain NUL characters since
+    /// they separate parts of the token. The body is not zeroed when dropped,
+    /// `Frame::new_req_auth_response_plain` doesn't keep the token outside a frame.
+    pub fn plain(username: &str, password: &str) -> error::Result<BodyReqAuthResponse> {
+        plain_token(username, password)
+            .map(|token| BodyReqAuthResponse::new(CBytes::new(token.expose_secret().to_vec())))
+    }
+}
+
+/// Returns SASL PLAIN token `\0username\0password`, see `AuthResponse::plain`.
+pub fn plain_token(username: &str, password: &str) -> error::Result<SecretVec> {
+    if username.contains('\0') || password.contains('\0') {
+        let msg = "SASL PLAIN credentials cannot contain NUL characters";
+        return Err(error::Error::InvalidRequest(msg.into()));
+    }
+
+    let mut token = Vec::with_capacity(2 + username.len() + password.len());
+    token.push(0);
+    token.extend_from_slice(username.as_bytes());
+    token.push(0);
+    token.extend_from_slice(password.as_bytes());
+    Ok(SecretVec::new(token))
 }
 
 impl IntoBytes for BodyReqAuthResponse {
     fn into_cbytes(&self) -> Vec<u8> {
         self.data.into_cbytes()
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        self.data.serialize_into(buf);
+    }
+
+    fn serialized_len(&self) -> usize {
+        self.data.serialized_len()
+    }
+}
+
+impl TryIntoBytes for BodyReqAuthResponse {
+    fn check(&self) -> error::Result<()> {
+        self.data.check()
+    }
 }
 
 // Frame implementation related to BodyReqStartup
 
 impl Frame {
-    /// Creates new frame of type `AuthResponse`.
+    /// Creates new frame of type `AuthResponse`. `bytes` are zeroed once they are copied
+    /// into the frame.
     pub fn new_req_auth_response(bytes: Vec<u8>) -> Frame {
+        Frame::new_req_auth_response_with_token(SecretVec::new(bytes).expose_secret())
+    }
+
+    /// Creates new frame of type `AuthResponse` with SASL PLAIN credentials,
+    /// see `AuthResponse::plain`.
+    pub fn new_req_auth_response_plain(username: &str, password: &str) -> error::Result<Frame> {
+        plain_token(username, password)
+            .map(|token| Frame::new_req_auth_response_with_token(token.expose_secret()))
+    }
+
+    fn new_req_auth_response_with_token(token: &[u8]) -> Frame {
         let version = Version::Request;
-        let flag = Flag::Ignore;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::AuthResponse;
-        let body = BodyReqAuthResponse::new(CBytes::new(bytes));
+
+        // the body is written at once, so a token isn't left in a reallocated buffer
+        let mut body = Vec::with_capacity(INT_LEN + token.len());
+        body.put_i32(token.len() as i32);
+        body.put_slice(token);
 
         Frame { version: version,
-                flags: vec![flag],
+                flags: Flags::empty(),
                 stream: stream,
                 opcode: opcode,
-                body: body.into_cbytes(),
+                body: body.into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
     }
 }
 
@@ -55,15 +118,29 @@ mod tests {
         assert_eq!(body.into_cbytes(), vec![0, 0, 0, 3, 1, 2, 3]);
     }
 
+    #[test]
+    fn plain() {
+        let body = AuthResponse::plain("user", "pass").unwrap();
+        assert_eq!(body.into_cbytes(), b"\0\0\0\x0a\0user\0pass".to_vec());
+        assert!(AuthResponse::plain("us\0er", "pass").is_err());
+        assert!(AuthResponse::plain("user", "pa\0ss").is_err());
+        assert!(!format!("{:?}", body).contains("pass"));
+
+        let frame = Frame::new_req_auth_response_plain("user", "").unwrap();
+        assert_eq!(frame.opcode, Opcode::AuthResponse);
+        assert_eq!(frame.body, b"\0\0\0\x06\0user\0".to_vec());
+        assert!(!format!("{:?}", frame).contains("user"));
+    }
+
     #[test]
     fn frame_body_req_auth_response() {
         let bytes = vec![1, 2, 3];
         let frame = Frame::new_req_auth_response(bytes);
 
         assert_eq!(frame.version, Version::Request);
-        assert_eq!(frame.flags, vec![Flag::Ignore]);
+        assert_eq!(frame.flags, Flags::empty());
         assert_eq!(frame.opcode, Opcode::AuthResponse);
-        assert_eq!(frame.body, &[0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(frame.body, &[0, 0, 0, 3, 1, 2, 3][..]);
         assert_eq!(frame.tracing_id, None);
         assert_eq!(frame.warnings.len(), 0);
     }
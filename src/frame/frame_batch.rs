@@ -1,6 +1,10 @@
-use rand;
+use std::io::Cursor;
 
-use super::{Frame, Flag, IntoBytes, AsByte, FromSingleByte, Opcode, Version};
+use bytes::BufMut;
+
+use super::{random_stream, Frame, Flags, IntoBytes, AsByte, FromCursor, FromSingleByte, Opcode,
+            TryIntoBytes, Version};
+use crate::error;
 use crate::query::QueryFlags;
 use crate::types::*;
 use crate::consistency::Consistency;
@@ -14,40 +18,162 @@ pub struct BodyReqBatch {
     pub consistency: Consistency,
     /// **IMPORTANT NOTE:** with names flag does not work and should not be used.
     /// https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L413
-    pub query_flags: Vec<QueryFlags>,
+    pub query_flags: QueryFlags,
     pub serial_consistency: Option<Consistency>,
     pub timestamp: Option<i64>,
+    /// Keyspace which non-qualified tables of batched queries belong to (protocol v5 only).
+    pub keyspace: Option<String>,
+    /// Current time in seconds which should be used by a server for TTL calculations
+    /// (protocol v5 only).
+    pub now_in_seconds: Option<i32>,
 }
 
 impl IntoBytes for BodyReqBatch {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
-
-        bytes.push(self.batch_type.as_byte());
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut bytes);
+        bytes
+    }
 
-        bytes.extend_from_slice(to_short(self.queries.len() as i16).as_slice());
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_u8(self.batch_type.as_byte());
 
-        bytes = self.queries.iter().fold(bytes, |mut _bytes, q| {
-            _bytes.extend_from_slice(q.into_cbytes().as_slice());
-            _bytes
-        });
+        buf.put_i16(self.queries.len() as i16);
+        for query in &self.queries {
+            query.serialize_into(buf);
+        }
 
-        bytes.extend_from_slice(self.consistency.into_cbytes().as_slice());
+        self.consistency.serialize_into(buf);
 
-        let flag_byte = self.query_flags.iter()
-                            .fold(0, |mut _bytes, f| _bytes | f.as_byte());
-        bytes.push(flag_byte);
+        let mut flags = self.query_flags;
+        flags.set(QueryFlags::WITH_KEYSPACE, self.keyspace.is_some());
+        flags.set(QueryFlags::WITH_NOW_IN_SECONDS, self.now_in_seconds.is_some());
+        if cfg!(feature = "v5") {
+            buf.put_u32(flags.bits());
+        } else {
+            buf.put_u8(flags.as_byte());
+        }
 
         if let Some(ref serial_consistency) = self.serial_consistency {
-            bytes.extend_from_slice(serial_consistency.into_cbytes().as_slice());
+            serial_consistency.serialize_into(buf);
         }
 
-        if let Some(ref timestamp) = self.timestamp {
-            //bytes.extend_from_slice(to_bigint(timestamp.clone()).as_slice());
-            bytes.extend_from_slice(to_bigint(*timestamp).as_slice());
+        if let Some(timestamp) = self.timestamp {
+            buf.put_i64(timestamp);
         }
 
-        bytes
+        if cfg!(feature = "v5") {
+            if let Some(ref keyspace) = self.keyspace {
+                put_string(buf, keyspace);
+            }
+
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                buf.put_i32(now_in_seconds);
+            }
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        let mut len = 1 + SHORT_LEN;
+        len += self.queries.iter().map(BatchQuery::serialized_len).sum::<usize>();
+        len += self.consistency.serialized_len();
+        len += if cfg!(feature = "v5") { INT_LEN } else { 1 };
+        len += self.serial_consistency.map_or(0, |_| SHORT_LEN);
+        len += self.timestamp.map_or(0, |_| LONG_LEN);
+        if cfg!(feature = "v5") {
+            len += self.keyspace.as_ref().map_or(0, |keyspace| SHORT_LEN + keyspace.len());
+            len += self.now_in_seconds.map_or(0, |_| INT_LEN);
+        }
+        len
+    }
+}
+
+impl TryIntoBytes for BodyReqBatch {
+    fn check(&self) -> error::Result<()> {
+        check_max_len(self.queries.len(), SHORT_MAX)?;
+
+        let optional = [(QueryFlags::WITH_SERIAL_CONSISTENCY,
+                         self.serial_consistency.is_some(),
+                         "serial consistency"),
+                        (QueryFlags::WITH_DEFAULT_TIMESTAMP,
+                         self.timestamp.is_some(),
+                         "timestamp")];
+        for &(flag, is_present, name) in optional.iter() {
+            if self.query_flags.contains(flag) != is_present {
+                let msg = format!("{:?} doesn't match presence of {}", flag, name);
+                return Err(error::Error::InvalidRequest(msg));
+            }
+        }
+
+        if !cfg!(feature = "v5") && (self.keyspace.is_some() || self.now_in_seconds.is_some()) {
+            let msg = "Batch keyspace and now_in_seconds are supported only by protocol v5";
+            return Err(error::Error::InvalidRequest(msg.into()));
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            check_max_len(keyspace.len(), SHORT_MAX)?;
+        }
+
+        let with_names = self.query_flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES);
+        self.queries.iter().try_for_each(|query| {
+                               if query.values.with_names() != with_names {
+                                   let msg = "WITH_NAMES_FOR_VALUES flag doesn't match values";
+                                   return Err(error::Error::InvalidRequest(msg.into()));
+                               }
+                               query.check()
+                           })
+    }
+}
+
+impl FromCursor for BodyReqBatch {
+    /// Values of batched queries are parsed as values without names since
+    /// `WithNamesForValues` flag is not supported for batches (see `BatchQuery::values`).
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqBatch> {
+        let batch_type = BatchType::from_cursor(cursor)?;
+        let queries_count = CIntShort::from_cursor(cursor)?;
+        // every query has at least a kind, a query string or an id and a number of values
+        check_count(cursor, i64::from(queries_count), 1 + 2 * SHORT_LEN)?;
+        let queries = (0..queries_count).map(|_| BatchQuery::from_cursor(cursor))
+                                        .collect::<error::Result<Vec<_>>>()?;
+        let consistency = Consistency::from_cursor(cursor)?;
+
+        let query_flags = if cfg!(feature = "v5") {
+            QueryFlags::from_bits_truncate(CInt::from_cursor(cursor)? as u32)
+        } else {
+            QueryFlags::from_byte_truncate(cursor_next_value(cursor, 1)?[0])
+        };
+        if query_flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES) {
+            return Err(error::Error::Protocol("Batch values with names are not supported".into()));
+        }
+
+        let serial_consistency = if query_flags.contains(QueryFlags::WITH_SERIAL_CONSISTENCY) {
+            Some(Consistency::from_cursor(cursor)?)
+        } else {
+            None
+        };
+        let timestamp = if query_flags.contains(QueryFlags::WITH_DEFAULT_TIMESTAMP) {
+            Some(CLong::from_cursor(cursor)?)
+        } else {
+            None
+        };
+        let keyspace = if query_flags.contains(QueryFlags::WITH_KEYSPACE) {
+            Some(CString::from_cursor(cursor)?.into_plain())
+        } else {
+            None
+        };
+        let now_in_seconds = if query_flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+            Some(CInt::from_cursor(cursor)?)
+        } else {
+            None
+        };
+
+        Ok(BodyReqBatch { batch_type,
+                          queries,
+                          consistency,
+                          query_flags,
+                          serial_consistency,
+                          timestamp,
+                          keyspace,
+                          now_in_seconds, })
     }
 }
 
@@ -75,6 +201,15 @@ impl FromSingleByte for BatchType {
     }
 }
 
+impl FromCursor for BatchType {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BatchType> {
+        match cursor_next_value(cursor, 1)?[0] {
+            byte @ 0..=2 => Ok(BatchType::from_byte(byte)),
+            byte => Err(error::Error::Protocol(format!("Unexpected batch type {}", byte))),
+        }
+    }
+}
+
 impl AsByte for BatchType {
     fn as_byte(&self) -> u8 {
         match *self {
@@ -111,46 +246,167 @@ pub enum BatchQuerySubj {
 
 impl IntoBytes for BatchQuery {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut bytes);
+        bytes
+    }
 
+    fn serialize_into(&self, buf: &mut impl BufMut) {
         // kind
-        if self.is_prepared {
-            bytes.push(1);
-        } else {
-            bytes.push(0);
-        }
+        buf.put_u8(u8::from(self.is_prepared));
 
         match self.subject {
-            BatchQuerySubj::PreparedId(ref s) => {
-                bytes.extend_from_slice(s.into_cbytes().as_slice());
-            }
-            BatchQuerySubj::QueryString(ref s) => {
-                bytes.extend_from_slice(s.into_cbytes().as_slice());
-            }
+            BatchQuerySubj::PreparedId(ref s) => s.serialize_into(buf),
+            BatchQuerySubj::QueryString(ref s) => s.serialize_into(buf),
         }
 
-        bytes.extend_from_slice(to_short(self.values.len() as i16).as_slice());
+        buf.put_i16(self.values.len() as i16);
+        self.values.serialize_into(buf);
+    }
 
-        bytes.extend_from_slice(self.values.into_cbytes().as_slice());
+    fn serialized_len(&self) -> usize {
+        let subject_len = match self.subject {
+            BatchQuerySubj::PreparedId(ref s) => s.serialized_len(),
+            BatchQuerySubj::QueryString(ref s) => s.serialized_len(),
+        };
+        1 + subject_len + SHORT_LEN + self.values.serialized_len()
+    }
+}
 
-        bytes
+impl TryIntoBytes for BatchQuery {
+    fn check(&self) -> error::Result<()> {
+        match self.subject {
+            BatchQuerySubj::PreparedId(ref id) => id.check()?,
+            BatchQuerySubj::QueryString(ref query) => query.check()?,
+        }
+        self.values.check()
+    }
+}
+
+impl FromCursor for BatchQuery {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BatchQuery> {
+        let (is_prepared, subject) = match cursor_next_value(cursor, 1)?[0] {
+            0 => (false, BatchQuerySubj::QueryString(CStringLong::from_cursor(cursor)?)),
+            1 => (true, BatchQuerySubj::PreparedId(CBytesShort::from_cursor(cursor)?)),
+            kind => {
+                return Err(error::Error::Protocol(format!("Unexpected batch query kind {}",
+                                                          kind)))
+            }
+        };
+        let values = QueryValues::from_cursor_with_names(cursor, false)?;
+
+        Ok(BatchQuery { is_prepared,
+                        subject,
+                        values, })
     }
 }
 
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_req_batch(query: BodyReqBatch, flags: Vec<Flag>) -> Frame {
+    pub fn new_req_batch(query: BodyReqBatch, flags: Flags) -> Frame {
         let version = Version::Request;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Batch;
 
         Frame { version: version,
                 flags: flags,
                 stream: stream,
                 opcode: opcode,
-                body: query.into_cbytes(),
+                body: query.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
+    }
+
+    /// Creates new frame of type `BATCH`, e.g. from a result of `BatchQueryBuilder`.
+    /// Unlike `new_req_batch` the batch is validated, so flags which don't match
+    /// optional fields or fields which are not supported by a protocol version
+    /// are reported instead of being sent to a server.
+    pub fn new_batch(batch: &BodyReqBatch, flags: Flags) -> error::Result<Frame> {
+        Ok(Frame { version: Version::Request,
+                   flags,
+                   stream: random_stream(),
+                   opcode: Opcode::Batch,
+                   body: batch.try_into_cbytes()?.into(),
+                   // for request frames it's always None
+                   tracing_id: None,
+                   warnings: vec![],
+                   custom_payload: Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::BatchQueryBuilder;
+    use crate::types::value::Value;
+
+    #[test]
+    fn batch_from_cursor() {
+        let counter_values = vec![Value::new_null(), Value::new_not_set()];
+        let prepared_values = QueryValues::SimpleValues(vec![Value::from(1_i32)]);
+        let batch = BatchQueryBuilder::new().batch_type(BatchType::Counter)
+                                            .add_query("UPDATE t SET c = c + 1 WHERE k = ?",
+                                                       QueryValues::SimpleValues(counter_values))
+                                            .add_query_prepared(CBytesShort::new(vec![1, 2]),
+                                                                prepared_values)
+                                            .consistency(Consistency::Quorum)
+                                            .serial_consistency(Some(Consistency::LocalSerial))
+                                            .timestamp(Some(42))
+                                            .finalize()
+                                            .unwrap();
+        let bytes = batch.into_cbytes();
+        assert_eq!(batch.serialized_len(), bytes.len());
+
+        let mut cursor = Cursor::new(bytes.as_slice());
+        let parsed = BodyReqBatch::from_cursor(&mut cursor).unwrap();
+        assert_eq!(parsed.batch_type, BatchType::Counter);
+        assert_eq!(parsed.queries.len(), 2);
+        assert!(parsed.queries[1].is_prepared);
+        assert_eq!(parsed.timestamp, Some(42));
+        assert_eq!(parsed.into_cbytes(), bytes);
+        assert_eq!(cursor.position() as usize, bytes.len());
+    }
+
+    #[test]
+    fn try_into_cbytes() {
+        let mut batch = BatchQueryBuilder::new().add_query("INSERT INTO t (a) VALUES (?)",
+                                                           QueryValues::from(vec![1_i32]))
+                                                .timestamp(Some(42))
+                                                .finalize()
+                                                .unwrap();
+        assert_eq!(batch.try_into_cbytes().unwrap(), batch.into_cbytes());
+
+        batch.timestamp = None;
+        assert!(matches!(batch.check(), Err(error::Error::InvalidRequest(_))));
+
+        batch.timestamp = Some(42);
+        let mut value = Value::new_normal(vec![0; 4]);
+        value.body = vec![0; 5].into();
+        batch.queries[0].values = QueryValues::SimpleValues(vec![value]);
+        assert!(matches!(batch.check(), Err(error::Error::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn new_batch() {
+        let mut batch = BatchQueryBuilder::new().add_query("INSERT INTO t (a) VALUES (?)",
+                                                           QueryValues::from(vec![1_i32]))
+                                                .serial_consistency(Some(Consistency::Serial))
+                                                .finalize()
+                                                .unwrap();
+        let frame = Frame::new_batch(&batch, Flags::empty()).unwrap();
+        assert_eq!(frame.opcode, Opcode::Batch);
+        assert_eq!(frame.body, batch.into_cbytes());
+
+        batch.serial_consistency = None;
+        assert!(Frame::new_batch(&batch, Flags::empty()).is_err());
+    }
+
+    #[test]
+    fn invalid_batch_type() {
+        let bytes = [3, 0, 0];
+        let mut cursor = Cursor::new(&bytes[..]);
+        assert!(BodyReqBatch::from_cursor(&mut cursor).is_err());
     }
 }
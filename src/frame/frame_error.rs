@@ -2,11 +2,15 @@
 //! (https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L1011)
 //! which server could respond to client.
 
+use std::collections::HashMap;
 use std::io;
+use std::net::IpAddr;
 use std::result;
 
 use crate::error;
+use crate::error::decode_context;
 use crate::consistency::Consistency;
+use crate::types::data_serialization_types::decode_inet;
 use crate::types::*;
 use crate::frame::traits::FromCursor;
 use crate::frame::Frame;
@@ -30,11 +34,68 @@ pub struct CDRSError {
     pub additional_info: AdditionalErrorInfo,
 }
 
+impl CDRSError {
+    /// Shows if the same request may succeed if it's retried, e.g. on another node or
+    /// after a pause. The request itself is not wrong in this case, however timeouts
+    /// should be retried only if the request is idempotent and `Unprepared` requires
+    /// preparing the statement again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.additional_info,
+                 AdditionalErrorInfo::Server(_)
+                 | AdditionalErrorInfo::Unavailable(_)
+                 | AdditionalErrorInfo::Overloaded(_)
+                 | AdditionalErrorInfo::IsBootstrapping(_)
+                 | AdditionalErrorInfo::Truncate(_)
+                 | AdditionalErrorInfo::WriteTimeout(_)
+                 | AdditionalErrorInfo::ReadTimeout(_)
+                 | AdditionalErrorInfo::Unprepared(_))
+    }
+
+    /// Shows if a coordinator is overloaded and the request should be sent to another node.
+    pub fn is_overloaded(&self) -> bool {
+        matches!(self.additional_info, AdditionalErrorInfo::Overloaded(_))
+    }
+
+    /// Shows if a coordinator is bootstrapping and the request should be sent
+    /// to another node.
+    pub fn is_bootstrapping(&self) -> bool {
+        matches!(self.additional_info, AdditionalErrorInfo::IsBootstrapping(_))
+    }
+
+    /// Shows if not enough replicas are alive to achieve a requested consistency.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self.additional_info, AdditionalErrorInfo::Unavailable(_))
+    }
+
+    /// Shows if replicas haven't responded to a read or a write in time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.additional_info,
+                 AdditionalErrorInfo::ReadTimeout(_) | AdditionalErrorInfo::WriteTimeout(_))
+    }
+
+    /// Shows if a prepared statement is not known by a node and should be prepared again.
+    pub fn is_unprepared(&self) -> bool {
+        matches!(self.additional_info, AdditionalErrorInfo::Unprepared(_))
+    }
+
+    /// Returns an id of a statement which should be prepared again if the error
+    /// is `Unprepared`. The id can be compared with ids of cached prepared statements.
+    pub fn unprepared_id(&self) -> Option<&CBytesShort> {
+        match self.additional_info {
+            AdditionalErrorInfo::Unprepared(ref err) => Some(&err.id),
+            _ => None,
+        }
+    }
+}
+
 impl FromCursor for CDRSError {
     fn from_cursor(mut cursor: &mut io::Cursor<&[u8]>) -> error::Result<CDRSError> {
         let error_code = CInt::from_cursor(&mut cursor)?;
         let message = CString::from_cursor(&mut cursor)?;
-        let additional_info = AdditionalErrorInfo::from_cursor_with_code(&mut cursor, error_code)?;
+        let entity = format_args!("error {:#06x} info", error_code);
+        let additional_info = decode_context(cursor, entity, |cursor| {
+                                  AdditionalErrorInfo::from_cursor_with_code(cursor, error_code)
+                              })?;
 
         Ok(CDRSError { error_code: error_code,
                        message: message,
@@ -57,7 +118,8 @@ pub enum AdditionalErrorInfo {
     WriteTimeout(WriteTimeoutError),
     ReadTimeout(ReadTimeoutError),
     ReadFailure(ReadFailureError),
-    FunctionFailure(FunctionFailureError),
+    // boxed as the largest variant, so `error::Error` stays small
+    FunctionFailure(Box<FunctionFailureError>),
     WriteFailure(WriteFailureError),
     Syntax(SimpleError),
     Unauthorized(SimpleError),
@@ -102,9 +164,9 @@ impl AdditionalErrorInfo {
             0x1300 => Ok(AdditionalErrorInfo::ReadFailure(
                 ReadFailureError::from_cursor(&mut cursor)?,
             )),
-            0x1400 => Ok(AdditionalErrorInfo::FunctionFailure(
+            0x1400 => Ok(AdditionalErrorInfo::FunctionFailure(Box::new(
                 FunctionFailureError::from_cursor(&mut cursor)?,
-            )),
+            ))),
             0x1500 => Ok(AdditionalErrorInfo::WriteFailure(
                 WriteFailureError::from_cursor(&mut cursor)?,
             )),
@@ -126,7 +188,9 @@ impl AdditionalErrorInfo {
             0x2500 => Ok(AdditionalErrorInfo::Unprepared(
                 UnpreparedError::from_cursor(&mut cursor)?,
             )),
-            code => Err(format!("Unexpected error code received: {}", code).into()),
+            code => {
+                Err(error::Error::Protocol(format!("Unexpected error code received: {}", code)))
+            }
         }
     }
 }
@@ -202,13 +266,14 @@ pub struct ReadTimeoutError {
     pub received: CInt,
     /// `i32` representing the number of replicas whose acknowledgement is required to achieve `cl`.
     pub blockfor: CInt,
-    data_present: u8,
+    /// Shows if the replica that was asked for data has responded.
+    pub data_present: bool,
 }
 
 impl ReadTimeoutError {
     /// Shows if replica has resonded to a query.
     pub fn replica_has_responded(&self) -> bool {
-        self.data_present != 0
+        self.data_present
     }
 }
 
@@ -217,7 +282,7 @@ impl FromCursor for ReadTimeoutError {
         let cl = Consistency::from_cursor(&mut cursor)?;
         let received = CInt::from_cursor(&mut cursor)?;
         let blockfor = CInt::from_cursor(&mut cursor)?;
-        let data_present = try_from_bytes(cursor_next_value(&mut cursor, 1)?.as_slice())? as u8;
+        let data_present = cursor_next_value(cursor, 1)?[0] != 0;
 
         Ok(ReadTimeoutError { cl: cl,
                               received: received,
@@ -237,13 +302,16 @@ pub struct ReadFailureError {
     pub blockfor: CInt,
     /// Represents the number of nodes that experience a failure while executing the request.
     pub num_failures: CInt,
-    data_present: u8,
+    /// Failure codes of replicas which failed (protocol v5 only, empty otherwise).
+    pub reasons: HashMap<IpAddr, u16>,
+    /// Shows if the replica that was asked for data has responded.
+    pub data_present: bool,
 }
 
 impl ReadFailureError {
     /// Shows if replica has resonded to a query.
     pub fn replica_has_responded(&self) -> bool {
-        self.data_present != 0
+        self.data_present
     }
 }
 
@@ -252,13 +320,14 @@ impl FromCursor for ReadFailureError {
         let cl = Consistency::from_cursor(&mut cursor)?;
         let received = CInt::from_cursor(&mut cursor)?;
         let blockfor = CInt::from_cursor(&mut cursor)?;
-        let num_failures = CInt::from_cursor(&mut cursor)?;
-        let data_present = try_from_bytes(cursor_next_value(&mut cursor, 1)?.as_slice())? as u8;
+        let (num_failures, reasons) = failures_from_cursor(cursor)?;
+        let data_present = cursor_next_value(cursor, 1)?[0] != 0;
 
         Ok(ReadFailureError { cl: cl,
                               received: received,
                               blockfor: blockfor,
                               num_failures: num_failures,
+                              reasons,
                               data_present: data_present, })
     }
 }
@@ -298,6 +367,8 @@ pub struct WriteFailureError {
     pub blockfor: CInt,
     /// Represents the number of nodes that experience a failure while executing the request.
     pub num_failures: CInt,
+    /// Failure codes of replicas which failed (protocol v5 only, empty otherwise).
+    pub reasons: HashMap<IpAddr, u16>,
     /// describes the type of the write that failed.
     pub write_type: WriteType,
 }
@@ -307,20 +378,44 @@ impl FromCursor for WriteFailureError {
         let cl = Consistency::from_cursor(&mut cursor)?;
         let received = CInt::from_cursor(&mut cursor)?;
         let blockfor = CInt::from_cursor(&mut cursor)?;
-        let num_failures = CInt::from_cursor(&mut cursor)?;
+        let (num_failures, reasons) = failures_from_cursor(cursor)?;
         let write_type = WriteType::from_cursor(&mut cursor)?;
 
         Ok(WriteFailureError { cl: cl,
                                received: received,
                                blockfor: blockfor,
                                num_failures: num_failures,
+                               reasons,
                                write_type: write_type, })
     }
 }
 
+/// Decodes a number of failed replicas which is `<numfailures>` in protocol v4
+/// and a size of `<reasonmap>` of replica addresses and their failure codes in protocol v5.
+fn failures_from_cursor(cursor: &mut io::Cursor<&[u8]>)
+                        -> error::Result<(CInt, HashMap<IpAddr, u16>)> {
+    let num_failures = CInt::from_cursor(cursor)?;
+    if !cfg!(feature = "v5") {
+        return Ok((num_failures, HashMap::new()));
+    }
+
+    // every entry has at least a length of an address and a failure code
+    let len = check_count(cursor, i64::from(num_failures), 1 + SHORT_LEN)?;
+    let mut reasons = HashMap::with_capacity(len);
+    for _ in 0..len {
+        // [inetaddr] is an address without a port
+        let addr_len = cursor_next_value(cursor, 1)?[0];
+        let addr = decode_inet(cursor_next_value(cursor, u64::from(addr_len))?.as_slice())?;
+        let code = CIntShort::from_cursor(cursor)? as u16;
+        reasons.insert(addr, code);
+    }
+
+    Ok((num_failures, reasons))
+}
+
 /// Describes the type of the write that failed.
 /// [Read more...](https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L1118)
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum WriteType {
     /// The write was a non-batched non-counter write
     Simple,
@@ -335,20 +430,32 @@ pub enum WriteType {
     /// The failure occured during the write to the batch log when a (logged) batch
     /// write was requested.
     BatchLog,
+    /// The timeout occured during the Compare And Set write/update.
+    Cas,
+    /// The timeout occured when a write involves view update and failure to acquire
+    /// local view lock for key within timeout.
+    View,
+    /// The timeout occured when cdc_total_space_in_mb is exceeded when doing a write
+    /// to data tracked by cdc.
+    Cdc,
 }
 
 impl FromCursor for WriteType {
-    fn from_cursor(mut cursor: &mut io::Cursor<&[u8]>) -> error::Result<WriteType> {
-        CString::from_cursor(&mut cursor).and_then(|wt| match wt.as_str() {
-                                                       "SIMPLE" => Ok(WriteType::Simple),
-                                                       "BATCH" => Ok(WriteType::Batch),
-                                                       "UNLOGGED_BATCH" => {
-                                                           Ok(WriteType::UnloggedBatch)
-                                                       }
-                                                       "COUNTER" => Ok(WriteType::Counter),
-                                                       "BATCH_LOG" => Ok(WriteType::BatchLog),
-                                                       _ => Err("Unexpected write type".into()),
-                                                   })
+    fn from_cursor(cursor: &mut io::Cursor<&[u8]>) -> error::Result<WriteType> {
+        let write_type = CString::from_cursor(cursor)?;
+        match write_type.as_str() {
+            "SIMPLE" => Ok(WriteType::Simple),
+            "BATCH" => Ok(WriteType::Batch),
+            "UNLOGGED_BATCH" => Ok(WriteType::UnloggedBatch),
+            "COUNTER" => Ok(WriteType::Counter),
+            "BATCH_LOG" => Ok(WriteType::BatchLog),
+            "CAS" => Ok(WriteType::Cas),
+            "VIEW" => Ok(WriteType::View),
+            "CDC" => Ok(WriteType::Cdc),
+            write_type => {
+                Err(error::Error::Protocol(format!("Unexpected write type {}", write_type)))
+            }
+        }
     }
 }
 
@@ -383,6 +490,13 @@ pub struct UnpreparedError {
     pub id: CBytesShort,
 }
 
+impl UnpreparedError {
+    /// Returns bytes of the unknown statement id.
+    pub fn statement_id(&self) -> &[u8] {
+        self.id.as_plain().unwrap_or(&[])
+    }
+}
+
 impl FromCursor for UnpreparedError {
     fn from_cursor(mut cursor: &mut io::Cursor<&[u8]>) -> error::Result<UnpreparedError> {
         let id = CBytesShort::from_cursor(&mut cursor)?;
@@ -390,3 +504,171 @@ impl FromCursor for UnpreparedError {
         Ok(UnpreparedError { id: id })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::traits::IntoBytes;
+
+    fn error_body(code: CInt, info: Vec<u8>) -> Vec<u8> {
+        let mut body = to_int(code);
+        body.extend(CString::new("message".into()).into_cbytes());
+        body.extend(info);
+        body
+    }
+
+    /// `<numfailures>` in protocol v4 or `<reasonmap>` with a failure of 127.0.0.1
+    /// in protocol v5.
+    fn failures() -> Vec<u8> {
+        let mut failures = to_int(1);
+        if cfg!(feature = "v5") {
+            failures.extend(vec![4, 127, 0, 0, 1, 0, 3]);
+        }
+        failures
+    }
+
+    fn decode(body: &[u8]) -> CDRSError {
+        CDRSError::from_cursor(&mut io::Cursor::new(body)).unwrap()
+    }
+
+    #[test]
+    fn read_failure() {
+        let mut info = Consistency::Quorum.into_cbytes();
+        info.extend(to_int(1));
+        info.extend(to_int(2));
+        info.extend(failures());
+        info.push(1);
+        match decode(&error_body(0x1300, info)).additional_info {
+            AdditionalErrorInfo::ReadFailure(err) => {
+                assert_eq!(err.cl, Consistency::Quorum);
+                assert_eq!(err.received, 1);
+                assert_eq!(err.blockfor, 2);
+                assert_eq!(err.num_failures, 1);
+                assert_eq!(err.reasons.len(), if cfg!(feature = "v5") { 1 } else { 0 });
+                assert!(err.data_present);
+                assert!(err.replica_has_responded());
+            }
+            info => panic!("Unexpected error info {:?}", info),
+        }
+    }
+
+    #[test]
+    fn write_failure() {
+        let mut info = Consistency::LocalQuorum.into_cbytes();
+        info.extend(to_int(0));
+        info.extend(to_int(2));
+        info.extend(failures());
+        info.extend(CString::new("CAS".into()).into_cbytes());
+        match decode(&error_body(0x1500, info)).additional_info {
+            AdditionalErrorInfo::WriteFailure(err) => {
+                assert_eq!(err.cl, Consistency::LocalQuorum);
+                assert_eq!(err.received, 0);
+                assert_eq!(err.blockfor, 2);
+                assert_eq!(err.num_failures, 1);
+                assert_eq!(err.write_type, WriteType::Cas);
+            }
+            info => panic!("Unexpected error info {:?}", info),
+        }
+    }
+
+    #[test]
+    fn function_failure() {
+        let mut info = CString::new("ks".into()).into_cbytes();
+        info.extend(CString::new("fn".into()).into_cbytes());
+        info.extend(CStringList { list: vec![CString::new("int".into()),
+                                             CString::new("text".into())] }.into_cbytes());
+        match decode(&error_body(0x1400, info)).additional_info {
+            AdditionalErrorInfo::FunctionFailure(err) => {
+                assert_eq!(err.keyspace.as_str(), "ks");
+                assert_eq!(err.function.as_str(), "fn");
+                assert_eq!(err.arg_types.into_plain(), vec!["int", "text"]);
+            }
+            info => panic!("Unexpected error info {:?}", info),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "v5")]
+    fn failure_reasons() {
+        let mut info = Consistency::One.into_cbytes();
+        info.extend(to_int(0));
+        info.extend(to_int(1));
+        info.extend(to_int(2));
+        info.extend(vec![4, 10, 0, 0, 1, 0, 1]);
+        info.extend(vec![16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2]);
+        info.push(0);
+        match decode(&error_body(0x1300, info)).additional_info {
+            AdditionalErrorInfo::ReadFailure(err) => {
+                assert_eq!(err.num_failures, 2);
+                assert_eq!(err.reasons.get(&"10.0.0.1".parse::<IpAddr>().unwrap()), Some(&1));
+                assert_eq!(err.reasons.get(&"::1".parse::<IpAddr>().unwrap()), Some(&2));
+                assert!(!err.data_present);
+            }
+            info => panic!("Unexpected error info {:?}", info),
+        }
+
+        let mut info = Consistency::One.into_cbytes();
+        info.extend(to_int(0));
+        info.extend(to_int(1));
+        info.extend(to_int(2));
+        info.extend(vec![4, 10, 0, 0, 1, 0, 1]);
+        assert!(CDRSError::from_cursor(&mut io::Cursor::new(&error_body(0x1300, info)[..]))
+            .is_err());
+    }
+
+    #[test]
+    fn classification() {
+        let overloaded = decode(&error_body(0x1001, vec![]));
+        assert!(overloaded.is_overloaded());
+        assert!(overloaded.is_retryable());
+        assert!(!overloaded.is_bootstrapping());
+
+        let bootstrapping = decode(&error_body(0x1002, vec![]));
+        assert!(bootstrapping.is_bootstrapping());
+        assert!(bootstrapping.is_retryable());
+
+        let unprepared = decode(&error_body(0x2500, vec![0, 1, 7]));
+        assert!(unprepared.is_unprepared());
+        assert!(unprepared.is_retryable());
+        assert!(overloaded.unprepared_id().is_none());
+
+        let mut info = Consistency::One.into_cbytes();
+        info.extend(to_int(0));
+        info.extend(to_int(1));
+        info.push(0);
+        let timeout = decode(&error_body(0x1200, info));
+        assert!(timeout.is_timeout());
+        assert!(timeout.is_retryable());
+        assert!(!timeout.is_unavailable());
+
+        let syntax = decode(&error_body(0x2000, vec![]));
+        assert!(!syntax.is_retryable());
+        assert!(!syntax.is_unprepared());
+    }
+
+    #[test]
+    fn unprepared() {
+        let err = decode(&error_body(0x2500, vec![0, 2, 7, 8]));
+        assert_eq!(err.unprepared_id().and_then(CBytesShort::as_plain), Some(&[7, 8][..]));
+        match err.additional_info {
+            AdditionalErrorInfo::Unprepared(ref unprepared) => {
+                assert_eq!(unprepared.statement_id(), &[7, 8]);
+            }
+            ref info => panic!("Unexpected error info {:?}", info),
+        }
+    }
+
+    #[test]
+    fn unknown_write_type() {
+        let mut cursor = io::Cursor::new(&[0, 3, b'F', b'O', b'O'][..]);
+        assert!(matches!(WriteType::from_cursor(&mut cursor), Err(error::Error::Protocol(_))));
+    }
+
+    #[test]
+    fn unknown_error_code() {
+        let body = error_body(0x0042, vec![]);
+        let err = CDRSError::from_cursor(&mut io::Cursor::new(body.as_slice())).unwrap_err();
+        assert!(matches!(err.root(), error::Error::Protocol(_)));
+        assert!(err.to_string().starts_with("Cannot decode error 0x0042 info at byte 13"));
+    }
+}
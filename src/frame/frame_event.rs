@@ -71,8 +71,7 @@ mod tests {
 
         match event {
             ServerEvent::TopologyChange(ref tc) => {
-                assert_eq!(tc.change_type, TopologyChangeType::NewNode);
-                assert_eq!(tc.addr.addr, "127.0.0.1:1".parse().unwrap());
+                assert_eq!(*tc, TopologyChange::NewNode("127.0.0.1:1".parse().unwrap()));
             }
             _ => panic!("should be topology change event"),
         }
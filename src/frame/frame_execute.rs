@@ -1,14 +1,21 @@
-use rand;
+use std::borrow::Cow;
+use std::io::Cursor;
 
+use bytes::BufMut;
+
+use crate::error;
 use crate::types::*;
 use crate::frame::*;
+use crate::frame::frame_result::BodyResResultPrepared;
 use crate::query::QueryParams;
 
 /// The structure that represents a body of a frame of type `execute`.
 #[derive(Debug)]
 pub struct BodyReqExecute<'a> {
     /// Id of prepared query
-    id: &'a CBytesShort,
+    id: Cow<'a, CBytesShort>,
+    /// Id of result metadata of prepared query. It's sent only by protocol v5.
+    result_metadata_id: Option<Cow<'a, CBytesShort>>,
     /// Query paramaters which have the same meaning as one for `query`
     /// TODO: clarify if it is QueryParams or its shortened variant
     query_parameters: QueryParams,
@@ -17,40 +24,208 @@ pub struct BodyReqExecute<'a> {
 impl<'a> BodyReqExecute<'a> {
     /// The method which creates new instance of `BodyReqExecute`
     pub fn new(id: &CBytesShort, query_parameters: QueryParams) -> BodyReqExecute {
-        BodyReqExecute { id: id,
+        BodyReqExecute { id: Cow::Borrowed(id),
+                         result_metadata_id: None,
                          query_parameters: query_parameters, }
     }
+
+    /// Sets result metadata id which is required by protocol v5 to let a server detect
+    /// whether result metadata cached by a client is outdated.
+    pub fn with_result_metadata_id(mut self, result_metadata_id: &'a CBytesShort) -> Self {
+        self.result_metadata_id = Some(Cow::Borrowed(result_metadata_id));
+        self
+    }
+
+    /// Id of prepared query.
+    pub fn id(&self) -> &CBytesShort {
+        &self.id
+    }
+
+    /// Id of result metadata of prepared query if it was provided.
+    pub fn result_metadata_id(&self) -> Option<&CBytesShort> {
+        self.result_metadata_id.as_deref()
+    }
+
+    /// Query parameters.
+    pub fn query_parameters(&self) -> &QueryParams {
+        &self.query_parameters
+    }
+}
+
+impl<'a> TryIntoBytes for BodyReqExecute<'a> {
+    fn check(&self) -> error::Result<()> {
+        self.id.check()?;
+        if let Some(ref result_metadata_id) = self.result_metadata_id {
+            result_metadata_id.check()?;
+        }
+        self.query_parameters.check()
+    }
 }
 
 impl<'a> IntoBytes for BodyReqExecute<'a> {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = vec![];
-        v.extend_from_slice(self.id.into_cbytes().as_slice());
-        v.extend_from_slice(self.query_parameters.into_cbytes().as_slice());
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
         v
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        self.id.serialize_into(buf);
+        if cfg!(feature = "v5") {
+            // an empty id never matches so a server responds with actual metadata
+            match self.result_metadata_id {
+                Some(ref result_metadata_id) => result_metadata_id.serialize_into(buf),
+                None => buf.put_i16(0),
+            }
+        }
+        self.query_parameters.serialize_into(buf);
+    }
+
+    fn serialized_len(&self) -> usize {
+        let result_metadata_id_len = match self.result_metadata_id {
+            Some(ref result_metadata_id) if cfg!(feature = "v5") => {
+                result_metadata_id.serialized_len()
+            }
+            None if cfg!(feature = "v5") => SHORT_LEN,
+            _ => 0,
+        };
+        self.id.serialized_len() + result_metadata_id_len + self.query_parameters.serialized_len()
+    }
+}
+
+impl FromCursor for BodyReqExecute<'static> {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqExecute<'static>> {
+        let id = CBytesShort::from_cursor(cursor)?;
+        let result_metadata_id = if cfg!(feature = "v5") {
+            Some(Cow::Owned(CBytesShort::from_cursor(cursor)?))
+        } else {
+            None
+        };
+
+        Ok(BodyReqExecute { id: Cow::Owned(id),
+                            result_metadata_id,
+                            query_parameters: QueryParams::from_cursor(cursor)?, })
+    }
 }
 
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
     pub fn new_req_execute(id: &CBytesShort,
                            query_parameters: QueryParams,
-                           flags: Vec<Flag>)
-                           -> Frame {
+                           flags: Flags)
+                           -> error::Result<Frame> {
+        Frame::new_req_execute_with_metadata_id(id, None, query_parameters, flags)
+    }
+
+    /// **Note:** This function should be used internally for building query request frames.
+    /// Result metadata id is sent only if `v5` feature is enabled. An error is returned
+    /// if parameters cannot be serialized.
+    pub fn new_req_execute_with_metadata_id(id: &CBytesShort,
+                                            result_metadata_id: Option<&CBytesShort>,
+                                            query_parameters: QueryParams,
+                                            flags: Flags)
+                                            -> error::Result<Frame> {
         let version = Version::Request;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Execute;
         debug!("prepared statement id{:?} getting executed  with parameters  {:?}",
                id, query_parameters);
-        let body = BodyReqExecute::new(id, query_parameters);
-
-        Frame { version: version,
-                flags: flags,
-                stream: stream,
-                opcode: opcode,
-                body: body.into_cbytes(),
-                // for request frames it's always None
-                tracing_id: None,
-                warnings: vec![], }
+        let mut body = BodyReqExecute::new(id, query_parameters);
+        if let Some(result_metadata_id) = result_metadata_id {
+            body = body.with_result_metadata_id(result_metadata_id);
+        }
+
+        Ok(Frame { version,
+                   flags,
+                   stream,
+                   opcode,
+                   body: body.try_into_cbytes()?.into(),
+                   // for request frames it's always None
+                   tracing_id: None,
+                   warnings: vec![],
+                   custom_payload: Default::default(), })
+    }
+
+    /// Creates new frame of type `EXECUTE` which executes a prepared query. Result
+    /// metadata id of the prepared result is sent if `v5` feature is enabled.
+    pub fn new_execute(prepared: &BodyResResultPrepared,
+                       query_parameters: QueryParams,
+                       flags: Flags)
+                       -> error::Result<Frame> {
+        Frame::new_req_execute_with_metadata_id(&prepared.id,
+                                                prepared.result_metadata_id.as_ref(),
+                                                query_parameters,
+                                                flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::Consistency;
+    use crate::frame::frame_result::{PreparedMetadata, RowsMetadata};
+    use crate::query::{QueryParamsBuilder, QueryValues};
+    use crate::types::value::Value;
+
+    #[test]
+    fn execute_from_cursor() {
+        let id = CBytesShort::new(vec![1, 2, 3]);
+        let result_metadata_id = CBytesShort::new(vec![4]);
+        let values = QueryValues::SimpleValues(vec![Value::from("a"), Value::new_null()]);
+        let params = QueryParamsBuilder::new().consistency(Consistency::Two)
+                                              .values(values)
+                                              .finalize();
+        let body = BodyReqExecute::new(&id, params).with_result_metadata_id(&result_metadata_id);
+        let bytes = body.into_cbytes();
+        assert_eq!(body.serialized_len(), bytes.len());
+
+        let mut cursor = Cursor::new(bytes.as_slice());
+        let parsed = BodyReqExecute::from_cursor(&mut cursor).unwrap();
+        assert_eq!(parsed.id().as_plain(), Some(&[1, 2, 3][..]));
+        assert_eq!(parsed.result_metadata_id().is_some(), cfg!(feature = "v5"));
+        assert_eq!(parsed.query_parameters().values.as_ref().map(QueryValues::len), Some(2));
+        assert_eq!(parsed.into_cbytes(), bytes);
+    }
+
+    #[test]
+    fn skip_metadata() {
+        let id = CBytesShort::new(vec![1]);
+        let params = QueryParamsBuilder::new().skip_metadata(true).build().unwrap();
+        assert!(params.skips_metadata());
+        let frame = Frame::new_req_execute(&id, params, Flags::empty()).unwrap();
+
+        let parsed = BodyReqExecute::from_cursor(&mut Cursor::new(frame.body.as_ref())).unwrap();
+        assert!(parsed.query_parameters().skips_metadata());
+
+        let params = QueryParamsBuilder::new().skip_metadata(true)
+                                              .skip_metadata(false)
+                                              .finalize();
+        assert!(!params.skips_metadata());
+    }
+
+    #[test]
+    fn new_execute() {
+        let metadata = PreparedMetadata { flags: 0,
+                                          columns_count: 0,
+                                          pk_count: 0,
+                                          pk_indexes: vec![],
+                                          global_table_spec: None,
+                                          col_specs: vec![] };
+        let result_metadata = RowsMetadata::for_test(vec![]);
+        let prepared = BodyResResultPrepared { id: CBytesShort::new(vec![1, 2]),
+                                               result_metadata_id: Some(CBytesShort::new(vec![3])),
+                                               metadata,
+                                               result_metadata };
+        let params = QueryParamsBuilder::new().consistency(Consistency::Two).finalize();
+        let frame = Frame::new_execute(&prepared, params, Flags::empty()).unwrap();
+        assert_eq!(frame.opcode, Opcode::Execute);
+
+        let parsed = BodyReqExecute::from_cursor(&mut Cursor::new(frame.body.as_ref())).unwrap();
+        assert_eq!(parsed.id().as_plain(), Some(&[1, 2][..]));
+        if cfg!(feature = "v5") {
+            assert_eq!(parsed.result_metadata_id().and_then(CBytesShort::as_plain),
+                       Some(&[3][..]));
+        }
+        assert_eq!(parsed.query_parameters().consistency, Consistency::Two);
     }
 }
@@ -1,4 +1,4 @@
-use rand;
+use bytes::BufMut;
 
 use crate::frame::*;
 
@@ -10,6 +10,12 @@ impl IntoBytes for BodyReqOptions {
     fn into_cbytes(&self) -> Vec<u8> {
         vec![]
     }
+
+    fn serialize_into(&self, _buf: &mut impl BufMut) {}
+
+    fn serialized_len(&self) -> usize {
+        0
+    }
 }
 
 // Frame implementation related to BodyReqStartup
@@ -18,19 +24,19 @@ impl Frame {
     /// Creates new frame of type `options`.
     pub fn new_req_options() -> Frame {
         let version = Version::Request;
-        let flag = Flag::Ignore;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Options;
         let body: BodyReqOptions = Default::default();
 
         Frame { version: version,
-                flags: vec![flag],
+                flags: Flags::empty(),
                 stream: stream,
                 opcode: opcode,
-                body: body.into_cbytes(),
+                body: body.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
     }
 }
 
@@ -43,6 +49,6 @@ mod tests {
         let frame = Frame::new_req_options();
         assert_eq!(frame.version, Version::Request);
         assert_eq!(frame.opcode, Opcode::Options);
-        assert_eq!(frame.body, vec![]);
+        assert_eq!(frame.body, Vec::<u8>::new());
     }
 }
@@ -1,5 +1,8 @@
-use rand;
+use std::io::Cursor;
 
+use bytes::BufMut;
+
+use crate::error;
 use crate::types::*;
 use crate::frame::*;
 
@@ -14,19 +17,44 @@ impl BodyReqPrepare {
     pub fn new(query: String) -> BodyReqPrepare {
         BodyReqPrepare { query: CStringLong::new(query), }
     }
+
+    /// Query which should be prepared.
+    pub fn query(&self) -> &str {
+        self.query.as_str()
+    }
+}
+
+impl FromCursor for BodyReqPrepare {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqPrepare> {
+        CStringLong::from_cursor(cursor).map(|query| BodyReqPrepare { query })
+    }
 }
 
 impl IntoBytes for BodyReqPrepare {
     fn into_cbytes(&self) -> Vec<u8> {
         self.query.into_cbytes()
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        self.query.serialize_into(buf);
+    }
+
+    fn serialized_len(&self) -> usize {
+        self.query.serialized_len()
+    }
+}
+
+impl TryIntoBytes for BodyReqPrepare {
+    fn check(&self) -> error::Result<()> {
+        self.query.check()
+    }
 }
 
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_req_prepare(query: String, flags: Vec<Flag>) -> Frame {
+    pub fn new_req_prepare(query: String, flags: Flags) -> Frame {
         let version = Version::Request;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Prepare;
         let body = BodyReqPrepare::new(query);
 
@@ -34,9 +62,23 @@ impl Frame {
                 flags: flags,
                 stream: stream,
                 opcode: opcode,
-                body: body.into_cbytes(),
+                body: body.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_from_cursor() {
+        let bytes = BodyReqPrepare::new("SELECT * FROM t".into()).into_cbytes();
+        let mut cursor = Cursor::new(bytes.as_slice());
+        let parsed = BodyReqPrepare::from_cursor(&mut cursor).unwrap();
+        assert_eq!(parsed.query(), "SELECT * FROM t");
     }
 }
@@ -1,6 +1,8 @@
 #![warn(missing_docs)]
 //! Contains Query Frame related functionality.
-use rand;
+use std::io::Cursor;
+
+use bytes::BufMut;
 
 use crate::frame::*;
 use crate::consistency::Consistency;
@@ -28,25 +30,13 @@ impl BodyReqQuery {
            timestamp: Option<i64>)
            -> BodyReqQuery {
         // query flags
-        let mut flags: Vec<QueryFlags> = vec![];
-        if values.is_some() {
-            flags.push(QueryFlags::Value);
-        }
-        if with_names.unwrap_or(false) {
-            flags.push(QueryFlags::WithNamesForValues);
-        }
-        if page_size.is_some() {
-            flags.push(QueryFlags::PageSize);
-        }
-        if paging_state.is_some() {
-            flags.push(QueryFlags::WithPagingState);
-        }
-        if serial_consistency.is_some() {
-            flags.push(QueryFlags::WithSerialConsistency);
-        }
-        if timestamp.is_some() {
-            flags.push(QueryFlags::WithDefaultTimestamp);
-        }
+        let mut flags = QueryFlags::empty();
+        flags.set(QueryFlags::VALUE, values.is_some());
+        flags.set(QueryFlags::WITH_NAMES_FOR_VALUES, with_names.unwrap_or(false));
+        flags.set(QueryFlags::PAGE_SIZE, page_size.is_some());
+        flags.set(QueryFlags::WITH_PAGING_STATE, paging_state.is_some());
+        flags.set(QueryFlags::WITH_SERIAL_CONSISTENCY, serial_consistency.is_some());
+        flags.set(QueryFlags::WITH_DEFAULT_TIMESTAMP, timestamp.is_some());
 
         BodyReqQuery { query: CStringLong::new(query),
                        query_params: QueryParams { consistency,
@@ -56,17 +46,42 @@ impl BodyReqQuery {
                                                    page_size,
                                                    paging_state,
                                                    serial_consistency,
-                                                   timestamp, }, }
+                                                   timestamp,
+                                                   keyspace: None,
+                                                   now_in_seconds: None,
+                                                   continuous_paging: None, }, }
     }
 }
 
 impl IntoBytes for BodyReqQuery {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = vec![];
-        v.extend_from_slice(self.query.clone().into_cbytes().as_slice());
-        v.extend_from_slice(self.query_params.into_cbytes().as_slice());
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
         v
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        self.query.serialize_into(buf);
+        self.query_params.serialize_into(buf);
+    }
+
+    fn serialized_len(&self) -> usize {
+        self.query.serialized_len() + self.query_params.serialized_len()
+    }
+}
+
+impl TryIntoBytes for BodyReqQuery {
+    fn check(&self) -> error::Result<()> {
+        self.query.check()?;
+        self.query_params.check()
+    }
+}
+
+impl FromCursor for BodyReqQuery {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqQuery> {
+        Ok(BodyReqQuery { query: CStringLong::from_cursor(cursor)?,
+                          query_params: QueryParams::from_cursor(cursor)?, })
+    }
 }
 
 // Frame implementation related to BodyReqStartup
@@ -81,10 +96,10 @@ impl Frame {
                          paging_state: Option<CBytes>,
                          serial_consistency: Option<Consistency>,
                          timestamp: Option<i64>,
-                         flags: Vec<Flag>)
+                         flags: Flags)
                          -> Frame {
         let version = Version::Request;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Query;
         let body = BodyReqQuery::new(query,
                                      consistency,
@@ -99,22 +114,129 @@ impl Frame {
                 flags: flags,
                 stream: stream,
                 opcode: opcode,
-                body: body.into_cbytes(),
+                body: body.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
     }
 
-    /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_query(query: Query, flags: Vec<Flag>) -> Frame {
-        Frame::new_req_query(query.query,
-                             query.params.consistency,
-                             query.params.values,
-                             query.params.with_names,
-                             query.params.page_size,
-                             query.params.paging_state,
-                             query.params.serial_consistency,
-                             query.params.timestamp,
-                             flags)
+    /// Creates new frame of type `QUERY` with given parameters. Flags of optional
+    /// parameters are derived from parameters which are set, see `QueryParams::effective_flags`.
+    /// An error is returned if the query or parameters cannot be serialized.
+    pub fn new_req_query_with_params(query: &str,
+                                     query_params: QueryParams,
+                                     flags: Flags)
+                                     -> error::Result<Frame> {
+        let body = BodyReqQuery { query: CStringLong::new(query.to_string()),
+                                  query_params };
+
+        Ok(Frame { version: Version::Request,
+                   flags,
+                   stream: random_stream(),
+                   opcode: Opcode::Query,
+                   body: body.try_into_cbytes()?.into(),
+                   // for request frames it's always None
+                   tracing_id: None,
+                   warnings: vec![],
+                   custom_payload: Default::default(), })
+    }
+
+    /// Creates new frame of type `QUERY` from a query and its parameters.
+    pub fn new_query(query: Query, flags: Flags) -> error::Result<Frame> {
+        Frame::new_req_query_with_params(&query.query, query.params, flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::error;
+    use crate::query::QueryParamsBuilder;
+    use crate::types::value::Value;
+
+    #[test]
+    fn query_from_cursor() {
+        let mut values = HashMap::new();
+        values.insert("id".to_string(), Value::from(1_i32));
+        let body = BodyReqQuery::new("SELECT * FROM t WHERE id = :id".into(),
+                                     Consistency::LocalQuorum,
+                                     Some(QueryValues::NamedValues(values)),
+                                     Some(true),
+                                     Some(100),
+                                     Some(CBytes::new(vec![1, 2, 3])),
+                                     Some(Consistency::Serial),
+                                     Some(7));
+        let bytes = body.into_cbytes();
+        assert_eq!(body.serialized_len(), bytes.len());
+
+        let mut cursor = Cursor::new(bytes.as_slice());
+        let parsed = BodyReqQuery::from_cursor(&mut cursor).unwrap();
+        assert_eq!(parsed.query.as_str(), "SELECT * FROM t WHERE id = :id");
+        assert_eq!(parsed.query_params.with_names, Some(true));
+        assert_eq!(parsed.query_params.page_size, Some(100));
+        assert_eq!(parsed.query_params.timestamp, Some(7));
+        assert_eq!(parsed.into_cbytes(), bytes);
+        assert_eq!(cursor.position() as usize, bytes.len());
+    }
+
+    #[test]
+    fn try_into_cbytes() {
+        let mut body = BodyReqQuery::new("SELECT * FROM t".into(),
+                                         Consistency::One,
+                                         Some(QueryValues::SimpleValues(vec![Value::from(1_i32)])),
+                                         Some(false),
+                                         Some(100),
+                                         None,
+                                         None,
+                                         None);
+        assert_eq!(body.try_into_cbytes().unwrap(), body.into_cbytes());
+
+        // flags of optional fields are derived from the fields
+        body.query_params.page_size = None;
+        body.query_params.flags.insert(QueryFlags::WITH_NAMES_FOR_VALUES);
+        let bytes = body.try_into_cbytes().unwrap();
+        let parsed = BodyReqQuery::from_cursor(&mut Cursor::new(bytes.as_slice())).unwrap();
+        assert_eq!(parsed.query_params.page_size, None);
+        assert!(!parsed.query_params.flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES));
+
+        let long_name = "n".repeat(SHORT_MAX + 1);
+        let mut values = HashMap::new();
+        values.insert(long_name, Value::from(1_i32));
+        body.query_params.values = Some(QueryValues::NamedValues(values));
+        assert!(matches!(body.try_into_cbytes(),
+                         Err(error::Error::LengthOverflow { max: SHORT_MAX, .. })));
+    }
+
+    #[test]
+    fn new_query() {
+        let params = QueryParamsBuilder::new().consistency(Consistency::Quorum)
+                                              .values(QueryValues::from(vec![1_i32]))
+                                              .page_size(10)
+                                              .skip_metadata(true)
+                                              .build()
+                                              .unwrap();
+        let query = Query { query: "SELECT * FROM t WHERE id = ?".into(),
+                            params };
+        let frame = Frame::new_query(query, Flags::TRACING).unwrap();
+        assert_eq!(frame.opcode, Opcode::Query);
+        assert_eq!(frame.flags, Flags::TRACING);
+
+        let mut cursor = Cursor::new(frame.body.as_ref());
+        let body = BodyReqQuery::from_cursor(&mut cursor).unwrap();
+        assert_eq!(body.query.as_str(), "SELECT * FROM t WHERE id = ?");
+        assert_eq!(body.query_params.consistency, Consistency::Quorum);
+        assert_eq!(body.query_params.page_size, Some(10));
+        assert!(body.query_params.skips_metadata());
+        assert_eq!(cursor.position() as usize, frame.body.len());
+
+        // the keyspace is longer than [string] allows
+        let params = QueryParams { keyspace: Some("k".repeat(u16::MAX as usize + 1)),
+                                   ..Default::default() };
+        let frame = Frame::new_req_query_with_params("SELECT * FROM t", params, Flags::empty());
+        assert!(frame.is_err());
     }
 }
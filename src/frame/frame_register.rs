@@ -1,8 +1,9 @@
-use rand;
+use bytes::BufMut;
 
 use crate::frame::*;
 use crate::frame::events::SimpleServerEvent;
-use crate::types::{CString, CStringList};
+use crate::error;
+use crate::types::{check_max_len, put_string, SHORT_LEN, SHORT_MAX};
 
 /// The structure which represents a body of a frame of type `options`.
 pub struct BodyReqRegister {
@@ -11,32 +12,71 @@ pub struct BodyReqRegister {
 
 impl IntoBytes for BodyReqRegister {
     fn into_cbytes(&self) -> Vec<u8> {
-        let events_string_list =
-            CStringList { list: self.events.iter()
-                                    .map(|event| CString::new(event.as_string()))
-                                    .collect(), };
-        events_string_list.into_cbytes()
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
+        v
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_i16(self.events.len() as i16);
+        for event in &self.events {
+            put_string(buf, &event.as_string());
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN
+        + self.events
+              .iter()
+              .map(|event| SHORT_LEN + event.as_string().len())
+              .sum::<usize>()
+    }
+}
+
+impl TryIntoBytes for BodyReqRegister {
+    fn check(&self) -> error::Result<()> {
+        check_max_len(self.events.len(), SHORT_MAX)
     }
 }
 
 // Frame implementation related to BodyReqRegister
 
 impl Frame {
-    /// Creates new frame of type `REGISTER`.
-    pub fn new_req_register(events: Vec<SimpleServerEvent>) -> Frame {
+    /// Creates new frame of type `REGISTER` which subscribes a connection to given events.
+    pub fn new_req_register(events: &[SimpleServerEvent]) -> Frame {
         let version = Version::Request;
-        let flag = Flag::Ignore;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Register;
-        let register_body = BodyReqRegister { events: events };
+        let register_body = BodyReqRegister { events: events.to_vec() };
 
         Frame { version: version,
-                flags: vec![flag],
+                flags: Flags::empty(),
                 stream: stream,
                 opcode: opcode,
-                body: register_body.into_cbytes(),
+                body: register_body.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::types::CStringList;
+
+    #[test]
+    fn register_events() {
+        let frame = Frame::new_req_register(&[SimpleServerEvent::TopologyChange,
+                                              SimpleServerEvent::SchemaChange]);
+        assert_eq!(frame.opcode, Opcode::Register);
+
+        let mut cursor = Cursor::new(frame.body.as_ref());
+        let list = CStringList::from_cursor(&mut cursor).unwrap();
+        assert_eq!(list.into_plain(),
+                   vec!["TOPOLOGY_CHANGE".to_string(), "SCHEMA_CHANGE".to_string()]);
     }
 }
@@ -12,6 +12,7 @@ use crate::frame::frame_auth_challenge::*;
 use crate::frame::frame_authenticate::BodyResAuthenticate;
 use crate::frame::frame_auth_success::BodyReqAuthSuccess;
 use crate::types::rows::Row;
+use crate::types::{remaining_len, CBytes};
 
 #[derive(Debug)]
 pub enum ResponseBody {
@@ -34,35 +35,71 @@ pub enum ResponseBody {
 }
 
 impl ResponseBody {
+    /// The same as `from` but rows results which were requested with `SkipMetadata`
+    /// flag are decoded using provided cached rows metadata.
+    pub fn from_with_metadata(bytes: &[u8],
+                              response_type: &Opcode,
+                              cached: &RowsMetadata)
+                              -> error::Result<ResponseBody> {
+        match *response_type {
+            Opcode::Result => {
+                let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+                ResResultBody::from_cursor_with_metadata(&mut cursor, cached)
+                    .map(ResponseBody::Result)
+            }
+            _ => ResponseBody::from(bytes, response_type),
+        }
+    }
+
     pub fn from(bytes: &[u8], response_type: &Opcode) -> error::Result<ResponseBody> {
         let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        ResponseBody::from_cursor(&mut cursor, response_type)
+    }
+
+    /// Decodes a body and returns a number of bytes left after it.
+    pub(crate) fn trailing_len(bytes: &[u8], response_type: &Opcode) -> error::Result<usize> {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
+        ResponseBody::from_cursor(&mut cursor, response_type)?;
+        if *response_type == Opcode::AuthSuccess {
+            // `BodyReqAuthSuccess` doesn't keep a token, so it isn't read
+            CBytes::from_cursor(&mut cursor)?;
+        }
+        Ok(remaining_len(&cursor))
+    }
+
+    fn from_cursor(cursor: &mut Cursor<&[u8]>,
+                   response_type: &Opcode)
+                   -> error::Result<ResponseBody> {
         Ok(match *response_type {
             // request frames
-            Opcode::Startup => unreachable!(),
-            Opcode::Options => unreachable!(),
-            Opcode::Query => unreachable!(),
-            Opcode::Prepare => unreachable!(),
-            Opcode::Execute => unreachable!(),
-            Opcode::Register => unreachable!(),
-            Opcode::Batch => unreachable!(),
-            Opcode::AuthResponse => unreachable!(),
+            Opcode::Startup
+            | Opcode::Options
+            | Opcode::Query
+            | Opcode::Prepare
+            | Opcode::Execute
+            | Opcode::Register
+            | Opcode::Batch
+            | Opcode::AuthResponse => {
+                return Err(error::Error::Protocol(format!("{:?} is not a response",
+                                                          response_type)))
+            }
 
             // response frames
-            Opcode::Error => ResponseBody::Error(CDRSError::from_cursor(&mut cursor)?),
-            Opcode::Ready => ResponseBody::Ready(BodyResResultVoid::from_cursor(&mut cursor)?),
+            Opcode::Error => ResponseBody::Error(CDRSError::from_cursor(cursor)?),
+            Opcode::Ready => ResponseBody::Ready(BodyResResultVoid::from_cursor(cursor)?),
             Opcode::Authenticate => {
-                ResponseBody::Authenticate(BodyResAuthenticate::from_cursor(&mut cursor)?)
+                ResponseBody::Authenticate(BodyResAuthenticate::from_cursor(cursor)?)
             }
             Opcode::Supported => {
-                ResponseBody::Supported(BodyResSupported::from_cursor(&mut cursor)?)
+                ResponseBody::Supported(BodyResSupported::from_cursor(cursor)?)
             }
-            Opcode::Result => ResponseBody::Result(ResResultBody::from_cursor(&mut cursor)?),
-            Opcode::Event => ResponseBody::Event(BodyResEvent::from_cursor(&mut cursor)?),
+            Opcode::Result => ResponseBody::Result(ResResultBody::from_cursor(cursor)?),
+            Opcode::Event => ResponseBody::Event(BodyResEvent::from_cursor(cursor)?),
             Opcode::AuthChallenge => {
-                ResponseBody::AuthChallenge(BodyResAuthChallenge::from_cursor(&mut cursor)?)
+                ResponseBody::AuthChallenge(BodyResAuthChallenge::from_cursor(cursor)?)
             }
             Opcode::AuthSuccess => {
-                ResponseBody::AuthSuccess(BodyReqAuthSuccess::from_cursor(&mut cursor)?)
+                ResponseBody::AuthSuccess(BodyReqAuthSuccess::from_cursor(cursor)?)
             }
         })
     }
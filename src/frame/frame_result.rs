@@ -1,9 +1,14 @@
 use std::io::Cursor;
+use std::sync::Arc;
+
+use bytes::Bytes;
 
 use crate::frame::{FromBytes, FromCursor, IntoBytes};
 use crate::error;
+use crate::error::decode_context;
 use crate::types::*;
-use crate::types::rows::Row;
+use crate::types::rows::{ColumnCase, Row};
+use crate::types::data_serialization_types::{Utf8Policy, DEFAULT_MAX_DEPTH};
 use crate::frame::events::SchemaChange;
 
 /// `ResultKind` is enum which represents types of result.
@@ -42,7 +47,10 @@ impl FromBytes for ResultKind {
                                            0x0003 => Ok(ResultKind::SetKeyspace),
                                            0x0004 => Ok(ResultKind::Prepared),
                                            0x0005 => Ok(ResultKind::SchemaChange),
-                                           _ => Err("Unexpected result kind".into()),
+                                           _ => {
+                                               let msg = "Unexpected result kind";
+                                               Err(error::Error::Protocol(msg.into()))
+                                           }
                                        })
     }
 }
@@ -74,10 +82,18 @@ pub enum ResResultBody {
 impl ResResultBody {
     /// It retrieves`ResResultBody` from `io::Cursor`
     /// having knowledge about expected kind of result.
-    fn parse_body_from_cursor(mut cursor: &mut Cursor<&[u8]>,
+    fn parse_body_from_cursor(cursor: &mut Cursor<&[u8]>,
                               result_kind: ResultKind)
                               -> error::Result<ResResultBody> {
-        Ok(match result_kind {
+        decode_context(cursor, format_args!("{:?} result", result_kind), |cursor| {
+            ResResultBody::parse_body(cursor, &result_kind)
+        })
+    }
+
+    fn parse_body(mut cursor: &mut Cursor<&[u8]>,
+                  result_kind: &ResultKind)
+                  -> error::Result<ResResultBody> {
+        Ok(match *result_kind {
             ResultKind::Void => ResResultBody::Void(BodyResResultVoid::from_cursor(&mut cursor)?),
             ResultKind::Rows => ResResultBody::Rows(BodyResResultRows::from_cursor(&mut cursor)?),
             ResultKind::SetKeyspace => {
@@ -92,6 +108,20 @@ impl ResResultBody {
         })
     }
 
+    /// It retrieves `ResResultBody` from `io::Cursor` using cached rows metadata
+    /// if the body is a rows result which was requested with `SkipMetadata` flag.
+    pub fn from_cursor_with_metadata(cursor: &mut Cursor<&[u8]>,
+                                     cached: &RowsMetadata)
+                                     -> error::Result<ResResultBody> {
+        match ResultKind::from_cursor(cursor)? {
+            ResultKind::Rows => {
+                BodyResResultRows::from_cursor_with_metadata(cursor, cached)
+                    .map(ResResultBody::Rows)
+            }
+            result_kind => ResResultBody::parse_body_from_cursor(cursor, result_kind),
+        }
+    }
+
     /// It converts body into `Vec<Row>` if body's type is `Row` and returns `None` otherwise.
     pub fn into_rows(self) -> Option<Vec<Row>> {
         match self {
@@ -187,32 +217,203 @@ pub struct BodyResResultRows {
     pub rows_content: Vec<Vec<CBytes>>,
 }
 
+/// Validates a number of rows before they are decoded. Every cell takes at least `INT_LEN`
+/// bytes. Rows without columns take no bytes at all, so nothing bounds their count
+/// and such rows are rejected.
+fn check_rows_count(cursor: &Cursor<&[u8]>,
+                    rows_count: i32,
+                    columns_count: i32)
+                    -> error::Result<()> {
+    let invalid_length = |length: i32| {
+        Err(error::Error::InvalidLength { length: i64::from(length),
+                                          remaining: remaining_len(cursor) })
+    };
+    if columns_count < 0 {
+        return invalid_length(columns_count);
+    }
+    if columns_count == 0 && rows_count != 0 {
+        return invalid_length(rows_count);
+    }
+
+    let row_len = columns_count as usize * INT_LEN;
+    check_count(cursor, i64::from(rows_count), row_len).map(|_| ())
+}
+
 impl BodyResResultRows {
     /// It retrieves rows content having knowledge about number of rows and columns.
-    fn get_rows_content(mut cursor: &mut Cursor<&[u8]>,
+    fn get_rows_content(cursor: &mut Cursor<&[u8]>,
                         rows_count: i32,
-                        columns_count: i32)
-                        -> Vec<Vec<CBytes>> {
-        (0..rows_count).map(|_| {
-                           (0..columns_count)
-                     // XXX unwrap()
-                         .map(|_| CBytes::from_cursor(&mut cursor).unwrap() as CBytes)
-                         .collect()
+                        columns_count: i32,
+                        buf: Option<&Bytes>)
+                        -> error::Result<Vec<Vec<CBytes>>> {
+        check_rows_count(cursor, rows_count, columns_count)?;
+        (0..rows_count).map(|i| {
+                           decode_context(cursor, format_args!("row {}", i), |cursor| {
+                               BodyResResultRows::get_row_content(cursor, columns_count, buf)
+                           })
                        })
                        .collect()
     }
+
+    /// It retrieves a content of a single row having knowledge about number of columns.
+    /// If `buf` is a buffer which the cursor reads then values reference it instead
+    /// of being copied.
+    fn get_row_content(cursor: &mut Cursor<&[u8]>,
+                       columns_count: i32,
+                       buf: Option<&Bytes>)
+                       -> error::Result<Vec<CBytes>> {
+        (0..columns_count).map(|i| {
+                              decode_context(cursor, format_args!("column {}", i), |cursor| {
+                                  match buf {
+                                      Some(buf) => CBytes::from_cursor_shared(cursor, buf),
+                                      None => CBytes::from_cursor(cursor),
+                                  }
+                              })
+                          })
+                          .collect()
+    }
+
+    /// It retrieves rows result from a body of rows result (the bytes following result kind).
+    /// Unlike `from_cursor` values of cells reference `bytes` instead of being copied.
+    pub fn from_shared(bytes: &Bytes) -> error::Result<BodyResResultRows> {
+        let mut cursor = Cursor::new(bytes.as_ref());
+        let metadata = RowsMetadata::from_cursor_shared(&mut cursor, bytes)?;
+        BodyResResultRows::from_cursor_and_metadata(&mut cursor, metadata, Some(bytes))
+    }
+
+    /// It retrieves rows result which was requested with `SkipMetadata` flag using
+    /// previously cached metadata to describe columns.
+    pub fn from_cursor_with_metadata(cursor: &mut Cursor<&[u8]>,
+                                     cached: &RowsMetadata)
+                                     -> error::Result<BodyResResultRows> {
+        let metadata = RowsMetadata::from_cursor(cursor)?.with_cached(cached)?;
+        BodyResResultRows::from_cursor_and_metadata(cursor, metadata, None)
+    }
+
+    fn from_cursor_and_metadata(cursor: &mut Cursor<&[u8]>,
+                                metadata: RowsMetadata,
+                                buf: Option<&Bytes>)
+                                -> error::Result<BodyResResultRows> {
+        let rows_count = CInt::from_cursor(cursor)?;
+        let rows_content: Vec<Vec<CBytes>> =
+            BodyResResultRows::get_rows_content(cursor, rows_count, metadata.columns_count, buf)?;
+
+        Ok(BodyResResultRows { metadata,
+                               rows_count,
+                               rows_content, })
+    }
+
+    /// It parses rows metadata and rows count from a body of rows result (the bytes following
+    /// result kind) and returns an iterator which decodes rows one by one on demand
+    /// instead of materializing the whole result set up front.
+    pub fn rows_iter(bytes: &[u8]) -> error::Result<RowsIter<'_>> {
+        let mut cursor = Cursor::new(bytes);
+        let metadata = RowsMetadata::from_cursor(&mut cursor)?;
+        BodyResResultRows::rows_iter_from_cursor(cursor, metadata, None)
+    }
+
+    /// The same as `rows_iter` but if `bytes` is a part of `buf` values of cells reference
+    /// `buf` instead of being copied.
+    pub fn rows_iter_shared<'a>(bytes: &'a [u8], buf: &Bytes) -> error::Result<RowsIter<'a>> {
+        let mut cursor = Cursor::new(bytes);
+        let metadata = RowsMetadata::from_cursor_shared(&mut cursor, buf)?;
+        BodyResResultRows::rows_iter_from_cursor(cursor, metadata, Some(buf.clone()))
+    }
+
+    /// The same as `rows_iter` but for rows result which was requested
+    /// with `SkipMetadata` flag.
+    pub fn rows_iter_with_metadata<'a>(bytes: &'a [u8],
+                                       cached: &RowsMetadata)
+                                       -> error::Result<RowsIter<'a>> {
+        let mut cursor = Cursor::new(bytes);
+        let metadata = RowsMetadata::from_cursor(&mut cursor)?.with_cached(cached)?;
+        BodyResResultRows::rows_iter_from_cursor(cursor, metadata, None)
+    }
+
+    fn rows_iter_from_cursor(mut cursor: Cursor<&[u8]>,
+                             metadata: RowsMetadata,
+                             buf: Option<Bytes>)
+                             -> error::Result<RowsIter<'_>> {
+        let rows_count = CInt::from_cursor(&mut cursor)?;
+        check_rows_count(&cursor, rows_count, metadata.columns_count)?;
+
+        Ok(RowsIter { metadata: Arc::new(metadata),
+                      cursor,
+                      buf,
+                      remaining: rows_count,
+                      utf8_policy: Utf8Policy::Lossy,
+                      column_case: ColumnCase::Exact, })
+    }
 }
 
 impl FromCursor for BodyResResultRows {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultRows> {
         let metadata = RowsMetadata::from_cursor(&mut cursor)?;
-        let rows_count = CInt::from_cursor(&mut cursor)?;
-        let rows_content: Vec<Vec<CBytes>> =
-            BodyResResultRows::get_rows_content(&mut cursor, rows_count, metadata.columns_count);
+        BodyResResultRows::from_cursor_and_metadata(cursor, metadata, None)
+    }
+}
+
+/// Iterator over rows of a rows result which borrows body bytes and decodes
+/// a single row per `next` call. Once a row fails to decode the error is returned
+/// and iteration stops.
+#[derive(Debug)]
+pub struct RowsIter<'a> {
+    metadata: Arc<RowsMetadata>,
+    cursor: Cursor<&'a [u8]>,
+    buf: Option<Bytes>,
+    remaining: CInt,
+    utf8_policy: Utf8Policy,
+    column_case: ColumnCase,
+}
 
-        Ok(BodyResResultRows { metadata: metadata,
-                               rows_count: rows_count,
-                               rows_content: rows_content, })
+impl<'a> RowsIter<'a> {
+    /// Rows metadata shared by all the rows.
+    pub fn metadata(&self) -> &RowsMetadata {
+        &self.metadata
+    }
+
+    /// Sets UTF-8 policy of all the rows returned by the iterator,
+    /// see `Row::with_utf8_policy`.
+    pub fn with_utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Sets how column names are matched in all the rows returned by the iterator,
+    /// see `Row::with_column_case`.
+    pub fn with_column_case(mut self, column_case: ColumnCase) -> Self {
+        self.column_case = column_case;
+        self
+    }
+}
+
+impl<'a> Iterator for RowsIter<'a> {
+    type Item = error::Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining <= 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        match BodyResResultRows::get_row_content(&mut self.cursor,
+                                                 self.metadata.columns_count,
+                                                 self.buf.as_ref()) {
+            Ok(row_content) => {
+                let row = Row::new(self.metadata.clone(), row_content);
+                Some(Ok(row.with_utf8_policy(self.utf8_policy)
+                           .with_column_case(self.column_case)))
+            }
+            Err(err) => {
+                self.remaining = 0;
+                Some(Err(err))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining.max(0) as usize;
+        (0, Some(remaining))
     }
 }
 
@@ -233,10 +434,39 @@ pub struct RowsMetadata {
     pub global_table_space: Option<Vec<CString>>,
     /// List of column specifications.
     pub col_specs: Vec<ColSpec>,
+    /// New result metadata id. It is sent by protocol v5 server with `MetadataChanged` flag
+    /// when result metadata of a prepared statement differs from one that was identified
+    /// by `result_metadata_id` of an execute request. In such case received metadata and
+    /// this id should replace cached ones.
+    pub new_metadata_id: Option<CBytesShort>,
+    /// Position of a page of DSE continuous paging, see `ContinuousPagingOptions`.
+    pub continuous_page: Option<ContinuousPage>,
+}
+
+/// Position of a page of a DSE continuous paging result. A server sends pages
+/// of a request as separate `RESULT` frames on its stream until the last one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuousPage {
+    /// Number of the page starting with 1.
+    pub number: i32,
+    /// Shows if no more pages are sent for the request.
+    pub last: bool,
 }
 
 impl FromCursor for RowsMetadata {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<RowsMetadata> {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<RowsMetadata> {
+        decode_context(cursor, "rows metadata", |cursor| RowsMetadata::decode(cursor, None))
+    }
+}
+
+impl RowsMetadata {
+    /// The same as `from_cursor` but if the cursor reads from `buf` names of columns
+    /// and tables reference `buf` instead of being copied.
+    fn from_cursor_shared(cursor: &mut Cursor<&[u8]>, buf: &Bytes) -> error::Result<RowsMetadata> {
+        decode_context(cursor, "rows metadata", |cursor| RowsMetadata::decode(cursor, Some(buf)))
+    }
+
+    fn decode(mut cursor: &mut Cursor<&[u8]>, buf: Option<&Bytes>) -> error::Result<RowsMetadata> {
         let flags = CInt::from_cursor(&mut cursor)?;
         let columns_count = CInt::from_cursor(&mut cursor)?;
 
@@ -245,33 +475,108 @@ impl FromCursor for RowsMetadata {
             paging_state = Some(CBytes::from_cursor(&mut cursor)?)
         }
 
+        let mut new_metadata_id: Option<CBytesShort> = None;
+        if cfg!(feature = "v5") && RowsMetadataFlag::has_metadata_changed(flags) {
+            new_metadata_id = Some(CBytesShort::from_cursor(cursor)?)
+        }
+
+        let mut continuous_page: Option<ContinuousPage> = None;
+        if cfg!(feature = "dse") && RowsMetadataFlag::has_continuous_paging(flags) {
+            let number = CInt::from_cursor(cursor)?;
+            let last = RowsMetadataFlag::has_last_continuous_page(flags);
+            continuous_page = Some(ContinuousPage { number, last });
+        }
+
         let mut global_table_space: Option<Vec<CString>> = None;
         let has_global_table_space = RowsMetadataFlag::has_global_table_space(flags);
         if has_global_table_space {
-            let keyspace = CString::from_cursor(&mut cursor)?;
-            let tablename = CString::from_cursor(&mut cursor)?;
+            let keyspace = decode_string(cursor, buf)?;
+            let tablename = decode_string(cursor, buf)?;
             global_table_space = Some(vec![keyspace, tablename])
         }
 
-        let col_specs = ColSpec::parse_colspecs(&mut cursor, columns_count, has_global_table_space);
+        // with `NoMetadata` flag only flags, columns count and paging state are sent
+        let col_specs = if RowsMetadataFlag::has_no_metadata(flags) {
+            vec![]
+        } else {
+            ColSpec::parse_colspecs_shared(cursor, columns_count, has_global_table_space, buf)?
+        };
 
         Ok(RowsMetadata { flags: flags,
                           columns_count: columns_count,
                           paging_state: paging_state,
                           global_table_space: global_table_space,
-                          col_specs: col_specs, })
+                          col_specs,
+                          new_metadata_id,
+                          continuous_page, })
+    }
+
+    /// Shows if a server reported that result metadata of a prepared statement has changed
+    /// so cached result metadata and its id should be replaced with received ones.
+    pub fn metadata_changed(&self) -> bool {
+        self.new_metadata_id.is_some()
+    }
+
+    /// If metadata was received with `NoMetadata` flag (i.e. a query was sent with
+    /// `SkipMetadata` flag) it takes global table space and column specs from previously
+    /// cached metadata, e.g. the result metadata of a prepared statement. Flags and paging
+    /// state are kept from the received metadata. Metadata which contains column specs
+    /// is returned as is.
+    pub fn with_cached(self, cached: &RowsMetadata) -> error::Result<RowsMetadata> {
+        if !RowsMetadataFlag::has_no_metadata(self.flags) {
+            return Ok(self);
+        }
+
+        if self.columns_count as usize != cached.col_specs.len() {
+            return Err(error::Error::Protocol(format!("Rows contain {} columns but cached \
+                                                       metadata describes {}",
+                                                      self.columns_count,
+                                                      cached.col_specs.len())));
+        }
+
+        Ok(RowsMetadata { global_table_space: cached.global_table_space.clone(),
+                          col_specs: cached.col_specs.clone(),
+                          ..self })
+    }
+}
+
+#[cfg(test)]
+impl RowsMetadata {
+    /// Metadata of rows with given column names and types, without flags,
+    /// table spec and paging state. Tests use it instead of spelling out every field.
+    pub(crate) fn for_test(columns: Vec<(&str, ColTypeOption)>) -> RowsMetadata {
+        let col_specs = columns.into_iter()
+                               .map(|(name, col_type)| ColSpec { ksname: None,
+                                                                 tablename: None,
+                                                                 name: CString::new(name.into()),
+                                                                 col_type, })
+                               .collect::<Vec<_>>();
+
+        RowsMetadata { flags: 0,
+                       columns_count: col_specs.len() as i32,
+                       paging_state: None,
+                       global_table_space: None,
+                       col_specs,
+                       new_metadata_id: None,
+                       continuous_page: None, }
     }
 }
 
 const GLOBAL_TABLE_SPACE: i32 = 0x0001;
 const HAS_MORE_PAGES: i32 = 0x0002;
 const NO_METADATA: i32 = 0x0004;
+const METADATA_CHANGED: i32 = 0x0008;
+// DSE continuous paging flags
+const CONTINUOUS_PAGING: i32 = 0x4000_0000;
+const LAST_CONTINUOUS_PAGE: i32 = i32::MIN;
 
 /// Enum that represent a set of possible row metadata flags that could be set.
 pub enum RowsMetadataFlag {
     GlobalTableSpace,
     HasMorePages,
     NoMetadata,
+    /// Protocol v5 only.
+    MetadataChanged,
 }
 
 impl RowsMetadataFlag {
@@ -304,6 +609,26 @@ impl RowsMetadataFlag {
     pub fn set_no_metadata(flag: i32) -> i32 {
         flag | NO_METADATA
     }
+
+    /// Shows if provided flag contains MetadataChanged rows metadata flag
+    pub fn has_metadata_changed(flag: i32) -> bool {
+        (flag & METADATA_CHANGED) != 0
+    }
+
+    /// Sets MetadataChanged rows metadata flag
+    pub fn set_metadata_changed(flag: i32) -> i32 {
+        flag | METADATA_CHANGED
+    }
+
+    /// Shows if provided flag contains DSE ContinuousPaging rows metadata flag
+    pub fn has_continuous_paging(flag: i32) -> bool {
+        (flag & CONTINUOUS_PAGING) != 0
+    }
+
+    /// Shows if provided flag contains DSE LastContinuousPage rows metadata flag
+    pub fn has_last_continuous_page(flag: i32) -> bool {
+        (flag & LAST_CONTINUOUS_PAGE) != 0
+    }
 }
 
 impl IntoBytes for RowsMetadataFlag {
@@ -312,6 +637,7 @@ impl IntoBytes for RowsMetadataFlag {
             RowsMetadataFlag::GlobalTableSpace => to_int(GLOBAL_TABLE_SPACE),
             RowsMetadataFlag::HasMorePages => to_int(HAS_MORE_PAGES),
             RowsMetadataFlag::NoMetadata => to_int(NO_METADATA),
+            RowsMetadataFlag::MetadataChanged => to_int(METADATA_CHANGED),
         }
     }
 }
@@ -325,7 +651,13 @@ impl FromBytes for RowsMetadataFlag {
                                            }
                                            HAS_MORE_PAGES => Ok(RowsMetadataFlag::HasMorePages),
                                            NO_METADATA => Ok(RowsMetadataFlag::NoMetadata),
-                                           _ => Err("Unexpected rows metadata flag".into()),
+                                           METADATA_CHANGED => {
+                                               Ok(RowsMetadataFlag::MetadataChanged)
+                                           }
+                                           _ => {
+                                               let msg = "Unexpected rows metadata flag";
+                                               Err(error::Error::Protocol(msg.into()))
+                                           }
                                        })
     }
 }
@@ -345,37 +677,67 @@ pub struct ColSpec {
     pub col_type: ColTypeOption,
 }
 
+/// Decodes [string] which references `buf` if it's given and the cursor reads from it.
+fn decode_string(cursor: &mut Cursor<&[u8]>, buf: Option<&Bytes>) -> error::Result<CString> {
+    match buf {
+        Some(buf) => CString::from_cursor_shared(cursor, buf),
+        None => CString::from_cursor(cursor),
+    }
+}
+
 impl ColSpec {
     /// parse_colspecs tables mutable cursor,
     /// number of columns (column_count) and flags that indicates
     /// if Global_tables_spec is specified. It returns column_count of ColSpecs.
-    pub fn parse_colspecs(mut cursor: &mut Cursor<&[u8]>,
+    pub fn parse_colspecs(cursor: &mut Cursor<&[u8]>,
                           column_count: i32,
                           with_globale_table_spec: bool)
-                          -> Vec<ColSpec> {
-        (0..column_count).map(|_| {
-                                  let ksname: Option<CString> = if !with_globale_table_spec {
-                                      Some(CString::from_cursor(&mut cursor).unwrap())
-                                  } else {
-                                      None
-                                  };
-
-                                  let tablename = if !with_globale_table_spec {
-                                      Some(CString::from_cursor(&mut cursor).unwrap())
-                                  } else {
-                                      None
-                                  };
-
-                                  // XXX unwrap
-                                  let name = CString::from_cursor(&mut cursor).unwrap();
-                                  let col_type = ColTypeOption::from_cursor(&mut cursor).unwrap();
-
-                                  ColSpec { ksname: ksname,
-                                            tablename: tablename,
-                                            name: name,
-                                            col_type: col_type, }
-                              })
-                         .collect()
+                          -> error::Result<Vec<ColSpec>> {
+        ColSpec::parse_colspecs_shared(cursor, column_count, with_globale_table_spec, None)
+    }
+
+    /// The same as `parse_colspecs` but names reference `buf` if the cursor reads from it.
+    fn parse_colspecs_shared(cursor: &mut Cursor<&[u8]>,
+                             column_count: i32,
+                             with_globale_table_spec: bool,
+                             buf: Option<&Bytes>)
+                             -> error::Result<Vec<ColSpec>> {
+        // every column spec has at least a name and a type id
+        let column_count = check_count(cursor, i64::from(column_count), 2 * SHORT_LEN)?;
+        let mut col_specs = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            let col_spec = decode_context(cursor, format_args!("column {}", i), |cursor| {
+                               ColSpec::parse_colspec(cursor, with_globale_table_spec, buf)
+                           })?;
+            col_specs.push(col_spec);
+        }
+
+        Ok(col_specs)
+    }
+
+    fn parse_colspec(cursor: &mut Cursor<&[u8]>,
+                     with_globale_table_spec: bool,
+                     buf: Option<&Bytes>)
+                     -> error::Result<ColSpec> {
+        let ksname: Option<CString> = if !with_globale_table_spec {
+            Some(decode_string(cursor, buf)?)
+        } else {
+            None
+        };
+
+        let tablename = if !with_globale_table_spec {
+            Some(decode_string(cursor, buf)?)
+        } else {
+            None
+        };
+
+        let name = decode_string(cursor, buf)?;
+        let col_type = decode_context(cursor, "type option", ColTypeOption::from_cursor)?;
+
+        Ok(ColSpec { ksname,
+                     tablename,
+                     name,
+                     col_type, })
     }
 }
 
@@ -439,7 +801,10 @@ impl FromBytes for ColType {
                                            0x0022 => Ok(ColType::Set),
                                            0x0030 => Ok(ColType::Udt),
                                            0x0031 => Ok(ColType::Tuple),
-                                           _ => Err("Unexpected column type".into()),
+                                           _ => {
+                                               let msg = "Unexpected column type";
+                                               Err(error::Error::Protocol(msg.into()))
+                                           }
                                        })
     }
 }
@@ -464,27 +829,47 @@ pub struct ColTypeOption {
 }
 
 impl FromCursor for ColTypeOption {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<ColTypeOption> {
-        let id = ColType::from_cursor(&mut cursor)?;
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<ColTypeOption> {
+        ColTypeOption::from_cursor_with_depth(cursor, 0)
+    }
+}
+
+impl ColTypeOption {
+    /// It retrieves `ColTypeOption` from `io::Cursor` checking that nesting level
+    /// of collection types does not exceed `DEFAULT_MAX_DEPTH`.
+    fn from_cursor_with_depth(cursor: &mut Cursor<&[u8]>,
+                              depth: usize)
+                              -> error::Result<ColTypeOption> {
+        if depth > DEFAULT_MAX_DEPTH {
+            return Err(error::Error::Protocol(format!("Column type nesting level exceeds \
+                                                       the limit of {}",
+                                                      DEFAULT_MAX_DEPTH)));
+        }
+
+        let id = ColType::from_cursor(cursor)?;
         let value = match id {
             ColType::Custom => {
-                Some(ColTypeOptionValue::CString(CString::from_cursor(&mut cursor)?))
+                Some(ColTypeOptionValue::CString(CString::from_cursor(cursor)?))
             }
             ColType::Set => {
-                let col_type = ColTypeOption::from_cursor(&mut cursor)?;
+                let col_type = ColTypeOption::from_cursor_with_depth(cursor, depth + 1)?;
                 Some(ColTypeOptionValue::CSet(Box::new(col_type)))
             }
             ColType::List => {
-                let col_type = ColTypeOption::from_cursor(&mut cursor)?;
+                let col_type = ColTypeOption::from_cursor_with_depth(cursor, depth + 1)?;
                 Some(ColTypeOptionValue::CList(Box::new(col_type)))
             }
-            ColType::Udt => Some(ColTypeOptionValue::UdtType(CUdt::from_cursor(&mut cursor)?)),
+            ColType::Udt => {
+                Some(ColTypeOptionValue::UdtType(CUdt::from_cursor_with_depth(cursor,
+                                                                              depth + 1)?))
+            }
             ColType::Tuple => {
-                Some(ColTypeOptionValue::TupleType(CTuple::from_cursor(&mut cursor)?))
+                Some(ColTypeOptionValue::TupleType(CTuple::from_cursor_with_depth(cursor,
+                                                                                  depth + 1)?))
             }
             ColType::Map => {
-                let name_type = ColTypeOption::from_cursor(&mut cursor)?;
-                let value_type = ColTypeOption::from_cursor(&mut cursor)?;
+                let name_type = ColTypeOption::from_cursor_with_depth(cursor, depth + 1)?;
+                let value_type = ColTypeOption::from_cursor_with_depth(cursor, depth + 1)?;
                 Some(ColTypeOptionValue::CMap((Box::new(name_type), Box::new(value_type))))
             }
             _ => None,
@@ -495,6 +880,14 @@ impl FromCursor for ColTypeOption {
     }
 }
 
+#[cfg(test)]
+impl ColTypeOption {
+    /// Type option of a type which has no nested types, e.g. `int`.
+    pub(crate) fn simple(id: ColType) -> ColTypeOption {
+        ColTypeOption { id, value: None }
+    }
+}
+
 /// Enum that represents all possible types of `value` of `ColTypeOption`.
 #[derive(Debug, Clone)]
 pub enum ColTypeOptionValue {
@@ -520,14 +913,21 @@ pub struct CUdt {
 }
 
 impl FromCursor for CUdt {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<CUdt> {
-        let ks = CString::from_cursor(&mut cursor)?;
-        let udt_name = CString::from_cursor(&mut cursor)?;
-        let n = try_from_bytes(cursor_next_value(&mut cursor, SHORT_LEN as u64)?.as_slice())?;
-        let mut descriptions = Vec::with_capacity(n as usize);
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<CUdt> {
+        CUdt::from_cursor_with_depth(cursor, 0)
+    }
+}
+
+impl CUdt {
+    fn from_cursor_with_depth(cursor: &mut Cursor<&[u8]>, depth: usize) -> error::Result<CUdt> {
+        let ks = CString::from_cursor(cursor)?;
+        let udt_name = CString::from_cursor(cursor)?;
+        let n = try_from_bytes(cursor_next_value(cursor, SHORT_LEN as u64)?.as_slice())?;
+        let n = check_count(cursor, n as i64, 2 * SHORT_LEN)?;
+        let mut descriptions = Vec::with_capacity(n);
         for _ in 0..n {
-            let name = CString::from_cursor(&mut cursor)?;
-            let col_type = ColTypeOption::from_cursor(&mut cursor)?;
+            let name = CString::from_cursor(cursor)?;
+            let col_type = ColTypeOption::from_cursor_with_depth(cursor, depth)?;
             descriptions.push((name, col_type));
         }
 
@@ -546,11 +946,18 @@ pub struct CTuple {
 }
 
 impl FromCursor for CTuple {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<CTuple> {
-        let n = try_from_bytes(cursor_next_value(&mut cursor, SHORT_LEN as u64)?.as_slice())?;
-        let mut types = Vec::with_capacity(n as usize);
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<CTuple> {
+        CTuple::from_cursor_with_depth(cursor, 0)
+    }
+}
+
+impl CTuple {
+    fn from_cursor_with_depth(cursor: &mut Cursor<&[u8]>, depth: usize) -> error::Result<CTuple> {
+        let n = try_from_bytes(cursor_next_value(cursor, SHORT_LEN as u64)?.as_slice())?;
+        let n = check_count(cursor, n as i64, SHORT_LEN)?;
+        let mut types = Vec::with_capacity(n);
         for _ in 0..n {
-            let col_type = ColTypeOption::from_cursor(&mut cursor)?;
+            let col_type = ColTypeOption::from_cursor_with_depth(cursor, depth)?;
             types.push(col_type);
         }
 
@@ -559,10 +966,13 @@ impl FromCursor for CTuple {
 }
 
 /// The structure represents a body of a response frame of type `prepared`
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BodyResResultPrepared {
     /// id of prepared request
     pub id: CBytesShort,
+    /// Id of result metadata which is sent by protocol v5 server. It should be provided
+    /// along with prepared id in execute requests.
+    pub result_metadata_id: Option<CBytesShort>,
     /// metadata
     pub metadata: PreparedMetadata,
     /// It is defined exactly the same as <metadata> in the Rows
@@ -573,17 +983,24 @@ pub struct BodyResResultPrepared {
 impl FromCursor for BodyResResultPrepared {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultPrepared> {
         let id = CBytesShort::from_cursor(&mut cursor)?;
-        let metadata = PreparedMetadata::from_cursor(&mut cursor)?;
+        let result_metadata_id = if cfg!(feature = "v5") {
+            Some(CBytesShort::from_cursor(cursor)?)
+        } else {
+            None
+        };
+        let metadata =
+            decode_context(cursor, "prepared metadata", PreparedMetadata::from_cursor)?;
         let result_metadata = RowsMetadata::from_cursor(&mut cursor)?;
 
         Ok(BodyResResultPrepared { id: id,
+                                   result_metadata_id,
                                    metadata: metadata,
                                    result_metadata: result_metadata, })
     }
 }
 
 /// The structure that represents metadata of prepared response.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PreparedMetadata {
     pub flags: i32,
     pub columns_count: i32,
@@ -593,6 +1010,16 @@ pub struct PreparedMetadata {
     pub col_specs: Vec<ColSpec>,
 }
 
+impl PreparedMetadata {
+    /// Shows if a prepared statement is a lightweight transaction according to ScyllaDB
+    /// LWT metadata mark. The mask is announced by a server in `SUPPORTED` response
+    /// (see `BodyResSupported::scylla_lwt_mask`) and the mark is set only if a client
+    /// enabled it in `STARTUP` options. LWT statements should be sent to primary replicas.
+    pub fn is_lwt(&self, scylla_lwt_mask: i32) -> bool {
+        scylla_lwt_mask != 0 && (self.flags & scylla_lwt_mask) == scylla_lwt_mask
+    }
+}
+
 impl FromCursor for PreparedMetadata {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<PreparedMetadata> {
         let flags = CInt::from_cursor(&mut cursor)?;
@@ -603,6 +1030,7 @@ impl FromCursor for PreparedMetadata {
             // v4 or v5
             CInt::from_cursor(&mut cursor)?
         };
+        check_count(cursor, i64::from(pk_count), SHORT_LEN)?;
         let pk_index_results: Vec<Option<i16>> = (0..pk_count).map(|_| {
             cursor_next_value(&mut cursor, SHORT_LEN as u64)
                     .ok()
@@ -611,7 +1039,7 @@ impl FromCursor for PreparedMetadata {
                                                               .collect();
 
         let pk_indexes: Vec<i16> = if pk_index_results.iter().any(Option::is_none) {
-            return Err("pk indexes error".into());
+            return Err(error::Error::Protocol("Invalid pk indexes".into()));
         } else {
             pk_index_results.iter()
                             .cloned()
@@ -625,7 +1053,8 @@ impl FromCursor for PreparedMetadata {
             let tablename = CString::from_cursor(&mut cursor)?;
             global_table_space = Some((keyspace, tablename))
         }
-        let col_specs = ColSpec::parse_colspecs(&mut cursor, columns_count, has_global_table_space);
+        let col_specs =
+            ColSpec::parse_colspecs(cursor, columns_count, has_global_table_space)?;
 
         Ok(PreparedMetadata { flags: flags,
                               columns_count: columns_count,
@@ -635,3 +1064,222 @@ impl FromCursor for PreparedMetadata {
                               col_specs: col_specs, })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{to_int, to_short};
+
+    fn rows_body(rows: &[&[u8]]) -> Vec<u8> {
+        let mut body = vec![];
+        body.extend(to_int(GLOBAL_TABLE_SPACE));
+        body.extend(to_int(1));
+        body.extend(CString::new("ks".into()).into_cbytes());
+        body.extend(CString::new("table".into()).into_cbytes());
+        body.extend(CString::new("id".into()).into_cbytes());
+        body.extend(to_short(0x0009));
+        body.extend(to_int(rows.len() as i32));
+        for row in rows {
+            body.extend_from_slice(row);
+        }
+        body
+    }
+
+    #[test]
+    fn malformed_column_type() {
+        let mut body = to_int(0x0002);
+        body.extend(to_int(GLOBAL_TABLE_SPACE));
+        body.extend(to_int(1));
+        body.extend(CString::new("ks".into()).into_cbytes());
+        body.extend(CString::new("table".into()).into_cbytes());
+        body.extend(CString::new("id".into()).into_cbytes());
+        body.extend(to_short(0x00ff));
+
+        match ResResultBody::from_cursor(&mut Cursor::new(body.as_slice())) {
+            Err(error::Error::Decode { context, offset, found, source }) => {
+                assert_eq!(context, vec!["Rows result", "rows metadata", "column 0",
+                                         "type option"]);
+                assert_eq!(offset, body.len() as u64 - 2);
+                assert_eq!(found, vec![0x00, 0xff]);
+                assert!(matches!(*source, error::Error::Protocol(_)));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rows_iter() {
+        let body = rows_body(&[&[0, 0, 0, 4, 0, 0, 0, 1], &[0, 0, 0, 4, 0, 0, 0, 2]]);
+        let mut rows = BodyResResultRows::rows_iter(&body).unwrap();
+        assert_eq!(rows.metadata().col_specs.len(), 1);
+        assert_eq!(rows.size_hint(), (0, Some(2)));
+
+        let ids = rows.by_ref()
+                      .map(|row| row.unwrap().get_r_by_name("id").unwrap())
+                      .collect::<Vec<i32>>();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(rows.next().is_none());
+
+        let mut rows = BodyResResultRows::rows_iter(&body).unwrap()
+                                                          .with_column_case(ColumnCase::Identifier);
+        let id: i32 = rows.next().unwrap().unwrap().get_r_by_name("ID").unwrap();
+        assert_eq!(id, 1);
+    }
+
+    #[test]
+    fn rows_iter_utf8_policy() {
+        let row: &[u8] = &[0, 0, 0, 2, b'a', 0xff];
+        let mut body = rows_body(&[row]);
+        // varchar instead of int column
+        let type_at = body.len() - row.len() - INT_LEN - SHORT_LEN;
+        body[type_at..type_at + SHORT_LEN].copy_from_slice(&to_short(0x000D));
+
+        let row = BodyResResultRows::rows_iter(&body).unwrap().next().unwrap().unwrap();
+        let name: String = row.get_r_by_name("id").unwrap();
+        assert_eq!(name, "a\u{fffd}");
+
+        let mut rows = BodyResResultRows::rows_iter(&body).unwrap()
+                                                          .with_utf8_policy(Utf8Policy::Strict);
+        let row = rows.next().unwrap().unwrap();
+        assert!(IntoRustByName::<String>::get_r_by_name(&row, "id").is_err());
+        assert!(row.into_map().is_err());
+    }
+
+    #[test]
+    fn shared_rows() {
+        let body = Bytes::from(rows_body(&[&[0, 0, 0, 4, 0, 0, 0, 1], &[0xff, 0xff, 0xff, 0xff]]));
+        let rows = BodyResResultRows::from_shared(&body).unwrap();
+        let value = rows.rows_content[0][0].as_slice().unwrap();
+        assert_eq!(value, &[0, 0, 0, 1]);
+        assert_eq!(value.as_ptr(), body[body.len() - 8..].as_ptr());
+        assert!(rows.rows_content[1][0].as_slice().is_none());
+        let name = rows.metadata.col_specs[0].name.as_str();
+        assert!(body.as_ptr_range().contains(&name.as_ptr()));
+
+        let mut rows = BodyResResultRows::rows_iter_shared(&body, &body).unwrap();
+        let id: i32 = rows.next().unwrap().unwrap().get_r_by_name("id").unwrap();
+        assert_eq!(id, 1);
+    }
+
+    #[test]
+    fn rows_iter_truncated_body() {
+        let body = rows_body(&[&[0, 0, 0, 4, 0, 0, 0, 1], &[0, 0, 0, 4, 0]]);
+        let mut rows = BodyResResultRows::rows_iter(&body).unwrap();
+        assert!(rows.next().unwrap().is_ok());
+        assert!(rows.next().unwrap().is_err());
+        assert!(rows.next().is_none());
+
+        let mut cursor = Cursor::new(body.as_slice());
+        assert!(BodyResResultRows::from_cursor(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn invalid_counts() {
+        // rows count which doesn't fit into the body
+        let mut body = rows_body(&[]);
+        let len = body.len();
+        body[len - INT_LEN..].copy_from_slice(&to_int(i32::MAX));
+        let mut cursor = Cursor::new(body.as_slice());
+        assert!(BodyResResultRows::from_cursor(&mut cursor).is_err());
+        assert!(BodyResResultRows::rows_iter(&body).is_err());
+
+        // rows without columns take no bytes, so their count isn't bounded by the body
+        let mut body = to_int(GLOBAL_TABLE_SPACE);
+        body.extend(to_int(0));
+        body.extend(CString::new("ks".into()).into_cbytes());
+        body.extend(CString::new("table".into()).into_cbytes());
+        body.extend(to_int(i32::MAX));
+        let mut cursor = Cursor::new(body.as_slice());
+        match BodyResResultRows::from_cursor(&mut cursor) {
+            Err(error::Error::InvalidLength { length, .. }) => {
+                assert_eq!(length, i64::from(i32::MAX))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(BodyResResultRows::rows_iter(&body).is_err());
+
+        // truncated column specs are reported instead of panicking
+        let body = rows_body(&[]);
+        let mut cursor = Cursor::new(&body[..body.len() - INT_LEN - 1]);
+        assert!(BodyResResultRows::from_cursor(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn rows_with_cached_metadata() {
+        let full_body = rows_body(&[]);
+        let cached = BodyResResultRows::rows_iter(&full_body).unwrap().metadata().clone();
+
+        let mut body = vec![];
+        body.extend(to_int(NO_METADATA));
+        body.extend(to_int(1));
+        body.extend(to_int(1));
+        body.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 7]);
+
+        let mut cursor = Cursor::new(body.as_slice());
+        let rows = BodyResResultRows::from_cursor_with_metadata(&mut cursor, &cached).unwrap();
+        assert_eq!(rows.metadata.col_specs.len(), 1);
+        assert!(RowsMetadataFlag::has_no_metadata(rows.metadata.flags));
+        let id: i32 = Row::from_frame_body(rows)[0].get_r_by_name("id").unwrap();
+        assert_eq!(id, 7);
+
+        let mut rows = BodyResResultRows::rows_iter_with_metadata(&body, &cached).unwrap();
+        let id: i32 = rows.next().unwrap().unwrap().get_r_by_name("id").unwrap();
+        assert_eq!(id, 7);
+
+        let mut other = cached.clone();
+        other.col_specs.clear();
+        let mut cursor = Cursor::new(body.as_slice());
+        assert!(BodyResResultRows::from_cursor_with_metadata(&mut cursor, &other).is_err());
+    }
+
+
+    #[test]
+    #[cfg(feature = "dse")]
+    fn continuous_page() {
+        let mut body = vec![];
+        body.extend(to_int(CONTINUOUS_PAGING | LAST_CONTINUOUS_PAGE | NO_METADATA));
+        body.extend(to_int(1));
+        body.extend(to_int(3));
+
+        let mut cursor = Cursor::new(body.as_slice());
+        let metadata = RowsMetadata::from_cursor(&mut cursor).unwrap();
+        assert_eq!(metadata.continuous_page,
+                   Some(ContinuousPage { number: 3,
+                                         last: true }));
+        assert_eq!(cursor.position() as usize, body.len());
+    }
+
+    #[test]
+    #[cfg(feature = "v5")]
+    fn rows_metadata_changed() {
+        let mut body = vec![];
+        body.extend(to_int(METADATA_CHANGED | GLOBAL_TABLE_SPACE));
+        body.extend(to_int(1));
+        body.extend(CBytesShort::new(vec![1, 2]).into_cbytes());
+        body.extend(CString::new("ks".into()).into_cbytes());
+        body.extend(CString::new("table".into()).into_cbytes());
+        body.extend(CString::new("id".into()).into_cbytes());
+        body.extend(to_short(0x0009));
+
+        let mut cursor = Cursor::new(body.as_slice());
+        let metadata = RowsMetadata::from_cursor(&mut cursor).unwrap();
+        assert!(metadata.metadata_changed());
+        assert_eq!(metadata.new_metadata_id.unwrap().into_plain(), Some(vec![1, 2]));
+        assert_eq!(metadata.col_specs.len(), 1);
+    }
+
+
+    #[test]
+    fn prepared_lwt() {
+        let metadata = PreparedMetadata { flags: i32::MIN | GLOBAL_TABLE_SPACE,
+                                          columns_count: 0,
+                                          pk_count: 0,
+                                          pk_indexes: vec![],
+                                          global_table_spec: None,
+                                          col_specs: vec![] };
+        assert!(metadata.is_lwt(i32::MIN));
+        assert!(!metadata.is_lwt(0x4000));
+        assert!(!metadata.is_lwt(0));
+    }
+
+}
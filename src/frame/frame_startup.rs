@@ -1,13 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
 
-use rand;
+use bytes::BufMut;
 
+use crate::error;
 use crate::frame::*;
-use crate::types::to_short;
+use crate::types::{check_max_len, put_string, to_short, CIntShort, CString, SHORT_LEN,
+                   SHORT_MAX};
 
 const CQL_VERSION: &'static str = "CQL_VERSION";
 const CQL_VERSION_VAL: &'static str = "3.0.0";
 const COMPRESSION: &'static str = "COMPRESSION";
+const DRIVER_NAME: &str = "DRIVER_NAME";
+const DRIVER_VERSION: &str = "DRIVER_VERSION";
 
 #[derive(Debug)]
 pub struct BodyReqStartup<'a> {
@@ -50,32 +55,172 @@ impl<'a> IntoBytes for BodyReqStartup<'a> {
     }
 }
 
+/// Typed options of `STARTUP` request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupParams {
+    /// Version of CQL, `3.0.0` by default.
+    pub cql_version: String,
+    /// Compression algorithm, e.g. `lz4` or `snappy`.
+    pub compression: Option<String>,
+    /// Name of a client driver.
+    pub driver_name: Option<String>,
+    /// Version of a client driver.
+    pub driver_version: Option<String>,
+    /// Other options, e.g. `NO_COMPACT` or vendor specific ones.
+    pub options: BTreeMap<String, String>,
+}
+
+impl Default for StartupParams {
+    fn default() -> StartupParams {
+        StartupParams { cql_version: CQL_VERSION_VAL.to_string(),
+                        compression: None,
+                        driver_name: None,
+                        driver_version: None,
+                        options: BTreeMap::new(), }
+    }
+}
+
+impl StartupParams {
+    /// Creates startup options with default CQL version and without compression.
+    pub fn new() -> StartupParams {
+        Default::default()
+    }
+
+    pub fn with_cql_version<S: Into<String>>(mut self, cql_version: S) -> Self {
+        self.cql_version = cql_version.into();
+        self
+    }
+
+    pub fn with_compression<S: Into<String>>(mut self, compression: S) -> Self {
+        self.compression = Some(compression.into());
+        self
+    }
+
+    pub fn with_driver<N: Into<String>, V: Into<String>>(mut self, name: N, version: V) -> Self {
+        self.driver_name = Some(name.into());
+        self.driver_version = Some(version.into());
+        self
+    }
+
+    /// Adds any other option.
+    pub fn with_option<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.options.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns all the options as pairs of keys and values.
+    pub fn to_map(&self) -> BTreeMap<&str, &str> {
+        let mut map: BTreeMap<&str, &str> = self.options
+                                                .iter()
+                                                .map(|(key, val)| (key.as_str(), val.as_str()))
+                                                .collect();
+        map.insert(CQL_VERSION, &self.cql_version);
+        let known = [(COMPRESSION, &self.compression),
+                     (DRIVER_NAME, &self.driver_name),
+                     (DRIVER_VERSION, &self.driver_version)];
+        for (key, val) in known.iter() {
+            if let Some(ref val) = val {
+                map.insert(key, val);
+            }
+        }
+        map
+    }
+}
+
+impl IntoBytes for StartupParams {
+    fn into_cbytes(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
+        v
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        let map = self.to_map();
+        buf.put_i16(map.len() as i16);
+        for (key, val) in map {
+            put_string(buf, key);
+            put_string(buf, val);
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN
+        + self.to_map()
+              .iter()
+              .map(|(key, val)| 2 * SHORT_LEN + key.len() + val.len())
+              .sum::<usize>()
+    }
+}
+
+impl TryIntoBytes for StartupParams {
+    fn check(&self) -> error::Result<()> {
+        let map = self.to_map();
+        check_max_len(map.len(), SHORT_MAX)?;
+        map.iter().try_for_each(|(key, val)| {
+                      check_max_len(key.len(), SHORT_MAX)?;
+                      check_max_len(val.len(), SHORT_MAX)
+                  })
+    }
+}
+
+impl FromCursor for StartupParams {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<StartupParams> {
+        let len = CIntShort::from_cursor(cursor)?;
+        let mut options = BTreeMap::new();
+        for _ in 0..len {
+            let key = CString::from_cursor(cursor)?.into_plain();
+            let val = CString::from_cursor(cursor)?.into_plain();
+            options.insert(key, val);
+        }
+
+        let cql_version = options.remove(CQL_VERSION)
+                                 .ok_or_else(|| {
+                                     error::Error::Protocol("CQL_VERSION is missing".into())
+                                 })?;
+
+        Ok(StartupParams { cql_version,
+                           compression: options.remove(COMPRESSION),
+                           driver_name: options.remove(DRIVER_NAME),
+                           driver_version: options.remove(DRIVER_VERSION),
+                           options, })
+    }
+}
+
 // Frame implementation related to BodyReqStartup
 
 impl Frame {
     /// Creates new frame of type `startup`.
     pub fn new_req_startup(compression: Option<&str>) -> Frame {
+        let params = match compression {
+            Some(compression) => StartupParams::new().with_compression(compression),
+            None => StartupParams::new(),
+        };
+        Frame::new_req_startup_with_params(&params)
+    }
+
+    /// Creates new frame of type `startup` with given options.
+    pub fn new_req_startup_with_params(params: &StartupParams) -> Frame {
         let version = Version::Request;
-        let flag = Flag::Ignore;
-        let stream = rand::random::<u16>();
+        let stream = random_stream();
         let opcode = Opcode::Startup;
-        let body = BodyReqStartup::new(compression);
+        let body = params;
 
         Frame { version: version,
-                flags: vec![flag],
+                flags: Flags::empty(),
                 stream: stream,
                 opcode: opcode,
-                body: body.into_cbytes(),
+                body: body.into_cbytes().into(),
                 // for request frames it's always None
                 tracing_id: None,
-                warnings: vec![], }
+                warnings: vec![],
+                custom_payload: Default::default(), }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::frame::{Flag, Frame, Opcode, Version};
+    use crate::frame::{Flags, Frame, Opcode, Version};
 
     #[test]
     fn new_body_req_startup_some_compression() {
@@ -98,9 +243,25 @@ mod test {
         let compression = Some("test_compression");
         let frame = Frame::new_req_startup(compression);
         assert_eq!(frame.version, Version::Request);
-        assert_eq!(frame.flags, vec![Flag::Ignore]);
+        assert_eq!(frame.flags, Flags::empty());
         assert_eq!(frame.opcode, Opcode::Startup);
         assert_eq!(frame.tracing_id, None);
-        assert_eq!(frame.warnings, vec![] as Vec<String>);
+        assert!(frame.warnings.is_empty());
+    }
+
+    #[test]
+    fn startup_params() {
+        let params = StartupParams::new().with_compression("lz4")
+                                         .with_driver("cdrs", "1.0")
+                                         .with_option("NO_COMPACT", "true");
+        let frame = Frame::new_req_startup_with_params(&params);
+
+        let mut cursor = Cursor::new(frame.body.as_ref());
+        let parsed = StartupParams::from_cursor(&mut cursor).unwrap();
+        assert_eq!(parsed, params);
+        assert_eq!(parsed.options.get("NO_COMPACT").map(String::as_str), Some("true"));
+
+        let mut cursor = Cursor::new(&[0, 0][..]);
+        assert!(StartupParams::from_cursor(&mut cursor).is_err());
     }
 }
@@ -1,19 +1,186 @@
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::io::Cursor;
+use std::str::FromStr;
 
 use crate::frame::FromCursor;
 use crate::error;
-use crate::types::{cursor_next_value, try_from_bytes, CString, CStringList, SHORT_LEN};
+use crate::types::{check_count, cursor_next_value, try_from_bytes, CString, CStringList,
+                   SHORT_LEN};
+
+const CQL_VERSION: &str = "CQL_VERSION";
+const COMPRESSION: &str = "COMPRESSION";
+const PROTOCOL_VERSIONS: &str = "PROTOCOL_VERSIONS";
+const SCYLLA_SHARD: &str = "SCYLLA_SHARD";
+const SCYLLA_NR_SHARDS: &str = "SCYLLA_NR_SHARDS";
+const SCYLLA_PARTITIONER: &str = "SCYLLA_PARTITIONER";
+const SCYLLA_SHARDING_ALGORITHM: &str = "SCYLLA_SHARDING_ALGORITHM";
+const SCYLLA_SHARDING_IGNORE_MSB: &str = "SCYLLA_SHARDING_IGNORE_MSB";
+const SCYLLA_SHARD_AWARE_PORT: &str = "SCYLLA_SHARD_AWARE_PORT";
+const SCYLLA_SHARD_AWARE_PORT_SSL: &str = "SCYLLA_SHARD_AWARE_PORT_SSL";
+/// ScyllaDB option which marks prepared LWT statements. It should be sent back in
+/// `STARTUP` options with the value received in `SUPPORTED` response to enable the mark.
+pub const SCYLLA_LWT_ADD_METADATA_MARK: &str = "SCYLLA_LWT_ADD_METADATA_MARK";
+const LWT_OPTIMIZATION_META_BIT_MASK: &str = "LWT_OPTIMIZATION_META_BIT_MASK";
 
 #[derive(Debug)]
 pub struct BodyResSupported {
     pub data: HashMap<String, Vec<String>>,
 }
 
+impl BodyResSupported {
+    /// Returns CQL versions supported by a server, e.g. `3.4.5`.
+    pub fn cql_versions(&self) -> &[String] {
+        self.values(CQL_VERSION)
+    }
+
+    /// Returns compression algorithms supported by a server, e.g. `lz4` or `snappy`.
+    pub fn compression_algorithms(&self) -> &[String] {
+        self.values(COMPRESSION)
+    }
+
+    /// Returns protocol versions supported by a server, e.g. `4/v4` or `5/v5-beta`.
+    /// Older servers don't report them.
+    pub fn protocol_versions(&self) -> &[String] {
+        self.values(PROTOCOL_VERSIONS)
+    }
+
+    /// Returns options other than CQL versions, compression algorithms and
+    /// protocol versions, e.g. vendor specific extensions.
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.data
+            .iter()
+            .filter(|(name, _)| {
+                        ![CQL_VERSION, COMPRESSION, PROTOCOL_VERSIONS].contains(&name.as_str())
+                    })
+            .map(|(name, values)| (name.as_str(), values.as_slice()))
+    }
+
+    /// Returns all the values of a supported option or an empty slice if there is no such one.
+    pub fn values(&self, name: &str) -> &[String] {
+        self.data
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Returns the first value of a supported option.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.data
+            .get(name)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+
+    /// Returns ScyllaDB sharding information if a server is ScyllaDB which supports
+    /// shard awareness and `None` otherwise.
+    pub fn scylla_shard_info(&self) -> error::Result<Option<ScyllaShardInfo>> {
+        if self.get(SCYLLA_SHARD).is_none() {
+            return Ok(None);
+        }
+
+        let shard_info = ScyllaShardInfo {
+            shard: self.parse(SCYLLA_SHARD)?.unwrap_or(0),
+            nr_shards: self.parse(SCYLLA_NR_SHARDS)?
+                           .ok_or_else(|| {
+                               error::Error::Protocol("SCYLLA_NR_SHARDS is missing".into())
+                           })?,
+            msb_ignore: self.parse(SCYLLA_SHARDING_IGNORE_MSB)?.unwrap_or(0),
+            sharding_algorithm: self.get(SCYLLA_SHARDING_ALGORITHM).map(str::to_string),
+            partitioner: self.get(SCYLLA_PARTITIONER).map(str::to_string),
+            shard_aware_port: self.parse(SCYLLA_SHARD_AWARE_PORT)?,
+            shard_aware_port_ssl: self.parse(SCYLLA_SHARD_AWARE_PORT_SSL)?,
+        };
+        if shard_info.nr_shards == 0 {
+            return Err(error::Error::Protocol("SCYLLA_NR_SHARDS should be greater than 0".into()));
+        }
+
+        Ok(Some(shard_info))
+    }
+
+    /// Returns a mask of prepared metadata flags which ScyllaDB uses for marking
+    /// lightweight transactions, see `PreparedMetadata::is_lwt`.
+    pub fn scylla_lwt_mask(&self) -> error::Result<Option<i32>> {
+        let value = match self.get(SCYLLA_LWT_ADD_METADATA_MARK) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let mask = value.split(',')
+                        .filter_map(|param| {
+                                        let mut parts = param.splitn(2, '=');
+                                        if parts.next() == Some(LWT_OPTIMIZATION_META_BIT_MASK) {
+                                            parts.next()
+                                        } else {
+                                            None
+                                        }
+                                    })
+                        .next()
+                        .and_then(|mask| mask.trim().parse::<u32>().ok());
+
+        match mask {
+            // the mask is an unsigned 32 bit number while flags are signed ints
+            Some(mask) => Ok(Some(mask as i32)),
+            None => {
+                Err(error::Error::Protocol(format!("Invalid {} value {:?}",
+                                                   SCYLLA_LWT_ADD_METADATA_MARK,
+                                                   value)))
+            }
+        }
+    }
+
+    fn parse<T: FromStr>(&self, name: &str) -> error::Result<Option<T>>
+        where T::Err: Display
+    {
+        self.get(name)
+            .map(|value| {
+                     value.parse().map_err(|err| {
+                                       error::Error::Protocol(format!("Invalid {} value {:?}: {}",
+                                                                      name,
+                                                                      value,
+                                                                      err))
+                                   })
+                 })
+            .transpose()
+    }
+}
+
+/// Sharding information which ScyllaDB sends in `SUPPORTED` response. It describes
+/// a shard (CPU core) which serves a connection and how tokens are distributed between shards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScyllaShardInfo {
+    /// Shard which serves a connection.
+    pub shard: u16,
+    /// Number of shards of a node.
+    pub nr_shards: u16,
+    /// Number of most significant token bits which are ignored by sharding algorithm.
+    pub msb_ignore: u8,
+    /// Sharding algorithm, e.g. `biased-token-round-robin`.
+    pub sharding_algorithm: Option<String>,
+    /// Partitioner class name.
+    pub partitioner: Option<String>,
+    /// Port which maps connections to shards by client side port number.
+    pub shard_aware_port: Option<u16>,
+    /// The same as `shard_aware_port` but for TLS connections.
+    pub shard_aware_port_ssl: Option<u16>,
+}
+
+impl ScyllaShardInfo {
+    /// Returns a shard which owns a Murmur3 token with respect to `biased-token-round-robin`
+    /// sharding algorithm.
+    pub fn shard_of(&self, token: i64) -> u16 {
+        let biased_token = (token as u64).wrapping_add(1 << 63)
+                                         .checked_shl(u32::from(self.msb_ignore))
+                                         .unwrap_or(0);
+        ((u128::from(biased_token) * u128::from(self.nr_shards)) >> 64) as u16
+    }
+}
+
 impl FromCursor for BodyResSupported {
     fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResSupported> {
-        let l =
-            try_from_bytes(cursor_next_value(&mut cursor, SHORT_LEN as u64)?.as_slice())? as usize;
+        let l = try_from_bytes(cursor_next_value(cursor, SHORT_LEN as u64)?.as_slice())?;
+        // every entry has at least a name and a number of values
+        let l = check_count(cursor, l as i64, 2 * SHORT_LEN)?;
         let mut data: HashMap<String, Vec<String>> = HashMap::with_capacity(l);
         for _ in 0..l {
             let name = CString::from_cursor(&mut cursor)?.into_plain();
@@ -55,4 +222,76 @@ mod tests {
         assert_eq!(option_ab[0], "a".to_string());
         assert_eq!(option_ab[1], "b".to_string());
     }
+
+    #[test]
+    fn typed_options() {
+        let mut data = HashMap::new();
+        data.insert(CQL_VERSION.to_string(), vec!["3.4.5".to_string()]);
+        data.insert(COMPRESSION.to_string(), vec!["lz4".to_string(), "snappy".to_string()]);
+        data.insert("SCYLLA_SHARD".to_string(), vec!["0".to_string()]);
+        let supported = BodyResSupported { data };
+
+        assert_eq!(supported.cql_versions(), &["3.4.5".to_string()]);
+        assert_eq!(supported.compression_algorithms(),
+                   &["lz4".to_string(), "snappy".to_string()]);
+        assert!(supported.protocol_versions().is_empty());
+        assert_eq!(supported.extensions().collect::<Vec<_>>(),
+                   vec![("SCYLLA_SHARD", &["0".to_string()][..])]);
+    }
+
+    #[test]
+    fn scylla_shard_info() {
+        let mut data = HashMap::new();
+        let mut option = |name: &str, value: &str| {
+            data.insert(name.to_string(), vec![value.to_string()]);
+        };
+        option("SCYLLA_SHARD", "1");
+        option("SCYLLA_NR_SHARDS", "2");
+        option("SCYLLA_SHARDING_IGNORE_MSB", "12");
+        option("SCYLLA_SHARDING_ALGORITHM", "biased-token-round-robin");
+        option("SCYLLA_SHARD_AWARE_PORT", "19042");
+        let supported = BodyResSupported { data };
+
+        let shard_info = supported.scylla_shard_info().unwrap().unwrap();
+        assert_eq!(shard_info,
+                   ScyllaShardInfo { shard: 1,
+                                     nr_shards: 2,
+                                     msb_ignore: 12,
+                                     sharding_algorithm: Some("biased-token-round-robin".into()),
+                                     partitioner: None,
+                                     shard_aware_port: Some(19042),
+                                     shard_aware_port_ssl: None });
+
+        let shard_info = ScyllaShardInfo { msb_ignore: 0,
+                                           ..shard_info };
+        assert_eq!(shard_info.shard_of(i64::MIN), 0);
+        assert_eq!(shard_info.shard_of(0), 1);
+        assert_eq!(shard_info.shard_of(i64::MAX), 1);
+    }
+
+    #[test]
+    fn scylla_lwt_mask() {
+        let mut data = HashMap::new();
+        data.insert(SCYLLA_LWT_ADD_METADATA_MARK.to_string(),
+                    vec!["LWT_OPTIMIZATION_META_BIT_MASK=2147483648".to_string()]);
+        let supported = BodyResSupported { data };
+        assert_eq!(supported.scylla_lwt_mask().unwrap(), Some(i32::MIN));
+
+        let supported = BodyResSupported { data: HashMap::new() };
+        assert_eq!(supported.scylla_lwt_mask().unwrap(), None);
+
+        let mut data = HashMap::new();
+        data.insert(SCYLLA_LWT_ADD_METADATA_MARK.to_string(), vec!["OTHER=1".to_string()]);
+        assert!(BodyResSupported { data }.scylla_lwt_mask().is_err());
+    }
+
+    #[test]
+    fn not_scylla() {
+        let supported = BodyResSupported { data: HashMap::new() };
+        assert_eq!(supported.scylla_shard_info().unwrap(), None);
+
+        let mut data = HashMap::new();
+        data.insert("SCYLLA_SHARD".to_string(), vec!["x".to_string()]);
+        assert!(BodyResSupported { data }.scylla_shard_info().is_err());
+    }
 }
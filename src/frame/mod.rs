@@ -1,13 +1,34 @@
 //! `frame` module contains general Frame functionality.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Cursor;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use bytes::{BufMut, Bytes};
+
 use crate::frame::frame_response::ResponseBody;
-use crate::types::to_n_bytes;
+use crate::frame::frame_result::{BodyResResultRows, ResResultBody, ResultKind, RowsIter,
+                                 RowsMetadata};
+use crate::types::{check_max_len, CBytes, CBytesMap, INT_MAX};
+use crate::frame::warning::Warning;
 use crate::uuid::Uuid;
 
 /// Number of stream bytes in accordance to protocol.
 pub const STREAM_LEN: usize = 2;
 /// Number of body length bytes in accordance to protocol.
 pub const LENGTH_LEN: usize = 4;
-
+/// Default maximal length of a frame body, 256 MB in accordance to protocol.
+pub const MAX_FRAME_LEN: usize = 256 * 1024 * 1024;
+/// Stream id of `EVENT` frames which are sent by a server without a request.
+pub const EVENT_STREAM: i16 = -1;
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub mod async_io;
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
+pub mod decoder;
+pub mod encoder;
 pub mod events;
 pub mod frame_auth_challenge;
 pub mod frame_auth_response;
@@ -26,61 +47,191 @@ pub mod frame_response;
 pub mod frame_result;
 pub mod frame_startup;
 pub mod frame_supported;
+pub mod parse_mode;
 pub mod parser;
 pub mod parser_async;
 pub mod traits;
+pub mod warning;
 
 use crate::error;
 
 pub use self::traits::*;
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct Frame {
     pub version: Version,
-    pub flags: Vec<Flag>,
+    pub flags: Flags,
     pub opcode: Opcode,
-    pub stream: u16,
-    pub body: Vec<u8>,
+    pub stream: i16,
+    pub body: Bytes,
     pub tracing_id: Option<Uuid>,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
+    /// Custom payload of a response, it's empty unless `Flags::CUSTOM_PAYLOAD` is set.
+    pub custom_payload: HashMap<String, Vec<u8>>,
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // a body of `AUTH_RESPONSE` carries credentials
+        let body: &dyn fmt::Debug = match self.opcode {
+            Opcode::AuthResponse => &"[REDACTED]",
+            _ => &self.body,
+        };
+        f.debug_struct("Frame")
+         .field("version", &self.version)
+         .field("flags", &self.flags)
+         .field("opcode", &self.opcode)
+         .field("stream", &self.stream)
+         .field("body", body)
+         .field("tracing_id", &self.tracing_id)
+         .field("warnings", &self.warnings)
+         .field("custom_payload", &self.custom_payload)
+         .finish()
+    }
 }
 
 impl Frame {
+    /// Shows if the frame is an event pushed by a server rather than a response to a request,
+    /// so it should be routed separately from request/response traffic.
+    pub fn is_event(&self) -> bool {
+        self.stream == EVENT_STREAM
+    }
+
+    /// Returns decoded response body. Values of rows result cells reference
+    /// the frame body instead of being copied.
     pub fn get_body(&self) -> error::Result<ResponseBody> {
-        ResponseBody::from(self.body.as_slice(), &self.opcode)
+        match self.rows_position()? {
+            Some(position) => {
+                BodyResResultRows::from_shared(&self.body.slice(position..))
+                    .map(|rows| ResponseBody::Result(ResResultBody::Rows(rows)))
+            }
+            None => ResponseBody::from(self.body.as_ref(), &self.opcode),
+        }
+    }
+
+    /// Returns response body decoding rows results requested with `SkipMetadata` flag
+    /// with a help of cached rows metadata, e.g. result metadata of a prepared query.
+    pub fn get_body_with_metadata(&self, cached: &RowsMetadata) -> error::Result<ResponseBody> {
+        ResponseBody::from_with_metadata(self.body.as_ref(), &self.opcode, cached)
+    }
+
+    /// Returns a lazy iterator over rows if the frame is a rows result and `None` otherwise.
+    /// Unlike `get_body` rows are decoded one by one directly from frame body.
+    pub fn rows_iter(&self) -> error::Result<Option<RowsIter<'_>>> {
+        match self.rows_position()? {
+            Some(position) => {
+                BodyResResultRows::rows_iter_shared(&self.body[position..], &self.body).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a position of rows result body following result kind if the frame
+    /// is a rows result and `None` otherwise.
+    fn rows_position(&self) -> error::Result<Option<usize>> {
+        if self.opcode != Opcode::Result {
+            return Ok(None);
+        }
+
+        let mut cursor = Cursor::new(self.body.as_ref());
+        match ResultKind::from_cursor(&mut cursor)? {
+            ResultKind::Rows => Ok(Some(cursor.position() as usize)),
+            _ => Ok(None),
+        }
     }
 
     pub fn tracing_id(&self) -> &Option<Uuid> {
         &self.tracing_id
     }
 
-    pub fn warnings(&self) -> &Vec<String> {
+    pub fn warnings(&self) -> &Vec<Warning> {
         &self.warnings
     }
+
+    pub fn custom_payload(&self) -> &HashMap<String, Vec<u8>> {
+        &self.custom_payload
+    }
+
+    /// Sets `Flags::TRACING` of a request, so a server traces it and a response contains
+    /// `tracing_id` of the trace session.
+    pub fn with_tracing(mut self) -> Frame {
+        self.flags.insert(Flags::TRACING);
+        self
+    }
+
+    /// Attaches a custom payload to a `QUERY`, `PREPARE`, `EXECUTE` or `BATCH` request.
+    /// The payload is encoded as `[bytes map]` at the beginning of the body and
+    /// `Flags::CUSTOM_PAYLOAD` is set. Custom payloads are supported since protocol v4.
+    pub fn with_custom_payload(mut self,
+                               payload: HashMap<String, Vec<u8>>)
+                               -> error::Result<Frame> {
+        if cfg!(feature = "v3") {
+            let err = "Custom payload is not supported by protocol v3";
+            return Err(error::Error::InvalidRequest(err.into()));
+        }
+
+        match self.opcode {
+            Opcode::Query | Opcode::Prepare | Opcode::Execute | Opcode::Batch => {}
+            _ => {
+                return Err(error::Error::InvalidRequest(format!("Custom payload cannot be sent \
+                                                                 with {:?} request",
+                                                                self.opcode)));
+            }
+        }
+
+        if self.flags.contains(Flags::CUSTOM_PAYLOAD) {
+            let err = "Custom payload has already been attached";
+            return Err(error::Error::InvalidRequest(err.into()));
+        }
+
+        let map = payload.iter()
+                         .map(|(key, value)| (key.clone(), CBytes::new(value.clone())))
+                         .collect();
+        let mut body = CBytesMap::new(map).into_cbytes();
+        body.extend_from_slice(self.body.as_ref());
+
+        self.flags.insert(Flags::CUSTOM_PAYLOAD);
+        self.body = body.into();
+        self.custom_payload = payload;
+        Ok(self)
+    }
+}
+
+/// Returns a random stream id of a request. Negative ids are reserved for frames
+/// initiated by a server, so they are never returned.
+pub(crate) fn random_stream() -> i16 {
+    (rand::random::<u16>() & i16::MAX as u16) as i16
+}
+
+impl TryIntoBytes for Frame {
+    fn check(&self) -> error::Result<()> {
+        check_max_len(self.body.len(), INT_MAX)
+    }
 }
 
 impl<'a> IntoBytes for Frame {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v = vec![];
-
-        let version_bytes = self.version.as_byte();
-        let flag_bytes = Flag::many_to_cbytes(&self.flags);
-        let opcode_bytes = self.opcode.as_byte();
-        let body_len = self.body.len();
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
+        v
+    }
 
-        v.push(version_bytes);
-        v.push(flag_bytes);
-        v.extend_from_slice(to_n_bytes(self.stream as u64, STREAM_LEN).as_slice());
-        v.push(opcode_bytes);
-        v.extend_from_slice(to_n_bytes(body_len as u64, LENGTH_LEN).as_slice());
-        v.extend_from_slice(self.body.as_slice());
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_u8(self.version.as_byte());
+        buf.put_u8(self.flags.bits());
+        buf.put_i16(self.stream);
+        buf.put_u8(self.opcode.as_byte());
+        buf.put_uint(self.body.len() as u64, LENGTH_LEN);
+        buf.put_slice(self.body.as_ref());
+    }
 
-        v
+    fn serialized_len(&self) -> usize {
+        decoder::HEADER_LEN + self.body.len()
     }
 }
 
 /// Frame's version
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Version {
     Request,
     Response,
@@ -92,10 +243,13 @@ impl Version {
 
     /// It returns an actual Cassandra request frame version that CDRS can work with.
     /// This version is based on selected feature - on of `v3`, `v4` or `v5`.
+    /// `v5` is experimental, see `Handshake::start`.
     fn request_version() -> u8 {
         if cfg!(feature = "v3") {
             0x03
-        } else if cfg!(feature = "v4") || cfg!(feature = "v5") {
+        } else if cfg!(feature = "v5") {
+            0x05
+        } else if cfg!(feature = "v4") {
             0x04
         } else {
             panic!(
@@ -111,7 +265,9 @@ impl Version {
     fn response_version() -> u8 {
         if cfg!(feature = "v3") {
             0x83
-        } else if cfg!(feature = "v4") || cfg!(feature = "v5") {
+        } else if cfg!(feature = "v5") {
+            0x85
+        } else if cfg!(feature = "v4") {
             0x84
         } else {
             panic!(
@@ -132,133 +288,153 @@ impl AsByte for Version {
     }
 }
 
-impl From<Vec<u8>> for Version {
-    fn from(v: Vec<u8>) -> Version {
+impl TryFrom<&[u8]> for Version {
+    type Error = error::Error;
+
+    /// Decodes a version byte. A byte of a protocol version which differs from the selected
+    /// one is reported as `UnsupportedVersion` error along with its direction bit.
+    fn try_from(v: &[u8]) -> error::Result<Version> {
         if v.len() != Self::BYTE_LENGTH {
-            error!(
-                "Unexpected Cassandra verion. Should has {} byte(-s), got {:?}",
-                Self::BYTE_LENGTH,
-                v
-            );
-            panic!(
-                "Unexpected Cassandra verion. Should has {} byte(-s), got {:?}",
-                Self::BYTE_LENGTH,
-                v
-            );
+            return Err(error::Error::Protocol(format!("Unexpected Cassandra version. \
+                                                       Should has {} byte(-s), got {:?}",
+                                                      Self::BYTE_LENGTH,
+                                                      v)));
         }
-        let version = v[0];
-        let req = Version::request_version();
-        let res = Version::response_version();
-
-        if version == req {
-            Version::Request
-        } else if version == res {
-            Version::Response
-        } else {
-            error!(
-                "Unexpected Cassandra version {:?}, either {:?} or {:?} is expected",
-                version, req, res
-            );
-            panic!(
-                "Unexpected Cassandra version {:?}, either {:?} or {:?} is expected",
-                version, req, res
-            );
+
+        match v[0] {
+            version if version == Version::request_version() => Ok(Version::Request),
+            version if version == Version::response_version() => Ok(Version::Response),
+            version => Err(error::Error::UnsupportedVersion(version)),
         }
     }
 }
 
-/// Frame's flag
-// Is not implemented functionality. Only Igonore works for now
-#[derive(Debug, PartialEq)]
-pub enum Flag {
-    Compression,
-    Tracing,
-    CustomPayload,
-    Warning,
-    Ignore,
-}
+const FLAG_COMPRESSION: u8 = 0x01;
+const FLAG_TRACING: u8 = 0x02;
+const FLAG_CUSTOM_PAYLOAD: u8 = 0x04;
+const FLAG_WARNING: u8 = 0x08;
+const FLAG_BETA: u8 = 0x10;
+
+/// Set of frame header flags, e.g. `Flags::COMPRESSION | Flags::TRACING`.
+/// Unknown flags are ignored in accordance to protocol.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Flags(u8);
 
-impl Flag {
+const FLAG_NAMES: [(Flags, &str); 5] = [(Flags::COMPRESSION, "COMPRESSION"),
+                                        (Flags::TRACING, "TRACING"),
+                                        (Flags::CUSTOM_PAYLOAD, "CUSTOM_PAYLOAD"),
+                                        (Flags::WARNING, "WARNING"),
+                                        (Flags::BETA, "BETA")];
+
+impl Flags {
     /// Number of flag bytes in accordance to protocol.
     const BYTE_LENGTH: usize = 1;
 
-    /// It returns selected flags collection.
-    pub fn get_collection(flags: u8) -> Vec<Flag> {
-        let mut found_flags: Vec<Flag> = vec![];
+    /// Frame body is compressed.
+    pub const COMPRESSION: Flags = Flags(FLAG_COMPRESSION);
+    /// Request asks for tracing, response body starts with a tracing id.
+    pub const TRACING: Flags = Flags(FLAG_TRACING);
+    /// Frame body contains a custom payload, `[bytes map]` which precedes the rest of a body.
+    pub const CUSTOM_PAYLOAD: Flags = Flags(FLAG_CUSTOM_PAYLOAD);
+    /// Response body contains warnings.
+    pub const WARNING: Flags = Flags(FLAG_WARNING);
+    /// Request uses a beta version of protocol (protocol v5).
+    pub const BETA: Flags = Flags(FLAG_BETA);
+
+    /// Returns a set without any flag.
+    pub const fn empty() -> Flags {
+        Flags(0)
+    }
 
-        if Flag::has_compression(flags) {
-            found_flags.push(Flag::Compression);
-        }
+    /// Returns a set of all known flags.
+    pub const fn all() -> Flags {
+        Flags(FLAG_COMPRESSION | FLAG_TRACING | FLAG_CUSTOM_PAYLOAD | FLAG_WARNING | FLAG_BETA)
+    }
 
-        if Flag::has_tracing(flags) {
-            found_flags.push(Flag::Tracing);
-        }
+    /// Returns a set of flags from a header byte ignoring unknown bits.
+    pub const fn from_bits_truncate(bits: u8) -> Flags {
+        Flags(bits & Flags::all().0)
+    }
 
-        if Flag::has_custom_payload(flags) {
-            found_flags.push(Flag::CustomPayload);
-        }
+    /// Returns a header byte of the set.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
 
-        if Flag::has_warning(flags) {
-            found_flags.push(Flag::Warning);
-        }
+    /// Shows if no flag is set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Shows if all the flags of `other` are set.
+    pub const fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
 
-        found_flags
+    /// Returns a set of flags which are set either in `self` or in `other`.
+    pub const fn union(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
     }
 
-    /// The method converts a serie of `Flag`-s into a single byte.
-    pub fn many_to_cbytes(flags: &Vec<Flag>) -> u8 {
-        flags
-            .iter()
-            .fold(Flag::Ignore.as_byte(), |acc, f| acc | f.as_byte())
+    /// Sets flags of `other`.
+    pub fn insert(&mut self, other: Flags) {
+        self.0 |= other.0;
     }
 
-    /// Indicates if flags contains `Flag::Compression`
-    pub fn has_compression(flags: u8) -> bool {
-        (flags & Flag::Compression.as_byte()) > 0
+    /// Unsets flags of `other`.
+    pub fn remove(&mut self, other: Flags) {
+        self.0 &= !other.0;
     }
 
-    /// Indicates if flags contains `Flag::Tracing`
-    pub fn has_tracing(flags: u8) -> bool {
-        (flags & Flag::Tracing.as_byte()) > 0
+    /// Sets or unsets flags of `other` depending on `value`.
+    pub fn set(&mut self, other: Flags, value: bool) {
+        if value {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
     }
+}
 
-    /// Indicates if flags contains `Flag::CustomPayload`
-    pub fn has_custom_payload(flags: u8) -> bool {
-        (flags & Flag::CustomPayload.as_byte()) > 0
+impl BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        self.union(other)
     }
+}
 
-    /// Indicates if flags contains `Flag::Warning`
-    pub fn has_warning(flags: u8) -> bool {
-        (flags & Flag::Warning.as_byte()) > 0
+impl BitOrAssign for Flags {
+    fn bitor_assign(&mut self, other: Flags) {
+        self.insert(other);
     }
 }
 
-impl AsByte for Flag {
-    fn as_byte(&self) -> u8 {
-        match self {
-            &Flag::Compression => 0x01,
-            &Flag::Tracing => 0x02,
-            &Flag::CustomPayload => 0x04,
-            &Flag::Warning => 0x08,
-            &Flag::Ignore => 0x00,
-            // assuming that ingoring value whould be other than [0x01, 0x02, 0x04, 0x08]
-        }
+impl BitAnd for Flags {
+    type Output = Flags;
+
+    fn bitand(self, other: Flags) -> Flags {
+        Flags(self.0 & other.0)
     }
 }
 
-impl From<u8> for Flag {
-    fn from(f: u8) -> Flag {
-        match f {
-            0x01 => Flag::Compression,
-            0x02 => Flag::Tracing,
-            0x04 => Flag::CustomPayload,
-            0x08 => Flag::Warning,
-            _ => Flag::Ignore, // ignore by specification
-        }
+impl fmt::Debug for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = FLAG_NAMES.iter()
+                              .filter(|(flag, _)| self.contains(*flag))
+                              .map(|(_, name)| *name)
+                              .collect::<Vec<_>>();
+        write!(f, "Flags({})", names.join(" | "))
+    }
+}
+
+impl AsByte for Flags {
+    fn as_byte(&self) -> u8 {
+        self.bits()
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Opcode {
     Error,
     Startup,
@@ -281,6 +457,19 @@ pub enum Opcode {
 impl Opcode {
     // Number of opcode bytes in accordance to protocol.
     pub const BYTE_LENGTH: usize = 1;
+
+    /// Shows if the opcode is sent by clients only.
+    pub fn is_request(&self) -> bool {
+        matches!(*self,
+                 Opcode::Startup
+                 | Opcode::Options
+                 | Opcode::Query
+                 | Opcode::Prepare
+                 | Opcode::Execute
+                 | Opcode::Register
+                 | Opcode::Batch
+                 | Opcode::AuthResponse)
+    }
 }
 
 impl AsByte for Opcode {
@@ -306,9 +495,13 @@ impl AsByte for Opcode {
     }
 }
 
-impl From<u8> for Opcode {
-    fn from(b: u8) -> Opcode {
-        match b {
+impl TryFrom<u8> for Opcode {
+    type Error = error::Error;
+
+    /// Unknown opcodes are reported as `UnknownOpcode` error, so a corrupted frame
+    /// doesn't abort a process.
+    fn try_from(b: u8) -> error::Result<Opcode> {
+        let opcode = match b {
             0x00 => Opcode::Error,
             0x01 => Opcode::Startup,
             0x02 => Opcode::Ready,
@@ -325,18 +518,49 @@ impl From<u8> for Opcode {
             0x0E => Opcode::AuthChallenge,
             0x0F => Opcode::AuthResponse,
             0x10 => Opcode::AuthSuccess,
-            _ => unreachable!(),
-        }
+            _ => return Err(error::Error::UnknownOpcode(b)),
+        };
+
+        Ok(opcode)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::query::BatchQueryBuilder;
     use crate::frame::traits::AsByte;
 
     #[test]
-    #[cfg(not(feature = "v3"))]
+    fn frame_clone_shares_body() {
+        let frame = Frame::new_req_startup(None);
+        let clone = frame.clone();
+        assert_eq!(clone.body, frame.body);
+        assert_eq!(clone.body.as_ptr(), frame.body.as_ptr());
+    }
+
+    #[test]
+    fn serialize_into_buffer() {
+        let mut frame = Frame::new_req_options();
+        frame.stream = 0x0102;
+        frame.body = vec![0xaa, 0xbb].into();
+
+        let mut buf = bytes::BytesMut::from(&[0xff][..]);
+        frame.serialize_into(&mut buf);
+        assert_eq!(&buf[..],
+                   &[0xff, Version::Request.as_byte(), 0x00, 0x01, 0x02, 0x05, 0, 0, 0, 2, 0xaa,
+                     0xbb][..]);
+        assert_eq!(&buf[1..], frame.into_cbytes().as_slice());
+    }
+
+    #[test]
+    fn request_stream_is_not_negative() {
+        assert!((0..1000).all(|_| random_stream() >= 0));
+        assert!(!Frame::new_req_options().is_event());
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "v3", feature = "v5")))]
     fn test_frame_version_as_byte() {
         let request_version = Version::Request;
         assert_eq!(request_version.as_byte(), 0x04);
@@ -354,85 +578,110 @@ mod tests {
     }
 
     #[test]
-    #[cfg(not(feature = "v3"))]
-    fn test_frame_version_from() {
-        let request: Vec<u8> = vec![0x04];
-        assert_eq!(Version::from(request), Version::Request);
-        let response: Vec<u8> = vec![0x84];
-        assert_eq!(Version::from(response), Version::Response);
+    #[cfg(not(any(feature = "v3", feature = "v5")))]
+    fn test_frame_version_try_from() {
+        assert_eq!(Version::try_from(&[0x04][..]).unwrap(), Version::Request);
+        assert_eq!(Version::try_from(&[0x84][..]).unwrap(), Version::Response);
+        assert!(matches!(Version::try_from(&[0x83][..]),
+                         Err(error::Error::UnsupportedVersion(0x83))));
+        assert!(matches!(Version::try_from(&[0x05][..]),
+                         Err(error::Error::UnsupportedVersion(0x05))));
+        assert!(Version::try_from(&[][..]).is_err());
     }
 
     #[test]
-    #[cfg(feature = "v3")]
-    fn test_frame_version_from_v3() {
-        let request: Vec<u8> = vec![0x03];
-        assert_eq!(Version::from(request), Version::Request);
-        let response: Vec<u8> = vec![0x83];
-        assert_eq!(Version::from(response), Version::Response);
+    #[cfg(all(feature = "v5", not(feature = "v3")))]
+    fn test_frame_version_as_byte_v5() {
+        assert_eq!(Version::Request.as_byte(), 0x05);
+        assert_eq!(Version::Response.as_byte(), 0x85);
     }
 
     #[test]
-    fn test_flag_from() {
-        assert_eq!(Flag::from(0x01 as u8), Flag::Compression);
-        assert_eq!(Flag::from(0x02 as u8), Flag::Tracing);
-        assert_eq!(Flag::from(0x04 as u8), Flag::CustomPayload);
-        assert_eq!(Flag::from(0x08 as u8), Flag::Warning);
-        // rest should be interpreted as Ignore
-        assert_eq!(Flag::from(0x10 as u8), Flag::Ignore);
-        assert_eq!(Flag::from(0x31 as u8), Flag::Ignore);
+    #[cfg(feature = "v3")]
+    fn test_frame_version_try_from_v3() {
+        assert_eq!(Version::try_from(&[0x03][..]).unwrap(), Version::Request);
+        assert_eq!(Version::try_from(&[0x83][..]).unwrap(), Version::Response);
     }
 
     #[test]
-    fn test_flag_as_byte() {
-        assert_eq!(Flag::Compression.as_byte(), 0x01);
-        assert_eq!(Flag::Tracing.as_byte(), 0x02);
-        assert_eq!(Flag::CustomPayload.as_byte(), 0x04);
-        assert_eq!(Flag::Warning.as_byte(), 0x08);
+    fn test_flags_from_bits() {
+        assert_eq!(Flags::from_bits_truncate(0x01), Flags::COMPRESSION);
+        assert_eq!(Flags::from_bits_truncate(0x02), Flags::TRACING);
+        assert_eq!(Flags::from_bits_truncate(0x04), Flags::CUSTOM_PAYLOAD);
+        assert_eq!(Flags::from_bits_truncate(0x08), Flags::WARNING);
+        assert_eq!(Flags::from_bits_truncate(0x10), Flags::BETA);
+        // unknown flags should be ignored
+        assert_eq!(Flags::from_bits_truncate(0x20), Flags::empty());
+        assert_eq!(Flags::from_bits_truncate(0x31), Flags::COMPRESSION | Flags::BETA);
     }
 
     #[test]
-    fn test_flag_has_x() {
-        assert!(Flag::has_compression(0x01));
-        assert!(!Flag::has_compression(0x02));
-
-        assert!(Flag::has_tracing(0x02));
-        assert!(!Flag::has_tracing(0x01));
+    fn test_flags_as_byte() {
+        assert_eq!(Flags::COMPRESSION.as_byte(), 0x01);
+        assert_eq!(Flags::TRACING.as_byte(), 0x02);
+        assert_eq!(Flags::CUSTOM_PAYLOAD.as_byte(), 0x04);
+        assert_eq!(Flags::WARNING.as_byte(), 0x08);
+        assert_eq!(Flags::BETA.as_byte(), 0x10);
+        assert_eq!(Flags::all().as_byte(), 1 | 2 | 4 | 8 | 0x10);
+        assert_eq!((Flags::COMPRESSION | Flags::WARNING).as_byte(), 1 | 8);
+        assert_eq!(Flags::empty().as_byte(), 0);
+    }
 
-        assert!(Flag::has_custom_payload(0x04));
-        assert!(!Flag::has_custom_payload(0x02));
+    #[test]
+    fn test_flags_contains() {
+        let mut flags = Flags::TRACING | Flags::CUSTOM_PAYLOAD;
+        assert!(flags.contains(Flags::TRACING));
+        assert!(flags.contains(Flags::CUSTOM_PAYLOAD));
+        assert!(!flags.contains(Flags::TRACING | Flags::WARNING));
+        assert_eq!(flags & Flags::TRACING, Flags::TRACING);
+
+        flags |= Flags::WARNING;
+        flags.remove(Flags::TRACING);
+        flags.set(Flags::CUSTOM_PAYLOAD, false);
+        assert_eq!(flags, Flags::WARNING);
+        flags.remove(Flags::WARNING);
+        assert!(flags.is_empty());
+        assert_eq!(format!("{:?}", Flags::COMPRESSION | Flags::CUSTOM_PAYLOAD),
+                   "Flags(COMPRESSION | CUSTOM_PAYLOAD)");
+    }
 
-        assert!(Flag::has_warning(0x08));
-        assert!(!Flag::has_warning(0x01));
+    #[test]
+    #[cfg(not(feature = "v3"))]
+    fn test_frame_with_custom_payload() {
+        let query = Frame::new_req_prepare("SELECT * FROM t".into(), Flags::empty());
+        let body = query.body.clone();
+        let mut payload = HashMap::new();
+        payload.insert("k".to_string(), vec![1, 2]);
+
+        let query = query.with_custom_payload(payload.clone()).unwrap();
+        assert!(query.flags.contains(Flags::CUSTOM_PAYLOAD));
+        assert_eq!(query.custom_payload(), &payload);
+        let mut expected = vec![0, 1, 0, 1, b'k', 0, 0, 0, 2, 1, 2];
+        expected.extend_from_slice(&body);
+        assert_eq!(query.body.as_ref(), expected.as_slice());
+        assert!(query.with_custom_payload(HashMap::new()).is_err());
+
+        assert!(Frame::new_req_options().with_custom_payload(HashMap::new()).is_err());
     }
 
     #[test]
-    fn test_flag_many_to_cbytes() {
-        let all = vec![
-            Flag::Compression,
-            Flag::Tracing,
-            Flag::CustomPayload,
-            Flag::Warning,
-        ];
-        assert_eq!(Flag::many_to_cbytes(&all), 1 | 2 | 4 | 8);
-        let some = vec![Flag::Compression, Flag::Warning];
-        assert_eq!(Flag::many_to_cbytes(&some), 1 | 8);
-        let one = vec![Flag::Compression];
-        assert_eq!(Flag::many_to_cbytes(&one), 1);
+    fn test_frame_with_tracing() {
+        let query = Frame::new_req_prepare("SELECT * FROM t".into(), Flags::empty()).with_tracing();
+        assert_eq!(query.flags, Flags::TRACING);
+        assert_eq!(query.into_cbytes()[1], 0x02);
+
+        let batch = BatchQueryBuilder::new().finalize_frame(Flags::empty())
+                                            .unwrap()
+                                            .with_tracing()
+                                            .with_tracing();
+        assert_eq!(batch.flags, Flags::TRACING);
     }
 
     #[test]
-    fn test_flag_get_collection() {
-        let all = vec![
-            Flag::Compression,
-            Flag::Tracing,
-            Flag::CustomPayload,
-            Flag::Warning,
-        ];
-        assert_eq!(Flag::get_collection(1 | 2 | 4 | 8), all);
-        let some = vec![Flag::Compression, Flag::Warning];
-        assert_eq!(Flag::get_collection(1 | 8), some);
-        let one = vec![Flag::Compression];
-        assert_eq!(Flag::get_collection(1), one);
+    fn test_frame_custom_payload_flag() {
+        let mut frame = Frame::new_req_options();
+        frame.flags.insert(Flags::CUSTOM_PAYLOAD);
+        assert_eq!(frame.into_cbytes()[1], 0x04);
     }
 
     #[test]
@@ -456,22 +705,24 @@ mod tests {
     }
 
     #[test]
-    fn test_opcode_from() {
-        assert_eq!(Opcode::from(0x00), Opcode::Error);
-        assert_eq!(Opcode::from(0x01), Opcode::Startup);
-        assert_eq!(Opcode::from(0x02), Opcode::Ready);
-        assert_eq!(Opcode::from(0x03), Opcode::Authenticate);
-        assert_eq!(Opcode::from(0x05), Opcode::Options);
-        assert_eq!(Opcode::from(0x06), Opcode::Supported);
-        assert_eq!(Opcode::from(0x07), Opcode::Query);
-        assert_eq!(Opcode::from(0x08), Opcode::Result);
-        assert_eq!(Opcode::from(0x09), Opcode::Prepare);
-        assert_eq!(Opcode::from(0x0A), Opcode::Execute);
-        assert_eq!(Opcode::from(0x0B), Opcode::Register);
-        assert_eq!(Opcode::from(0x0C), Opcode::Event);
-        assert_eq!(Opcode::from(0x0D), Opcode::Batch);
-        assert_eq!(Opcode::from(0x0E), Opcode::AuthChallenge);
-        assert_eq!(Opcode::from(0x0F), Opcode::AuthResponse);
-        assert_eq!(Opcode::from(0x10), Opcode::AuthSuccess);
+    fn test_opcode_try_from() {
+        assert_eq!(Opcode::try_from(0x00).unwrap(), Opcode::Error);
+        assert_eq!(Opcode::try_from(0x01).unwrap(), Opcode::Startup);
+        assert_eq!(Opcode::try_from(0x02).unwrap(), Opcode::Ready);
+        assert_eq!(Opcode::try_from(0x03).unwrap(), Opcode::Authenticate);
+        assert_eq!(Opcode::try_from(0x05).unwrap(), Opcode::Options);
+        assert_eq!(Opcode::try_from(0x06).unwrap(), Opcode::Supported);
+        assert_eq!(Opcode::try_from(0x07).unwrap(), Opcode::Query);
+        assert_eq!(Opcode::try_from(0x08).unwrap(), Opcode::Result);
+        assert_eq!(Opcode::try_from(0x09).unwrap(), Opcode::Prepare);
+        assert_eq!(Opcode::try_from(0x0A).unwrap(), Opcode::Execute);
+        assert_eq!(Opcode::try_from(0x0B).unwrap(), Opcode::Register);
+        assert_eq!(Opcode::try_from(0x0C).unwrap(), Opcode::Event);
+        assert_eq!(Opcode::try_from(0x0D).unwrap(), Opcode::Batch);
+        assert_eq!(Opcode::try_from(0x0E).unwrap(), Opcode::AuthChallenge);
+        assert_eq!(Opcode::try_from(0x0F).unwrap(), Opcode::AuthResponse);
+        assert_eq!(Opcode::try_from(0x10).unwrap(), Opcode::AuthSuccess);
+        assert!(matches!(Opcode::try_from(0x04), Err(error::Error::UnknownOpcode(0x04))));
+        assert!(matches!(Opcode::try_from(0xff), Err(error::Error::UnknownOpcode(0xff))));
     }
 }
@@ -0,0 +1,123 @@
+//! Validation of received frames against the protocol specification. Servers and proxies
+//! occasionally send frames which deviate from the specification, e.g. with unknown header
+//! flags or with bytes left after a body. `ParseMode` selects whether `FrameDecoder` rejects
+//! such frames or passes them through reporting deviations.
+use std::fmt;
+
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::{Flags, Frame, Opcode, EVENT_STREAM};
+
+/// How a decoder treats frames which deviate from the protocol specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// A deviation is returned as `Error::Protocol`, e.g. for conformance testing.
+    Strict,
+    /// A frame is returned as is and deviations are passed to a `DeviationHandler`
+    /// or logged, e.g. for proxies which shouldn't break a connection because
+    /// of a misbehaving server.
+    Lenient,
+}
+
+/// Callback which is invoked with every deviation found in `ParseMode::Lenient`.
+pub type DeviationHandler = Box<dyn Fn(&Deviation) + Send + Sync>;
+
+/// Kind of a deviation from the protocol specification.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviationKind {
+    /// Header flags have bits which are not defined by protocol.
+    UnknownFlags(u8),
+    /// Opcode of a request is received in a response frame.
+    RequestOpcode,
+    /// `EVENT` is received on a stream other than `EVENT_STREAM` or another response
+    /// is received on `EVENT_STREAM`.
+    UnexpectedStream,
+    /// Number of bytes left in a body after it has been decoded.
+    TrailingBytes(usize),
+    /// A body cannot be decoded, e.g. a count exceeds the number of remaining bytes.
+    InvalidBody(String),
+}
+
+/// Deviation from the protocol specification found in a received frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deviation {
+    pub kind: DeviationKind,
+    /// Opcode of a frame.
+    pub opcode: Opcode,
+    /// Stream id of a frame.
+    pub stream: i16,
+}
+
+impl Deviation {
+    pub fn new(kind: DeviationKind, opcode: Opcode, stream: i16) -> Deviation {
+        Deviation { kind,
+                    opcode,
+                    stream }
+    }
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            DeviationKind::UnknownFlags(flags) => write!(f, "Unknown header flags {:#04x}", flags)?,
+            DeviationKind::RequestOpcode => write!(f, "Request opcode in response frame")?,
+            DeviationKind::UnexpectedStream => write!(f, "Unexpected stream of {:?}", self.opcode)?,
+            DeviationKind::TrailingBytes(len) => write!(f, "{} trailing bytes in body", len)?,
+            DeviationKind::InvalidBody(ref err) => write!(f, "Invalid body: {}", err)?,
+        }
+        write!(f, " ({:?} frame, stream {})", self.opcode, self.stream)
+    }
+}
+
+/// Returns deviations of a decoded frame. `raw_flags` are header flags as they were
+/// received, since unknown bits are dropped from `Frame::flags`. Unlike header checks
+/// the body check decodes a whole body.
+pub(crate) fn check_frame(frame: &Frame, raw_flags: u8) -> Vec<Deviation> {
+    let mut kinds = vec![];
+
+    let unknown_flags = raw_flags & !Flags::all().bits();
+    if unknown_flags != 0 {
+        kinds.push(DeviationKind::UnknownFlags(unknown_flags));
+    }
+
+    if (frame.opcode == Opcode::Event) != (frame.stream == EVENT_STREAM) {
+        kinds.push(DeviationKind::UnexpectedStream);
+    }
+
+    if frame.opcode.is_request() {
+        kinds.push(DeviationKind::RequestOpcode);
+    } else {
+        match ResponseBody::trailing_len(frame.body.as_ref(), &frame.opcode) {
+            Ok(0) => {}
+            Ok(len) => kinds.push(DeviationKind::TrailingBytes(len)),
+            Err(err) => kinds.push(DeviationKind::InvalidBody(err.to_string())),
+        }
+    }
+
+    kinds.into_iter()
+         .map(|kind| Deviation::new(kind, frame.opcode.clone(), frame.stream))
+         .collect()
+}
+
+/// Applies a parse mode to deviations of a frame: strict mode fails on the first one
+/// while lenient mode passes them to `handler` or logs them if there is no handler.
+pub(crate) fn apply_mode(mode: ParseMode,
+                         deviations: Vec<Deviation>,
+                         handler: Option<&DeviationHandler>)
+                         -> error::Result<()> {
+    match mode {
+        ParseMode::Strict => match deviations.into_iter().next() {
+            Some(deviation) => Err(error::Error::Protocol(deviation.to_string())),
+            None => Ok(()),
+        },
+        ParseMode::Lenient => {
+            for deviation in &deviations {
+                match handler {
+                    Some(handler) => handler(deviation),
+                    None => warn!("{}", deviation),
+                }
+            }
+            Ok(())
+        }
+    }
+}
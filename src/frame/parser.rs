@@ -1,23 +1,32 @@
 use std::cell::RefCell;
-use std::io::{Cursor, Read};
+use std::convert::TryFrom;
+use std::io::Read;
 
+use bytes::Bytes;
+
+use super::decoder::{check_frame_len, frame_from_body};
 use super::*;
 use crate::compression::Compressor;
 use crate::error;
 use crate::frame::frame_response::ResponseBody;
-use crate::frame::FromCursor;
-use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_bytes, from_u16_bytes, CStringList, UUID_LEN};
+use crate::types::{from_bytes, from_i16_bytes};
+
+pub fn parse_frame(
+    cursor_cell: &RefCell<dyn Read>,
+    compressor: &dyn Compressor,
+) -> error::Result<Frame> {
+    parse_frame_with_max_len(cursor_cell, compressor, MAX_FRAME_LEN)
+}
 
-pub fn parse_frame<E>(
+/// The same as `parse_frame` but `FrameTooLarge` error is returned without reading
+/// a body if it's longer than `max_len`.
+pub fn parse_frame_with_max_len(
     cursor_cell: &RefCell<dyn Read>,
-    compressor: &dyn Compressor<CompressorError = E>,
-) -> error::Result<Frame>
-where
-    E: std::error::Error,
-{
+    compressor: &dyn Compressor,
+    max_len: usize,
+) -> error::Result<Frame> {
     let mut version_bytes = [0; Version::BYTE_LENGTH];
-    let mut flag_bytes = [0; Flag::BYTE_LENGTH];
+    let mut flag_bytes = [0; Flags::BYTE_LENGTH];
     let mut opcode_bytes = [0; Opcode::BYTE_LENGTH];
     let mut stream_bytes = [0; STREAM_LEN];
     let mut length_bytes = [0; LENGTH_LEN];
@@ -30,11 +39,12 @@ where
     cursor.read_exact(&mut opcode_bytes)?;
     cursor.read_exact(&mut length_bytes)?;
 
-    let version = Version::from(version_bytes.to_vec());
-    let flags = Flag::get_collection(flag_bytes[0]);
-    let stream = from_u16_bytes(&stream_bytes);
-    let opcode = Opcode::from(opcode_bytes[0]);
+    let version = Version::try_from(&version_bytes[..])?;
+    let flags = Flags::from_bits_truncate(flag_bytes[0]);
+    let stream = from_i16_bytes(&stream_bytes);
+    let opcode = Opcode::try_from(opcode_bytes[0])?;
     let length = from_bytes(&length_bytes) as usize;
+    check_frame_len(length, max_len)?;
 
     // FIXME:
     //   Once a new feature to safely pass an uninitialized buffer to `Read` becomes available,
@@ -42,48 +52,17 @@ where
     let mut body_bytes = vec![0; length];
     cursor.read_exact(&mut body_bytes)?;
 
-    let full_body = if flags.iter().any(|flag| flag == &Flag::Compression) {
+    let full_body = if flags.contains(Flags::COMPRESSION) {
+        let mut decoded = vec![];
         compressor
-            .decode(body_bytes)
-            .map_err(|err| error::Error::from(err.to_string()))?
+            .decode_with_max_len(&body_bytes, &mut decoded, max_len)
+            .map_err(|err| error::Error::Compression(err.to_string()))?;
+        decoded
     } else {
         body_bytes
     };
 
-    // Use cursor to get tracing id, warnings and actual body
-    let mut body_cursor = Cursor::new(full_body.as_slice());
-
-    let tracing_id = if flags.iter().any(|flag| flag == &Flag::Tracing) {
-        let mut tracing_bytes = Vec::with_capacity(UUID_LEN);
-        unsafe {
-            tracing_bytes.set_len(UUID_LEN);
-        }
-        body_cursor.read_exact(&mut tracing_bytes)?;
-
-        decode_timeuuid(tracing_bytes.as_slice()).ok()
-    } else {
-        None
-    };
-
-    let warnings = if flags.iter().any(|flag| flag == &Flag::Warning) {
-        CStringList::from_cursor(&mut body_cursor)?.into_plain()
-    } else {
-        vec![]
-    };
-
-    let mut body = vec![];
-
-    body_cursor.read_to_end(&mut body)?;
-
-    let frame = Frame {
-        version: version,
-        flags: flags,
-        opcode: opcode,
-        stream: stream,
-        body: body,
-        tracing_id: tracing_id,
-        warnings: warnings,
-    };
+    let frame = frame_from_body(version, flags, stream, opcode, Bytes::from(full_body))?;
 
     convert_frame_into_result(frame)
 }
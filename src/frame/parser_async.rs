@@ -1,99 +1,129 @@
-use std::io::{Cursor, Read};
+use std::convert::TryFrom;
+use std::io::{self, Read};
 
+use bytes::Bytes;
+
+use super::decoder::{check_frame_len, frame_from_body, HEADER_LEN};
 use super::*;
 use crate::compression::Compressor;
 use crate::error;
 use crate::frame::frame_response::ResponseBody;
-use crate::frame::FromCursor;
-use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_bytes, from_u16_bytes, CStringList, UUID_LEN};
-
-macro_rules! proceed_if_filled {
-  ($res: expr, $expected_len: expr) => {
-    if $expected_len > $res? {
-      return Ok(None);
-    }
-  };
-}
+use crate::types::{from_bytes, from_i16_bytes};
 
-pub fn parse_frame_async<E, C>(
+/// Reads a frame from a reader. Short reads are retried until the whole frame is read,
+/// so the function blocks until a frame is received if the reader does.
+///
+/// `Ok(None)` is returned only if the reader is closed before the first byte of a frame,
+/// i.e. there are no more frames. If the reader is closed in the middle of a frame
+/// `UnexpectedEof` IO error is returned. Non-blocking readers should be used with
+/// `FrameDecoder` instead since bytes read before `WouldBlock` error are lost.
+pub fn parse_frame_async<C>(
   cursor: &mut C,
-  compressor: &dyn Compressor<CompressorError = E>,
+  compressor: &dyn Compressor,
 ) -> error::Result<Option<Frame>>
 where
-  E: std::error::Error,
   C: Read,
 {
-  let mut version_bytes = [0; Version::BYTE_LENGTH];
-  let mut flag_bytes = [0; Flag::BYTE_LENGTH];
-  let mut opcode_bytes = [0; Opcode::BYTE_LENGTH];
-  let mut stream_bytes = [0; STREAM_LEN];
-  let mut length_bytes = [0; LENGTH_LEN];
-
-  // NOTE: order of reads matters
-  proceed_if_filled!(cursor.read(&mut version_bytes), Version::BYTE_LENGTH);
-  proceed_if_filled!(cursor.read(&mut flag_bytes), Flag::BYTE_LENGTH);
-  proceed_if_filled!(cursor.read(&mut stream_bytes), STREAM_LEN);
-  proceed_if_filled!(cursor.read(&mut opcode_bytes), Opcode::BYTE_LENGTH);
-  proceed_if_filled!(cursor.read(&mut length_bytes), LENGTH_LEN);
-
-  let version = Version::from(version_bytes.to_vec());
-  let flags = Flag::get_collection(flag_bytes[0]);
-  let stream = from_u16_bytes(&stream_bytes);
-  let opcode = Opcode::from(opcode_bytes[0]);
-  let length = from_bytes(&length_bytes) as usize;
-
-  // FIXME:
-    //   Once a new feature to safely pass an uninitialized buffer to `Read` becomes available,
-    //   we no longer need to zero-initialize `body_bytes` before passing to `Read`.
-  let mut body_bytes = vec![0; length];
+  parse_frame_async_with_max_len(cursor, compressor, MAX_FRAME_LEN)
+}
 
-  proceed_if_filled!(cursor.read(&mut body_bytes), length);
+/// The same as `parse_frame_async` but `FrameTooLarge` error is returned without reading
+/// a body if it's longer than `max_len`.
+pub fn parse_frame_async_with_max_len<C>(
+  cursor: &mut C,
+  compressor: &dyn Compressor,
+  max_len: usize,
+) -> error::Result<Option<Frame>>
+where
+  C: Read,
+{
+  let mut header = [0; HEADER_LEN];
+  match read_full(cursor, &mut header)? {
+    0 => return Ok(None),
+    HEADER_LEN => {}
+    _ => return Err(unexpected_eof().into()),
+  }
 
-  let full_body = if flags.iter().any(|flag| flag == &Flag::Compression) {
-    compressor
-      .decode(body_bytes)
-      .map_err(|err| error::Error::from(err.to_string()))?
-  } else {
-    body_bytes
-  };
+  let version = Version::try_from(&header[..Version::BYTE_LENGTH])?;
+  let flags = Flags::from_bits_truncate(header[1]);
+  let stream = from_i16_bytes(&header[2..4]);
+  let opcode = Opcode::try_from(header[4])?;
+  let length = from_bytes(&header[HEADER_LEN - LENGTH_LEN..]) as usize;
+  check_frame_len(length, max_len)?;
 
-  // Use cursor to get tracing id, warnings and actual body
-  let mut body_cursor = Cursor::new(full_body.as_slice());
+  let compressed = flags.contains(Flags::COMPRESSION);
 
-  let tracing_id = if flags.iter().any(|flag| flag == &Flag::Tracing) {
-    // FIXME:
-    //   Once a new feature to safely pass an uninitialized buffer to `Read` becomes available,
-    //   we no longer need to zero-initialize `tracing_bytes` before passing to `Read`.
-    let mut tracing_bytes = vec![0; UUID_LEN];
-    body_cursor.read_exact(&mut tracing_bytes)?;
+  // Large compressed bodies are decoded right from the reader if a compressor supports it
+  // (zstd), so the whole compressed body is not kept in memory along with the decoded one.
+  // Other bodies are read at once and decoded with `decode_with_max_len`.
+  let full_body = if compressed {
+    let mut limited = Read::take(&mut *cursor, length as u64);
+    let decoded = compressor
+      .decoding_reader(Box::new(&mut limited))
+      .map_err(|err| error::Error::Compression(err.to_string()))?
+      .map(|reader| {
+        // one byte above the limit is read to detect too long bodies
+        let mut decoded = vec![];
+        reader.take(max_len as u64 + 1).read_to_end(&mut decoded).map(|_| decoded)
+      })
+      .transpose()
+      .map_err(|err| error::Error::Compression(err.to_string()))?;
 
-    decode_timeuuid(tracing_bytes.as_slice()).ok()
+    match decoded {
+      Some(decoded) => {
+        if decoded.len() > max_len {
+          return Err(error::Error::Compression(format!(
+            "Decoded body length exceeds maximal length {}",
+            max_len
+          )));
+        }
+        // skip trailing bytes which are not consumed by a decoder
+        io::copy(&mut limited, &mut io::sink())?;
+        if limited.limit() > 0 {
+          return Err(unexpected_eof().into());
+        }
+        decoded
+      }
+      None => {
+        let body_bytes = read_body(cursor, length)?;
+        let mut decoded = vec![];
+        compressor
+          .decode_with_max_len(&body_bytes, &mut decoded, max_len)
+          .map_err(|err| error::Error::Compression(err.to_string()))?;
+        decoded
+      }
+    }
   } else {
-    None
+    read_body(cursor, length)?
   };
 
-  let warnings = if flags.iter().any(|flag| flag == &Flag::Warning) {
-    CStringList::from_cursor(&mut body_cursor)?.into_plain()
-  } else {
-    vec![]
-  };
+  frame_from_body(version, flags, stream, opcode, Bytes::from(full_body)).map(Some)
+}
 
-  let mut body = vec![];
+/// Reads bytes until the buffer is full or the reader is closed.
+/// Returns a number of read bytes.
+fn read_full<C: Read>(cursor: &mut C, buf: &mut [u8]) -> io::Result<usize> {
+  let mut filled = 0;
+  while filled < buf.len() {
+    match cursor.read(&mut buf[filled..]) {
+      Ok(0) => break,
+      Ok(read) => filled += read,
+      Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+      Err(err) => return Err(err),
+    }
+  }
 
-  body_cursor.read_to_end(&mut body)?;
+  Ok(filled)
+}
 
-  let frame = Frame {
-    version: version,
-    flags: flags,
-    opcode: opcode,
-    stream: stream,
-    body: body,
-    tracing_id: tracing_id,
-    warnings: warnings,
-  };
+fn read_body<C: Read>(cursor: &mut C, length: usize) -> io::Result<Vec<u8>> {
+  let mut body_bytes = vec![0; length];
+  cursor.read_exact(&mut body_bytes)?;
+  Ok(body_bytes)
+}
 
-  Ok(Some(frame))
+fn unexpected_eof() -> io::Error {
+  io::Error::new(io::ErrorKind::UnexpectedEof, "Frame is incomplete")
 }
 
 pub fn convert_frame_into_result(frame: Frame) -> error::Result<Frame> {
@@ -105,3 +135,143 @@ pub fn convert_frame_into_result(frame: Frame) -> error::Result<Frame> {
     _ => Ok(frame),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::compression::{CompressorError, ReverseCompressor};
+  use crate::types::to_int;
+
+  /// Inverts bits and decodes data incrementally.
+  struct StreamingCompressor;
+
+  struct InvertingReader<R>(R);
+
+  impl<R: Read> Read for InvertingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      let read = self.0.read(buf)?;
+      buf[..read].iter_mut().for_each(|byte| *byte = !*byte);
+      Ok(read)
+    }
+  }
+
+  impl Compressor for StreamingCompressor {
+    fn encode(&self, bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+      buf.extend(bytes.iter().map(|byte| !byte));
+      Ok(())
+    }
+
+    fn decode(&self, _bytes: &[u8], _buf: &mut Vec<u8>) -> Result<(), CompressorError> {
+      Err("streaming is expected".into())
+    }
+
+    fn decoding_reader<'a>(
+      &self,
+      reader: Box<dyn Read + 'a>,
+    ) -> Result<Option<Box<dyn Read + 'a>>, CompressorError> {
+      Ok(Some(Box::new(InvertingReader(reader))))
+    }
+
+    fn into_string(&self) -> Option<String> {
+      None
+    }
+  }
+
+  #[test]
+  fn streaming_decompression() {
+    let body = vec![1, 2, 3];
+    let mut compressed = vec![];
+    StreamingCompressor.encode(&body, &mut compressed).unwrap();
+
+    let mut bytes = vec![Version::Response.as_byte(), 0x01, 0, 1, 0x02];
+    bytes.extend_from_slice(&to_int(compressed.len() as i32));
+    bytes.extend_from_slice(&compressed);
+    // the next frame should stay unread
+    bytes.push(0xff);
+
+    let mut reader = bytes.as_slice();
+    let frame = parse_frame_async(&mut reader, &StreamingCompressor)
+      .unwrap()
+      .unwrap();
+    assert_eq!(frame.body, body);
+    assert_eq!(reader, &[0xff]);
+
+    let mut short = &bytes[..bytes.len() - 2];
+    assert!(parse_frame_async(&mut short, &StreamingCompressor).is_err());
+  }
+
+  /// Returns a single byte per read.
+  struct ByteByByte<'a>(&'a [u8]);
+
+  impl<'a> Read for ByteByByte<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      let len = self.0.len().min(buf.len()).min(1);
+      buf[..len].copy_from_slice(&self.0[..len]);
+      self.0 = &self.0[len..];
+      Ok(len)
+    }
+  }
+
+  #[test]
+  fn short_reads() {
+    let mut bytes = vec![Version::Response.as_byte(), 0, 0, 5, 0x02];
+    bytes.extend_from_slice(&to_int(2));
+    bytes.extend_from_slice(&[1, 2]);
+
+    let mut reader = ByteByByte(&bytes);
+    let frame = parse_frame_async(&mut reader, &ReverseCompressor)
+      .unwrap()
+      .unwrap();
+    assert_eq!(frame.stream, 5);
+    assert_eq!(frame.body, vec![1, 2]);
+    assert!(parse_frame_async(&mut reader, &ReverseCompressor)
+      .unwrap()
+      .is_none());
+  }
+
+  #[test]
+  fn compressed_frame_with_warnings() {
+    // warnings ["w"] followed by READY body which is empty, extra bytes make the body not empty
+    let body = vec![0, 1, 0, 1, b'w', 1, 2];
+    let mut compressed = vec![];
+    ReverseCompressor.encode(&body, &mut compressed).unwrap();
+
+    let mut bytes = vec![Version::Response.as_byte(), 0x01 | 0x08, 0, 7, 0x02];
+    bytes.extend_from_slice(&to_int(compressed.len() as i32));
+    bytes.extend_from_slice(&compressed);
+
+    let frame = parse_frame_async(&mut bytes.as_slice(), &ReverseCompressor)
+      .unwrap()
+      .unwrap();
+    assert_eq!(frame.stream, 7);
+    assert_eq!(frame.opcode, Opcode::Ready);
+    assert_eq!(frame.warnings[0].message, "w");
+    assert_eq!(frame.body, vec![1, 2]);
+
+    assert!(parse_frame_async(&mut &bytes[..6], &ReverseCompressor).is_err());
+    assert!(parse_frame_async(&mut &bytes[..0], &ReverseCompressor)
+      .unwrap()
+      .is_none());
+  }
+
+  #[test]
+  fn frame_too_large() {
+    // header claims 2 GB body which must not be allocated
+    let mut bytes = vec![Version::Response.as_byte(), 0, 0, 1, 0x02];
+    bytes.extend_from_slice(&to_int(i32::MAX));
+
+    match parse_frame_async(&mut bytes.as_slice(), &ReverseCompressor) {
+      Err(error::Error::FrameTooLarge { length, max }) => {
+        assert_eq!(length, i32::MAX as usize);
+        assert_eq!(max, MAX_FRAME_LEN);
+      }
+      result => panic!("Unexpected result {:?}", result),
+    }
+
+    let mut bytes = vec![Version::Response.as_byte(), 0, 0, 1, 0x02];
+    bytes.extend_from_slice(&to_int(2));
+    bytes.extend_from_slice(&[1, 2]);
+    assert!(parse_frame_async_with_max_len(&mut bytes.as_slice(), &ReverseCompressor, 1).is_err());
+    assert!(parse_frame_async_with_max_len(&mut bytes.as_slice(), &ReverseCompressor, 2).is_ok());
+  }
+}
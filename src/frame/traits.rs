@@ -1,5 +1,7 @@
 use std::io::Cursor;
 
+use bytes::BufMut;
+
 use crate::error;
 use crate::types::rows::Row;
 use crate::types::udt::UDT;
@@ -9,6 +11,37 @@ use crate::query;
 pub trait IntoBytes {
   /// It should convert a struct into an array of bytes.
   fn into_cbytes(&self) -> Vec<u8>;
+
+  /// It writes a struct into a buffer. The default implementation copies bytes returned
+  /// by `into_cbytes`, parts of request frames override it to be encoded in one pass
+  /// without intermediate vectors.
+  fn serialize_into(&self, buf: &mut impl BufMut)
+  where
+    Self: Sized,
+  {
+    buf.put_slice(&self.into_cbytes());
+  }
+
+  /// It returns a number of bytes written by `serialize_into`, so a buffer could be
+  /// allocated once. The default implementation serializes a struct to count them.
+  fn serialized_len(&self) -> usize {
+    self.into_cbytes().len()
+  }
+}
+
+/// `TryIntoBytes` is a fallible counterpart of `IntoBytes`. Values which don't fit
+/// protocol limits, e.g. a `[string]` longer than `u16::MAX` bytes or a batch timestamp
+/// flag without a timestamp, are reported as errors instead of being silently truncated
+/// or skipped by `into_cbytes`.
+pub trait TryIntoBytes: IntoBytes {
+  /// It should check that a struct could be serialized in accordance to protocol.
+  fn check(&self) -> error::Result<()>;
+
+  /// It converts a struct into an array of bytes if it passes `check`.
+  fn try_into_cbytes(&self) -> error::Result<Vec<u8>> {
+    self.check()?;
+    Ok(self.into_cbytes())
+  }
 }
 
 /// `FromBytes` should be used to parse an array of bytes into a structure.
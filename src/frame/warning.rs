@@ -0,0 +1,33 @@
+//! Warnings which a server sends along with responses, e.g. when a batch is too large
+//! or a query reads too many tombstones.
+use std::fmt;
+
+use crate::frame::Opcode;
+
+/// Callback which is invoked with every warning of a decoded frame.
+pub type WarningHandler = Box<dyn Fn(&Warning) + Send + Sync>;
+
+/// Server warning together with a response it has been received with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Warning text.
+    pub message: String,
+    /// Opcode of a response frame.
+    pub opcode: Opcode,
+    /// Stream id of a response frame, i.e. of a request which caused the warning.
+    pub stream: i16,
+}
+
+impl Warning {
+    pub fn new(message: String, opcode: Opcode, stream: i16) -> Warning {
+        Warning { message,
+                  opcode,
+                  stream }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (stream {})", self.message, self.stream)
+    }
+}
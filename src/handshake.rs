@@ -0,0 +1,312 @@
+//! Connection bring-up which doesn't own any socket. `Handshake` produces a `STARTUP`
+//! request, answers an `AUTHENTICATE` response if a server requires authentication
+//! and reports when a connection is ready for queries. A caller is responsible
+//! for sending produced frames and for passing received ones.
+use crate::authenticator::{AuthAction, AuthFlow, Authenticator, PlainTextAuthenticator,
+                           SecretString, SecretVec, StaticTokenAuthenticator};
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::frame_startup::StartupParams;
+use crate::frame::{AsByte, Frame, Version};
+
+/// What a connection should do next during a handshake.
+#[derive(Debug)]
+pub enum HandshakeAction {
+    /// The frame should be sent and a response to it should be passed to `Handshake::on_frame`.
+    Send(Frame),
+    /// The connection is ready for queries.
+    Ready,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Created,
+    Startup,
+    Authenticating,
+    Ready,
+    Failed,
+}
+
+/// State machine of a connection handshake:
+/// `STARTUP` -> (`AUTHENTICATE` -> `AUTH_RESPONSE` -> `AUTH_CHALLENGE`* -> `AUTH_SUCCESS`)?
+/// -> `READY`.
+#[derive(Debug)]
+pub struct Handshake {
+    params: StartupParams,
+    auth: Option<AuthFlow>,
+    state: State,
+    // stream id of a request which waits for a response
+    stream: Option<i16>,
+}
+
+impl Handshake {
+    /// Creates new handshake which sends `STARTUP` request with given options.
+    pub fn new(params: StartupParams) -> Handshake {
+        Handshake { params,
+                    auth: None,
+                    state: State::Created,
+                    stream: None }
+    }
+
+    /// Sets a token which is sent in `AUTH_RESPONSE` if a server requires authentication,
+    /// e.g. `\0user\0password` for `PasswordAuthenticator`. Challenges of a server
+    /// fail the handshake.
+    pub fn with_auth_token<T: Into<SecretVec>>(self, token: T) -> Self {
+        self.with_authenticator(Box::new(StaticTokenAuthenticator(token.into())))
+    }
+
+    /// Sets an authenticator which produces tokens of `AUTH_RESPONSE` frames
+    /// if a server requires authentication.
+    pub fn with_authenticator(mut self, authenticator: Box<dyn Authenticator>) -> Self {
+        self.auth = Some(AuthFlow::new(authenticator));
+        self
+    }
+
+    /// Authenticates with SASL PLAIN mechanism if a server requires authentication.
+    pub fn with_credentials<P: Into<SecretString>>(self, username: &str, password: P) -> Self {
+        self.with_authenticator(Box::new(PlainTextAuthenticator::new(username, password)))
+    }
+
+    /// Starts the handshake returning `STARTUP` frame which should be sent.
+    /// Fails if the crate is built for protocol v5 because v5 framing, which a server
+    /// expects after `STARTUP`, is not implemented.
+    pub fn start(&mut self) -> error::Result<Frame> {
+        if self.state != State::Created {
+            return Err(error::Error::Handshake("Handshake has already been started".into()));
+        }
+
+        if Version::Request.as_byte() == 0x05 {
+            self.state = State::Failed;
+            let err = "Protocol v5 framing is not implemented, `v5` feature is experimental \
+                       and cannot be used to connect to a server";
+            return Err(error::Error::Handshake(err.into()));
+        }
+
+        self.state = State::Startup;
+        Ok(self.expect_response(Frame::new_req_startup_with_params(&self.params)))
+    }
+
+    /// Should be called with every frame received during the handshake. A server error
+    /// or an unexpected frame fails the handshake.
+    pub fn on_frame(&mut self, frame: &Frame) -> error::Result<HandshakeAction> {
+        match self.handle(frame) {
+            Ok(action) => Ok(action),
+            Err(err) => {
+                self.state = State::Failed;
+                Err(err)
+            }
+        }
+    }
+
+    /// Shows if the connection is ready for queries.
+    pub fn is_ready(&self) -> bool {
+        self.state == State::Ready
+    }
+
+    /// Shows if the handshake has failed.
+    pub fn is_failed(&self) -> bool {
+        self.state == State::Failed
+    }
+
+    fn handle(&mut self, frame: &Frame) -> error::Result<HandshakeAction> {
+        if self.stream != Some(frame.stream) {
+            return Err(error::Error::Handshake(format!("Unexpected frame on stream {} \
+                                                        during handshake",
+                                                       frame.stream)));
+        }
+
+        match (self.state, frame.get_body()?) {
+            (_, ResponseBody::Error(err)) => Err(err.into()),
+            (State::Startup, ResponseBody::Ready(_)) => {
+                self.state = State::Ready;
+                self.stream = None;
+                Ok(HandshakeAction::Ready)
+            }
+            (State::Startup, ResponseBody::Authenticate(authenticate)) => {
+                if self.auth.is_none() {
+                    let err = format!("Server requires authentication with {} but \
+                                       authenticator is not set",
+                                      authenticate.data.as_str());
+                    return Err(error::Error::Authentication(err));
+                }
+                self.state = State::Authenticating;
+                self.authenticate(frame)
+            }
+            (State::Authenticating, _) => self.authenticate(frame),
+            (state, _) => {
+                Err(error::Error::Handshake(format!("Unexpected {:?} frame in {:?} \
+                                                     handshake state",
+                                                    frame.opcode, state)))
+            }
+        }
+    }
+
+    fn authenticate(&mut self, frame: &Frame) -> error::Result<HandshakeAction> {
+        let auth = self.auth
+                       .as_mut()
+                       .ok_or_else(|| {
+                           let err = "Authentication frame without authenticator";
+                           error::Error::Authentication(err.into())
+                       })?;
+        match auth.on_frame(frame)? {
+            AuthAction::Send(frame) => Ok(HandshakeAction::Send(self.expect_response(frame))),
+            AuthAction::Authenticated => {
+                self.state = State::Ready;
+                self.stream = None;
+                Ok(HandshakeAction::Ready)
+            }
+        }
+    }
+
+    fn expect_response(&mut self, frame: Frame) -> Frame {
+        self.stream = Some(frame.stream);
+        frame
+    }
+}
+
+#[cfg(all(test, any(feature = "v3", not(feature = "v5"))))]
+mod tests {
+    use super::*;
+    use crate::frame::{Flags, IntoBytes, Opcode, Version};
+    use crate::types::{CBytes, CString};
+
+    fn response(request: &Frame, opcode: Opcode, body: Vec<u8>) -> Frame {
+        Frame { version: Version::Response,
+                flags: Flags::empty(),
+                opcode,
+                stream: request.stream,
+                body: body.into(),
+                tracing_id: None,
+                warnings: vec![],
+                custom_payload: Default::default() }
+    }
+
+    fn send(action: HandshakeAction) -> Frame {
+        match action {
+            HandshakeAction::Send(frame) => frame,
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn without_authentication() {
+        let mut handshake = Handshake::new(StartupParams::new());
+        let startup = handshake.start().unwrap();
+        assert_eq!(startup.opcode, Opcode::Startup);
+        assert!(handshake.start().is_err());
+
+        let ready = response(&startup, Opcode::Ready, vec![]);
+        assert!(matches!(handshake.on_frame(&ready).unwrap(), HandshakeAction::Ready));
+        assert!(handshake.is_ready());
+    }
+
+    #[test]
+    fn with_authentication() {
+        let token = b"\0user\0password".to_vec();
+        let mut handshake = Handshake::new(StartupParams::new()).with_auth_token(token.clone());
+        let startup = handshake.start().unwrap();
+
+        let authenticator = CString::new("PasswordAuthenticator".into()).into_cbytes();
+        let authenticate = response(&startup, Opcode::Authenticate, authenticator);
+        let auth_response = send(handshake.on_frame(&authenticate).unwrap());
+        assert_eq!(auth_response.opcode, Opcode::AuthResponse);
+        assert_eq!(auth_response.body, CBytes::new(token).into_cbytes());
+        assert!(!handshake.is_ready());
+
+        let success = response(&auth_response, Opcode::AuthSuccess, vec![0xff, 0xff, 0xff, 0xff]);
+        assert!(matches!(handshake.on_frame(&success).unwrap(), HandshakeAction::Ready));
+        assert!(handshake.is_ready());
+    }
+
+    // answers a challenge with its first byte incremented and expects `[2]` on success
+    struct Counting;
+
+    impl Authenticator for Counting {
+        fn initial_response(&mut self, _authenticator: &str) -> error::Result<Vec<u8>> {
+            Ok(vec![0])
+        }
+
+        fn evaluate_challenge(&mut self, challenge: Option<&[u8]>) -> error::Result<Vec<u8>> {
+            Ok(vec![challenge.unwrap()[0] + 1])
+        }
+
+        fn on_success(&mut self, token: Option<&[u8]>) -> error::Result<()> {
+            match token {
+                Some([2]) => Ok(()),
+                _ => Err(error::Error::Authentication("Unexpected token of AUTH_SUCCESS".into())),
+            }
+        }
+    }
+
+    #[test]
+    fn multi_round_authentication() {
+        let mut handshake =
+            Handshake::new(StartupParams::new()).with_authenticator(Box::new(Counting));
+        let startup = handshake.start().unwrap();
+
+        let authenticator = CString::new("KerberosAuthenticator".into()).into_cbytes();
+        let authenticate = response(&startup, Opcode::Authenticate, authenticator);
+        let mut auth_response = send(handshake.on_frame(&authenticate).unwrap());
+        assert_eq!(auth_response.body, CBytes::new(vec![0]).into_cbytes());
+
+        for round in 1..3 {
+            let challenge = CBytes::new(vec![round]).into_cbytes();
+            let challenge = response(&auth_response, Opcode::AuthChallenge, challenge);
+            auth_response = send(handshake.on_frame(&challenge).unwrap());
+            assert_eq!(auth_response.opcode, Opcode::AuthResponse);
+            assert_eq!(auth_response.body, CBytes::new(vec![round + 1]).into_cbytes());
+        }
+
+        let success = response(&auth_response,
+                               Opcode::AuthSuccess,
+                               CBytes::new(vec![2]).into_cbytes());
+        assert!(matches!(handshake.on_frame(&success).unwrap(), HandshakeAction::Ready));
+        assert!(handshake.is_ready());
+    }
+
+    #[test]
+    fn with_credentials() {
+        let mut handshake = Handshake::new(StartupParams::new()).with_credentials("user", "pass");
+        let startup = handshake.start().unwrap();
+
+        let authenticator = "com.datastax.bdp.cassandra.auth.DseAuthenticator".to_string();
+        let authenticator = CString::new(authenticator).into_cbytes();
+        let authenticate = response(&startup, Opcode::Authenticate, authenticator);
+        let auth_response = send(handshake.on_frame(&authenticate).unwrap());
+        assert_eq!(auth_response.body, CBytes::new(b"PLAIN".to_vec()).into_cbytes());
+
+        let challenge = CBytes::new(b"PLAIN-START".to_vec()).into_cbytes();
+        let challenge = response(&auth_response, Opcode::AuthChallenge, challenge);
+        let auth_response = send(handshake.on_frame(&challenge).unwrap());
+        assert_eq!(auth_response.body, CBytes::new(b"\0user\0pass".to_vec()).into_cbytes());
+    }
+
+    #[test]
+    fn unexpected_frames() {
+        let mut handshake = Handshake::new(StartupParams::new());
+        let startup = handshake.start().unwrap();
+        let mut ready = response(&startup, Opcode::Ready, vec![]);
+        ready.stream = ready.stream.wrapping_add(1);
+        assert!(handshake.on_frame(&ready).is_err());
+        assert!(handshake.is_failed());
+
+        let mut handshake = Handshake::new(StartupParams::new());
+        let startup = handshake.start().unwrap();
+        let authenticator = CString::new("PasswordAuthenticator".into()).into_cbytes();
+        let authenticate = response(&startup, Opcode::Authenticate, authenticator);
+        assert!(handshake.on_frame(&authenticate).is_err());
+        assert!(handshake.is_failed());
+    }
+}
+
+#[cfg(all(test, feature = "v5", not(feature = "v3")))]
+mod v5_tests {
+    use super::*;
+
+    #[test]
+    fn start_fails() {
+        let mut handshake = Handshake::new(StartupParams::new());
+        assert!(handshake.start().is_err());
+        assert!(handshake.is_failed());
+    }
+}
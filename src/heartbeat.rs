@@ -0,0 +1,173 @@
+//! Connection liveness checks which don't own any socket. `Heartbeat` decides when
+//! an idle connection should be probed with an `OPTIONS` request and whether
+//! the connection is dead because probes are not answered in time. A caller
+//! is responsible for sending produced frames and for reporting received ones.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::error;
+use crate::frame::Frame;
+
+/// What a connection should do next with respect to heartbeats.
+#[derive(Debug)]
+pub enum HeartbeatAction {
+    /// Nothing to do until `Heartbeat::next_deadline`.
+    Wait,
+    /// The connection has been idle for too long, the frame should be sent.
+    Send(Frame),
+    /// A heartbeat has not been answered in time, the connection should be closed.
+    Dead,
+}
+
+/// State machine which produces `OPTIONS` keepalive frames on idle connections
+/// and tracks heartbeats which are waiting for a response.
+#[derive(Debug)]
+pub struct Heartbeat {
+    interval: Duration,
+    timeout: Duration,
+    last_activity: Instant,
+    // stream id -> time when a heartbeat was sent
+    outstanding: HashMap<i16, Instant>,
+}
+
+impl Heartbeat {
+    /// Creates new heartbeat which probes a connection after it has been idle for `interval`
+    /// and considers it dead if a probe is not answered within `timeout`.
+    pub fn new(interval: Duration, timeout: Duration, now: Instant) -> Heartbeat {
+        Heartbeat {
+            interval,
+            timeout,
+            last_activity: now,
+            outstanding: HashMap::new(),
+        }
+    }
+
+    /// Should be called when any frame is received from a server. Returns `true`
+    /// if the frame is a response to a heartbeat, so it shouldn't be dispatched further.
+    pub fn on_response(&mut self, stream: i16, now: Instant) -> bool {
+        self.last_activity = now;
+        self.outstanding.remove(&stream).is_some()
+    }
+
+    /// Returns the next action. It should be called no later than `next_deadline`.
+    ///
+    /// If a heartbeat should be sent `assign_stream` is called to set a stream id
+    /// of the frame, so it doesn't collide with other requests in flight, e.g.
+    /// `|frame| router.register(frame, pending)` for a `ResponseRouter`. An error
+    /// of `assign_stream` is returned as is.
+    pub fn poll<F>(&mut self, now: Instant, assign_stream: F) -> error::Result<HeartbeatAction>
+    where
+        F: FnOnce(&mut Frame) -> error::Result<i16>,
+    {
+        if self.is_dead(now) {
+            return Ok(HeartbeatAction::Dead);
+        }
+
+        if !self.outstanding.is_empty() || now < self.last_activity + self.interval {
+            return Ok(HeartbeatAction::Wait);
+        }
+
+        let mut frame = Frame::new_req_options();
+        frame.stream = assign_stream(&mut frame)?;
+        self.outstanding.insert(frame.stream, now);
+        Ok(HeartbeatAction::Send(frame))
+    }
+
+    /// Shows if any heartbeat has not been answered within the timeout.
+    pub fn is_dead(&self, now: Instant) -> bool {
+        self.outstanding
+            .values()
+            .any(|sent| now >= *sent + self.timeout)
+    }
+
+    /// Shows if a stream is used by a heartbeat which waits for a response.
+    pub fn is_outstanding(&self, stream: i16) -> bool {
+        self.outstanding.contains_key(&stream)
+    }
+
+    /// Returns the time when `poll` should be called next.
+    pub fn next_deadline(&self) -> Instant {
+        match self.outstanding.values().min() {
+            Some(sent) => *sent + self.timeout,
+            None => self.last_activity + self.interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Opcode;
+    use crate::router::ResponseRouter;
+    use crate::stream_id::StreamIdPool;
+
+    const INTERVAL: Duration = Duration::from_secs(30);
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn poll(heartbeat: &mut Heartbeat, now: Instant) -> HeartbeatAction {
+        heartbeat.poll(now, |_| panic!("Unexpected heartbeat")).unwrap()
+    }
+
+    fn send(heartbeat: &mut Heartbeat, now: Instant) -> Frame {
+        let mut pool = StreamIdPool::new();
+        match heartbeat.poll(now, |_| pool.acquire()).unwrap() {
+            HeartbeatAction::Send(frame) => frame,
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn answered_heartbeat() {
+        let start = Instant::now();
+        let mut heartbeat = Heartbeat::new(INTERVAL, TIMEOUT, start);
+        assert!(matches!(poll(&mut heartbeat, start), HeartbeatAction::Wait));
+        assert_eq!(heartbeat.next_deadline(), start + INTERVAL);
+
+        let now = start + INTERVAL;
+        let frame = send(&mut heartbeat, now);
+        assert_eq!(frame.opcode, Opcode::Options);
+        assert!(heartbeat.is_outstanding(frame.stream));
+        assert!(matches!(poll(&mut heartbeat, now), HeartbeatAction::Wait));
+        assert_eq!(heartbeat.next_deadline(), now + TIMEOUT);
+
+        let now = now + Duration::from_secs(1);
+        assert!(heartbeat.on_response(frame.stream, now));
+        assert!(!heartbeat.on_response(frame.stream, now));
+        assert!(matches!(poll(&mut heartbeat, now), HeartbeatAction::Wait));
+        assert_eq!(heartbeat.next_deadline(), now + INTERVAL);
+    }
+
+    #[test]
+    fn dead_connection() {
+        let start = Instant::now();
+        let mut heartbeat = Heartbeat::new(INTERVAL, TIMEOUT, start);
+        send(&mut heartbeat, start + INTERVAL);
+
+        assert!(!heartbeat.is_dead(start + INTERVAL + Duration::from_secs(1)));
+        assert!(heartbeat.is_dead(start + INTERVAL + TIMEOUT));
+        assert!(matches!(poll(&mut heartbeat, start + INTERVAL + TIMEOUT), HeartbeatAction::Dead));
+    }
+
+    #[test]
+    fn stream_from_router() {
+        let start = Instant::now();
+        let now = start + INTERVAL;
+        let mut router = ResponseRouter::with_pool(StreamIdPool::with_capacity(1));
+
+        let mut heartbeat = Heartbeat::new(INTERVAL, TIMEOUT, start);
+        let mut query = Frame::new_req_options();
+        let query_stream = router.register(&mut query, "query").unwrap();
+        assert!(heartbeat.poll(now, |frame| router.register(frame, "heartbeat")).is_err());
+        assert!(matches!(poll(&mut heartbeat, start), HeartbeatAction::Wait));
+
+        let mut router = ResponseRouter::with_pool(StreamIdPool::with_capacity(2));
+        router.register(&mut query, "query").unwrap();
+        match heartbeat.poll(now, |frame| router.register(frame, "heartbeat")).unwrap() {
+            HeartbeatAction::Send(frame) => {
+                assert_ne!(frame.stream, query_stream);
+                assert!(heartbeat.is_outstanding(frame.stream));
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+}
@@ -14,9 +14,21 @@ pub mod frame;
 pub mod query;
 pub mod types;
 
+pub mod authenticator;
 pub mod compression;
 pub mod consistency;
 pub mod error;
+pub mod event_dispatcher;
+pub mod handshake;
+pub mod heartbeat;
+pub mod node_info;
+pub mod prepared_cache;
+pub mod router;
+pub mod stream_id;
+pub mod token;
+
+#[cfg(feature = "derive")]
+pub use cassandra_proto_derive::{IntoQueryValues, TryFromRow};
 
 pub type Error = error::Error;
 pub type Result<T> = error::Result<T>;
@@ -25,41 +25,51 @@ macro_rules! query_values {
     };
 }
 
+/// Generates `AsRustType` implementations which convert `List` into `Vec<Option<T>>`,
+/// where NULL items are `None`, and into `Vec<T>`, which fails on NULL items.
 #[macro_export]
 macro_rules! list_as_rust {
     ($($into_type:tt)+) => (
-        impl AsRustType<Vec<$($into_type)+>> for List {
-            fn as_rust_type(&self) -> Result<Option<Vec<$($into_type)+>>> {
+        impl AsRustType<Vec<Option<$($into_type)+>>> for List {
+            fn as_rust_type(&self) -> Result<Option<Vec<Option<$($into_type)+>>>> {
                 match self.metadata.value {
                     Some(ColTypeOptionValue::CList(ref type_option)) |
                     Some(ColTypeOptionValue::CSet(ref type_option)) => {
                         let type_option_ref = type_option.as_ref();
-                        let convert = self
-                            .map(|bytes| {
-                                as_rust_type!(type_option_ref, bytes, $($into_type)+)
-                                    .unwrap()
-                                    // item in a list supposed to be a non-null value.
-                                    // TODO: check if it's true
-                                    .unwrap()
-                            });
-
-                        Ok(Some(convert))
+                        self.data
+                            .iter()
+                            .map(|bytes| as_rust_type!(type_option_ref, bytes, $($into_type)+))
+                            .collect::<Result<Vec<_>>>()
+                            .map(Some)
                     },
-                    _ => Err(Error::General(format!("Invalid conversion. \
+                    _ => Err(Error::TypeMismatch(format!("Invalid conversion. \
                             Cannot convert {:?} into List (valid types: List, Set).",
                             self.metadata.value)))
                 }
             }
         }
+
+        impl AsRustType<Vec<$($into_type)+>> for List {
+            fn as_rust_type(&self) -> Result<Option<Vec<$($into_type)+>>> {
+                AsRustType::<Vec<Option<$($into_type)+>>>::as_r_type(self)?
+                    .into_iter()
+                    .map(|item| item.ok_or_else(|| null_item_err("List")))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Some)
+            }
+        }
     );
 }
 
+/// Generates `AsRustType` implementations which convert `Map` into `HashMap<K, Option<V>>`,
+/// where NULL values are `None`, and into `HashMap<K, V>`, which fails on NULL values.
+/// NULL keys fail both conversions.
 #[macro_export]
 macro_rules! map_as_rust {
     ({ $($key_type:tt)+ }, { $($val_type:tt)+ }) => (
-        impl AsRustType<HashMap<$($key_type)+, $($val_type)+>> for Map {
-            /// Converts `Map` into `HashMap` for blob values.
-            fn as_rust_type(&self) -> Result<Option<HashMap<$($key_type)+, $($val_type)+>>> {
+        impl AsRustType<HashMap<$($key_type)+, Option<$($val_type)+>>> for Map {
+            fn as_rust_type(&self)
+                            -> Result<Option<HashMap<$($key_type)+, Option<$($val_type)+>>>> {
                 match self.metadata.value {
                     Some(ColTypeOptionValue::CMap((ref key_type_option, ref val_type_option))) => {
                         let mut map = HashMap::with_capacity(self.data.len());
@@ -67,11 +77,10 @@ macro_rules! map_as_rust {
                         for &(ref key, ref val) in self.data.iter() {
                             let key_type_option = key_type_option.as_ref();
                             let val_type_option = val_type_option.as_ref();
-                            let key = as_rust_type!(key_type_option, key, $($key_type)+)?;
+                            let key = as_rust_type!(key_type_option, key, $($key_type)+)?
+                                .ok_or_else(|| null_item_err("Map key"))?;
                             let val = as_rust_type!(val_type_option, val, $($val_type)+)?;
-                            if val.is_some() && key.is_some() {
-                                map.insert(key.unwrap(), val.unwrap());
-                            }
+                            map.insert(key, val);
                         }
 
                         Ok(Some(map))
@@ -80,6 +89,16 @@ macro_rules! map_as_rust {
                 }
             }
         }
+
+        impl AsRustType<HashMap<$($key_type)+, $($val_type)+>> for Map {
+            fn as_rust_type(&self) -> Result<Option<HashMap<$($key_type)+, $($val_type)+>>> {
+                AsRustType::<HashMap<$($key_type)+, Option<$($val_type)+>>>::as_r_type(self)?
+                    .into_iter()
+                    .map(|(key, val)| Ok((key, val.ok_or_else(|| null_item_err("Map value"))?)))
+                    .collect::<Result<HashMap<_, _>>>()
+                    .map(Some)
+            }
+        }
     );
 }
 
@@ -89,10 +108,13 @@ macro_rules! into_rust_by_name {
         impl IntoRustByName<$($into_type)+> for Row {
             fn get_by_name(&self, name: &str) -> Result<Option<$($into_type)+>> {
                 self.get_col_spec_by_name(name)
-                    .ok_or(column_is_empty_err(name))
                     .and_then(|(col_spec, cbytes)| {
                         let ref col_type = col_spec.col_type;
-                        as_rust_type!(col_type, cbytes, $($into_type)+)
+                        as_rust_type!(col_type, cbytes, $($into_type)+).map_err(|err| {
+                            err.in_column(col_spec.name.as_str(),
+                                          &col_type.id,
+                                          stringify!($($into_type)+))
+                        })
                     })
             }
         }
@@ -135,18 +157,93 @@ macro_rules! into_rust_by_index {
                     .ok_or(column_is_empty_err(index))
                     .and_then(|(col_spec, cbytes)| {
                         let ref col_type = col_spec.col_type;
-                        as_rust_type!(col_type, cbytes, $($into_type)+)
+                        as_rust_type!(col_type, cbytes, $($into_type)+).map_err(|err| {
+                            err.in_column(col_spec.name.as_str(),
+                                          &col_type.id,
+                                          stringify!($($into_type)+))
+                        })
                     })
             }
         }
     );
 }
 
+/// Generates `IntoRustColumn` implementation which decodes a single column
+/// of all the rows into Rust values.
+#[macro_export]
+macro_rules! into_rust_column {
+    (BodyResResultRows, $($into_type:tt)+) => (
+        impl IntoRustColumn<$($into_type)+> for BodyResResultRows {
+            fn get_column(&self, name: &str) -> Result<Vec<Option<$($into_type)+>>> {
+                let index = self.metadata
+                    .col_specs
+                    .iter()
+                    .position(|spec| spec.name.as_str() == name)
+                    .ok_or(column_is_empty_err(name))?;
+                let col_type = &self.metadata.col_specs[index].col_type;
+
+                self.rows_content
+                    .iter()
+                    .map(|row| {
+                        let cbytes = row.get(index).ok_or(column_is_empty_err(name))?;
+                        as_rust_type!(col_type, cbytes, $($into_type)+).map_err(|err| {
+                            err.in_column(name, &col_type.id, stringify!($($into_type)+))
+                        })
+                    })
+                    .collect()
+            }
+        }
+    );
+}
+
+/// Generates `AsRustType` implementation which converts `Tuple` into a Rust tuple
+/// of the given arity. Each element is type checked against tuple's metadata.
+#[macro_export]
+macro_rules! tuple_as_rust {
+    ($arity:expr; $($into_type:ident: $index:tt),+) => (
+        impl<$($into_type),+> AsRustType<($($into_type,)+)> for Tuple
+        where
+            $(Tuple: IntoRustByIndex<$into_type>),+
+        {
+            fn as_rust_type(&self) -> Result<Option<($($into_type,)+)>> {
+                if self.len() != $arity {
+                    return Err(Error::TypeMismatch(format!(
+                        "Invalid conversion. \
+                         Cannot convert tuple of {} elements into Rust tuple of {} elements.",
+                        self.len(),
+                        $arity
+                    )));
+                }
+
+                Ok(Some(($(
+                    IntoRustByIndex::<$into_type>::get_r_by_index(self, $index)?,
+                )+)))
+            }
+        }
+    );
+}
+
+/// Generates `From` implementation which converts a Rust tuple of values into
+/// `QueryValues::SimpleValues` keeping their order.
+#[macro_export]
+macro_rules! tuple_into_query_values {
+    ($($value_type:ident: $index:tt),+) => (
+        impl<$($value_type),+> From<($($value_type,)+)> for QueryValues
+        where
+            $($value_type: Into<Value>),+
+        {
+            fn from(values: ($($value_type,)+)) -> QueryValues {
+                QueryValues::SimpleValues(vec![$(values.$index.into()),+])
+            }
+        }
+    );
+}
+
 #[macro_export]
 macro_rules! as_res_opt {
     ($data_value:ident, $deserialize:expr) => {
-        match $data_value.as_plain() {
-            Some(ref bytes) => ($deserialize)(bytes).map(|v| Some(v)).map_err(Into::into),
+        match $data_value.as_slice() {
+            Some(bytes) => ($deserialize)(bytes).map(|v| Some(v)).map_err(Into::into),
             None => Ok(None),
         }
     };
@@ -160,7 +257,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, Blob) => {
         match $data_type_option.id {
             ColType::Blob => as_res_opt!($data_value, decode_blob),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Vec<u8> (valid types: Blob).",
                 $data_type_option.id
@@ -176,7 +273,7 @@ macro_rules! as_rust_type {
             // it's not mentioned in
             // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L582
             // ColType::XXX => decode_text($data_value)?
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into String (valid types: Custom, Ascii, Varchar).",
                 $data_type_option.id
@@ -186,7 +283,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, bool) => {
         match $data_type_option.id {
             ColType::Boolean => as_res_opt!($data_value, decode_boolean),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into bool (valid types: Boolean).",
                 $data_type_option.id
@@ -200,7 +297,7 @@ macro_rules! as_rust_type {
             ColType::Time => as_res_opt!($data_value, decode_time),
             ColType::Varint => as_res_opt!($data_value, decode_varint),
             ColType::Counter => as_res_opt!($data_value, decode_bigint),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into i64 (valid types: Bigint, Timestamp, Time, Variant,\
                  Counter).",
@@ -212,7 +309,7 @@ macro_rules! as_rust_type {
         match $data_type_option.id {
             ColType::Int => as_res_opt!($data_value, decode_int),
             ColType::Date => as_res_opt!($data_value, decode_date),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into i32 (valid types: Int, Date).",
                 $data_type_option.id
@@ -222,7 +319,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, i16) => {
         match $data_type_option.id {
             ColType::Smallint => as_res_opt!($data_value, decode_smallint),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into i16 (valid types: Smallint).",
                 $data_type_option.id
@@ -232,7 +329,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, i8) => {
         match $data_type_option.id {
             ColType::Tinyint => as_res_opt!($data_value, decode_tinyint),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into i8 (valid types: Tinyint).",
                 $data_type_option.id
@@ -242,7 +339,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, f64) => {
         match $data_type_option.id {
             ColType::Double => as_res_opt!($data_value, decode_double),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into f64 (valid types: Double).",
                 $data_type_option.id
@@ -252,7 +349,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, f32) => {
         match $data_type_option.id {
             ColType::Float => as_res_opt!($data_value, decode_float),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into f32 (valid types: Float).",
                 $data_type_option.id
@@ -262,7 +359,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, IpAddr) => {
         match $data_type_option.id {
             ColType::Inet => as_res_opt!($data_value, decode_inet),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into IpAddr (valid types: Inet).",
                 $data_type_option.id
@@ -272,7 +369,7 @@ macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, Uuid) => {
         match $data_type_option.id {
             ColType::Uuid | ColType::Timeuuid => as_res_opt!($data_value, decode_timeuuid),
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Uuid (valid types: Uuid, Timeuuid).",
                 $data_type_option.id
@@ -287,7 +384,7 @@ macro_rules! as_rust_type {
                     .map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into List (valid types: List, Set).",
                 $data_type_option.id
@@ -302,7 +399,7 @@ macro_rules! as_rust_type {
                     .map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Map (valid types: Map).",
                 $data_type_option.id
@@ -320,7 +417,7 @@ macro_rules! as_rust_type {
                     .map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into UDT (valid types: UDT).",
                 $data_type_option.id
@@ -338,7 +435,7 @@ macro_rules! as_rust_type {
                     .map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Tuple (valid types: tuple).",
                 $data_type_option.id
@@ -353,7 +450,7 @@ macro_rules! as_rust_type {
                     .map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Timespec (valid types: Timestamp).",
                 $data_type_option.id
@@ -366,11 +463,73 @@ macro_rules! as_rust_type {
                 Some(ref bytes) => decode_decimal(bytes).map(|d| Some(d)).map_err(Into::into),
                 None => Ok(None),
             },
-            _ => Err(Error::General(format!(
+            _ => Err(Error::TypeMismatch(format!(
                 "Invalid conversion. \
                  Cannot convert {:?} into Decimal (valid types: Decimal).",
                 $data_type_option.id
             ))),
         }
     };
+    ($data_type_option:ident, $data_value:ident, CqlValue) => {
+        match $data_value.as_slice() {
+            Some(_) => CqlValue::decode(&$data_value, &$data_type_option).map(Some),
+            None => Ok(None),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, Point) => {
+        match $data_type_option.value {
+            Some(ColTypeOptionValue::CString(ref class_name))
+                if class_name.as_str() == POINT_TYPE =>
+            {
+                as_res_opt!($data_value, decode_point)
+            }
+            _ => Err(Error::TypeMismatch(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Point (valid types: PointType).",
+                $data_type_option.id
+            ))),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, LineString) => {
+        match $data_type_option.value {
+            Some(ColTypeOptionValue::CString(ref class_name))
+                if class_name.as_str() == LINE_STRING_TYPE =>
+            {
+                as_res_opt!($data_value, decode_line_string)
+            }
+            _ => Err(Error::TypeMismatch(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into LineString (valid types: LineStringType).",
+                $data_type_option.id
+            ))),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, Polygon) => {
+        match $data_type_option.value {
+            Some(ColTypeOptionValue::CString(ref class_name))
+                if class_name.as_str() == POLYGON_TYPE =>
+            {
+                as_res_opt!($data_value, decode_polygon)
+            }
+            _ => Err(Error::TypeMismatch(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Polygon (valid types: PolygonType).",
+                $data_type_option.id
+            ))),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, DateRange) => {
+        match $data_type_option.value {
+            Some(ColTypeOptionValue::CString(ref class_name))
+                if class_name.as_str() == DATE_RANGE_TYPE =>
+            {
+                as_res_opt!($data_value, decode_date_range)
+            }
+            _ => Err(Error::TypeMismatch(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into DateRange (valid types: DateRangeType).",
+                $data_type_option.id
+            ))),
+        }
+    };
 }
@@ -0,0 +1,170 @@
+//! Typed decoding of cluster topology rows. Rows of `system.local`, `system.peers` and
+//! `system.peers_v2` tables are converted into `NodeInfo`, so topology aware clients
+//! could discover nodes, their data centers, racks and tokens.
+use std::net::IpAddr;
+
+use crate::error::Result;
+use crate::types::list::List;
+use crate::types::rows::Row;
+use crate::types::{AsRustType, IntoRustByName};
+use crate::uuid::Uuid;
+
+/// Query which selects information about a node a client is connected to.
+pub const SELECT_LOCAL: &str = "SELECT * FROM system.local WHERE key='local'";
+/// Query which selects information about other nodes of a cluster.
+pub const SELECT_PEERS: &str = "SELECT * FROM system.peers";
+/// Query which selects information about other nodes of a cluster (Cassandra 4.0+).
+pub const SELECT_PEERS_V2: &str = "SELECT * FROM system.peers_v2";
+
+/// Information about a cluster node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInfo {
+    /// Address which clients should connect to. It is `None` if neither rpc/native address
+    /// nor broadcast/peer address is known, e.g. for `system.local` row of a node which
+    /// listens on all interfaces, so the address of a connection should be used.
+    pub address: Option<IpAddr>,
+    /// Native protocol port. It is known only for `system.peers_v2` and
+    /// Cassandra 4.0+ `system.local` rows.
+    pub port: Option<u16>,
+    pub datacenter: Option<String>,
+    pub rack: Option<String>,
+    /// String representations of tokens owned by a node,
+    /// see `token::Partitioner::parse_token`.
+    pub tokens: Vec<String>,
+    pub host_id: Option<Uuid>,
+    pub schema_version: Option<Uuid>,
+}
+
+impl NodeInfo {
+    /// Decodes a row of `system.local` table.
+    pub fn from_local_row(row: &Row) -> Result<NodeInfo> {
+        let address = first_address(row, &["rpc_address", "broadcast_address"])?;
+        let port = column::<i32>(row, "native_transport_port")?;
+        NodeInfo::from_row(row, address, port)
+    }
+
+    /// Decodes a row of `system.peers` or `system.peers_v2` table.
+    pub fn from_peer_row(row: &Row) -> Result<NodeInfo> {
+        let address = first_address(row, &["native_address", "rpc_address", "peer"])?;
+        let port = column::<i32>(row, "native_port")?;
+        NodeInfo::from_row(row, address, port)
+    }
+
+    fn from_row(row: &Row, address: Option<IpAddr>, port: Option<i32>) -> Result<NodeInfo> {
+        let tokens = match column::<List>(row, "tokens")? {
+            Some(tokens) => tokens.as_r_type()?,
+            None => vec![],
+        };
+
+        Ok(NodeInfo { address,
+                      port: port.map(|port| port as u16),
+                      datacenter: column(row, "data_center")?,
+                      rack: column(row, "rack")?,
+                      tokens,
+                      host_id: column(row, "host_id")?,
+                      schema_version: column(row, "schema_version")? })
+    }
+}
+
+/// Decodes rows of `system.peers` or `system.peers_v2` table.
+pub fn peers_from_rows(rows: &[Row]) -> Result<Vec<NodeInfo>> {
+    rows.iter().map(NodeInfo::from_peer_row).collect()
+}
+
+/// Returns the first of given columns which contains a specific (not wildcard) address.
+fn first_address(row: &Row, names: &[&str]) -> Result<Option<IpAddr>> {
+    for name in names {
+        match column::<IpAddr>(row, name)? {
+            Some(address) if !address.is_unspecified() => return Ok(Some(address)),
+            _ => {}
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns a value of a column or `None` if the column is null or doesn't exist,
+/// as a set of columns differs between Cassandra versions.
+fn column<T>(row: &Row, name: &str) -> Result<Option<T>>
+    where Row: IntoRustByName<T>
+{
+    if row.columns().any(|(col_spec, _)| col_spec.name.as_str() == name) {
+        row.get_by_name(name)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata};
+    use crate::types::cql_value::CqlValue;
+    use crate::types::value::Value;
+    use crate::types::CBytes;
+
+    fn row(columns: Vec<(&str, ColTypeOption, CqlValue)>) -> Row {
+        let (col_types, row): (Vec<_>, Vec<_>) =
+            columns.into_iter()
+                   .map(|(name, col_type, value)| {
+                            ((name, col_type),
+                             match value {
+                                 CqlValue::Null => CBytes::new_empty(),
+                                 value => CBytes::new_shared(Value::from(value).body),
+                             })
+                        })
+                   .unzip();
+        Row::new(RowsMetadata::for_test(col_types), row)
+    }
+
+    fn ip(a: u8, b: u8, c: u8, d: u8) -> CqlValue {
+        CqlValue::Inet(IpAddr::V4(Ipv4Addr::new(a, b, c, d)))
+    }
+
+    #[test]
+    fn local_row() {
+        let inet = || ColTypeOption::simple(ColType::Inet);
+        let varchar = || ColTypeOption::simple(ColType::Varchar);
+        let text = Box::new(varchar());
+        let tokens = ColTypeOption { id: ColType::Set,
+                                     value: Some(ColTypeOptionValue::CSet(text)) };
+        let host_id = Uuid::parse_str("5a3d6f47-2a2f-4d3f-9b3e-1a2b3c4d5e6f").unwrap();
+        let row = row(vec![("rpc_address", inet(), ip(0, 0, 0, 0)),
+                           ("broadcast_address", inet(), ip(10, 0, 0, 1)),
+                           ("data_center", varchar(), CqlValue::Varchar("dc1".into())),
+                           ("rack", varchar(), CqlValue::Null),
+                           ("tokens",
+                            tokens,
+                            CqlValue::Set(vec![CqlValue::Varchar("-1".into()),
+                                               CqlValue::Varchar("42".into())])),
+                           ("host_id",
+                            ColTypeOption::simple(ColType::Uuid),
+                            CqlValue::Uuid(host_id))]);
+
+        assert_eq!(NodeInfo::from_local_row(&row).unwrap(),
+                   NodeInfo { address: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+                              port: None,
+                              datacenter: Some("dc1".into()),
+                              rack: None,
+                              tokens: vec!["-1".into(), "42".into()],
+                              host_id: Some(host_id),
+                              schema_version: None });
+    }
+
+    #[test]
+    fn peer_v2_row() {
+        let inet = || ColTypeOption::simple(ColType::Inet);
+        let rows = vec![row(vec![("peer", inet(), ip(10, 0, 0, 2)),
+                                 ("native_address", inet(), ip(192, 168, 0, 2)),
+                                 ("native_port",
+                                  ColTypeOption::simple(ColType::Int),
+                                  CqlValue::Int(9142))])];
+
+        let peers = peers_from_rows(&rows).unwrap();
+        assert_eq!(peers[0].address, Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 2))));
+        assert_eq!(peers[0].port, Some(9142));
+        assert!(peers[0].tokens.is_empty());
+    }
+}
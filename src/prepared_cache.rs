@@ -0,0 +1,242 @@
+//! Cache of prepared statements. Prepared results are stored under a keyspace and
+//! a query string so the same query is prepared only once per keyspace. When the cache
+//! is full the least recently used entry is evicted.
+//!
+//! Entries should be invalidated when a server responds with `Unprepared` error
+//! (see `invalidate_by_id`) or when a keyspace or a table schema changes
+//! (see `invalidate_keyspace`).
+use std::collections::{BTreeMap, HashMap};
+
+use crate::frame::frame_result::{BodyResResultPrepared, RowsMetadata};
+
+type CacheKey = (Option<String>, String);
+
+/// LRU cache of prepared statements keyed by keyspace and query string.
+#[derive(Debug)]
+pub struct PreparedCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, (u64, BodyResResultPrepared)>,
+    // last usage tick -> key, the first item is the least recently used one
+    usage: BTreeMap<u64, CacheKey>,
+}
+
+impl PreparedCache {
+    /// Creates new cache which stores at most `capacity` prepared statements.
+    pub fn new(capacity: usize) -> PreparedCache {
+        assert!(capacity > 0, "Prepared cache capacity should be greater than 0");
+        PreparedCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::with_capacity(capacity),
+            usage: BTreeMap::new(),
+        }
+    }
+
+    /// Maximum number of cached prepared statements.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of cached prepared statements.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns prepared result of a query and marks it as the most recently used one.
+    pub fn get(&mut self, keyspace: Option<&str>, query: &str) -> Option<&BodyResResultPrepared> {
+        let key = cache_key(keyspace, query);
+        let tick = self.next_tick();
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                self.usage.remove(&entry.0);
+                entry.0 = tick;
+                self.usage.insert(tick, key);
+                Some(&entry.1)
+            }
+            None => None,
+        }
+    }
+
+    /// Shows if a query is cached without changing its usage.
+    pub fn contains(&self, keyspace: Option<&str>, query: &str) -> bool {
+        self.entries.contains_key(&cache_key(keyspace, query))
+    }
+
+    /// Stores prepared result of a query. It returns previously cached result of the same query
+    /// if any. If the cache is full the least recently used entry is evicted.
+    pub fn insert(&mut self,
+                  keyspace: Option<&str>,
+                  query: &str,
+                  prepared: BodyResResultPrepared)
+                  -> Option<BodyResResultPrepared> {
+        let key = cache_key(keyspace, query);
+        let previous = self.remove(&key);
+
+        if self.entries.len() >= self.capacity {
+            let lru = self.usage.keys().next().cloned();
+            if let Some(lru) = lru.and_then(|tick| self.usage.remove(&tick)) {
+                self.entries.remove(&lru);
+            }
+        }
+
+        let tick = self.next_tick();
+        self.usage.insert(tick, key.clone());
+        self.entries.insert(key, (tick, prepared));
+
+        previous
+    }
+
+    /// Replaces result metadata of a cached query if a server reported (protocol v5
+    /// `MetadataChanged` rows flag) that it has changed. Returns `true` if an entry was updated.
+    pub fn update_result_metadata(&mut self,
+                                  keyspace: Option<&str>,
+                                  query: &str,
+                                  metadata: &RowsMetadata)
+                                  -> bool {
+        let new_metadata_id = match metadata.new_metadata_id {
+            Some(ref id) => id.clone(),
+            None => return false,
+        };
+
+        match self.entries.get_mut(&cache_key(keyspace, query)) {
+            Some(entry) => {
+                let prepared = &mut entry.1;
+                prepared.result_metadata_id = Some(new_metadata_id);
+                prepared.result_metadata = RowsMetadata { new_metadata_id: None,
+                                                          ..metadata.clone() };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a cached query.
+    pub fn invalidate(&mut self,
+                      keyspace: Option<&str>,
+                      query: &str)
+                      -> Option<BodyResResultPrepared> {
+        self.remove(&cache_key(keyspace, query))
+    }
+
+    /// Removes all the queries prepared with a given id, e.g. the one reported
+    /// by `Unprepared` error.
+    pub fn invalidate_by_id(&mut self, id: &[u8]) -> usize {
+        self.invalidate_where(|_, prepared| prepared.id.as_plain() == Some(id))
+    }
+
+    /// Removes all the queries prepared for a given keyspace.
+    pub fn invalidate_keyspace(&mut self, keyspace: &str) -> usize {
+        self.invalidate_where(|key, _| key.0.as_deref() == Some(keyspace))
+    }
+
+    /// Removes all cached queries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage.clear();
+    }
+
+    fn invalidate_where<F>(&mut self, predicate: F) -> usize
+        where F: Fn(&CacheKey, &BodyResResultPrepared) -> bool
+    {
+        let keys = self.entries
+                       .iter()
+                       .filter(|(key, entry)| predicate(key, &entry.1))
+                       .map(|(key, _)| key.clone())
+                       .collect::<Vec<_>>();
+        for key in keys.iter() {
+            self.remove(key);
+        }
+
+        keys.len()
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<BodyResResultPrepared> {
+        self.entries.remove(key).map(|(tick, prepared)| {
+                                    self.usage.remove(&tick);
+                                    prepared
+                                })
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+fn cache_key(keyspace: Option<&str>, query: &str) -> CacheKey {
+    (keyspace.map(str::to_string), query.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::PreparedMetadata;
+    use crate::types::CBytesShort;
+
+    fn prepared(id: u8) -> BodyResResultPrepared {
+        BodyResResultPrepared { id: CBytesShort::new(vec![id]),
+                                result_metadata_id: None,
+                                metadata: PreparedMetadata { flags: 0,
+                                                             columns_count: 0,
+                                                             pk_count: 0,
+                                                             pk_indexes: vec![],
+                                                             global_table_spec: None,
+                                                             col_specs: vec![] },
+                                result_metadata: RowsMetadata::for_test(vec![]) }
+    }
+
+    fn id(prepared: Option<&BodyResResultPrepared>) -> Option<Vec<u8>> {
+        prepared.and_then(|p| p.id.as_plain().map(<[u8]>::to_vec))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = PreparedCache::new(2);
+        cache.insert(Some("ks"), "q1", prepared(1));
+        cache.insert(Some("ks"), "q2", prepared(2));
+        assert_eq!(id(cache.get(Some("ks"), "q1")), Some(vec![1]));
+
+        cache.insert(None, "q3", prepared(3));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(Some("ks"), "q2"));
+        assert!(cache.contains(Some("ks"), "q1"));
+        assert_eq!(id(cache.get(None, "q3")), Some(vec![3]));
+        assert!(cache.get(Some("other"), "q1").is_none());
+    }
+
+    #[test]
+    fn invalidation() {
+        let mut cache = PreparedCache::new(10);
+        cache.insert(Some("ks"), "q1", prepared(1));
+        cache.insert(Some("ks"), "q2", prepared(2));
+        cache.insert(Some("other"), "q1", prepared(1));
+        cache.insert(None, "q3", prepared(3));
+
+        assert_eq!(cache.invalidate_by_id(&[1]), 2);
+        assert_eq!(cache.invalidate_keyspace("ks"), 1);
+        assert!(cache.invalidate(None, "q3").is_some());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn update_result_metadata() {
+        let mut cache = PreparedCache::new(1);
+        cache.insert(Some("ks"), "q", prepared(1));
+
+        let mut metadata = prepared(1).result_metadata;
+        assert!(!cache.update_result_metadata(Some("ks"), "q", &metadata));
+
+        metadata.new_metadata_id = Some(CBytesShort::new(vec![9]));
+        metadata.columns_count = 1;
+        assert!(cache.update_result_metadata(Some("ks"), "q", &metadata));
+        let prepared = cache.get(Some("ks"), "q").unwrap();
+        assert_eq!(prepared.result_metadata.columns_count, 1);
+        assert_eq!(prepared.result_metadata_id.as_ref().and_then(|id| id.as_plain()),
+                   Some(&[9][..]));
+    }
+}
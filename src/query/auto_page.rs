@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+
+use crate::error;
+use crate::frame::Frame;
+use crate::types::rows::Row;
+use super::Pager;
+
+/// Returns a stream of rows of all the pages of a result. `send` should send a frame
+/// and resolve to a response to it, the next page is requested once all the rows
+/// of the previous one have been taken. The stream ends after an error, including one
+/// of building a request.
+pub fn auto_page<F, Fut>(pager: Pager, send: F) -> RowStream<F, Fut>
+  where F: FnMut(Frame) -> Fut,
+        Fut: Future<Output = error::Result<Frame>>
+{
+  let first_page = pager.first_page();
+  RowStream { pager,
+              send,
+              rows: VecDeque::new(),
+              next_page: Some(first_page),
+              response: None }
+}
+
+/// Stream of rows across pages of a result, see `auto_page`.
+pub struct RowStream<F, Fut> {
+  pager: Pager,
+  send: F,
+  // rows of the last received page which have not been taken yet
+  rows: VecDeque<Row>,
+  // request of the next page which has not been sent yet
+  next_page: Option<error::Result<Frame>>,
+  response: Option<Pin<Box<Fut>>>,
+}
+
+impl<F, Fut> Stream for RowStream<F, Fut>
+  where F: FnMut(Frame) -> Fut + Unpin,
+        Fut: Future<Output = error::Result<Frame>>
+{
+  type Item = error::Result<Row>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    loop {
+      if let Some(row) = this.rows.pop_front() {
+        return Poll::Ready(Some(Ok(row)));
+      }
+
+      if let Some(response) = this.response.as_mut() {
+        let response = ready!(response.as_mut().poll(cx));
+        this.response = None;
+        match response.and_then(|frame| this.pager.decode_page(&frame)) {
+          Ok((rows, paging_state)) => {
+            this.rows.extend(rows);
+            this.next_page = paging_state.map(|state| this.pager.page_with_state(state));
+          }
+          Err(err) => return Poll::Ready(Some(Err(err))),
+        }
+        continue;
+      }
+
+      match this.next_page.take() {
+        Some(Ok(frame)) => this.response = Some(Box::pin((this.send)(frame))),
+        Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+        None => return Poll::Ready(None),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryInto;
+  use std::future::{poll_fn, ready};
+  use std::io::{self, Cursor};
+
+  use super::*;
+  use crate::frame::frame_query::BodyReqQuery;
+  use crate::frame::traits::FromCursor;
+  use crate::frame::{Flags, IntoBytes, Opcode, Version};
+  use crate::query::QueryParams;
+  use crate::types::{to_int, to_short, CBytes, CString, IntoRustByName};
+
+  // rows result with an `int` column `id` whose paging state is the next value of `id`
+  fn page(stream: i16, ids: &[i32], next: Option<i32>) -> Frame {
+    let mut body = to_int(0x0002);
+    body.extend(to_int(if next.is_some() { 0x0003 } else { 0x0001 }));
+    body.extend(to_int(1));
+    if let Some(next) = next {
+      body.extend(CBytes::new(to_int(next)).into_cbytes());
+    }
+    body.extend(CString::new("ks".into()).into_cbytes());
+    body.extend(CString::new("table".into()).into_cbytes());
+    body.extend(CString::new("id".into()).into_cbytes());
+    body.extend(to_short(0x0009));
+    body.extend(to_int(ids.len() as i32));
+    for id in ids {
+      body.extend(CBytes::new(to_int(*id)).into_cbytes());
+    }
+    Frame { version: Version::Response,
+            flags: Flags::empty(),
+            opcode: Opcode::Result,
+            stream,
+            body: body.into(),
+            tracing_id: None,
+            warnings: vec![],
+            custom_payload: Default::default() }
+  }
+
+  // responds to a request starting at `id` from its paging state with 2 rows of 5
+  fn respond(request: Frame) -> error::Result<Frame> {
+    let query = BodyReqQuery::from_cursor(&mut Cursor::new(request.body.as_ref()))?;
+    assert_eq!(query.query_params.page_size, Some(2));
+    let start = match query.query_params.paging_state {
+      Some(paging_state) => i32::from_be_bytes(paging_state.as_slice().unwrap().try_into()
+                                                                              .unwrap()),
+      None => 0,
+    };
+    let ids = (start..5).take(2).collect::<Vec<i32>>();
+    let next = Some(start + 2).filter(|next| *next < 5);
+    Ok(page(request.stream, &ids, next))
+  }
+
+  async fn collect<S: Stream<Item = error::Result<Row>> + Unpin>(mut rows: S) -> Vec<i32> {
+    let mut ids = vec![];
+    while let Some(row) = poll_fn(|cx| Pin::new(&mut rows).poll_next(cx)).await {
+      ids.push(row.unwrap().get_r_by_name("id").unwrap());
+    }
+    ids
+  }
+
+  #[tokio::test]
+  async fn all_pages() {
+    let pager = Pager::query("SELECT id FROM ks.table", QueryParams::default(), Flags::empty())
+      .with_page_size(2);
+    let mut requests = 0;
+    let rows = auto_page(pager, |frame| {
+                 requests += 1;
+                 ready(respond(frame))
+               });
+    assert_eq!(collect(rows).await, vec![0, 1, 2, 3, 4]);
+    assert_eq!(requests, 3);
+  }
+
+  #[tokio::test]
+  async fn error() {
+    let pager = Pager::query("SELECT id FROM ks.table", QueryParams::default(), Flags::empty());
+    let closed = || io::Error::new(io::ErrorKind::ConnectionAborted, "Connection closed");
+    let mut rows = auto_page(pager, |_| ready(Err(closed().into())));
+    let first = poll_fn(|cx| Pin::new(&mut rows).poll_next(cx)).await;
+    assert!(matches!(first, Some(Err(_))));
+    assert!(poll_fn(|cx| Pin::new(&mut rows).poll_next(cx)).await.is_none());
+  }
+}
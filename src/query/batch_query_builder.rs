@@ -1,11 +1,16 @@
-use super::{QueryFlags, QueryValues};
+use super::{QueryFlags, QueryValues, QueryValuesError};
 use crate::consistency::Consistency;
 use crate::error::{Error as CError, Result as CResult};
 use crate::frame::frame_batch::{BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch};
+use crate::frame::frame_result::BodyResResultPrepared;
+use crate::frame::{Flags, Frame};
 use crate::types::{CBytesShort, CStringLong};
 
 pub type QueryBatch = BodyReqBatch;
 
+/// Builder of a body of `BATCH` request. Each statement of a batch is either a query string
+/// or an id of a prepared query along with its values. Default batch type is `Logged` and
+/// default consistency is `One`.
 #[derive(Debug)]
 pub struct BatchQueryBuilder {
   batch_type: BatchType,
@@ -13,6 +18,14 @@ pub struct BatchQueryBuilder {
   consistency: Consistency,
   serial_consistency: Option<Consistency>,
   timestamp: Option<i64>,
+  keyspace: Option<String>,
+  now_in_seconds: Option<i32>,
+}
+
+impl Default for BatchQueryBuilder {
+  fn default() -> Self {
+    BatchQueryBuilder::new()
+  }
 }
 
 impl BatchQueryBuilder {
@@ -23,6 +36,8 @@ impl BatchQueryBuilder {
       consistency: Consistency::One,
       serial_consistency: None,
       timestamp: None,
+      keyspace: None,
+      now_in_seconds: None,
     }
   }
 
@@ -51,6 +66,11 @@ impl BatchQueryBuilder {
     self
   }
 
+  /// Add a query prepared with a given result of `PREPARE` request
+  pub fn add_prepared(self, prepared: &BodyResResultPrepared, values: QueryValues) -> Self {
+    self.add_query_prepared(prepared.id.clone(), values)
+  }
+
   pub fn clear_queries(mut self) -> Self {
     self.queries = vec![];
     self
@@ -71,32 +91,45 @@ impl BatchQueryBuilder {
     self
   }
 
+  /// Sets keyspace of non-qualified tables of batched queries. Protocol v5 only.
+  pub fn keyspace<T: Into<String>>(mut self, keyspace: Option<T>) -> Self {
+    self.keyspace = keyspace.map(Into::into);
+    self
+  }
+
+  /// Sets current time in seconds which is used by a server as a reference time
+  /// for TTL calculations. Protocol v5 only.
+  pub fn now_in_seconds(mut self, now_in_seconds: Option<i32>) -> Self {
+    self.now_in_seconds = now_in_seconds;
+    self
+  }
+
   pub fn finalize(self) -> CResult<BodyReqBatch> {
-    let mut flags = vec![];
+    let mut flags = QueryFlags::empty();
+
+    if !cfg!(feature = "v5") && (self.keyspace.is_some() || self.now_in_seconds.is_some()) {
+      return Err(CError::InvalidRequest(String::from(
+        "Batch keyspace and now_in_seconds are supported only by protocol v5",
+      )));
+    }
 
     if self.serial_consistency.is_some() {
-      flags.push(QueryFlags::WithSerialConsistency);
+      flags.insert(QueryFlags::WITH_SERIAL_CONSISTENCY);
     }
 
     if self.timestamp.is_some() {
-      flags.push(QueryFlags::WithDefaultTimestamp);
+      flags.insert(QueryFlags::WITH_DEFAULT_TIMESTAMP);
     }
 
-    let with_names_for_values = self.queries.iter().all(|q| q.values.with_names());
-
-    if !with_names_for_values {
-      let some_names_for_values = self.queries.iter().any(|q| q.values.with_names());
+    let with_names_for_values = self.queries.first().is_some_and(|q| q.values.with_names());
 
-      if some_names_for_values {
-        return Err(CError::General(String::from(
-          "Inconsistent query values - mixed \
-           with and without names values",
-        )));
-      }
+    let mixed = self.queries.iter().position(|q| q.values.with_names() != with_names_for_values);
+    if let Some(index) = mixed {
+      return Err(CError::InvalidValues(QueryValuesError::Mixed { index }));
     }
 
     if with_names_for_values {
-      flags.push(QueryFlags::WithNamesForValues);
+      flags.insert(QueryFlags::WITH_NAMES_FOR_VALUES);
     }
 
     Ok(BodyReqBatch {
@@ -106,6 +139,86 @@ impl BatchQueryBuilder {
       consistency: self.consistency,
       serial_consistency: self.serial_consistency,
       timestamp: self.timestamp,
+      keyspace: self.keyspace,
+      now_in_seconds: self.now_in_seconds,
     })
   }
+
+  /// Builds `BATCH` request frame.
+  pub fn finalize_frame(self, flags: Flags) -> CResult<Frame> {
+    self.finalize().and_then(|batch| Frame::new_batch(&batch, flags))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+  use crate::frame::{AsByte, IntoBytes, Opcode};
+  use crate::types::to_int;
+  use crate::types::value::Value;
+
+  #[test]
+  fn build_batch() {
+    let frame = BatchQueryBuilder::new()
+      .batch_type(BatchType::Unlogged)
+      .add_query("INSERT INTO t (a) VALUES (?)", QueryValues::SimpleValues(vec![Value::from(1_i8)]))
+      .add_query_prepared(CBytesShort::new(vec![7]), QueryValues::SimpleValues(vec![]))
+      .consistency(Consistency::Quorum)
+      .timestamp(Some(1))
+      .finalize_frame(Flags::empty())
+      .unwrap();
+    assert_eq!(frame.opcode, Opcode::Batch);
+
+    let mut expected = vec![1, 0, 2, 0];
+    expected.extend(CStringLong::new("INSERT INTO t (a) VALUES (?)".into()).into_cbytes());
+    expected.extend(vec![0, 1, 0, 0, 0, 1, 1]);
+    expected.extend(vec![1, 0, 1, 7, 0, 0]);
+    expected.extend(Consistency::Quorum.into_cbytes());
+    let flags = QueryFlags::WITH_DEFAULT_TIMESTAMP;
+    if cfg!(feature = "v5") {
+      expected.extend(to_int(flags.bits() as i32));
+    } else {
+      expected.push(flags.as_byte());
+    }
+    expected.extend(vec![0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(frame.body, expected);
+  }
+
+  #[test]
+  #[cfg(not(feature = "v5"))]
+  fn v5_only_fields() {
+    let batch = BatchQueryBuilder::new().keyspace(Some("ks")).finalize();
+    assert!(batch.is_err());
+  }
+
+  #[test]
+  #[cfg(feature = "v5")]
+  fn v5_fields() {
+    let batch = BatchQueryBuilder::new()
+      .keyspace(Some("ks"))
+      .now_in_seconds(Some(2))
+      .finalize()
+      .unwrap();
+
+    let mut expected = vec![0, 0, 0];
+    expected.extend(Consistency::One.into_cbytes());
+    expected.extend(vec![0, 0, 1, 0x80, 0, 2, b'k', b's', 0, 0, 0, 2]);
+    assert_eq!(batch.into_cbytes(), expected);
+  }
+
+  #[test]
+  fn mixed_values() {
+    let mut named = HashMap::new();
+    named.insert("a".to_string(), Value::from(1_i8));
+    let batch = BatchQueryBuilder::new()
+      .add_query("q1", QueryValues::NamedValues(named))
+      .add_query("q2", QueryValues::SimpleValues(vec![Value::from(1_i8)]))
+      .finalize();
+    match batch {
+      Err(CError::InvalidValues(QueryValuesError::Mixed { index: 1 })) => {}
+      batch => panic!("Unexpected batch {:?}", batch),
+    }
+  }
 }
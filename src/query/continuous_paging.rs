@@ -0,0 +1,16 @@
+/// Options of DataStax Enterprise continuous paging. Instead of a request per page
+/// a server streams pages of a result as separate `RESULT` frames on the stream of
+/// a single request, which speeds up bulk reads. DSE negotiates it only under its own
+/// protocol versions (`DSE_V1`, `DSE_V2`), which aren't supported yet, so query params
+/// with these options are rejected by `check` and never serialized. Each page
+/// contains `ContinuousPage` in its rows metadata.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContinuousPagingOptions {
+  /// Maximum number of pages a server sends, 0 means all the pages.
+  pub max_pages: i32,
+  /// Maximum number of pages a server sends per second, 0 means no limit.
+  pub pages_per_second: i32,
+  /// Number of pages a server sends before it waits for a client to request more
+  /// (DSE_V2 backpressure), 0 means no limit.
+  pub next_pages: i32,
+}
@@ -1,13 +1,27 @@
+#[cfg(feature = "stream")]
+mod auto_page;
 mod batch_query_builder;
+mod continuous_paging;
+mod pager;
+mod paging_state;
 mod query;
 mod query_flags;
 mod query_params;
 mod query_params_builder;
 mod query_values;
+mod query_values_builder;
+mod routing_key;
 
+#[cfg(feature = "stream")]
+pub use auto_page::{auto_page, RowStream};
 pub use batch_query_builder::*;
+pub use continuous_paging::ContinuousPagingOptions;
+pub use pager::{Pager, DEFAULT_PAGE_SIZE};
+pub use paging_state::PagingState;
 pub use query::Query;
 pub use query_flags::QueryFlags;
 pub use query_params::QueryParams;
 pub use query_params_builder::QueryParamsBuilder;
-pub use query_values::QueryValues;
+pub use query_values::{QueryValues, QueryValuesError};
+pub use query_values_builder::QueryValuesBuilder;
+pub use routing_key::routing_key;
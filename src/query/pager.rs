@@ -0,0 +1,174 @@
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::frame_result::{BodyResResultPrepared, RowsMetadata};
+use crate::frame::{Flags, Frame};
+use crate::types::rows::Row;
+use crate::types::{CBytes, CBytesShort};
+use super::{PagingState, QueryParams};
+
+/// Page size which is used if paged params don't set one, otherwise a server
+/// would return all the rows at once.
+pub const DEFAULT_PAGE_SIZE: i32 = 5000;
+
+#[derive(Debug, Clone)]
+enum PagedRequest {
+  Query(String),
+  Execute {
+    id: CBytesShort,
+    result_metadata_id: Option<CBytesShort>,
+    // decodes rows of pages requested with `SKIP_METADATA`
+    result_metadata: RowsMetadata,
+  },
+}
+
+/// Producer of `QUERY` or `EXECUTE` frames which fetch consecutive pages of a result.
+/// Every page is requested with the same params except for paging state which is taken
+/// from rows metadata of the previous page.
+#[derive(Debug, Clone)]
+pub struct Pager {
+  request: PagedRequest,
+  params: QueryParams,
+  flags: Flags,
+}
+
+impl Pager {
+  /// Creates new pager of a query string.
+  pub fn query(query: &str, params: QueryParams, flags: Flags) -> Pager {
+    Pager::new(PagedRequest::Query(query.to_string()), params, flags)
+  }
+
+  /// Creates new pager of a prepared query.
+  pub fn execute(prepared: &BodyResResultPrepared, params: QueryParams, flags: Flags) -> Pager {
+    let request = PagedRequest::Execute { id: prepared.id.clone(),
+                                          result_metadata_id: prepared.result_metadata_id
+                                                                      .clone(),
+                                          result_metadata: prepared.result_metadata.clone() };
+    Pager::new(request, params, flags)
+  }
+
+  fn new(request: PagedRequest, mut params: QueryParams, flags: Flags) -> Pager {
+    params.page_size = Some(params.page_size.unwrap_or(DEFAULT_PAGE_SIZE));
+    params.paging_state = None;
+    Pager { request,
+            params,
+            flags }
+  }
+
+  /// Sets a number of rows in a page.
+  pub fn with_page_size(mut self, page_size: i32) -> Self {
+    self.params.page_size = Some(page_size);
+    self
+  }
+
+  /// Returns a number of rows in a page.
+  pub fn page_size(&self) -> i32 {
+    self.params.page_size.unwrap_or(DEFAULT_PAGE_SIZE)
+  }
+
+  /// Returns a frame which requests the first page. An error is returned if params
+  /// cannot be serialized.
+  pub fn first_page(&self) -> error::Result<Frame> {
+    self.frame(None)
+  }
+
+  /// Returns a frame which requests a page following one with given rows metadata,
+  /// `None` is returned if it's the last page.
+  pub fn next_page(&self, metadata: &RowsMetadata) -> error::Result<Option<Frame>> {
+    PagingState::from_metadata(metadata).map(|paging_state| self.page_with_state(paging_state))
+                                        .transpose()
+  }
+
+  /// Returns a frame which requests a page starting at a given paging state, e.g. one
+  /// which was returned to a client of an application earlier.
+  pub fn page_with_state(&self, paging_state: PagingState) -> error::Result<Frame> {
+    self.frame(Some(paging_state.into()))
+  }
+
+  /// Decodes a response to a page request returning its rows and paging state
+  /// of the next page, which is `None` if it's the last page. A server error
+  /// is returned as `Error::Server`.
+  pub fn decode_page(&self, frame: &Frame) -> error::Result<(Vec<Row>, Option<PagingState>)> {
+    let body = match self.request {
+      PagedRequest::Execute { ref result_metadata,
+                              .. } if self.params.skips_metadata() => {
+        frame.get_body_with_metadata(result_metadata)?
+      }
+      _ => frame.get_body()?,
+    };
+
+    let paging_state = body.as_rows_metadata()
+                           .as_ref()
+                           .and_then(PagingState::from_metadata);
+    match body {
+      ResponseBody::Error(err) => Err(err.into()),
+      body => body.into_rows()
+                  .map(|rows| (rows, paging_state))
+                  .ok_or_else(|| {
+                    let msg = format!("Unexpected {:?} response to a page request", frame.opcode);
+                    error::Error::Protocol(msg)
+                  }),
+    }
+  }
+
+  fn frame(&self, paging_state: Option<CBytes>) -> error::Result<Frame> {
+    let mut params = self.params.clone();
+    params.paging_state = paging_state;
+    match self.request {
+      PagedRequest::Query(ref query) => {
+        Frame::new_req_query_with_params(query, params, self.flags)
+      }
+      PagedRequest::Execute { ref id,
+                              ref result_metadata_id,
+                              .. } => {
+        Frame::new_req_execute_with_metadata_id(id,
+                                                result_metadata_id.as_ref(),
+                                                params,
+                                                self.flags)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::consistency::Consistency;
+  use crate::query::QueryParamsBuilder;
+
+  fn metadata(paging_state: Option<CBytes>) -> RowsMetadata {
+    RowsMetadata { paging_state,
+                   ..RowsMetadata::for_test(vec![]) }
+  }
+
+  #[test]
+  fn query_pages() {
+    let params = QueryParamsBuilder::new().consistency(Consistency::Quorum)
+                                          .finalize();
+    let pager = Pager::query("SELECT * FROM t", params, Flags::empty()).with_page_size(10);
+    assert_eq!(pager.page_size(), 10);
+
+    let expected = |paging_state: Option<CBytes>| {
+      let mut params = QueryParamsBuilder::new().consistency(Consistency::Quorum)
+                                                .page_size(10)
+                                                .finalize();
+      params.paging_state = paging_state;
+      Frame::new_req_query_with_params("SELECT * FROM t", params, Flags::empty()).unwrap()
+                                                                                .body
+    };
+    assert_eq!(pager.first_page().unwrap().body, expected(None));
+
+    let paging_state = CBytes::new(vec![1, 2, 3]);
+    let next = pager.next_page(&metadata(Some(paging_state.clone()))).unwrap().unwrap();
+    assert_eq!(next.body, expected(Some(paging_state)));
+    assert!(pager.next_page(&metadata(None)).unwrap().is_none());
+  }
+
+  #[test]
+  fn default_page_size() {
+    let params = QueryParams { paging_state: Some(CBytes::new(vec![1])),
+                               ..Default::default() };
+    let pager = Pager::query("SELECT * FROM t", params, Flags::empty());
+    assert_eq!(pager.page_size(), DEFAULT_PAGE_SIZE);
+    assert_eq!(pager.params.paging_state, None);
+  }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error;
+use crate::frame::frame_result::RowsMetadata;
+use crate::types::CBytes;
+
+/// Position of a paged result after the last returned row. Its bytes are defined
+/// by a server and may change between server versions, so they should be neither
+/// interpreted nor built by a client, only sent back with a request of the next page
+/// of the same query. It's displayed as URL-safe base64 without padding, so it could be
+/// passed through web APIs as a page token.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PagingState(Vec<u8>);
+
+impl PagingState {
+  /// Wraps paging state bytes previously returned by `as_bytes`.
+  pub fn from_bytes(bytes: Vec<u8>) -> PagingState {
+    PagingState(bytes)
+  }
+
+  /// Returns paging state of a page following one with given rows metadata,
+  /// `None` is returned if it's the last page.
+  pub fn from_metadata(metadata: &RowsMetadata) -> Option<PagingState> {
+    metadata.paging_state
+            .as_ref()
+            .and_then(CBytes::as_slice)
+            .filter(|bytes| !bytes.is_empty())
+            .map(|bytes| PagingState(bytes.to_vec()))
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+}
+
+impl From<PagingState> for CBytes {
+  fn from(paging_state: PagingState) -> CBytes {
+    CBytes::new(paging_state.0)
+  }
+}
+
+impl fmt::Debug for PagingState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "PagingState({})", self)
+  }
+}
+
+impl fmt::Display for PagingState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&encode_base64(&self.0))
+  }
+}
+
+impl FromStr for PagingState {
+  type Err = error::Error;
+
+  /// Parses paging state from URL-safe base64 without padding.
+  fn from_str(s: &str) -> error::Result<PagingState> {
+    decode_base64(s).map(PagingState)
+                    .ok_or_else(|| error::Error::Parse("Paging state is not valid base64".into()))
+  }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PagingState {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PagingState {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<PagingState, D::Error> {
+    let token = String::deserialize(deserializer)?;
+    token.parse().map_err(serde::de::Error::custom)
+  }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn encode_base64(bytes: &[u8]) -> String {
+  let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+  for chunk in bytes.chunks(3) {
+    let mut group = [0u8; 3];
+    group[..chunk.len()].copy_from_slice(chunk);
+    let bits = u32::from(group[0]) << 16 | u32::from(group[1]) << 8 | u32::from(group[2]);
+    for i in 0..=chunk.len() {
+      let index = (bits >> (18 - 6 * i)) & 0x3f;
+      encoded.push(char::from(BASE64_ALPHABET[index as usize]));
+    }
+  }
+  encoded
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+  let mut decoded = Vec::with_capacity(s.len() * 3 / 4);
+  for chunk in s.as_bytes().chunks(4) {
+    // a single character can't encode a whole byte
+    if chunk.len() == 1 {
+      return None;
+    }
+    let mut bits = 0u32;
+    for (i, c) in chunk.iter().enumerate() {
+      let index = BASE64_ALPHABET.iter().position(|a| a == c)?;
+      bits |= (index as u32) << (18 - 6 * i);
+    }
+    for i in 0..chunk.len() - 1 {
+      decoded.push((bits >> (16 - 8 * i)) as u8);
+    }
+  }
+  Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn base64() {
+    let cases: &[(&[u8], &str)] = &[(b"", ""),
+                                    (b"f", "Zg"),
+                                    (b"fo", "Zm8"),
+                                    (b"foo", "Zm9v"),
+                                    (b"foob", "Zm9vYg"),
+                                    (&[0xfb, 0xff], "-_8")];
+    for (bytes, encoded) in cases {
+      let paging_state = PagingState::from_bytes(bytes.to_vec());
+      assert_eq!(paging_state.to_string(), *encoded);
+      assert_eq!(encoded.parse::<PagingState>().unwrap(), paging_state);
+    }
+    assert!("Zm9vY".parse::<PagingState>().is_err());
+    assert!("Zm9v+g".parse::<PagingState>().is_err());
+  }
+
+  #[test]
+  fn from_metadata() {
+    let mut metadata = RowsMetadata::for_test(vec![]);
+    assert_eq!(PagingState::from_metadata(&metadata), None);
+
+    metadata.paging_state = Some(CBytes::new(vec![1, 2]));
+    let paging_state = PagingState::from_metadata(&metadata).unwrap();
+    assert_eq!(paging_state.as_bytes(), &[1, 2]);
+    assert_eq!(CBytes::from(paging_state), CBytes::new(vec![1, 2]));
+  }
+
+  #[test]
+  #[cfg(all(feature = "serde", feature = "json"))]
+  fn serde() {
+    let paging_state = PagingState::from_bytes(b"foob".to_vec());
+    let json = serde_json::to_string(&paging_state).unwrap();
+    assert_eq!(json, "\"Zm9vYg\"");
+    assert_eq!(serde_json::from_str::<PagingState>(&json).unwrap(), paging_state);
+    assert!(serde_json::from_str::<PagingState>("\"!\"").is_err());
+  }
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
 use crate::frame::AsByte;
 
 const FLAGS_VALUE: u8 = 0x01;
@@ -7,24 +10,150 @@ const WITH_PAGING_STATE: u8 = 0x08;
 const WITH_SERIAL_CONSISTENCY: u8 = 0x10;
 const WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
 const WITH_NAME_FOR_VALUES: u8 = 0x40;
+// flags of protocol v3 and v4 where flags are a byte
+const BYTE_FLAGS: u8 = FLAGS_VALUE | FLAGS_SKIP_METADATA | WITH_PAGE_SIZE | WITH_PAGING_STATE
+                       | WITH_SERIAL_CONSISTENCY | WITH_DEFAULT_TIMESTAMP | WITH_NAME_FOR_VALUES;
+
+// protocol v5 flags are [int], these ones don't fit in a byte of earlier versions
+const WITH_KEYSPACE: u32 = 0x80;
+const WITH_NOW_IN_SECONDS: u32 = 0x100;
+const WITH_CONTINUOUS_PAGING: u32 = 0x8000_0000;
+
+/// Set of Cassandra query flags, e.g. `QueryFlags::VALUE | QueryFlags::PAGE_SIZE`.
+/// Every flag is either set or not, so it cannot be duplicated.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct QueryFlags(u32);
+
+const NAMES: [(QueryFlags, &str); 10] = [(QueryFlags::VALUE, "VALUE"),
+                                         (QueryFlags::SKIP_METADATA, "SKIP_METADATA"),
+                                         (QueryFlags::PAGE_SIZE, "PAGE_SIZE"),
+                                         (QueryFlags::WITH_PAGING_STATE, "WITH_PAGING_STATE"),
+                                         (QueryFlags::WITH_SERIAL_CONSISTENCY,
+                                          "WITH_SERIAL_CONSISTENCY"),
+                                         (QueryFlags::WITH_DEFAULT_TIMESTAMP,
+                                          "WITH_DEFAULT_TIMESTAMP"),
+                                         (QueryFlags::WITH_NAMES_FOR_VALUES,
+                                          "WITH_NAMES_FOR_VALUES"),
+                                         (QueryFlags::WITH_KEYSPACE, "WITH_KEYSPACE"),
+                                         (QueryFlags::WITH_NOW_IN_SECONDS, "WITH_NOW_IN_SECONDS"),
+                                         (QueryFlags::WITH_CONTINUOUS_PAGING,
+                                          "WITH_CONTINUOUS_PAGING")];
 
-/// Cassandra Query Flags.
-#[derive(Clone, Debug)]
-pub enum QueryFlags {
+impl QueryFlags {
   /// If set indicates that Query Params contains value.
-  Value,
+  pub const VALUE: QueryFlags = QueryFlags(FLAGS_VALUE as u32);
   /// If set indicates that Query Params does not contain metadata.
-  SkipMetadata,
+  pub const SKIP_METADATA: QueryFlags = QueryFlags(FLAGS_SKIP_METADATA as u32);
   /// If set indicates that Query Params contains page size.
-  PageSize,
+  pub const PAGE_SIZE: QueryFlags = QueryFlags(WITH_PAGE_SIZE as u32);
   /// If set indicates that Query Params contains paging state.
-  WithPagingState,
+  pub const WITH_PAGING_STATE: QueryFlags = QueryFlags(WITH_PAGING_STATE as u32);
   /// If set indicates that Query Params contains serial consistency.
-  WithSerialConsistency,
+  pub const WITH_SERIAL_CONSISTENCY: QueryFlags = QueryFlags(WITH_SERIAL_CONSISTENCY as u32);
   /// If set indicates that Query Params contains default timestamp.
-  WithDefaultTimestamp,
+  pub const WITH_DEFAULT_TIMESTAMP: QueryFlags = QueryFlags(WITH_DEFAULT_TIMESTAMP as u32);
   /// If set indicates that Query Params values are named ones.
-  WithNamesForValues,
+  pub const WITH_NAMES_FOR_VALUES: QueryFlags = QueryFlags(WITH_NAME_FOR_VALUES as u32);
+  /// If set indicates that Query Params or a batch contain keyspace (protocol v5 only).
+  pub const WITH_KEYSPACE: QueryFlags = QueryFlags(WITH_KEYSPACE);
+  /// If set indicates that Query Params or a batch contain `now_in_seconds`
+  /// (protocol v5 only).
+  pub const WITH_NOW_IN_SECONDS: QueryFlags = QueryFlags(WITH_NOW_IN_SECONDS);
+  /// If set indicates that Query Params contain DSE continuous paging options. It is
+  /// defined only by DSE protocol versions, so it is never sent.
+  pub const WITH_CONTINUOUS_PAGING: QueryFlags = QueryFlags(WITH_CONTINUOUS_PAGING);
+
+  /// Returns a set without any flag.
+  pub const fn empty() -> QueryFlags {
+    QueryFlags(0)
+  }
+
+  /// Returns a set of all known flags.
+  pub const fn all() -> QueryFlags {
+    QueryFlags(BYTE_FLAGS as u32 | WITH_KEYSPACE | WITH_NOW_IN_SECONDS | WITH_CONTINUOUS_PAGING)
+  }
+
+  /// Returns a set of flags from protocol v5 `[int]` ignoring unknown bits.
+  pub const fn from_bits_truncate(bits: u32) -> QueryFlags {
+    QueryFlags(bits & QueryFlags::all().0)
+  }
+
+  /// Returns a set of flags from protocol v3 or v4 byte ignoring unknown bits,
+  /// so protocol v5 flags are never set.
+  pub const fn from_byte_truncate(byte: u8) -> QueryFlags {
+    QueryFlags((byte & BYTE_FLAGS) as u32)
+  }
+
+  /// Returns protocol v5 `[int]` representation of the set.
+  pub const fn bits(self) -> u32 {
+    self.0
+  }
+
+  /// Shows if no flag is set.
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+
+  /// Shows if all the flags of `other` are set.
+  pub const fn contains(self, other: QueryFlags) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  /// Returns a set of flags which are set either in `self` or in `other`.
+  pub const fn union(self, other: QueryFlags) -> QueryFlags {
+    QueryFlags(self.0 | other.0)
+  }
+
+  /// Sets flags of `other`.
+  pub fn insert(&mut self, other: QueryFlags) {
+    self.0 |= other.0;
+  }
+
+  /// Unsets flags of `other`.
+  pub fn remove(&mut self, other: QueryFlags) {
+    self.0 &= !other.0;
+  }
+
+  /// Sets or unsets flags of `other` depending on `value`.
+  pub fn set(&mut self, other: QueryFlags, value: bool) {
+    if value {
+      self.insert(other);
+    } else {
+      self.remove(other);
+    }
+  }
+}
+
+impl BitOr for QueryFlags {
+  type Output = QueryFlags;
+
+  fn bitor(self, other: QueryFlags) -> QueryFlags {
+    self.union(other)
+  }
+}
+
+impl BitOrAssign for QueryFlags {
+  fn bitor_assign(&mut self, other: QueryFlags) {
+    self.insert(other);
+  }
+}
+
+impl BitAnd for QueryFlags {
+  type Output = QueryFlags;
+
+  fn bitand(self, other: QueryFlags) -> QueryFlags {
+    QueryFlags(self.0 & other.0)
+  }
+}
+
+impl fmt::Debug for QueryFlags {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let names = NAMES.iter()
+                     .filter(|(flag, _)| self.contains(*flag))
+                     .map(|(_, name)| *name)
+                     .collect::<Vec<_>>();
+    write!(f, "QueryFlags({})", names.join(" | "))
+  }
 }
 
 impl QueryFlags {
@@ -99,17 +228,10 @@ impl QueryFlags {
   }
 }
 
+/// Byte representation of protocol v3 and v4, protocol v5 flags are dropped.
 impl AsByte for QueryFlags {
   fn as_byte(&self) -> u8 {
-    match *self {
-      QueryFlags::Value => FLAGS_VALUE,
-      QueryFlags::SkipMetadata => FLAGS_SKIP_METADATA,
-      QueryFlags::PageSize => WITH_PAGE_SIZE,
-      QueryFlags::WithPagingState => WITH_PAGING_STATE,
-      QueryFlags::WithSerialConsistency => WITH_SERIAL_CONSISTENCY,
-      QueryFlags::WithDefaultTimestamp => WITH_DEFAULT_TIMESTAMP,
-      QueryFlags::WithNamesForValues => WITH_NAME_FOR_VALUES,
-    }
+    self.bits() as u8 & BYTE_FLAGS
   }
 }
 
@@ -224,32 +346,64 @@ mod tests {
 
   #[test]
   fn as_byte_test() {
-    assert_eq!(QueryFlags::Value.as_byte(),
+    assert_eq!(QueryFlags::VALUE.as_byte(),
                FLAGS_VALUE,
                "should propery convert values flag");
 
-    assert_eq!(QueryFlags::SkipMetadata.as_byte(),
+    assert_eq!(QueryFlags::SKIP_METADATA.as_byte(),
                FLAGS_SKIP_METADATA,
                "should propery convert skip metadata flag");
 
-    assert_eq!(QueryFlags::PageSize.as_byte(),
+    assert_eq!(QueryFlags::PAGE_SIZE.as_byte(),
                WITH_PAGE_SIZE,
                "should propery convert with page size flag");
 
-    assert_eq!(QueryFlags::WithPagingState.as_byte(),
+    assert_eq!(QueryFlags::WITH_PAGING_STATE.as_byte(),
                WITH_PAGING_STATE,
                "should propery convert with paging state flag");
 
-    assert_eq!(QueryFlags::WithSerialConsistency.as_byte(),
+    assert_eq!(QueryFlags::WITH_SERIAL_CONSISTENCY.as_byte(),
                WITH_SERIAL_CONSISTENCY,
                "should propery convert with serial consistency flag");
 
-    assert_eq!(QueryFlags::WithDefaultTimestamp.as_byte(),
+    assert_eq!(QueryFlags::WITH_DEFAULT_TIMESTAMP.as_byte(),
                WITH_DEFAULT_TIMESTAMP,
                "should propery convert with default timestamp flag");
 
-    assert_eq!(QueryFlags::WithNamesForValues.as_byte(),
+    assert_eq!(QueryFlags::WITH_NAMES_FOR_VALUES.as_byte(),
                WITH_NAME_FOR_VALUES,
                "should propery convert with name for values flag");
+
+    assert_eq!((QueryFlags::VALUE | QueryFlags::PAGE_SIZE).as_byte(),
+               FLAGS_VALUE | WITH_PAGE_SIZE,
+               "should propery convert a set of flags");
+  }
+
+  #[test]
+  fn set_operations_test() {
+    let mut flags = QueryFlags::empty();
+    assert!(flags.is_empty());
+    flags |= QueryFlags::VALUE;
+    flags |= QueryFlags::VALUE;
+    flags.insert(QueryFlags::PAGE_SIZE);
+    assert_eq!(flags.as_byte(), FLAGS_VALUE | WITH_PAGE_SIZE);
+    assert!(flags.contains(QueryFlags::VALUE | QueryFlags::PAGE_SIZE));
+    assert!(!flags.contains(QueryFlags::VALUE | QueryFlags::SKIP_METADATA));
+    assert_eq!(flags & QueryFlags::PAGE_SIZE, QueryFlags::PAGE_SIZE);
+
+    flags.set(QueryFlags::VALUE, false);
+    assert_eq!(flags, QueryFlags::PAGE_SIZE);
+    flags.remove(QueryFlags::PAGE_SIZE);
+    assert!(flags.is_empty());
+
+    assert_eq!(QueryFlags::from_bits_truncate(u32::MAX), QueryFlags::all());
+    assert_eq!(QueryFlags::from_bits_truncate(0x200), QueryFlags::empty());
+    assert_eq!(QueryFlags::from_byte_truncate(0xff).bits(), u32::from(BYTE_FLAGS));
+    assert_eq!(QueryFlags::WITH_KEYSPACE.bits(), 0x80);
+    assert_eq!(QueryFlags::WITH_NOW_IN_SECONDS.bits(), 0x100);
+    assert_eq!(QueryFlags::WITH_CONTINUOUS_PAGING.bits(), 0x8000_0000);
+    assert_eq!((QueryFlags::VALUE | QueryFlags::WITH_KEYSPACE).as_byte(), FLAGS_VALUE);
+    assert_eq!(format!("{:?}", QueryFlags::VALUE | QueryFlags::WITH_NAMES_FOR_VALUES),
+               "QueryFlags(VALUE | WITH_NAMES_FOR_VALUES)");
   }
 }
@@ -1,17 +1,24 @@
+use std::io::Cursor;
+
+use bytes::BufMut;
+
 use crate::consistency::Consistency;
-use crate::types::{to_bigint, to_int, to_short, CBytes};
-use crate::frame::AsByte;
-use crate::frame::IntoBytes;
+use crate::error;
+use crate::types::{check_max_len, cursor_next_value, put_string, CBytes, CInt, CLong, CString,
+                   INT_LEN, LONG_LEN, SHORT_LEN, SHORT_MAX};
+use crate::frame::{AsByte, FromCursor, IntoBytes, TryIntoBytes};
+use super::continuous_paging::ContinuousPagingOptions;
 use super::query_flags::QueryFlags;
 use super::query_values::QueryValues;
 
 /// Parameters of Query for query operation.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct QueryParams {
   /// Cassandra consistency level.
   pub consistency: Consistency,
-  /// Array of query flags.
-  pub flags: Vec<QueryFlags>,
+  /// Set of query flags. Flags of optional fields are derived from the fields
+  /// when params are serialized, see `effective_flags`.
+  pub flags: QueryFlags,
   /// Were values provided with names
   pub with_names: Option<bool>,
   /// Array of values.
@@ -24,96 +31,306 @@ pub struct QueryParams {
   pub serial_consistency: Option<Consistency>,
   /// Timestamp.
   pub timestamp: Option<i64>,
+  /// Keyspace which non-qualified tables of a query belong to (protocol v5 only).
+  pub keyspace: Option<String>,
+  /// Current time in seconds which should be used by a server for TTL calculations
+  /// (protocol v5 only).
+  pub now_in_seconds: Option<i32>,
+  /// Options of DSE continuous paging. They cannot be sent yet, see `ContinuousPagingOptions`.
+  pub continuous_paging: Option<ContinuousPagingOptions>,
 }
 
 impl QueryParams {
   /// Sets values of Query request params.
   pub fn set_values(&mut self, values: QueryValues) {
-    self.flags.push(QueryFlags::Value);
+    self.flags.insert(QueryFlags::VALUE);
     self.values = Some(values);
   }
 
-  fn flags_as_byte(&self) -> u8 {
-    self.flags.iter().fold(0, |acc, flag| acc | flag.as_byte())
+  /// Shows if a result should be sent without rows metadata.
+  pub fn skips_metadata(&self) -> bool {
+    self.flags.contains(QueryFlags::SKIP_METADATA)
+  }
+
+  /// Returns flags which are sent to a server. Flags of optional fields are derived
+  /// from fields which are set, so only flags without a field, e.g. `SKIP_METADATA`,
+  /// are taken from `flags`.
+  pub fn effective_flags(&self) -> QueryFlags {
+    let mut flags = self.flags;
+    flags.set(QueryFlags::VALUE, self.values.is_some());
+    flags.set(QueryFlags::WITH_NAMES_FOR_VALUES,
+              self.values.as_ref().is_some_and(QueryValues::with_names));
+    flags.set(QueryFlags::PAGE_SIZE, self.page_size.is_some());
+    flags.set(QueryFlags::WITH_PAGING_STATE, self.paging_state.is_some());
+    flags.set(QueryFlags::WITH_SERIAL_CONSISTENCY, self.serial_consistency.is_some());
+    flags.set(QueryFlags::WITH_DEFAULT_TIMESTAMP, self.timestamp.is_some());
+    flags.set(QueryFlags::WITH_KEYSPACE, self.keyspace.is_some());
+    flags.set(QueryFlags::WITH_NOW_IN_SECONDS, self.now_in_seconds.is_some());
+    flags.remove(QueryFlags::WITH_CONTINUOUS_PAGING);
+    flags
   }
+}
 
-  #[allow(dead_code)]
-  fn parse_query_flags(byte: u8) -> Vec<QueryFlags> {
-    let mut flags: Vec<QueryFlags> = vec![];
+impl IntoBytes for QueryParams {
+  fn into_cbytes(&self) -> Vec<u8> {
+    let mut v = Vec::with_capacity(self.serialized_len());
+    self.serialize_into(&mut v);
+    v
+  }
 
-    if QueryFlags::has_value(byte) {
-      flags.push(QueryFlags::Value);
+  fn serialize_into(&self, buf: &mut impl BufMut) {
+    self.consistency.serialize_into(buf);
+    if cfg!(feature = "v5") {
+      buf.put_u32(self.effective_flags().bits());
+    } else {
+      buf.put_u8(self.effective_flags().as_byte());
     }
-    if QueryFlags::has_skip_metadata(byte) {
-      flags.push(QueryFlags::SkipMetadata);
+    if let Some(ref values) = self.values {
+      buf.put_i16(values.len() as i16);
+      values.serialize_into(buf);
     }
-    if QueryFlags::has_page_size(byte) {
-      flags.push(QueryFlags::PageSize);
+    if let Some(page_size) = self.page_size {
+      buf.put_i32(page_size);
     }
-    if QueryFlags::has_with_paging_state(byte) {
-      flags.push(QueryFlags::WithPagingState);
+    if let Some(ref paging_state) = self.paging_state {
+      paging_state.serialize_into(buf);
     }
-    if QueryFlags::has_with_serial_consistency(byte) {
-      flags.push(QueryFlags::WithSerialConsistency);
+    if let Some(ref serial_consistency) = self.serial_consistency {
+      serial_consistency.serialize_into(buf);
     }
-    if QueryFlags::has_with_default_timestamp(byte) {
-      flags.push(QueryFlags::WithDefaultTimestamp);
+    if let Some(timestamp) = self.timestamp {
+      buf.put_i64(timestamp);
     }
-    if QueryFlags::has_with_names_for_values(byte) {
-      flags.push(QueryFlags::WithNamesForValues);
+    if cfg!(feature = "v5") {
+      if let Some(ref keyspace) = self.keyspace {
+        put_string(buf, keyspace);
+      }
+      if let Some(now_in_seconds) = self.now_in_seconds {
+        buf.put_i32(now_in_seconds);
+      }
     }
+  }
 
-    flags
+  fn serialized_len(&self) -> usize {
+    let flags_len = if cfg!(feature = "v5") { INT_LEN } else { 1 };
+    let values_len = self.values
+                         .as_ref()
+                         .map_or(0, |values| SHORT_LEN + values.serialized_len());
+    let paging_state_len = self.paging_state.as_ref().map_or(0, CBytes::serialized_len);
+    let v5_len = if cfg!(feature = "v5") {
+      self.keyspace.as_ref().map_or(0, |keyspace| SHORT_LEN + keyspace.len())
+      + self.now_in_seconds.map_or(0, |_| INT_LEN)
+    } else {
+      0
+    };
+
+    self.consistency.serialized_len()
+    + flags_len
+    + values_len
+    + self.page_size.map_or(0, |_| INT_LEN)
+    + paging_state_len
+    + self.serial_consistency.map_or(0, |_| SHORT_LEN)
+    + self.timestamp.map_or(0, |_| LONG_LEN)
+    + v5_len
   }
 }
 
-impl IntoBytes for QueryParams {
-  fn into_cbytes(&self) -> Vec<u8> {
-    let mut v: Vec<u8> = vec![];
-
-    v.extend_from_slice(self.consistency.into_cbytes().as_slice());
-    v.push(self.flags_as_byte());
-    if QueryFlags::has_value(self.flags_as_byte()) {
-      if let Some(ref values) = self.values {
-        v.extend_from_slice(to_short(values.len() as i16).as_slice());
-        v.extend_from_slice(values.into_cbytes().as_slice());
-      }
+impl TryIntoBytes for QueryParams {
+  fn check(&self) -> error::Result<()> {
+    if let Some(ref values) = self.values {
+      values.check()?;
     }
-    if QueryFlags::has_page_size(self.flags_as_byte()) && self.page_size.is_some() {
-      // XXX clone
-      v.extend_from_slice(to_int(self.page_size
-                                    .clone()
-                                    // unwrap is safe as we've checked that
-                                    // self.page_size.is_some()
-                                    .unwrap())
-                                    .as_slice());
-    }
-    if QueryFlags::has_with_paging_state(self.flags_as_byte()) && self.paging_state.is_some() {
-      // XXX clone
-      v.extend_from_slice(self.paging_state
-                                    .clone()
-                                    // unwrap is safe as we've checked that
-                                    // self.paging_state.is_some()
-                                    .unwrap()
-                                    .into_cbytes()
-                                    .as_slice());
-    }
-    if QueryFlags::has_with_serial_consistency(self.flags_as_byte())
-       && self.serial_consistency.is_some() {
-      // XXX clone
-      v.extend_from_slice(self.serial_consistency
-                                    .clone()
-                                    // unwrap is safe as we've checked that
-                                    // self.serial_consistency.is_some()
-                                    .unwrap()
-                                    .into_cbytes()
-                                    .as_slice());
-    }
-    if QueryFlags::has_with_default_timestamp(self.flags_as_byte()) && self.timestamp.is_some() {
-      // unwrap is safe as we've checked that self.timestamp.is_some()
-      v.extend_from_slice(to_bigint(self.timestamp.unwrap()).as_slice());
+
+    if !cfg!(feature = "v5") && (self.keyspace.is_some() || self.now_in_seconds.is_some()) {
+      let msg = "Query keyspace and now_in_seconds are supported only by protocol v5";
+      return Err(error::Error::InvalidRequest(msg.into()));
+    }
+    if let Some(ref keyspace) = self.keyspace {
+      check_max_len(keyspace.len(), SHORT_MAX)?;
+    }
+    if self.continuous_paging.is_some() {
+      let msg = "Continuous paging requires DSE protocol versions which are not supported";
+      return Err(error::Error::InvalidRequest(msg.into()));
     }
 
-    v
+    match self.paging_state {
+      Some(ref paging_state) => paging_state.check(),
+      None => Ok(()),
+    }
+  }
+}
+
+impl FromCursor for QueryParams {
+  fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<QueryParams> {
+    let consistency = Consistency::from_cursor(cursor)?;
+    let flags = if cfg!(feature = "v5") {
+      QueryFlags::from_bits_truncate(CInt::from_cursor(cursor)? as u32)
+    } else {
+      QueryFlags::from_byte_truncate(cursor_next_value(cursor, 1)?[0])
+    };
+
+    let with_names = flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES);
+    let values = if flags.contains(QueryFlags::VALUE) {
+      Some(QueryValues::from_cursor_with_names(cursor, with_names)?)
+    } else {
+      None
+    };
+    let page_size = if flags.contains(QueryFlags::PAGE_SIZE) {
+      Some(CInt::from_cursor(cursor)?)
+    } else {
+      None
+    };
+    let paging_state = if flags.contains(QueryFlags::WITH_PAGING_STATE) {
+      Some(CBytes::from_cursor(cursor)?)
+    } else {
+      None
+    };
+    let serial_consistency = if flags.contains(QueryFlags::WITH_SERIAL_CONSISTENCY) {
+      Some(Consistency::from_cursor(cursor)?)
+    } else {
+      None
+    };
+    let timestamp = if flags.contains(QueryFlags::WITH_DEFAULT_TIMESTAMP) {
+      Some(CLong::from_cursor(cursor)?)
+    } else {
+      None
+    };
+    let keyspace = if flags.contains(QueryFlags::WITH_KEYSPACE) {
+      Some(CString::from_cursor(cursor)?.into_plain())
+    } else {
+      None
+    };
+    let now_in_seconds = if flags.contains(QueryFlags::WITH_NOW_IN_SECONDS) {
+      Some(CInt::from_cursor(cursor)?)
+    } else {
+      None
+    };
+    if flags.contains(QueryFlags::WITH_CONTINUOUS_PAGING) {
+      let msg = "Continuous paging is not defined by protocol v5";
+      return Err(error::Error::Protocol(msg.into()));
+    }
+
+    Ok(QueryParams {
+      consistency,
+      flags,
+      with_names: values.as_ref().map(|_| with_names),
+      values,
+      page_size,
+      paging_state,
+      serial_consistency,
+      timestamp,
+      keyspace,
+      now_in_seconds,
+      continuous_paging: None,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::query::QueryParamsBuilder;
+
+  #[test]
+  fn serialize_into() {
+    let mut params = QueryParams { consistency: Consistency::One,
+                                   // VALUE is stale as there are no values
+                                   flags: QueryFlags::SKIP_METADATA | QueryFlags::VALUE,
+                                   page_size: Some(2),
+                                   paging_state: Some(CBytes::new(vec![7])),
+                                   timestamp: Some(3),
+                                   ..Default::default() };
+    let flags = (QueryFlags::SKIP_METADATA
+                 | QueryFlags::PAGE_SIZE
+                 | QueryFlags::WITH_PAGING_STATE
+                 | QueryFlags::WITH_DEFAULT_TIMESTAMP).as_byte();
+    assert_eq!(params.effective_flags().as_byte(), flags);
+    let mut expected = vec![0, 1];
+    if cfg!(feature = "v5") {
+      expected.extend_from_slice(&[0, 0, 0, flags]);
+    } else {
+      expected.push(flags);
+    }
+    expected.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 1, 7, 0, 0, 0, 0, 0, 0, 0, 3]);
+    assert_eq!(params.into_cbytes(), expected);
+    assert_eq!(params.serialized_len(), expected.len());
+
+    params.serial_consistency = Some(Consistency::Serial);
+    params.values = Some(QueryValues::SimpleValues(vec![]));
+    params.flags.insert(QueryFlags::WITH_NAMES_FOR_VALUES);
+    let bytes = params.into_cbytes();
+    assert_eq!(params.serialized_len(), bytes.len());
+
+    let parsed = QueryParams::from_cursor(&mut Cursor::new(bytes.as_slice())).unwrap();
+    assert!(parsed.flags.contains(QueryFlags::VALUE | QueryFlags::WITH_SERIAL_CONSISTENCY));
+    assert!(!parsed.flags.contains(QueryFlags::WITH_NAMES_FOR_VALUES));
+    assert_eq!(parsed.serial_consistency, Some(Consistency::Serial));
+    assert_eq!(parsed.timestamp, Some(3));
+  }
+  #[test]
+  fn now_in_seconds() {
+    let params = QueryParamsBuilder::new().keyspace("ks")
+                                          .now_in_seconds(42)
+                                          .finalize();
+    let bytes = params.into_cbytes();
+    assert_eq!(params.serialized_len(), bytes.len());
+
+    let parsed = QueryParams::from_cursor(&mut Cursor::new(bytes.as_slice())).unwrap();
+    if cfg!(feature = "v5") {
+      assert_eq!(&bytes[2..6], &[0, 0, 0x01, 0x80]);
+      assert_eq!(&bytes[bytes.len() - 4..], &[0, 0, 0, 42]);
+      assert_eq!(parsed.now_in_seconds, Some(42));
+      assert_eq!(parsed.keyspace, Some("ks".into()));
+    } else {
+      assert!(matches!(params.check(), Err(error::Error::InvalidRequest(_))));
+      assert_eq!(parsed.now_in_seconds, None);
+    }
+  }
+
+  #[test]
+  fn keyspace() {
+    let params = QueryParams { keyspace: Some("ks".into()),
+                               timestamp: Some(1),
+                               ..Default::default() };
+    let bytes = params.into_cbytes();
+    assert_eq!(params.serialized_len(), bytes.len());
+
+    let parsed = QueryParams::from_cursor(&mut Cursor::new(bytes.as_slice())).unwrap();
+    if cfg!(feature = "v5") {
+      assert!(params.check().is_ok());
+      assert_eq!(&bytes[2..6], &[0, 0, 0, 0x80 | 0x20]);
+      assert_eq!(&bytes[bytes.len() - 4..], &[0, 2, b'k', b's']);
+      assert_eq!(parsed.keyspace, Some("ks".into()));
+    } else {
+      assert!(matches!(params.check(), Err(error::Error::InvalidRequest(_))));
+      assert_eq!(parsed.keyspace, None);
+
+      // 0x80 is not a flag of a [byte], so no keyspace is read
+      let parsed = QueryParams::from_cursor(&mut Cursor::new(&[0, 1, 0x80][..])).unwrap();
+      assert_eq!(parsed.flags, QueryFlags::empty());
+      assert_eq!(parsed.keyspace, None);
+    }
+  }
+
+  #[test]
+  fn continuous_paging() {
+    let options = ContinuousPagingOptions { max_pages: 10,
+                                            pages_per_second: 2,
+                                            next_pages: 4 };
+    let params = QueryParamsBuilder::new().continuous_paging(options)
+                                          .flags(QueryFlags::WITH_CONTINUOUS_PAGING)
+                                          .finalize();
+    assert!(matches!(params.check(), Err(error::Error::InvalidRequest(_))));
+    // neither the flag nor the options are sent
+    assert!(!params.effective_flags().contains(QueryFlags::WITH_CONTINUOUS_PAGING));
+    let bytes = params.into_cbytes();
+    assert_eq!(params.serialized_len(), bytes.len());
+    assert_eq!(bytes.len(), if cfg!(feature = "v5") { 6 } else { 3 });
+
+    if cfg!(feature = "v5") {
+      let bytes = [0, 1, 0x80, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 2, 0, 0, 0, 4];
+      let parsed = QueryParams::from_cursor(&mut Cursor::new(&bytes[..]));
+      assert!(matches!(parsed, Err(error::Error::Protocol(_))));
+    }
   }
 }
@@ -1,17 +1,22 @@
-use super::{QueryFlags, QueryParams, QueryValues};
+use super::{ContinuousPagingOptions, QueryFlags, QueryParams, QueryValues};
 use crate::consistency::Consistency;
+use crate::error::{Error as CError, Result as CResult};
+use crate::frame::TryIntoBytes;
 use crate::types::CBytes;
 
 #[derive(Debug, Default)]
 pub struct QueryParamsBuilder {
   consistency: Consistency,
-  flags: Option<Vec<QueryFlags>>,
+  flags: QueryFlags,
   values: Option<QueryValues>,
   with_names: Option<bool>,
   page_size: Option<i32>,
   paging_state: Option<CBytes>,
   serial_consistency: Option<Consistency>,
   timestamp: Option<i64>,
+  keyspace: Option<String>,
+  now_in_seconds: Option<i32>,
+  continuous_paging: Option<ContinuousPagingOptions>,
 }
 
 impl QueryParamsBuilder {
@@ -29,8 +34,8 @@ impl QueryParamsBuilder {
   }
 
   /// Sets new flags.
-  pub fn flags(mut self, flags: Vec<QueryFlags>) -> Self {
-    self.flags = Some(flags);
+  pub fn flags(mut self, flags: QueryFlags) -> Self {
+    self.flags = flags;
 
     self
   }
@@ -41,13 +46,8 @@ impl QueryParamsBuilder {
     let with_names = values.with_names();
     self.with_names = Some(with_names);
     self.values = Some(values);
-    self.flags = self.flags.or(Some(vec![])).map(|mut flags| {
-      flags.push(QueryFlags::Value);
-      if with_names {
-        flags.push(QueryFlags::WithNamesForValues);
-      }
-      flags
-    });
+    self.flags.insert(QueryFlags::VALUE);
+    self.flags.set(QueryFlags::WITH_NAMES_FOR_VALUES, with_names);
 
     self
   }
@@ -63,10 +63,7 @@ impl QueryParamsBuilder {
   /// Sets new query consistency
   pub fn page_size(mut self, size: i32) -> Self {
     self.page_size = Some(size);
-    self.flags = self.flags.or(Some(vec![])).map(|mut flags| {
-      flags.push(QueryFlags::PageSize);
-      flags
-    });
+    self.flags.insert(QueryFlags::PAGE_SIZE);
 
     self
   }
@@ -75,10 +72,16 @@ impl QueryParamsBuilder {
   /// Sets new query consistency
   pub fn paging_state(mut self, state: CBytes) -> Self {
     self.paging_state = Some(state);
-    self.flags = self.flags.or(Some(vec![])).map(|mut flags| {
-      flags.push(QueryFlags::WithPagingState);
-      flags
-    });
+    self.flags.insert(QueryFlags::WITH_PAGING_STATE);
+
+    self
+  }
+
+  /// Sets `SKIP_METADATA` flag, so rows of a result are sent without their metadata.
+  /// It makes sense for `EXECUTE` requests whose result metadata is known from a prepared
+  /// result; such rows are decoded with `Frame::get_body_with_metadata`.
+  pub fn skip_metadata(mut self, skip_metadata: bool) -> Self {
+    self.flags.set(QueryFlags::SKIP_METADATA, skip_metadata);
 
     self
   }
@@ -97,17 +100,118 @@ impl QueryParamsBuilder {
     self
   }
 
+  /// Sets keyspace which non-qualified tables of a query belong to, so a connection
+  /// doesn't need `USE` statement (protocol v5 only).
+  pub fn keyspace(mut self, keyspace: &str) -> Self {
+    self.keyspace = Some(keyspace.to_string());
+
+    self
+  }
+
+  /// Sets current time in seconds which should be used by a server for TTL calculations,
+  /// e.g. to test expiration deterministically (protocol v5 only).
+  pub fn now_in_seconds(mut self, now_in_seconds: i32) -> Self {
+    self.now_in_seconds = Some(now_in_seconds);
+
+    self
+  }
+
+  /// Sets options of DSE continuous paging. Such params are rejected by `check`
+  /// until DSE protocol versions are supported.
+  pub fn continuous_paging(mut self, options: ContinuousPagingOptions) -> Self {
+    self.continuous_paging = Some(options);
+
+    self
+  }
+
   /// Finalizes query building process and returns query itself
   pub fn finalize(self) -> QueryParams {
     QueryParams {
       consistency: self.consistency,
-      flags: self.flags.unwrap_or(vec![]),
+      flags: self.flags,
       values: self.values,
       with_names: self.with_names,
       page_size: self.page_size,
       paging_state: self.paging_state,
       serial_consistency: self.serial_consistency,
       timestamp: self.timestamp,
+      keyspace: self.keyspace,
+      now_in_seconds: self.now_in_seconds,
+      continuous_paging: self.continuous_paging,
     }
   }
+
+  /// The same as `finalize` but query params are validated, so invalid ones are
+  /// reported before a request is sent to a server.
+  pub fn build(self) -> CResult<QueryParams> {
+    if self.consistency == Consistency::Unknown {
+      return Err(CError::InvalidRequest(String::from("Unknown consistency level")));
+    }
+
+    if let Some(serial_consistency) = self.serial_consistency {
+      if !serial_consistency.is_serial() {
+        return Err(CError::InvalidRequest(format!(
+          "Serial consistency should be Serial or LocalSerial but {:?} is given",
+          serial_consistency
+        )));
+      }
+    }
+
+    match (self.page_size, &self.paging_state) {
+      (Some(page_size), _) if page_size <= 0 => {
+        return Err(CError::InvalidRequest(format!("Invalid page size {}", page_size)));
+      }
+      (None, Some(_)) => {
+        return Err(CError::InvalidRequest(String::from(
+          "Paging state is set without a page size",
+        )));
+      }
+      _ => {}
+    }
+
+    if let (Some(with_names), Some(values)) = (self.with_names, &self.values) {
+      if with_names != values.with_names() {
+        return Err(CError::InvalidRequest(String::from(
+          "with_names doesn't match query values",
+        )));
+      }
+    }
+
+    let params = self.finalize();
+    params.check()?;
+    Ok(params)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::types::value::Value;
+  use crate::types::SHORT_MAX;
+
+  fn is_invalid(builder: QueryParamsBuilder) -> bool {
+    matches!(builder.build(), Err(CError::InvalidRequest(_)))
+  }
+
+  #[test]
+  fn build() {
+    let params = QueryParamsBuilder::new().values(QueryValues::from(vec![1_i32]))
+                                          .page_size(10)
+                                          .paging_state(CBytes::new(vec![1]))
+                                          .serial_consistency(Consistency::LocalSerial)
+                                          .build()
+                                          .unwrap();
+    assert_eq!(params.page_size, Some(10));
+
+    assert!(is_invalid(QueryParamsBuilder::new().consistency(Consistency::Unknown)));
+    assert!(is_invalid(QueryParamsBuilder::new().serial_consistency(Consistency::Quorum)));
+    assert!(is_invalid(QueryParamsBuilder::new().page_size(0)));
+    assert!(is_invalid(QueryParamsBuilder::new().paging_state(CBytes::new(vec![1]))));
+    assert!(is_invalid(QueryParamsBuilder::new().values(QueryValues::from(vec![1_i32]))
+                                                .with_names(true)));
+
+    let values = vec![Value::new_null(); SHORT_MAX + 1];
+    let builder = QueryParamsBuilder::new().values(QueryValues::SimpleValues(values));
+    assert!(matches!(builder.build(), Err(CError::LengthOverflow { .. })));
+  }
 }
@@ -1,8 +1,13 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::hash::Hash;
+use std::io::Cursor;
 
-use crate::frame::IntoBytes;
-use crate::types::CString;
+use bytes::BufMut;
+
+use crate::error;
+use crate::frame::{FromCursor, IntoBytes, TryIntoBytes};
+use crate::types::{check_max_len, put_string, CIntShort, CString, SHORT_LEN, SHORT_MAX};
 use crate::types::value::Value;
 
 /// Enum that represents two types of query values:
@@ -14,6 +19,30 @@ pub enum QueryValues {
   NamedValues(HashMap<String, Value>),
 }
 
+/// Reason why query values are rejected, see `Error::InvalidValues`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryValuesError {
+  /// Named and positional values are mixed. `index` is a position of the first value,
+  /// or of the first batched query, whose kind differs from preceding ones.
+  Mixed { index: usize },
+  /// A value has an empty name.
+  EmptyName,
+  /// Several values have the same name.
+  DuplicateName(String),
+}
+
+impl fmt::Display for QueryValuesError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      QueryValuesError::Mixed { index } => {
+        write!(f, "named and positional values are mixed at {}", index)
+      }
+      QueryValuesError::EmptyName => write!(f, "value name is empty"),
+      QueryValuesError::DuplicateName(ref name) => write!(f, "duplicate value name '{}'", name),
+    }
+  }
+}
+
 impl QueryValues {
   /// It returns `true` if query values is with names and `false` otherwise.
   pub fn with_names(&self) -> bool {
@@ -31,19 +60,32 @@ impl QueryValues {
     }
   }
 
-  fn named_value_into_bytes_fold(mut bytes: Vec<u8>, vals: (&String, &Value)) -> Vec<u8> {
-    let mut name_bytes = CString::new(vals.0.clone()).into_cbytes();
-    let mut vals_bytes = vals.1.into_cbytes();
-    bytes.append(&mut name_bytes);
-    bytes.append(&mut vals_bytes);
-    bytes
-  }
+  /// It reads `[short]` number of values followed by values which are preceded by
+  /// their names if `with_names` is `true`.
+  pub fn from_cursor_with_names(cursor: &mut Cursor<&[u8]>,
+                                with_names: bool)
+                                -> error::Result<QueryValues> {
+    let len = CIntShort::from_cursor(cursor)?;
+    if len < 0 {
+      return Err(error::Error::InvalidLength { length: i64::from(len),
+                                               remaining: cursor.get_ref().len()
+                                                          - cursor.position() as usize });
+    }
 
-  fn value_into_bytes_fold(mut bytes: Vec<u8>, val: &Value) -> Vec<u8> {
-    let mut val_bytes = val.into_cbytes();
-    bytes.append(&mut val_bytes);
-    bytes
+    if with_names {
+      let mut values = HashMap::with_capacity(len as usize);
+      for _ in 0..len {
+        let name = CString::from_cursor(cursor)?.into_plain();
+        values.insert(name, Value::from_cursor(cursor)?);
+      }
+      Ok(QueryValues::NamedValues(values))
+    } else {
+      (0..len).map(|_| Value::from_cursor(cursor))
+              .collect::<error::Result<Vec<_>>>()
+              .map(QueryValues::SimpleValues)
+    }
   }
+
 }
 
 impl<T: Into<Value> + Clone> From<Vec<T>> for QueryValues {
@@ -76,14 +118,102 @@ impl<S: ToString + Hash + Eq, V: Into<Value> + Clone> From<HashMap<S, V>> for Qu
   }
 }
 
+tuple_into_query_values!(A: 0);
+tuple_into_query_values!(A: 0, B: 1);
+tuple_into_query_values!(A: 0, B: 1, C: 2);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10,
+                         L: 11);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10,
+                         L: 11, M: 12);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10,
+                         L: 11, M: 12, N: 13);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10,
+                         L: 11, M: 12, N: 13, O: 14);
+tuple_into_query_values!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10,
+                         L: 11, M: 12, N: 13, O: 14, P: 15);
+
+impl TryIntoBytes for QueryValues {
+  fn check(&self) -> error::Result<()> {
+    check_max_len(self.len(), SHORT_MAX)?;
+    match *self {
+      QueryValues::SimpleValues(ref v) => v.iter().try_for_each(Value::check),
+      QueryValues::NamedValues(ref v) => v.iter().try_for_each(|(name, value)| {
+                                                     if name.is_empty() {
+                                                       let err = QueryValuesError::EmptyName;
+                                                       return Err(error::Error::InvalidValues(err));
+                                                     }
+                                                     check_max_len(name.len(), SHORT_MAX)?;
+                                                     value.check()
+                                                   }),
+    }
+  }
+}
+
 impl IntoBytes for QueryValues {
   fn into_cbytes(&self) -> Vec<u8> {
-    let bytes: Vec<u8> = vec![];
+    let mut bytes = Vec::with_capacity(self.serialized_len());
+    self.serialize_into(&mut bytes);
+    bytes
+  }
+
+  /// Writes values without their number which precedes them in a body.
+  fn serialize_into(&self, buf: &mut impl BufMut) {
     match *self {
-      QueryValues::SimpleValues(ref v) => v.iter().fold(bytes, QueryValues::value_into_bytes_fold),
+      QueryValues::SimpleValues(ref v) => v.iter().for_each(|value| value.serialize_into(buf)),
       QueryValues::NamedValues(ref v) => {
-        v.iter().fold(bytes, QueryValues::named_value_into_bytes_fold)
+        for (name, value) in v {
+          put_string(buf, name);
+          value.serialize_into(buf);
+        }
       }
     }
   }
+
+  fn serialized_len(&self) -> usize {
+    match *self {
+      QueryValues::SimpleValues(ref v) => v.iter().map(Value::serialized_len).sum(),
+      QueryValues::NamedValues(ref v) => {
+        v.iter()
+         .map(|(name, value)| SHORT_LEN + name.len() + value.serialized_len())
+         .sum()
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::types::value::Bind;
+
+  #[test]
+  fn from_tuple() {
+    let values: QueryValues = (1i32, "foo", None::<i64>, Bind::<i8>::Unset).into();
+    let expected = QueryValues::SimpleValues(vec![Value::new_normal(1i32),
+                                                  Value::new_normal("foo"),
+                                                  Value::new_null(),
+                                                  Value::new_not_set()]);
+    assert_eq!(values.into_cbytes(), expected.into_cbytes());
+
+    let values: QueryValues = (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16).into();
+    assert_eq!(values.len(), 16);
+  }
+
+  #[test]
+  fn empty_name() {
+    let mut values = HashMap::new();
+    values.insert(String::new(), Value::from(1_i32));
+    match QueryValues::NamedValues(values).check() {
+      Err(error::Error::InvalidValues(QueryValuesError::EmptyName)) => {}
+      result => panic!("Unexpected result {:?}", result),
+    }
+  }
 }
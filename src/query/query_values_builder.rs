@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use super::{QueryValues, QueryValuesError};
+use crate::error::{Error as CError, Result as CResult};
+use crate::types::value::Value;
+
+/// Builder of query values which are either all positional or all named. Unlike
+/// constructing `QueryValues` directly, a combination the server would reject, e.g. mixed
+/// named and positional values or an empty name, is reported by `build`.
+#[derive(Debug, Default)]
+pub struct QueryValuesBuilder {
+  values: Vec<(Option<String>, Value)>,
+}
+
+impl QueryValuesBuilder {
+  pub fn new() -> QueryValuesBuilder {
+    Default::default()
+  }
+
+  /// Adds a positional value.
+  pub fn value<V: Into<Value>>(mut self, value: V) -> Self {
+    self.values.push((None, value.into()));
+
+    self
+  }
+
+  /// Adds a value bound to a named marker, e.g. `:id` of a query.
+  pub fn named_value<S: Into<String>, V: Into<Value>>(mut self, name: S, value: V) -> Self {
+    self.values.push((Some(name.into()), value.into()));
+
+    self
+  }
+
+  /// Returns positional values if no value has a name or named values otherwise.
+  pub fn build(self) -> CResult<QueryValues> {
+    let with_names = self.values.first().is_some_and(|(name, _)| name.is_some());
+    let mixed = self.values
+                    .iter()
+                    .position(|(name, _)| name.is_some() != with_names);
+    if let Some(index) = mixed {
+      return Err(CError::InvalidValues(QueryValuesError::Mixed { index }));
+    }
+
+    if !with_names {
+      let values = self.values.into_iter().map(|(_, value)| value).collect();
+      return Ok(QueryValues::SimpleValues(values));
+    }
+
+    let mut named = HashMap::with_capacity(self.values.len());
+    for (name, value) in self.values {
+      let name = name.unwrap_or_default();
+      if name.is_empty() {
+        return Err(CError::InvalidValues(QueryValuesError::EmptyName));
+      }
+      if named.contains_key(&name) {
+        return Err(CError::InvalidValues(QueryValuesError::DuplicateName(name)));
+      }
+      named.insert(name, value);
+    }
+    Ok(QueryValues::NamedValues(named))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn invalid_values(builder: QueryValuesBuilder) -> QueryValuesError {
+    match builder.build() {
+      Err(CError::InvalidValues(err)) => err,
+      result => panic!("Unexpected result {:?}", result),
+    }
+  }
+
+  #[test]
+  fn build() {
+    let values = QueryValuesBuilder::new().value(1_i32).value("a").build().unwrap();
+    assert!(!values.with_names());
+    assert_eq!(values.len(), 2);
+
+    let values = QueryValuesBuilder::new().named_value("id", 1_i32).build().unwrap();
+    assert!(values.with_names());
+
+    assert!(!QueryValuesBuilder::new().build().unwrap().with_names());
+  }
+
+  #[test]
+  fn invalid() {
+    let builder = QueryValuesBuilder::new().named_value("id", 1_i32).value("a");
+    assert_eq!(invalid_values(builder), QueryValuesError::Mixed { index: 1 });
+
+    let builder = QueryValuesBuilder::new().named_value("", 1_i32);
+    assert_eq!(invalid_values(builder), QueryValuesError::EmptyName);
+
+    let builder = QueryValuesBuilder::new().named_value("id", 1_i32).named_value("id", 2_i32);
+    assert_eq!(invalid_values(builder), QueryValuesError::DuplicateName("id".into()));
+  }
+}
@@ -0,0 +1,104 @@
+use crate::frame::frame_result::PreparedMetadata;
+use crate::query::QueryValues;
+use crate::types::value::{Value, ValueType};
+
+/// Computes a serialized routing key of a prepared query with given bound values, so
+/// token aware clients could pick a replica which owns the partition. For a single column
+/// partition key the routing key is the value itself. Values of composite partition key
+/// components are serialized as `[short length][value][0x00]` each.
+///
+/// It returns `None` if prepared metadata doesn't contain partition key indexes or
+/// some of partition key values is missing, null or not set.
+pub fn routing_key(metadata: &PreparedMetadata, values: &QueryValues) -> Option<Vec<u8>> {
+    let components = metadata.pk_indexes
+                             .iter()
+                             .map(|index| pk_component(metadata, values, *index as usize))
+                             .collect::<Option<Vec<&[u8]>>>()?;
+
+    match components.len() {
+        0 => None,
+        1 => Some(components[0].to_vec()),
+        _ => {
+            let len = components.iter().map(|c| c.len() + 3).sum();
+            let mut key = Vec::with_capacity(len);
+            for component in components {
+                if component.len() > u16::MAX as usize {
+                    return None;
+                }
+                key.extend_from_slice(&(component.len() as u16).to_be_bytes());
+                key.extend_from_slice(component);
+                key.push(0);
+            }
+            Some(key)
+        }
+    }
+}
+
+fn pk_component<'a>(metadata: &PreparedMetadata,
+                    values: &'a QueryValues,
+                    index: usize)
+                    -> Option<&'a [u8]> {
+    let value = match *values {
+        QueryValues::SimpleValues(ref values) => values.get(index)?,
+        QueryValues::NamedValues(ref values) => {
+            let name = metadata.col_specs.get(index)?.name.as_str();
+            values.get(name)?
+        }
+    };
+
+    match *value {
+        Value { value_type: ValueType::Normal(_),
+                ref body, } => Some(body.as_ref()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::frame::frame_result::{ColSpec, ColType, ColTypeOption};
+    use crate::types::blob::Blob;
+    use crate::types::CString;
+
+    fn metadata(pk_indexes: Vec<i16>) -> PreparedMetadata {
+        let col_spec = |name: &str| ColSpec { ksname: None,
+                                              tablename: None,
+                                              name: CString::new(name.into()),
+                                              col_type: ColTypeOption { id: ColType::Int,
+                                                                        value: None } };
+        PreparedMetadata { flags: 0,
+                           columns_count: 3,
+                           pk_count: pk_indexes.len() as i32,
+                           pk_indexes,
+                           global_table_spec: None,
+                           col_specs: vec![col_spec("a"), col_spec("b"), col_spec("c")] }
+    }
+
+    #[test]
+    fn single_column_key() {
+        let values = QueryValues::SimpleValues(vec![Value::from(1_i32), Value::from(2_i32)]);
+        assert_eq!(routing_key(&metadata(vec![1]), &values), Some(vec![0, 0, 0, 2]));
+        assert_eq!(routing_key(&metadata(vec![]), &values), None);
+        assert_eq!(routing_key(&metadata(vec![2]), &values), None);
+    }
+
+    #[test]
+    fn composite_key() {
+        let mut values = HashMap::new();
+        values.insert("a".to_string(), Value::from(1_i8));
+        values.insert("c".to_string(), Value::new_normal(Blob::new(vec![2, 3])));
+        let values = QueryValues::NamedValues(values);
+
+        assert_eq!(routing_key(&metadata(vec![2, 0]), &values),
+                   Some(vec![0, 2, 2, 3, 0, 0, 1, 1, 0]));
+        assert_eq!(routing_key(&metadata(vec![0, 1]), &values), None);
+    }
+
+    #[test]
+    fn null_key() {
+        let values = QueryValues::SimpleValues(vec![Value::new_null()]);
+        assert_eq!(routing_key(&metadata(vec![0]), &values), None);
+    }
+}
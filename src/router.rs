@@ -0,0 +1,134 @@
+//! Correlation of responses with requests on a multiplexed connection. `ResponseRouter`
+//! assigns stream ids to outgoing frames and keeps a caller defined value per request,
+//! e.g. a oneshot sender, which is returned once a response with the same stream id
+//! is received. Events and responses nobody waits for are surfaced separately.
+use std::collections::HashMap;
+
+use crate::error;
+use crate::frame::Frame;
+use crate::stream_id::StreamIdPool;
+
+/// Result of routing a received frame.
+#[derive(Debug)]
+pub enum Routed<T> {
+    /// Response to a registered request together with a value given on registration.
+    Response(T, Frame),
+    /// Event pushed by a server.
+    Event(Frame),
+    /// Response on a stream which has no registered request.
+    Orphaned(Frame),
+}
+
+/// Map of in-flight requests keyed by stream id.
+#[derive(Debug)]
+pub struct ResponseRouter<T> {
+    ids: StreamIdPool,
+    pending: HashMap<i16, T>,
+}
+
+impl<T> Default for ResponseRouter<T> {
+    fn default() -> ResponseRouter<T> {
+        ResponseRouter::with_pool(StreamIdPool::new())
+    }
+}
+
+impl<T> ResponseRouter<T> {
+    /// Creates new router which may have up to `MAX_STREAM_IDS` requests in flight.
+    pub fn new() -> ResponseRouter<T> {
+        Default::default()
+    }
+
+    /// Creates new router which takes stream ids from a given pool.
+    pub fn with_pool(ids: StreamIdPool) -> ResponseRouter<T> {
+        ResponseRouter { ids,
+                         pending: HashMap::new() }
+    }
+
+    /// Assigns a free stream id to a request frame and remembers `pending` until
+    /// a response is received. An error is returned if all stream ids are in flight.
+    pub fn register(&mut self, frame: &mut Frame, pending: T) -> error::Result<i16> {
+        let stream = self.ids.acquire()?;
+        frame.stream = stream;
+        self.pending.insert(stream, pending);
+        Ok(stream)
+    }
+
+    /// Matches a received frame with a registered request releasing its stream id.
+    pub fn route(&mut self, frame: Frame) -> Routed<T> {
+        if frame.is_event() {
+            return Routed::Event(frame);
+        }
+
+        match self.pending.remove(&frame.stream) {
+            Some(pending) => {
+                self.ids.release(frame.stream);
+                Routed::Response(pending, frame)
+            }
+            None => Routed::Orphaned(frame),
+        }
+    }
+
+    /// Number of requests which wait for responses.
+    pub fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Removes all the registered requests, e.g. to fail them when a connection is closed.
+    pub fn drain(&mut self) -> Vec<(i16, T)> {
+        let pending = self.pending.drain().collect::<Vec<_>>();
+        for (stream, _) in &pending {
+            self.ids.release(*stream);
+        }
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Flags, Opcode, Version, EVENT_STREAM};
+
+    fn response(stream: i16) -> Frame {
+        Frame { version: Version::Response,
+                flags: Flags::empty(),
+                opcode: Opcode::Ready,
+                stream,
+                body: Default::default(),
+                tracing_id: None,
+                warnings: vec![],
+                custom_payload: Default::default() }
+    }
+
+    #[test]
+    fn route() {
+        let mut router = ResponseRouter::new();
+        let mut first = Frame::new_req_options();
+        let mut second = Frame::new_req_options();
+        let first_stream = router.register(&mut first, "first").unwrap();
+        let second_stream = router.register(&mut second, "second").unwrap();
+        assert_eq!(first.stream, first_stream);
+        assert_ne!(first_stream, second_stream);
+        assert_eq!(router.in_flight(), 2);
+
+        match router.route(response(second_stream)) {
+            Routed::Response(pending, frame) => {
+                assert_eq!(pending, "second");
+                assert_eq!(frame.stream, second_stream);
+            }
+            routed => panic!("Unexpected {:?}", routed),
+        }
+        assert!(matches!(router.route(response(second_stream)), Routed::Orphaned(_)));
+        assert!(matches!(router.route(response(EVENT_STREAM)), Routed::Event(_)));
+
+        assert_eq!(router.drain(), vec![(first_stream, "first")]);
+        assert_eq!(router.in_flight(), 0);
+        assert!(matches!(router.route(response(first_stream)), Routed::Orphaned(_)));
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut router = ResponseRouter::with_pool(StreamIdPool::with_capacity(1));
+        router.register(&mut Frame::new_req_options(), ()).unwrap();
+        assert!(router.register(&mut Frame::new_req_options(), ()).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Allocation of stream ids for multiplexed connections. Every request sent through
+//! a connection needs a stream id which isn't used by other in-flight requests,
+//! so a response could be matched with a request. Negative ids are reserved
+//! for server initiated frames, so only non-negative ones are handed out.
+use std::collections::HashSet;
+
+use crate::error;
+
+/// Maximal number of stream ids which can be in flight on one connection (protocol v3+).
+pub const MAX_STREAM_IDS: usize = i16::MAX as usize + 1;
+
+/// Pool of stream ids which tracks ids of in-flight requests and recycles them
+/// once responses are received.
+#[derive(Debug)]
+pub struct StreamIdPool {
+    capacity: usize,
+    // ids below are handed out for the first time, ids from `free` are recycled
+    next: usize,
+    free: Vec<i16>,
+    in_flight: HashSet<i16>,
+}
+
+impl Default for StreamIdPool {
+    fn default() -> StreamIdPool {
+        StreamIdPool::with_capacity(MAX_STREAM_IDS)
+    }
+}
+
+impl StreamIdPool {
+    /// Creates new pool of all `MAX_STREAM_IDS` ids.
+    pub fn new() -> StreamIdPool {
+        Default::default()
+    }
+
+    /// Creates new pool of ids from `0` up to `capacity` exclusively, e.g. to limit
+    /// a number of concurrent requests. `capacity` is capped by `MAX_STREAM_IDS`.
+    pub fn with_capacity(capacity: usize) -> StreamIdPool {
+        StreamIdPool { capacity: capacity.min(MAX_STREAM_IDS),
+                       next: 0,
+                       free: vec![],
+                       in_flight: HashSet::new() }
+    }
+
+    /// Returns an id which is not in flight and marks it as in flight.
+    /// An error is returned if all the ids are in flight.
+    pub fn acquire(&mut self) -> error::Result<i16> {
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None if self.next < self.capacity => {
+                self.next += 1;
+                (self.next - 1) as i16
+            }
+            None => {
+                return Err(error::Error::StreamIdsExhausted { capacity: self.capacity });
+            }
+        };
+
+        self.in_flight.insert(id);
+        Ok(id)
+    }
+
+    /// Returns an id to the pool once a response is received. Returns `false`
+    /// if the id is not in flight, e.g. the response is orphaned.
+    pub fn release(&mut self, id: i16) -> bool {
+        if !self.in_flight.remove(&id) {
+            return false;
+        }
+
+        self.free.push(id);
+        true
+    }
+
+    /// Shows if an id has been acquired and not released yet.
+    pub fn is_in_flight(&self, id: i16) -> bool {
+        self.in_flight.contains(&id)
+    }
+
+    /// Number of in-flight ids.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Number of ids which can be acquired.
+    pub fn available(&self) -> usize {
+        self.capacity - self.in_flight.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_release() {
+        let mut pool = StreamIdPool::with_capacity(2);
+        let first = pool.acquire().unwrap();
+        let second = pool.acquire().unwrap();
+        assert_ne!(first, second);
+        assert!(pool.is_in_flight(first));
+        assert_eq!(pool.in_flight(), 2);
+        assert_eq!(pool.available(), 0);
+        assert!(matches!(pool.acquire(), Err(error::Error::StreamIdsExhausted { capacity: 2 })));
+
+        assert!(pool.release(first));
+        assert!(!pool.release(first));
+        assert!(!pool.is_in_flight(first));
+        assert_eq!(pool.acquire().unwrap(), first);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut pool = StreamIdPool::with_capacity(MAX_STREAM_IDS + 1);
+        assert_eq!(pool.available(), MAX_STREAM_IDS);
+        let ids = (0..MAX_STREAM_IDS).map(|_| pool.acquire().unwrap())
+                                     .collect::<HashSet<i16>>();
+        assert_eq!(ids.len(), MAX_STREAM_IDS);
+        assert!(ids.iter().all(|id| *id >= 0));
+        assert!(pool.acquire().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Tokens of partitions which are used by token aware clients to find replicas
+//! owning a partition. A token is calculated over a routing key
+//! (see `query::routing_key`) by a partitioner configured in a cluster.
+use std::fmt::Debug;
+
+use crate::error;
+
+/// Partitioner which calculates tokens of routing keys.
+pub trait Partitioner {
+    /// Type of tokens produced by a partitioner. Tokens are ordered along the ring.
+    type Token: Ord + Clone + Debug;
+
+    /// Fully qualified class name of a partitioner as it's reported by `system.local` table.
+    fn name(&self) -> &'static str;
+
+    /// Calculates a token of a routing key.
+    fn token(&self, routing_key: &[u8]) -> Self::Token;
+
+    /// Parses a string representation of a token, e.g. the one stored in `tokens` column
+    /// of `system.local` and `system.peers` tables.
+    fn parse_token(&self, token: &str) -> error::Result<Self::Token>;
+}
+
+/// `org.apache.cassandra.dht.Murmur3Partitioner`, the default one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Murmur3Partitioner;
+
+impl Partitioner for Murmur3Partitioner {
+    type Token = i64;
+
+    fn name(&self) -> &'static str {
+        "org.apache.cassandra.dht.Murmur3Partitioner"
+    }
+
+    fn token(&self, routing_key: &[u8]) -> i64 {
+        murmur3_token(routing_key)
+    }
+
+    fn parse_token(&self, token: &str) -> error::Result<i64> {
+        token.parse()
+             .map_err(|err| {
+                          error::Error::Parse(format!("Invalid Murmur3 token {:?}: {}", token, err))
+                      })
+    }
+}
+
+/// `org.apache.cassandra.dht.RandomPartitioner` which tokens are absolute values of
+/// MD5 digests of routing keys interpreted as signed 128 bit integers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomPartitioner;
+
+impl Partitioner for RandomPartitioner {
+    type Token = u128;
+
+    fn name(&self) -> &'static str {
+        "org.apache.cassandra.dht.RandomPartitioner"
+    }
+
+    fn token(&self, routing_key: &[u8]) -> u128 {
+        i128::from_be_bytes(md5::compute(routing_key).0).unsigned_abs()
+    }
+
+    fn parse_token(&self, token: &str) -> error::Result<u128> {
+        token.parse()
+             .map_err(|err| {
+                          error::Error::Parse(format!("Invalid random partitioner token {:?}: {}",
+                                                      token, err))
+                      })
+    }
+}
+
+/// `org.apache.cassandra.dht.ByteOrderedPartitioner` which tokens are routing keys
+/// themselves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteOrderedPartitioner;
+
+impl Partitioner for ByteOrderedPartitioner {
+    type Token = Vec<u8>;
+
+    fn name(&self) -> &'static str {
+        "org.apache.cassandra.dht.ByteOrderedPartitioner"
+    }
+
+    fn token(&self, routing_key: &[u8]) -> Vec<u8> {
+        routing_key.to_vec()
+    }
+
+    /// Byte ordered tokens are represented as hex strings.
+    fn parse_token(&self, token: &str) -> error::Result<Vec<u8>> {
+        let invalid = || error::Error::Parse(format!("Invalid byte ordered token {:?}", token));
+        if token.len() & 1 != 0 || !token.is_ascii() {
+            return Err(invalid());
+        }
+
+        (0..token.len()).step_by(2)
+                        .map(|i| u8::from_str_radix(&token[i..i + 2], 16).map_err(|_| invalid()))
+                        .collect()
+    }
+}
+
+const C1: i64 = 0x87c3_7b91_1142_53d5_u64 as i64;
+const C2: i64 = 0x4cf5_ad43_2745_937f_u64 as i64;
+
+/// Calculates a token of a routing key the same way Cassandra's `Murmur3Partitioner` does.
+/// It is the first half of 128 bit x64 MurmurHash3 with zero seed, however Cassandra
+/// sign-extends trailing bytes of a key (that's how Java bytes work), so results differ
+/// from the reference hash for keys with trailing bytes greater than `0x7f`.
+pub fn murmur3_token(routing_key: &[u8]) -> i64 {
+    let h1 = murmur3_h1(routing_key);
+    // `i64::MIN` is reserved by Cassandra as the minimum token
+    if h1 == i64::MIN {
+        i64::MAX
+    } else {
+        h1
+    }
+}
+
+fn murmur3_h1(data: &[u8]) -> i64 {
+    let len = data.len();
+    let mut h1: i64 = 0;
+    let mut h2: i64 = 0;
+
+    let mut blocks = data.chunks_exact(16);
+    for block in blocks.by_ref() {
+        let k1 = read_i64_le(&block[..8]);
+        let k2 = read_i64_le(&block[8..]);
+
+        h1 ^= mix_k1(k1);
+        h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dc_e729);
+
+        h2 ^= mix_k2(k2);
+        h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = blocks.remainder();
+    let mut k1: i64 = 0;
+    let mut k2: i64 = 0;
+    for (i, byte) in tail.iter().enumerate() {
+        // sign extension of Java bytes
+        let byte = i64::from(*byte as i8);
+        if i < 8 {
+            k1 ^= byte << (i * 8);
+        } else {
+            k2 ^= byte << ((i - 8) * 8);
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= mix_k2(k2);
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(k1);
+    }
+
+    h1 ^= len as i64;
+    h2 ^= len as i64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1.wrapping_add(h2)
+}
+
+fn mix_k1(k1: i64) -> i64 {
+    k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2)
+}
+
+fn mix_k2(k2: i64) -> i64 {
+    k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1)
+}
+
+fn fmix(k: i64) -> i64 {
+    let mut k = k as u64;
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k as i64
+}
+
+fn read_i64_le(bytes: &[u8]) -> i64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    i64::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3() {
+        assert_eq!(murmur3_token(b""), 0);
+        assert_eq!(murmur3_token(b"hello"), 0xcbd8_a7b3_41bd_9b02_u64 as i64);
+        assert_eq!(murmur3_token(b"hello, world"), 0x342f_ac62_3a5e_bc8e_u64 as i64);
+        assert_eq!(murmur3_token(b"19 Jan 2038 at 3:14:07 AM"),
+                   0xb89e_5988_b737_affc_u64 as i64);
+        assert_eq!(murmur3_token(b"The quick brown fox jumps over the lazy dog."),
+                   0xcd99_481f_9ee9_02c9_u64 as i64);
+        assert_eq!(Murmur3Partitioner.token(b"hello"), murmur3_token(b"hello"));
+        assert_eq!(Murmur3Partitioner.parse_token("-42").unwrap(), -42);
+        assert!(Murmur3Partitioner.parse_token("x").is_err());
+    }
+
+    #[test]
+    fn random() {
+        assert_eq!(RandomPartitioner.token(b""), 58332598431525814501020785164969033090);
+        assert_eq!(RandomPartitioner.token(b"hello"), 123957004363873451094272536567338222994);
+        assert_eq!(RandomPartitioner.token(b"test"), 12707736894140473154801792860916528374);
+        assert_eq!(RandomPartitioner.parse_token("42").unwrap(), 42);
+    }
+
+    #[test]
+    fn byte_ordered() {
+        assert_eq!(ByteOrderedPartitioner.token(&[1, 2]), vec![1, 2]);
+        assert_eq!(ByteOrderedPartitioner.parse_token("00ff7a").unwrap(), vec![0, 255, 122]);
+        assert!(ByteOrderedPartitioner.parse_token("0").is_err());
+        assert!(ByteOrderedPartitioner.parse_token("zz").is_err());
+    }
+}
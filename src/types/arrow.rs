@@ -0,0 +1,425 @@
+//! Conversion of query results into Apache Arrow `RecordBatch` for analytics pipelines.
+//!
+//! CQL types are mapped into Arrow types in a following way: `ascii`, `text` and `inet`
+//! into `Utf8`; `bigint`, `counter` and `varint` into `Int64`; `int`, `smallint`
+//! and `tinyint` into `Int32`, `Int16` and `Int8`; `decimal` and `double` into `Float64`;
+//! `float` into `Float32`; `blob` and custom types into `Binary`; `uuid` and `timeuuid`
+//! into `FixedSizeBinary(16)`; `timestamp` into `Timestamp(Millisecond, "UTC")`; `date`
+//! into `Date32`; `time` into `Time64(Nanosecond)`; `list` and `set` into `List`;
+//! `map` into `Map`; UDTs and tuples into `Struct` (tuple fields are named by indexes).
+use std::sync::Arc;
+
+use arrow_array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, FixedSizeBinaryArray, Float32Array,
+    Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, ListArray, MapArray, NullArray,
+    RecordBatch, RecordBatchOptions, StringArray, StructArray, Time64NanosecondArray,
+    TimestampMillisecondArray,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_schema::{ArrowError, Field, Fields, Schema};
+
+use crate::error::{Error, Result};
+use crate::frame::frame_result::{BodyResResultRows, ColType, ColTypeOption, ColTypeOptionValue};
+use crate::types::cql_value::CqlValue;
+
+const DATE_EPOCH_OFFSET: i64 = 1 << 31;
+
+impl From<ArrowError> for Error {
+    fn from(err: ArrowError) -> Error {
+        Error::Arrow(err)
+    }
+}
+
+/// Converts rows into Arrow `RecordBatch` with a column per result column.
+/// All the fields of resulting schema are nullable.
+pub fn rows_to_record_batch(rows: &BodyResResultRows) -> Result<RecordBatch> {
+    let col_specs = &rows.metadata.col_specs;
+    let mut fields = Vec::with_capacity(col_specs.len());
+    let mut columns = Vec::with_capacity(col_specs.len());
+
+    for (i, col_spec) in col_specs.iter().enumerate() {
+        let values = rows
+            .rows_content
+            .iter()
+            .map(|row| match row.get(i) {
+                Some(cell) => CqlValue::decode(cell, &col_spec.col_type),
+                None => Err(Error::Protocol(format!("Row has no value of column {}", i))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let column = build_array(&col_spec.col_type, values)?;
+        fields.push(Field::new(
+            col_spec.name.as_str(),
+            column.data_type().clone(),
+            true,
+        ));
+        columns.push(column);
+    }
+
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.rows_content.len()));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+        .map_err(Into::into)
+}
+
+fn build_array(col_type: &ColTypeOption, values: Vec<CqlValue>) -> Result<ArrayRef> {
+    let array: ArrayRef = match (&col_type.id, &col_type.value) {
+        (ColType::Ascii, _) | (ColType::Varchar, _) => {
+            Arc::new(StringArray::from(scalars(values, |v| match v {
+                CqlValue::Ascii(s) | CqlValue::Varchar(s) => Ok(s),
+                v => Err(v),
+            })?))
+        }
+        (ColType::Inet, _) => Arc::new(StringArray::from(scalars(values, |v| match v {
+            CqlValue::Inet(ip) => Ok(ip.to_string()),
+            v => Err(v),
+        })?)),
+        (ColType::Bigint, _) | (ColType::Counter, _) | (ColType::Varint, _) => {
+            Arc::new(Int64Array::from(scalars(values, |v| match v {
+                CqlValue::Bigint(i) | CqlValue::Counter(i) => Ok(i),
+                CqlValue::Varint(v) => v.to_i64().ok_or(CqlValue::Varint(v)),
+                v => Err(v),
+            })?))
+        }
+        (ColType::Int, _) => Arc::new(Int32Array::from(scalars(values, |v| match v {
+            CqlValue::Int(i) => Ok(i),
+            v => Err(v),
+        })?)),
+        (ColType::Smallint, _) => Arc::new(Int16Array::from(scalars(values, |v| match v {
+            CqlValue::Smallint(i) => Ok(i),
+            v => Err(v),
+        })?)),
+        (ColType::Tinyint, _) => Arc::new(Int8Array::from(scalars(values, |v| match v {
+            CqlValue::Tinyint(i) => Ok(i),
+            v => Err(v),
+        })?)),
+        (ColType::Boolean, _) => Arc::new(BooleanArray::from(scalars(values, |v| match v {
+            CqlValue::Boolean(b) => Ok(b),
+            v => Err(v),
+        })?)),
+        (ColType::Double, _) | (ColType::Decimal, _) => {
+            Arc::new(Float64Array::from(scalars(values, |v| match v {
+                CqlValue::Double(f) => Ok(f),
+                CqlValue::Decimal(d) => Ok(d.as_plain()),
+                v => Err(v),
+            })?))
+        }
+        (ColType::Float, _) => Arc::new(Float32Array::from(scalars(values, |v| match v {
+            CqlValue::Float(f) => Ok(f),
+            v => Err(v),
+        })?)),
+        (ColType::Blob, _) | (ColType::Custom, _) => {
+            let bytes = scalars(values, |v| match v {
+                CqlValue::Blob(blob) => Ok(blob.into_vec()),
+                CqlValue::Custom(bytes) => Ok(bytes),
+                v => Err(v),
+            })?;
+            Arc::new(bytes.into_iter().collect::<BinaryArray>())
+        }
+        (ColType::Uuid, _) | (ColType::Timeuuid, _) => {
+            let uuids = scalars(values, |v| match v {
+                CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => Ok(*uuid.as_bytes()),
+                v => Err(v),
+            })?;
+            Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                uuids.into_iter(),
+                16,
+            )?)
+        }
+        (ColType::Timestamp, _) => Arc::new(
+            TimestampMillisecondArray::from(scalars(values, |v| match v {
+                CqlValue::Timestamp(ms) => Ok(ms),
+                v => Err(v),
+            })?)
+            .with_timezone("UTC"),
+        ),
+        (ColType::Date, _) => Arc::new(Date32Array::from(scalars(values, |v| match v {
+            CqlValue::Date(days) => Ok((i64::from(days as u32) - DATE_EPOCH_OFFSET) as i32),
+            v => Err(v),
+        })?)),
+        (ColType::Time, _) => {
+            Arc::new(Time64NanosecondArray::from(scalars(values, |v| match v {
+                CqlValue::Time(nanos) => Ok(nanos),
+                v => Err(v),
+            })?))
+        }
+        (ColType::Null, _) => Arc::new(NullArray::new(values.len())),
+        (ColType::List, Some(ColTypeOptionValue::CList(ref item_type)))
+        | (ColType::Set, Some(ColTypeOptionValue::CSet(ref item_type))) => {
+            let items = nested(values, |v| match v {
+                CqlValue::List(items) | CqlValue::Set(items) => Ok(items),
+                v => Err(v),
+            })?;
+            let (offsets, nulls, items) = flatten(items);
+            let child = build_array(item_type, items)?;
+            let field = Field::new("item", child.data_type().clone(), true);
+            Arc::new(ListArray::try_new(Arc::new(field), offsets, child, nulls)?)
+        }
+        (ColType::Map, Some(ColTypeOptionValue::CMap((ref key_type, ref val_type)))) => {
+            let entries = nested(values, |v| match v {
+                CqlValue::Map(entries) => Ok(entries),
+                v => Err(v),
+            })?;
+            let (offsets, nulls, entries) = flatten(entries);
+            let (keys, vals) = entries.into_iter().unzip();
+            let keys = build_array(key_type, keys)?;
+            let vals = build_array(val_type, vals)?;
+            let fields = Fields::from(vec![
+                Field::new("key", keys.data_type().clone(), false),
+                Field::new("value", vals.data_type().clone(), true),
+            ]);
+            let entries = StructArray::try_new(fields, vec![keys, vals], None)?;
+            let field = Field::new("entries", entries.data_type().clone(), false);
+            Arc::new(MapArray::try_new(
+                Arc::new(field),
+                offsets,
+                entries,
+                nulls,
+                false,
+            )?)
+        }
+        (ColType::Udt, Some(ColTypeOptionValue::UdtType(ref udt_type))) => {
+            let names = udt_type
+                .descriptions
+                .iter()
+                .map(|(name, _)| name.as_plain())
+                .collect();
+            let types = udt_type.descriptions.iter().map(|(_, t)| t).collect();
+            let rows = nested(values, |v| match v {
+                CqlValue::Udt(fields) => Ok(fields.into_iter().map(|(_, v)| v).collect()),
+                v => Err(v),
+            })?;
+            Arc::new(build_struct(names, types, rows)?)
+        }
+        (ColType::Tuple, Some(ColTypeOptionValue::TupleType(ref tuple_type))) => {
+            let names = (0..tuple_type.types.len()).map(|i| i.to_string()).collect();
+            let types = tuple_type.types.iter().collect();
+            let rows = nested(values, |v| match v {
+                CqlValue::Tuple(items) => Ok(items),
+                v => Err(v),
+            })?;
+            Arc::new(build_struct(names, types, rows)?)
+        }
+        (id, _) => {
+            return Err(Error::Protocol(format!(
+                "Cannot convert {:?} into Arrow array, type option is invalid",
+                id
+            )))
+        }
+    };
+
+    Ok(array)
+}
+
+/// Builds struct array from rows of field values. Missing trailing fields are nulls.
+fn build_struct(
+    names: Vec<String>,
+    types: Vec<&ColTypeOption>,
+    rows: Vec<Option<Vec<CqlValue>>>,
+) -> Result<StructArray> {
+    let len = rows.len();
+    let nulls = null_buffer(rows.iter().map(Option::is_some).collect());
+    let mut columns: Vec<Vec<CqlValue>> = names.iter().map(|_| Vec::with_capacity(len)).collect();
+    for row in rows {
+        let mut row = row.unwrap_or_default().into_iter();
+        for column in columns.iter_mut() {
+            column.push(row.next().unwrap_or(CqlValue::Null));
+        }
+    }
+
+    let mut fields = Vec::with_capacity(names.len());
+    let mut arrays = Vec::with_capacity(names.len());
+    for ((name, col_type), values) in names.into_iter().zip(types).zip(columns) {
+        let array = build_array(col_type, values)?;
+        fields.push(Field::new(name, array.data_type().clone(), true));
+        arrays.push(array);
+    }
+
+    StructArray::try_new_with_length(Fields::from(fields), arrays, nulls, len).map_err(Into::into)
+}
+
+/// Converts values of a column into Rust values where nulls are `None`.
+/// `f` returns a value back if it has unexpected type.
+fn scalars<T, F>(values: Vec<CqlValue>, f: F) -> Result<Vec<Option<T>>>
+where
+    F: Fn(CqlValue) -> ::std::result::Result<T, CqlValue>,
+{
+    values
+        .into_iter()
+        .map(|value| match value {
+            CqlValue::Null => Ok(None),
+            value => f(value).map(Some).map_err(unexpected_value),
+        })
+        .collect()
+}
+
+/// The same as `scalars` but for collections, UDTs and tuples.
+fn nested<T, F>(values: Vec<CqlValue>, f: F) -> Result<Vec<Option<Vec<T>>>>
+where
+    F: Fn(CqlValue) -> ::std::result::Result<Vec<T>, CqlValue>,
+{
+    scalars(values, f)
+}
+
+/// Flattens collections into a single list of items returning offsets of collections
+/// and their validity.
+fn flatten<T>(collections: Vec<Option<Vec<T>>>) -> (OffsetBuffer<i32>, Option<NullBuffer>, Vec<T>) {
+    let offsets = OffsetBuffer::from_lengths(
+        collections
+            .iter()
+            .map(|c| c.as_ref().map(Vec::len).unwrap_or(0)),
+    );
+    let nulls = null_buffer(collections.iter().map(Option::is_some).collect());
+    let items = collections.into_iter().flatten().flatten().collect();
+
+    (offsets, nulls, items)
+}
+
+fn null_buffer(validity: Vec<bool>) -> Option<NullBuffer> {
+    if validity.iter().all(|valid| *valid) {
+        None
+    } else {
+        Some(NullBuffer::from(validity))
+    }
+}
+
+fn unexpected_value(value: CqlValue) -> Error {
+    Error::TypeMismatch(format!(
+        "Unexpected value {:?} while building Arrow array",
+        value
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int32Type;
+    use arrow_schema::{DataType, TimeUnit};
+
+    use super::*;
+    use crate::frame::frame_result::{CUdt, RowsMetadata};
+    use crate::types::value::Value;
+    use crate::types::{CBytes, CString};
+
+    fn cell(value: CqlValue) -> CBytes {
+        match value {
+            CqlValue::Null => CBytes::new_empty(),
+            value => CBytes::new_shared(Value::from(value).body),
+        }
+    }
+
+    fn rows(col_types: Vec<(&str, ColTypeOption)>, rows: Vec<Vec<CqlValue>>) -> BodyResResultRows {
+        BodyResResultRows {
+            metadata: RowsMetadata::for_test(col_types),
+            rows_count: rows.len() as i32,
+            rows_content: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(cell).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scalar_columns() {
+        let rows = rows(
+            vec![
+                ("id", ColTypeOption::simple(ColType::Int)),
+                ("name", ColTypeOption::simple(ColType::Varchar)),
+                ("created", ColTypeOption::simple(ColType::Timestamp)),
+                ("token", ColTypeOption::simple(ColType::Uuid)),
+            ],
+            vec![
+                vec![
+                    CqlValue::Int(1),
+                    CqlValue::Varchar("John".into()),
+                    CqlValue::Timestamp(1000),
+                    CqlValue::Null,
+                ],
+                vec![
+                    CqlValue::Int(2),
+                    CqlValue::Null,
+                    CqlValue::Null,
+                    CqlValue::Null,
+                ],
+            ],
+        );
+        let batch = rows_to_record_batch(&rows).unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Int32);
+        assert_eq!(
+            batch.schema().field(2).data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+        );
+        assert_eq!(
+            batch.schema().field(3).data_type(),
+            &DataType::FixedSizeBinary(16)
+        );
+        assert_eq!(
+            batch
+                .column(0)
+                .as_primitive::<Int32Type>()
+                .values()
+                .to_vec(),
+            vec![1, 2]
+        );
+        let names = batch.column(1).as_string::<i32>();
+        assert_eq!(names.value(0), "John");
+        assert!(names.is_null(1));
+    }
+
+    #[test]
+    fn nested_columns() {
+        let list_type = ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(ColTypeOption::simple(
+                ColType::Int,
+            )))),
+        };
+        let udt_type = ColTypeOption {
+            id: ColType::Udt,
+            value: Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: CString::new("ks".into()),
+                udt_name: CString::new("address".into()),
+                descriptions: vec![(
+                    CString::new("street".into()),
+                    ColTypeOption::simple(ColType::Varchar),
+                )],
+            })),
+        };
+        let map_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap((
+                Box::new(ColTypeOption::simple(ColType::Varchar)),
+                Box::new(ColTypeOption::simple(ColType::Boolean)),
+            ))),
+        };
+        let rows = rows(
+            vec![
+                ("scores", list_type),
+                ("address", udt_type),
+                ("flags", map_type),
+            ],
+            vec![
+                vec![
+                    CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)]),
+                    CqlValue::Udt(vec![("street".into(), CqlValue::Varchar("Main".into()))]),
+                    CqlValue::Map(vec![(
+                        CqlValue::Varchar("a".into()),
+                        CqlValue::Boolean(true),
+                    )]),
+                ],
+                vec![CqlValue::Null, CqlValue::Null, CqlValue::Null],
+            ],
+        );
+        let batch = rows_to_record_batch(&rows).unwrap();
+
+        let scores = batch.column(0).as_list::<i32>();
+        assert_eq!(scores.value_offsets(), &[0, 2, 2]);
+        assert!(scores.is_null(1));
+        let address = batch.column(1).as_struct();
+        assert_eq!(address.column(0).as_string::<i32>().value(0), "Main");
+        assert!(address.is_null(1));
+        let flags = batch.column(2).as_map();
+        assert_eq!(flags.keys().as_string::<i32>().value(0), "a");
+        assert!(flags.is_null(1));
+    }
+}
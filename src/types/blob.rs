@@ -8,6 +8,11 @@ impl Blob {
         Blob(bytes)
     }
 
+    /// Returns a reference to an underlying slice of bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
     /// Returns a mutable reference to an underlying slice of bytes.
     pub fn as_mut_slice<'a>(&'a mut self) -> &'a [u8] {
         self.0.as_mut_slice()
@@ -0,0 +1,439 @@
+//! Conversion of column type metadata into CQL type strings
+//! such as `map<text, frozen<list<int>>>` and back.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::frame::frame_result::{CTuple, CUdt, ColType, ColTypeOption, ColTypeOptionValue};
+use crate::types::CString;
+
+/// Maximum nesting level of parsed type strings.
+const MAX_PARSE_DEPTH: usize = 64;
+
+/// Class name of `duration` type which is sent as a custom type.
+const DURATION_CLASS: &str = "org.apache.cassandra.db.marshal.DurationType";
+
+/// Names which are parsed as types rather than UDT names, so UDTs named so are quoted.
+const TYPE_KEYWORDS: &[&str] = &[
+    "ascii", "bigint", "blob", "boolean", "counter", "decimal", "double", "float", "int",
+    "timestamp", "uuid", "text", "varchar", "varint", "timeuuid", "inet", "date", "time",
+    "smallint", "tinyint", "duration", "frozen", "list", "set", "map", "tuple",
+];
+
+impl fmt::Display for ColType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ColType::Custom => "custom",
+            ColType::Ascii => "ascii",
+            ColType::Bigint => "bigint",
+            ColType::Blob => "blob",
+            ColType::Boolean => "boolean",
+            ColType::Counter => "counter",
+            ColType::Decimal => "decimal",
+            ColType::Double => "double",
+            ColType::Float => "float",
+            ColType::Int => "int",
+            ColType::Timestamp => "timestamp",
+            ColType::Uuid => "uuid",
+            ColType::Varchar => "text",
+            ColType::Varint => "varint",
+            ColType::Timeuuid => "timeuuid",
+            ColType::Inet => "inet",
+            ColType::Date => "date",
+            ColType::Time => "time",
+            ColType::Smallint => "smallint",
+            ColType::Tinyint => "tinyint",
+            ColType::List => "list",
+            ColType::Map => "map",
+            ColType::Set => "set",
+            ColType::Udt => "udt",
+            ColType::Tuple => "tuple",
+            ColType::Null => "null",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Renders column type as a canonical CQL type string. Collections, UDTs and tuples
+/// nested into other types are rendered as `frozen<...>` since Cassandra does not
+/// allow non-frozen nested types. Custom types are rendered as quoted class names except
+/// for `duration`. UDT names which are not lowercase identifiers are double quoted.
+impl fmt::Display for ColTypeOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_col_type(self, f, false)
+    }
+}
+
+fn fmt_col_type(col_type: &ColTypeOption, f: &mut fmt::Formatter, nested: bool) -> fmt::Result {
+    let frozen = nested
+        && matches!(
+            col_type.id,
+            ColType::List | ColType::Map | ColType::Set | ColType::Udt | ColType::Tuple
+        );
+
+    if frozen {
+        f.write_str("frozen<")?;
+    }
+
+    match col_type.value {
+        Some(ColTypeOptionValue::CString(ref class_name)) => {
+            if class_name.as_str() == DURATION_CLASS {
+                f.write_str("duration")?
+            } else {
+                write!(f, "'{}'", class_name.as_str())?
+            }
+        }
+        Some(ColTypeOptionValue::CList(ref item_type)) => {
+            f.write_str("list<")?;
+            fmt_col_type(item_type, f, true)?;
+            f.write_str(">")?;
+        }
+        Some(ColTypeOptionValue::CSet(ref item_type)) => {
+            f.write_str("set<")?;
+            fmt_col_type(item_type, f, true)?;
+            f.write_str(">")?;
+        }
+        Some(ColTypeOptionValue::CMap((ref key_type, ref val_type))) => {
+            f.write_str("map<")?;
+            fmt_col_type(key_type, f, true)?;
+            f.write_str(", ")?;
+            fmt_col_type(val_type, f, true)?;
+            f.write_str(">")?;
+        }
+        Some(ColTypeOptionValue::UdtType(ref udt_type)) => {
+            if !udt_type.ks.as_str().is_empty() {
+                fmt_name(udt_type.ks.as_str(), f)?;
+                f.write_str(".")?;
+            }
+            fmt_name(udt_type.udt_name.as_str(), f)?;
+        }
+        Some(ColTypeOptionValue::TupleType(ref tuple_type)) => {
+            f.write_str("tuple<")?;
+            for (i, item_type) in tuple_type.types.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                fmt_col_type(item_type, f, true)?;
+            }
+            f.write_str(">")?;
+        }
+        Some(ColTypeOptionValue::ColType(ref id)) => write!(f, "{}", id)?,
+        None => write!(f, "{}", col_type.id)?,
+    }
+
+    if frozen {
+        f.write_str(">")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a keyspace or UDT name double quoting it unless it would be parsed back as is.
+fn fmt_name(name: &str, f: &mut fmt::Formatter) -> fmt::Result {
+    let unquoted = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !TYPE_KEYWORDS.contains(&name);
+    if unquoted {
+        f.write_str(name)
+    } else {
+        write!(f, "\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// Parses CQL type string into `ColTypeOption`. `frozen<...>` wrappers are accepted
+/// and dropped as frozenness is not a part of column type metadata. Names which are
+/// not native CQL types are treated as user defined types (optionally prefixed
+/// by a keyspace) with no field descriptions.
+impl FromStr for ColTypeOption {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ColTypeOption> {
+        let mut parser = TypeParser { input: s, pos: 0 };
+        let col_type = parser.parse_type(0)?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(col_type)
+    }
+}
+
+struct TypeParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> TypeParser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::Protocol(format!(
+            "Cannot parse CQL type '{}' at position {}: {}",
+            self.input, self.pos, message
+        ))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}' is expected", c)))
+        }
+    }
+
+    fn next_is(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.rest().starts_with(c)
+    }
+
+    /// Reads a name which may be double quoted. Quotes inside of a quoted name are doubled.
+    /// Unquoted names are case insensitive, so they are converted into lowercase.
+    fn parse_name(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let mut name = String::new();
+            let mut chars = quoted.char_indices();
+            while let Some((i, c)) = chars.next() {
+                if c != '"' {
+                    name.push(c);
+                } else if quoted[i + 1..].starts_with('"') {
+                    name.push('"');
+                    chars.next();
+                } else {
+                    self.pos += i + 2;
+                    return Ok(name);
+                }
+            }
+            return Err(self.error("unterminated quoted name"));
+        }
+
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("type name is expected"));
+        }
+        self.pos += len;
+
+        Ok(rest[..len].to_lowercase())
+    }
+
+    fn parse_type(&mut self, depth: usize) -> Result<ColTypeOption> {
+        if depth > MAX_PARSE_DEPTH {
+            return Err(self.error("type nesting level exceeds the limit"));
+        }
+
+        self.skip_whitespace();
+        if let Some(quoted) = self.rest().strip_prefix('\'') {
+            let end = quoted
+                .find('\'')
+                .ok_or_else(|| self.error("unterminated custom type class name"))?;
+            let class_name = quoted[..end].to_string();
+            self.pos += end + 2;
+            return Ok(custom_type(class_name));
+        }
+
+        let quoted = self.rest().starts_with('"');
+        let name = self.parse_name()?;
+        let simple = |id: ColType| Ok(ColTypeOption { id, value: None });
+        // a quoted name is always a name of UDT
+        let keyword = if quoted { "" } else { name.as_str() };
+        match keyword {
+            "ascii" => simple(ColType::Ascii),
+            "bigint" => simple(ColType::Bigint),
+            "blob" => simple(ColType::Blob),
+            "boolean" => simple(ColType::Boolean),
+            "counter" => simple(ColType::Counter),
+            "decimal" => simple(ColType::Decimal),
+            "double" => simple(ColType::Double),
+            "float" => simple(ColType::Float),
+            "int" => simple(ColType::Int),
+            "timestamp" => simple(ColType::Timestamp),
+            "uuid" => simple(ColType::Uuid),
+            "text" | "varchar" => simple(ColType::Varchar),
+            "varint" => simple(ColType::Varint),
+            "timeuuid" => simple(ColType::Timeuuid),
+            "inet" => simple(ColType::Inet),
+            "date" => simple(ColType::Date),
+            "time" => simple(ColType::Time),
+            "smallint" => simple(ColType::Smallint),
+            "tinyint" => simple(ColType::Tinyint),
+            "duration" => Ok(custom_type(DURATION_CLASS.to_string())),
+            "frozen" => {
+                self.expect('<')?;
+                let col_type = self.parse_type(depth + 1)?;
+                self.expect('>')?;
+                Ok(col_type)
+            }
+            "list" | "set" => {
+                self.expect('<')?;
+                let item_type = Box::new(self.parse_type(depth + 1)?);
+                self.expect('>')?;
+                Ok(if name == "list" {
+                    ColTypeOption {
+                        id: ColType::List,
+                        value: Some(ColTypeOptionValue::CList(item_type)),
+                    }
+                } else {
+                    ColTypeOption {
+                        id: ColType::Set,
+                        value: Some(ColTypeOptionValue::CSet(item_type)),
+                    }
+                })
+            }
+            "map" => {
+                self.expect('<')?;
+                let key_type = Box::new(self.parse_type(depth + 1)?);
+                self.expect(',')?;
+                let val_type = Box::new(self.parse_type(depth + 1)?);
+                self.expect('>')?;
+                Ok(ColTypeOption {
+                    id: ColType::Map,
+                    value: Some(ColTypeOptionValue::CMap((key_type, val_type))),
+                })
+            }
+            "tuple" => {
+                self.expect('<')?;
+                let mut types = vec![self.parse_type(depth + 1)?];
+                while self.next_is(',') {
+                    self.expect(',')?;
+                    types.push(self.parse_type(depth + 1)?);
+                }
+                self.expect('>')?;
+                Ok(ColTypeOption {
+                    id: ColType::Tuple,
+                    value: Some(ColTypeOptionValue::TupleType(CTuple { types })),
+                })
+            }
+            _ => {
+                let (ks, udt_name) = if self.next_is('.') {
+                    self.expect('.')?;
+                    (name, self.parse_name()?)
+                } else {
+                    (String::new(), name)
+                };
+                Ok(ColTypeOption {
+                    id: ColType::Udt,
+                    value: Some(ColTypeOptionValue::UdtType(CUdt {
+                        ks: CString::new(ks),
+                        udt_name: CString::new(udt_name),
+                        descriptions: vec![],
+                    })),
+                })
+            }
+        }
+    }
+}
+
+fn custom_type(class_name: String) -> ColTypeOption {
+    ColTypeOption {
+        id: ColType::Custom,
+        value: Some(ColTypeOptionValue::CString(CString::new(class_name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_of(item_type: ColTypeOption) -> ColTypeOption {
+        ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(item_type))),
+        }
+    }
+
+    #[test]
+    fn format_simple_types() {
+        assert_eq!(ColTypeOption::simple(ColType::Int).to_string(), "int");
+        assert_eq!(ColTypeOption::simple(ColType::Varchar).to_string(), "text");
+        assert_eq!(
+            list_of(ColTypeOption::simple(ColType::Timeuuid)).to_string(),
+            "list<timeuuid>"
+        );
+
+        let custom = |class_name: &str| ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString(CString::new(class_name.into()))),
+        };
+        assert_eq!(custom(DURATION_CLASS).to_string(), "duration");
+        assert_eq!(custom("a.b.C").to_string(), "'a.b.C'");
+    }
+
+    #[test]
+    fn format_nested_types() {
+        let map = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap((
+                Box::new(ColTypeOption::simple(ColType::Varchar)),
+                Box::new(list_of(ColTypeOption::simple(ColType::Int))),
+            ))),
+        };
+        assert_eq!(map.to_string(), "map<text, frozen<list<int>>>");
+
+        let udt = ColTypeOption {
+            id: ColType::Udt,
+            value: Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: CString::new("ks".into()),
+                udt_name: CString::new("address".into()),
+                descriptions: vec![],
+            })),
+        };
+        let tuple = ColTypeOption {
+            id: ColType::Tuple,
+            value: Some(ColTypeOptionValue::TupleType(CTuple {
+                types: vec![ColTypeOption::simple(ColType::Bigint), udt],
+            })),
+        };
+        assert_eq!(
+            list_of(tuple).to_string(),
+            "list<frozen<tuple<bigint, frozen<ks.address>>>>"
+        );
+    }
+
+    #[test]
+    fn parse_types() {
+        let round_trip = |s: &str| s.parse::<ColTypeOption>().unwrap().to_string();
+
+        assert_eq!(round_trip("int"), "int");
+        assert_eq!(round_trip("VARCHAR"), "text");
+        assert_eq!(
+            round_trip(" map < text , frozen<list<int>> > "),
+            "map<text, frozen<list<int>>>"
+        );
+        assert_eq!(round_trip("map<text, list<int>>"), "map<text, frozen<list<int>>>");
+        assert_eq!(
+            round_trip("frozen<tuple<bigint, frozen<ks.address>, 'a.b.C'>>"),
+            "tuple<bigint, frozen<ks.address>, 'a.b.C'>"
+        );
+        assert_eq!(round_trip("set<\"MyType\">"), "set<frozen<\"MyType\">>");
+        assert_eq!(round_trip("set<MyType>"), "set<frozen<mytype>>");
+        assert_eq!(round_trip("\"Ks\".\"int\""), "\"Ks\".\"int\"");
+        assert_eq!(round_trip("\"a\"\"b\""), "\"a\"\"b\"");
+        assert_eq!(round_trip("duration"), "duration");
+    }
+
+    #[test]
+    fn parse_invalid_types() {
+        assert!("".parse::<ColTypeOption>().is_err());
+        assert!("list<int".parse::<ColTypeOption>().is_err());
+        assert!("map<int>".parse::<ColTypeOption>().is_err());
+        assert!("int>".parse::<ColTypeOption>().is_err());
+        assert!("'unterminated".parse::<ColTypeOption>().is_err());
+        assert!("list<".repeat(100).parse::<ColTypeOption>().is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use std::net::IpAddr;
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
+use crate::frame::IntoBytes;
+use crate::types::blob::Blob;
+use crate::types::data_serialization_types::*;
+use crate::types::decimal::Decimal;
+use crate::types::value::{Bytes, Value};
+use crate::types::varint::Varint;
+use crate::types::{to_bigint, to_float, to_float_big, to_int, to_short, CBytes};
+
+/// Dynamically typed CQL value. It can represent a value of any CQL type,
+/// so it could be used when a schema is not known at compile time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CqlValue {
+    Null,
+    /// Raw bytes of a custom type value.
+    Custom(Vec<u8>),
+    Ascii(String),
+    Bigint(i64),
+    Blob(Blob),
+    Boolean(bool),
+    Counter(i64),
+    Decimal(Decimal),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    /// Milliseconds since the epoch.
+    Timestamp(i64),
+    Uuid(Uuid),
+    Varchar(String),
+    Varint(Varint),
+    Timeuuid(Uuid),
+    Inet(IpAddr),
+    Date(i32),
+    /// Nanoseconds since midnight.
+    Time(i64),
+    Smallint(i16),
+    Tinyint(i8),
+    List(Vec<CqlValue>),
+    Set(Vec<CqlValue>),
+    /// Map as a list of key-value pairs in an order they were received.
+    Map(Vec<(CqlValue, CqlValue)>),
+    /// User defined type as a list of field name-value pairs.
+    Udt(Vec<(String, CqlValue)>),
+    Tuple(Vec<CqlValue>),
+}
+
+impl CqlValue {
+    /// Decodes a cell of a given type into `CqlValue` using default `DecodeLimits`.
+    pub fn decode(bytes: &CBytes, col_type: &ColTypeOption) -> Result<CqlValue> {
+        CqlValue::decode_with_limits(bytes, col_type, &DecodeLimits::default())
+    }
+
+    /// Decodes a cell of a given type into `CqlValue` applying provided limits
+    /// to nested collections.
+    pub fn decode_with_limits(
+        bytes: &CBytes,
+        col_type: &ColTypeOption,
+        limits: &DecodeLimits,
+    ) -> Result<CqlValue> {
+        CqlValue::decode_with_options(bytes, col_type, limits, Utf8Policy::Lossy)
+    }
+
+    /// Decodes a cell of a given type into `CqlValue` using default `DecodeLimits`.
+    /// `ascii` and `varchar` values, including nested ones, are decoded in accordance
+    /// to the UTF-8 policy.
+    pub fn decode_with_utf8_policy(
+        bytes: &CBytes,
+        col_type: &ColTypeOption,
+        policy: Utf8Policy,
+    ) -> Result<CqlValue> {
+        CqlValue::decode_with_options(bytes, col_type, &DecodeLimits::default(), policy)
+    }
+
+    fn decode_with_options(
+        bytes: &CBytes,
+        col_type: &ColTypeOption,
+        limits: &DecodeLimits,
+        policy: Utf8Policy,
+    ) -> Result<CqlValue> {
+        match bytes.as_slice() {
+            Some(bytes) => {
+                let nested = decode_nested(bytes, col_type, limits)?;
+                CqlValue::from_nested(nested, col_type, policy)
+            }
+            None => Ok(CqlValue::Null),
+        }
+    }
+
+    /// Returns `true` if the value is `CqlValue::Null`.
+    pub fn is_null(&self) -> bool {
+        *self == CqlValue::Null
+    }
+
+    fn from_nested(
+        nested: NestedValue,
+        col_type: &ColTypeOption,
+        policy: Utf8Policy,
+    ) -> Result<CqlValue> {
+        let items = |items: Vec<NestedValue>, item_type: &ColTypeOption| {
+            items
+                .into_iter()
+                .map(|item| CqlValue::from_nested(item, item_type, policy))
+                .collect::<Result<Vec<CqlValue>>>()
+        };
+
+        match (nested, &col_type.value) {
+            (NestedValue::Null, _) => Ok(CqlValue::Null),
+            (NestedValue::Item(bytes), _) => match bytes.as_slice() {
+                Some(bytes) => CqlValue::decode_scalar(bytes, &col_type.id, policy),
+                None => Ok(CqlValue::Null),
+            },
+            (NestedValue::List(list), Some(ColTypeOptionValue::CList(ref item_type))) => {
+                items(list, item_type).map(CqlValue::List)
+            }
+            (NestedValue::Set(set), Some(ColTypeOptionValue::CSet(ref item_type))) => {
+                items(set, item_type).map(CqlValue::Set)
+            }
+            (
+                NestedValue::Map(map),
+                Some(ColTypeOptionValue::CMap((ref key_type, ref val_type))),
+            ) => map
+                .into_iter()
+                .map(|(key, val)| {
+                    Ok((
+                        CqlValue::from_nested(key, key_type, policy)?,
+                        CqlValue::from_nested(val, val_type, policy)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(CqlValue::Map),
+            (NestedValue::Udt(fields), Some(ColTypeOptionValue::UdtType(ref udt_type))) => fields
+                .into_iter()
+                .zip(udt_type.descriptions.iter())
+                .map(|((name, field), (_, field_type))| {
+                    CqlValue::from_nested(field, field_type, policy).map(|value| (name, value))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(CqlValue::Udt),
+            (NestedValue::Tuple(tuple), Some(ColTypeOptionValue::TupleType(ref tuple_type))) => {
+                tuple
+                    .into_iter()
+                    .zip(tuple_type.types.iter())
+                    .map(|(item, item_type)| CqlValue::from_nested(item, item_type, policy))
+                    .collect::<Result<Vec<_>>>()
+                    .map(CqlValue::Tuple)
+            }
+            _ => Err(Error::TypeMismatch(format!(
+                "Invalid conversion. Cannot convert {:?} into CqlValue.",
+                col_type.id
+            ))),
+        }
+    }
+
+    fn decode_scalar(bytes: &[u8], col_type: &ColType, policy: Utf8Policy) -> Result<CqlValue> {
+        let value = match *col_type {
+            ColType::Custom => CqlValue::Custom(bytes.to_vec()),
+            ColType::Ascii => CqlValue::Ascii(decode_text_with_policy(bytes, policy)?),
+            ColType::Bigint => CqlValue::Bigint(decode_bigint(bytes)?),
+            ColType::Blob => CqlValue::Blob(Blob::new(bytes.to_vec())),
+            ColType::Boolean => CqlValue::Boolean(decode_boolean(bytes)?),
+            ColType::Counter => CqlValue::Counter(decode_bigint(bytes)?),
+            ColType::Decimal => CqlValue::Decimal(decode_decimal(bytes)?),
+            ColType::Double => CqlValue::Double(decode_double(bytes)?),
+            ColType::Float => CqlValue::Float(decode_float(bytes)?),
+            ColType::Int => CqlValue::Int(decode_int(bytes)?),
+            ColType::Timestamp => CqlValue::Timestamp(decode_timestamp(bytes)?),
+            ColType::Uuid => CqlValue::Uuid(decode_timeuuid(bytes)?),
+            ColType::Varchar => CqlValue::Varchar(decode_text_with_policy(bytes, policy)?),
+            ColType::Varint => CqlValue::Varint(
+                Varint::from_bytes(bytes)
+                    .ok_or_else(|| Error::Protocol("varint cannot be empty".into()))?,
+            ),
+            ColType::Timeuuid => CqlValue::Timeuuid(decode_timeuuid(bytes)?),
+            ColType::Inet => CqlValue::Inet(decode_inet(bytes)?),
+            ColType::Date => CqlValue::Date(decode_date(bytes)?),
+            ColType::Time => CqlValue::Time(decode_time(bytes)?),
+            ColType::Smallint => CqlValue::Smallint(decode_smallint(bytes)?),
+            ColType::Tinyint => CqlValue::Tinyint(decode_tinyint(bytes)?),
+            ColType::Null => CqlValue::Null,
+            ColType::List | ColType::Map | ColType::Set | ColType::Udt | ColType::Tuple => {
+                return Err(Error::TypeMismatch(format!(
+                    "Invalid conversion. Type option of {:?} does not describe its items.",
+                    col_type
+                )))
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Serializes non-null value. Nulls are handled by `Value`.
+    fn encode(self) -> Vec<u8> {
+        match self {
+            CqlValue::Null => vec![],
+            CqlValue::Custom(bytes) => bytes,
+            CqlValue::Ascii(s) | CqlValue::Varchar(s) => s.into_bytes(),
+            CqlValue::Bigint(i) | CqlValue::Counter(i) | CqlValue::Timestamp(i) => to_bigint(i),
+            CqlValue::Time(i) => to_bigint(i),
+            CqlValue::Blob(blob) => blob.into_vec(),
+            CqlValue::Boolean(b) => vec![b as u8],
+            CqlValue::Decimal(d) => d.into_cbytes(),
+            CqlValue::Double(f) => to_float_big(f),
+            CqlValue::Float(f) => to_float(f),
+            CqlValue::Int(i) | CqlValue::Date(i) => to_int(i),
+            CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => uuid.as_bytes().to_vec(),
+            CqlValue::Varint(v) => v.into_vec(),
+            CqlValue::Inet(IpAddr::V4(ip)) => ip.octets().to_vec(),
+            CqlValue::Inet(IpAddr::V6(ip)) => ip.octets().to_vec(),
+            CqlValue::Smallint(i) => to_short(i),
+            CqlValue::Tinyint(i) => vec![i as u8],
+            CqlValue::List(items) | CqlValue::Set(items) => {
+                let mut bytes = to_int(items.len() as i32);
+                for item in items {
+                    bytes.extend(Value::from(item).into_cbytes());
+                }
+                bytes
+            }
+            CqlValue::Map(map) => {
+                let mut bytes = to_int(map.len() as i32);
+                for (key, val) in map {
+                    bytes.extend(Value::from(key).into_cbytes());
+                    bytes.extend(Value::from(val).into_cbytes());
+                }
+                bytes
+            }
+            CqlValue::Udt(fields) => fields
+                .into_iter()
+                .flat_map(|(_, field)| Value::from(field).into_cbytes())
+                .collect(),
+            CqlValue::Tuple(items) => items
+                .into_iter()
+                .flat_map(|item| Value::from(item).into_cbytes())
+                .collect(),
+        }
+    }
+}
+
+impl From<CqlValue> for Value {
+    fn from(value: CqlValue) -> Value {
+        match value {
+            CqlValue::Null => Value::new_null(),
+            value => Value::new_normal(Bytes::new(value.encode())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::{CTuple, CUdt};
+    use crate::types::CString;
+
+    fn round_trip(value: CqlValue, col_type: &ColTypeOption) -> CqlValue {
+        let value: Value = value.into();
+        CqlValue::decode(&CBytes::new_shared(value.body), col_type).unwrap()
+    }
+
+    #[test]
+    fn scalars_round_trip() {
+        let values = vec![
+            (CqlValue::Ascii("ascii".into()), ColType::Ascii),
+            (CqlValue::Bigint(-1), ColType::Bigint),
+            (CqlValue::Blob(Blob::new(vec![1, 2])), ColType::Blob),
+            (CqlValue::Boolean(true), ColType::Boolean),
+            (CqlValue::Decimal(Decimal::new(12345, 2)), ColType::Decimal),
+            (CqlValue::Double(1.5), ColType::Double),
+            (CqlValue::Float(2.5), ColType::Float),
+            (CqlValue::Int(42), ColType::Int),
+            (CqlValue::Timestamp(1_500_000_000_000), ColType::Timestamp),
+            (CqlValue::Uuid(Uuid::nil()), ColType::Uuid),
+            (CqlValue::Varchar("varchar".into()), ColType::Varchar),
+            (CqlValue::Varint(Varint::from(-300)), ColType::Varint),
+            (CqlValue::Inet("127.0.0.1".parse().unwrap()), ColType::Inet),
+            (CqlValue::Smallint(-2), ColType::Smallint),
+            (CqlValue::Tinyint(-3), ColType::Tinyint),
+        ];
+
+        for (value, id) in values {
+            assert_eq!(round_trip(value.clone(), &ColTypeOption::simple(id)), value);
+        }
+    }
+
+    #[test]
+    fn collections_round_trip() {
+        let list_type = ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(ColTypeOption::simple(
+                ColType::Int,
+            )))),
+        };
+        let map_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap((
+                Box::new(ColTypeOption::simple(ColType::Varchar)),
+                Box::new(list_type),
+            ))),
+        };
+        let value = CqlValue::Map(vec![
+            (
+                CqlValue::Varchar("a".into()),
+                CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)]),
+            ),
+            (CqlValue::Varchar("b".into()), CqlValue::List(vec![])),
+        ]);
+
+        assert_eq!(round_trip(value.clone(), &map_type), value);
+    }
+
+    #[test]
+    fn udt_and_tuple_round_trip() {
+        let udt_type = ColTypeOption {
+            id: ColType::Udt,
+            value: Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: CString::new("ks".into()),
+                udt_name: CString::new("udt".into()),
+                descriptions: vec![
+                    (
+                        CString::new("name".into()),
+                        ColTypeOption::simple(ColType::Varchar),
+                    ),
+                    (
+                        CString::new("age".into()),
+                        ColTypeOption::simple(ColType::Int),
+                    ),
+                ],
+            })),
+        };
+        let tuple_type = ColTypeOption {
+            id: ColType::Tuple,
+            value: Some(ColTypeOptionValue::TupleType(CTuple {
+                types: vec![udt_type, ColTypeOption::simple(ColType::Boolean)],
+            })),
+        };
+        let value = CqlValue::Tuple(vec![
+            CqlValue::Udt(vec![
+                ("name".into(), CqlValue::Varchar("John".into())),
+                ("age".into(), CqlValue::Null),
+            ]),
+            CqlValue::Boolean(false),
+        ]);
+
+        assert_eq!(round_trip(value.clone(), &tuple_type), value);
+    }
+
+    #[test]
+    fn null_value() {
+        let value: Value = CqlValue::Null.into();
+        assert_eq!(value.into_cbytes(), vec![255, 255, 255, 255]);
+        assert!(
+            CqlValue::decode(&CBytes::new_empty(), &ColTypeOption::simple(ColType::Int))
+                .unwrap()
+                .is_null()
+        );
+    }
+
+    #[test]
+    fn big_and_empty_varints() {
+        let varint_type = ColTypeOption::simple(ColType::Varint);
+        let bytes = vec![0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+        let value = CqlValue::decode(&CBytes::new(bytes.clone()), &varint_type).unwrap();
+        assert_eq!(value, CqlValue::Varint(Varint::from_bytes(&bytes).unwrap()));
+        assert_eq!(round_trip(value.clone(), &varint_type), value);
+
+        assert!(CqlValue::decode(&CBytes::new(vec![]), &varint_type).is_err());
+    }
+
+    #[test]
+    fn as_rust_cql_value() {
+        let int_type = ColTypeOption::simple(ColType::Int);
+        let data = CBytes::new(vec![0, 0, 0, 5]);
+        let value: Result<Option<CqlValue>> = as_rust_type!(int_type, data, CqlValue);
+        assert_eq!(value.unwrap(), Some(CqlValue::Int(5)));
+    }
+}
@@ -5,10 +5,77 @@ use std::string::FromUtf8Error;
 use super::blob::Blob;
 use super::decimal::Decimal;
 use super::*;
-use crate::error;
+use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::frame::FromCursor;
 use uuid;
 
+/// Default maximum nesting level of collections, UDTs and tuples.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Limits which are applied while decoding collections so malformed frames
+/// cannot trigger unbounded recursion or huge allocations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeLimits {
+    /// Maximum nesting level of collections, UDTs and tuples.
+    pub max_depth: usize,
+    /// Maximum number of elements in a single collection.
+    pub max_collection_len: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> DecodeLimits {
+        DecodeLimits {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_collection_len: i32::MAX as usize,
+        }
+    }
+}
+
+/// How bytes which are not valid UTF-8 are decoded into strings. Real clusters occasionally
+/// store invalid UTF-8 in text columns, so failing on it may lose a whole row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Invalid UTF-8 is an error.
+    Strict,
+    /// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    Lossy,
+}
+
+impl Utf8Policy {
+    /// Converts bytes into a string in accordance to the policy. Valid UTF-8
+    /// is not copied.
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String, FromUtf8Error> {
+        match (String::from_utf8(bytes), self) {
+            (Ok(string), _) => Ok(string),
+            (Err(err), Utf8Policy::Strict) => Err(err),
+            (Err(err), Utf8Policy::Lossy) => {
+                Ok(String::from_utf8_lossy(err.as_bytes()).into_owned())
+            }
+        }
+    }
+}
+
+/// Fully decoded value of a (possibly nested) collection, UDT or tuple.
+/// Non-collection values are kept as raw `CBytes`, so they can be converted
+/// into Rust types in a usual way.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NestedValue {
+    /// Null value.
+    Null,
+    /// Non-collection value.
+    Item(CBytes),
+    /// Decoded `list`.
+    List(Vec<NestedValue>),
+    /// Decoded `set`.
+    Set(Vec<NestedValue>),
+    /// Decoded `map` as a list of key-value pairs.
+    Map(Vec<(NestedValue, NestedValue)>),
+    /// Decoded user defined type as a list of field name-value pairs.
+    Udt(Vec<(String, NestedValue)>),
+    /// Decoded tuple.
+    Tuple(Vec<NestedValue>),
+}
+
 // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L813
 
 // Decodes Cassandra `ascii` data (bytes) into Rust's `Result<String, FromUtf8Error>`.
@@ -32,9 +99,9 @@ pub fn decode_bigint(bytes: &[u8]) -> Result<i64, io::Error> {
 }
 
 // Decodes Cassandra `blob` data (bytes) into Rust's `Result<Vec<u8>, io::Error>`
-pub fn decode_blob(bytes: &Vec<u8>) -> Result<Blob, io::Error> {
+pub fn decode_blob(bytes: &[u8]) -> Result<Blob, io::Error> {
     // in fact we just pass it through.
-    Ok(bytes.clone().into())
+    Ok(bytes.to_vec().into())
 }
 
 // Decodes Cassandra `boolean` data (bytes) into Rust's `Result<i32, io::Error>`
@@ -65,6 +132,13 @@ pub fn decode_date(bytes: &[u8]) -> Result<i32, io::Error> {
 
 // Decodes Cassandra `decimal` data (bytes) into Rust's `Result<f32, io::Error>`
 pub fn decode_decimal(bytes: &[u8]) -> Result<Decimal, io::Error> {
+    if bytes.len() <= INT_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("decimal needs more than {} bytes, got {}", INT_LEN, bytes.len()),
+        ));
+    }
+
     let lr = bytes.split_at(INT_LEN);
 
     let scale = try_i_from_bytes(lr.0)? as u32;
@@ -122,10 +196,18 @@ pub fn decode_timestamp(bytes: &[u8]) -> Result<i64, io::Error> {
 
 // Decodes Cassandra `list` data (bytes) into Rust's `Result<Vec<CBytes>, io::Error>`
 pub fn decode_list(bytes: &[u8]) -> Result<Vec<CBytes>, io::Error> {
+    decode_list_with_limits(bytes, &DecodeLimits::default())
+}
+
+// Decodes Cassandra `list` data (bytes) into Rust's `Result<Vec<CBytes>, io::Error>`
+// checking the number of elements against provided limits.
+pub fn decode_list_with_limits(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+) -> Result<Vec<CBytes>, io::Error> {
     let mut cursor: io::Cursor<&[u8]> = io::Cursor::new(bytes);
-    let l = CInt::from_cursor(&mut cursor)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-    let mut list = Vec::with_capacity(l as usize);
+    let l = decode_collection_len(&mut cursor, limits)?;
+    let mut list = Vec::with_capacity(l);
     for _ in 0..l {
         let b = CBytes::from_cursor(&mut cursor)
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
@@ -141,10 +223,18 @@ pub fn decode_set(bytes: &[u8]) -> Result<Vec<CBytes>, io::Error> {
 
 // Decodes Cassandra `map` data (bytes) into Rust's `Result<Vec<(CBytes, CBytes)>, io::Error>`
 pub fn decode_map(bytes: &[u8]) -> Result<Vec<(CBytes, CBytes)>, io::Error> {
+    decode_map_with_limits(bytes, &DecodeLimits::default())
+}
+
+// Decodes Cassandra `map` data (bytes) into Rust's `Result<Vec<(CBytes, CBytes)>, io::Error>`
+// checking the number of elements against provided limits.
+pub fn decode_map_with_limits(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+) -> Result<Vec<(CBytes, CBytes)>, io::Error> {
     let mut cursor: io::Cursor<&[u8]> = io::Cursor::new(bytes);
-    let l = CInt::from_cursor(&mut cursor)
-        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
-    let mut map = Vec::with_capacity(l as usize);
+    let l = decode_collection_len(&mut cursor, limits)?;
+    let mut map = Vec::with_capacity(l);
     for _ in 0..l {
         let n = CBytes::from_cursor(&mut cursor)
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
@@ -155,6 +245,115 @@ pub fn decode_map(bytes: &[u8]) -> Result<Vec<(CBytes, CBytes)>, io::Error> {
     Ok(map)
 }
 
+// Reads number of collection elements and validates it. Each element takes
+// at least `INT_LEN` bytes, so the number could not exceed remaining bytes.
+fn decode_collection_len(
+    cursor: &mut io::Cursor<&[u8]>,
+    limits: &DecodeLimits,
+) -> Result<usize, io::Error> {
+    let l = CInt::from_cursor(cursor).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let remaining = cursor.get_ref().len() as u64 - cursor.position();
+    if l < 0 || l as usize > limits.max_collection_len || l as u64 * INT_LEN as u64 > remaining {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid number of collection elements {}", l),
+        ));
+    }
+
+    Ok(l as usize)
+}
+
+// Recursively decodes Cassandra collection, UDT or tuple data (bytes) of a given type
+// into `NestedValue`. Values of other types are returned as `NestedValue::Item`.
+pub fn decode_nested(
+    bytes: &[u8],
+    col_type: &ColTypeOption,
+    limits: &DecodeLimits,
+) -> Result<NestedValue, io::Error> {
+    decode_nested_with_depth(bytes, col_type, limits, 0)
+}
+
+fn decode_nested_item(
+    item: &CBytes,
+    col_type: &ColTypeOption,
+    limits: &DecodeLimits,
+    depth: usize,
+) -> Result<NestedValue, io::Error> {
+    match item.as_slice() {
+        Some(bytes) => decode_nested_with_depth(bytes, col_type, limits, depth),
+        None => Ok(NestedValue::Null),
+    }
+}
+
+fn decode_nested_with_depth(
+    bytes: &[u8],
+    col_type: &ColTypeOption,
+    limits: &DecodeLimits,
+    depth: usize,
+) -> Result<NestedValue, io::Error> {
+    if depth > limits.max_depth {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Nesting level exceeds the limit of {}", limits.max_depth),
+        ));
+    }
+
+    let nested = |items: Vec<CBytes>, item_type: &ColTypeOption| {
+        items
+            .iter()
+            .map(|item| decode_nested_item(item, item_type, limits, depth + 1))
+            .collect::<Result<Vec<NestedValue>, io::Error>>()
+    };
+
+    match (&col_type.id, &col_type.value) {
+        (ColType::List, Some(ColTypeOptionValue::CList(ref item_type))) => {
+            nested(decode_list_with_limits(bytes, limits)?, item_type).map(NestedValue::List)
+        }
+        (ColType::Set, Some(ColTypeOptionValue::CSet(ref item_type))) => {
+            nested(decode_list_with_limits(bytes, limits)?, item_type).map(NestedValue::Set)
+        }
+        (ColType::Map, Some(ColTypeOptionValue::CMap((ref key_type, ref val_type)))) => {
+            decode_map_with_limits(bytes, limits)?
+                .iter()
+                .map(|(key, val)| {
+                    let key = decode_nested_item(key, key_type, limits, depth + 1)?;
+                    let val = decode_nested_item(val, val_type, limits, depth + 1)?;
+                    Ok((key, val))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()
+                .map(NestedValue::Map)
+        }
+        (ColType::Udt, Some(ColTypeOptionValue::UdtType(ref udt_type))) => {
+            decode_udt(bytes, udt_type.descriptions.len())?
+                .iter()
+                .zip(udt_type.descriptions.iter())
+                .map(|(field, (name, field_type))| {
+                    decode_nested_item(field, field_type, limits, depth + 1)
+                        .map(|value| (name.as_plain(), value))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()
+                .map(NestedValue::Udt)
+        }
+        (ColType::Tuple, Some(ColTypeOptionValue::TupleType(ref tuple_type))) => {
+            decode_tuple(bytes, tuple_type.types.len())?
+                .iter()
+                .zip(tuple_type.types.iter())
+                .map(|(item, item_type)| decode_nested_item(item, item_type, limits, depth + 1))
+                .collect::<Result<Vec<_>, io::Error>>()
+                .map(NestedValue::Tuple)
+        }
+        (ColType::List, _)
+        | (ColType::Set, _)
+        | (ColType::Map, _)
+        | (ColType::Udt, _)
+        | (ColType::Tuple, _) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Type option of {:?} does not describe its items", col_type.id),
+        )),
+        _ => Ok(NestedValue::Item(CBytes::new(bytes.to_vec()))),
+    }
+}
+
 // Decodes Cassandra `smallint` data (bytes) into Rust's `Result<i16, io::Error>`
 pub fn decode_smallint(bytes: &[u8]) -> Result<i16, io::Error> {
     try_from_bytes(bytes).map(|i| i as i16)
@@ -170,6 +369,12 @@ pub fn decode_text(bytes: &[u8]) -> Result<String, FromUtf8Error> {
     Ok(String::from_utf8_lossy(bytes).into_owned())
 }
 
+// Decodes Cassandra `text`, `varchar` or `ascii` data (bytes) into Rust's
+// `Result<String, FromUtf8Error>` handling invalid UTF-8 in accordance to a policy.
+pub fn decode_text_with_policy(bytes: &[u8], policy: Utf8Policy) -> Result<String, FromUtf8Error> {
+    policy.decode(bytes.to_vec())
+}
+
 // Decodes Cassandra `time` data (bytes) into Rust's `Result<String, FromUtf8Error>`.
 pub fn decode_time(bytes: &[u8]) -> Result<i64, io::Error> {
     try_i_from_bytes(bytes)
@@ -191,17 +396,13 @@ pub fn decode_udt(bytes: &[u8], l: usize) -> Result<Vec<CBytes>, io::Error> {
     let mut cursor: io::Cursor<&[u8]> = io::Cursor::new(bytes);
     let mut udt = Vec::with_capacity(l);
     for _ in 0..l {
+        // fields added to a type after a value was written are missing at the end
+        if remaining_len(&cursor) == 0 {
+            udt.push(CBytes::new_empty());
+            continue;
+        }
+
         let v = CBytes::from_cursor(&mut cursor)
-            .or_else(|err| match err {
-                error::Error::Io(io_err) => {
-                    if io_err.kind() == io::ErrorKind::UnexpectedEof {
-                        Ok(CBytes::new_empty())
-                    } else {
-                        Err(io_err.into())
-                    }
-                }
-                _ => Err(err),
-            })
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
         udt.push(v);
     }
@@ -363,6 +564,9 @@ mod tests {
             decode_decimal(&[0, 0, 0, 1, 0xFF, 0x7F]).unwrap(),
             Decimal::new(-129, 1)
         );
+
+        assert!(decode_decimal(&[0, 1]).is_err());
+        assert!(decode_decimal(&[0, 0, 0, 1]).is_err());
     }
 
     #[test]
@@ -370,6 +574,13 @@ mod tests {
         assert_eq!(decode_text(b"abcba").unwrap(), "abcba");
     }
 
+    #[test]
+    fn decode_text_with_policy_test() {
+        assert_eq!(decode_text_with_policy(b"abc", Utf8Policy::Strict).unwrap(), "abc");
+        assert!(decode_text_with_policy(b"a\xffc", Utf8Policy::Strict).is_err());
+        assert_eq!(decode_text_with_policy(b"a\xffc", Utf8Policy::Lossy).unwrap(), "a\u{fffd}c");
+    }
+
     #[test]
     fn decode_time_test() {
         assert_eq!(decode_time(&[0, 0, 0, 0, 0, 0, 0, 10]).unwrap(), 10);
@@ -395,6 +606,8 @@ mod tests {
         assert_eq!(decode_varint(&[0xFF]).unwrap(), -1);
         assert_eq!(decode_varint(&[0x80]).unwrap(), -128);
         assert_eq!(decode_varint(&[0xFF, 0x7F]).unwrap(), -129);
+        assert!(decode_varint(&[]).is_err());
+        assert!(decode_varint(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
     }
 
     #[test]
@@ -563,6 +776,87 @@ mod tests {
         assert!(as_rust_type!(wrong_type, data, f32).is_err());
     }
 
+    #[test]
+    fn decode_list_wrong_len_test() {
+        assert!(decode_list(&[0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        assert!(decode_list(&[0x7F, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]).is_err());
+        let limits = DecodeLimits {
+            max_collection_len: 1,
+            ..Default::default()
+        };
+        let bytes = &[0, 0, 0, 2, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2];
+        assert_eq!(decode_list(bytes).unwrap().len(), 2);
+        assert!(decode_list_with_limits(bytes, &limits).is_err());
+    }
+
+    fn list_of(item_type: ColTypeOption) -> ColTypeOption {
+        ColTypeOption {
+            id: ColType::List,
+            value: Some(ColTypeOptionValue::CList(Box::new(item_type))),
+        }
+    }
+
+    #[test]
+    fn decode_nested_test() {
+        // map<text, frozen<set<int>>>
+        let set_type = ColTypeOption {
+            id: ColType::Set,
+            value: Some(ColTypeOptionValue::CSet(Box::new(ColTypeOption::simple(
+                ColType::Int,
+            )))),
+        };
+        let map_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap((
+                Box::new(ColTypeOption::simple(ColType::Varchar)),
+                Box::new(set_type),
+            ))),
+        };
+        let bytes = &[
+            0, 0, 0, 1, // map length
+            0, 0, 0, 1, 97, // key "a"
+            0, 0, 0, 16, // value length
+            0, 0, 0, 2, // set length
+            0, 0, 0, 4, 0, 0, 0, 1, // 1
+            0xFF, 0xFF, 0xFF, 0xFF, // null
+        ];
+
+        let expected = NestedValue::Map(vec![(
+            NestedValue::Item(CBytes::new(b"a".to_vec())),
+            NestedValue::Set(vec![
+                NestedValue::Item(CBytes::new(vec![0, 0, 0, 1])),
+                NestedValue::Null,
+            ]),
+        )]);
+        assert_eq!(
+            decode_nested(bytes, &map_type, &DecodeLimits::default()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn decode_nested_depth_limit_test() {
+        // list<list<int>>
+        let col_type = list_of(list_of(ColTypeOption::simple(ColType::Int)));
+        let bytes = &[
+            0, 0, 0, 1, // outer list length
+            0, 0, 0, 12, // inner list bytes length
+            0, 0, 0, 1, // inner list length
+            0, 0, 0, 4, 0, 0, 0, 1,
+        ];
+
+        let limits = DecodeLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(decode_nested(bytes, &col_type, &limits).is_ok());
+        let limits = DecodeLimits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(decode_nested(bytes, &col_type, &limits).is_err());
+    }
+
     struct DataType {
         id: ColType,
     }
@@ -0,0 +1,226 @@
+//! DSE `DateRangeType` custom type used by DSE Search.
+use std::io;
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::types::value::Bytes;
+
+/// Class name of DSE `DateRangeType` custom type.
+pub const DATE_RANGE_TYPE: &str = "org.apache.cassandra.db.marshal.DateRangeType";
+
+const SINGLE_DATE: u8 = 0x00;
+const CLOSED_RANGE: u8 = 0x01;
+const OPEN_RANGE_HIGH: u8 = 0x02;
+const OPEN_RANGE_LOW: u8 = 0x03;
+const BOTH_OPEN_RANGE: u8 = 0x04;
+const SINGLE_DATE_OPEN: u8 = 0x05;
+
+/// Precision of a date range bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateRangePrecision {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Millisecond,
+}
+
+impl DateRangePrecision {
+    fn from_byte(byte: u8) -> Result<DateRangePrecision, io::Error> {
+        match byte {
+            0x00 => Ok(DateRangePrecision::Year),
+            0x01 => Ok(DateRangePrecision::Month),
+            0x02 => Ok(DateRangePrecision::Day),
+            0x03 => Ok(DateRangePrecision::Hour),
+            0x04 => Ok(DateRangePrecision::Minute),
+            0x05 => Ok(DateRangePrecision::Second),
+            0x06 => Ok(DateRangePrecision::Millisecond),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected date range precision {}", byte),
+            )),
+        }
+    }
+
+    fn as_byte(self) -> u8 {
+        match self {
+            DateRangePrecision::Year => 0x00,
+            DateRangePrecision::Month => 0x01,
+            DateRangePrecision::Day => 0x02,
+            DateRangePrecision::Hour => 0x03,
+            DateRangePrecision::Minute => 0x04,
+            DateRangePrecision::Second => 0x05,
+            DateRangePrecision::Millisecond => 0x06,
+        }
+    }
+}
+
+/// Bound of a date range. `timestamp` is a number of milliseconds since the epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateRangeBound {
+    Unbounded,
+    Bounded {
+        timestamp: i64,
+        precision: DateRangePrecision,
+    },
+}
+
+impl DateRangeBound {
+    pub fn new(timestamp: i64, precision: DateRangePrecision) -> DateRangeBound {
+        DateRangeBound::Bounded {
+            timestamp,
+            precision,
+        }
+    }
+
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> Result<DateRangeBound, io::Error> {
+        let timestamp = cursor.read_i64::<BigEndian>()?;
+        let precision = DateRangePrecision::from_byte(cursor.read_u8()?)?;
+
+        Ok(DateRangeBound::new(timestamp, precision))
+    }
+
+    fn write(self, bytes: &mut Vec<u8>) {
+        if let DateRangeBound::Bounded {
+            timestamp,
+            precision,
+        } = self
+        {
+            // writing into a vector does not fail
+            bytes.write_i64::<BigEndian>(timestamp).unwrap();
+            bytes.push(precision.as_byte());
+        }
+    }
+}
+
+/// DSE `DateRangeType` value. A range without `upper` bound is a single date
+/// (e.g. `2017-01-01` or `*`), otherwise it's a range (e.g. `[2017-01 TO *]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub lower: DateRangeBound,
+    pub upper: Option<DateRangeBound>,
+}
+
+impl DateRange {
+    pub fn single(bound: DateRangeBound) -> DateRange {
+        DateRange {
+            lower: bound,
+            upper: None,
+        }
+    }
+
+    pub fn range(lower: DateRangeBound, upper: DateRangeBound) -> DateRange {
+        DateRange {
+            lower,
+            upper: Some(upper),
+        }
+    }
+}
+
+// Decodes DSE `DateRangeType` data into Rust's `Result<DateRange, io::Error>`.
+pub fn decode_date_range(bytes: &[u8]) -> Result<DateRange, io::Error> {
+    let mut cursor = Cursor::new(bytes);
+    let range_type = cursor.read_u8()?;
+    let range = match range_type {
+        SINGLE_DATE => DateRange::single(DateRangeBound::from_cursor(&mut cursor)?),
+        CLOSED_RANGE => {
+            let lower = DateRangeBound::from_cursor(&mut cursor)?;
+            let upper = DateRangeBound::from_cursor(&mut cursor)?;
+            DateRange::range(lower, upper)
+        }
+        OPEN_RANGE_HIGH => DateRange::range(
+            DateRangeBound::from_cursor(&mut cursor)?,
+            DateRangeBound::Unbounded,
+        ),
+        OPEN_RANGE_LOW => DateRange::range(
+            DateRangeBound::Unbounded,
+            DateRangeBound::from_cursor(&mut cursor)?,
+        ),
+        BOTH_OPEN_RANGE => DateRange::range(DateRangeBound::Unbounded, DateRangeBound::Unbounded),
+        SINGLE_DATE_OPEN => DateRange::single(DateRangeBound::Unbounded),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected date range type {}", range_type),
+            ))
+        }
+    };
+
+    Ok(range)
+}
+
+impl From<DateRange> for Bytes {
+    fn from(range: DateRange) -> Bytes {
+        use self::DateRangeBound::*;
+
+        let range_type = match (range.lower, range.upper) {
+            (Bounded { .. }, None) => SINGLE_DATE,
+            (Unbounded, None) => SINGLE_DATE_OPEN,
+            (Bounded { .. }, Some(Bounded { .. })) => CLOSED_RANGE,
+            (Bounded { .. }, Some(Unbounded)) => OPEN_RANGE_HIGH,
+            (Unbounded, Some(Bounded { .. })) => OPEN_RANGE_LOW,
+            (Unbounded, Some(Unbounded)) => BOTH_OPEN_RANGE,
+        };
+
+        let mut bytes = Vec::with_capacity(19);
+        bytes.push(range_type);
+        range.lower.write(&mut bytes);
+        if let Some(upper) = range.upper {
+            upper.write(&mut bytes);
+        }
+
+        Bytes::new(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::value::Value;
+
+    fn round_trip(range: DateRange) -> DateRange {
+        let value = Value::new_normal(range);
+        decode_date_range(value.body.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn decode_single_date_test() {
+        let bytes = &[0x00, 0, 0, 0, 0, 0, 0, 0x03, 0xE8, 0x02];
+        assert_eq!(
+            decode_date_range(bytes).unwrap(),
+            DateRange::single(DateRangeBound::new(1000, DateRangePrecision::Day))
+        );
+        assert_eq!(
+            decode_date_range(&[0x05]).unwrap(),
+            DateRange::single(DateRangeBound::Unbounded)
+        );
+    }
+
+    #[test]
+    fn date_range_round_trip_test() {
+        let lower = DateRangeBound::new(1_500_000_000_000, DateRangePrecision::Month);
+        let upper = DateRangeBound::new(1_600_000_000_000, DateRangePrecision::Millisecond);
+        let ranges = vec![
+            DateRange::single(lower),
+            DateRange::single(DateRangeBound::Unbounded),
+            DateRange::range(lower, upper),
+            DateRange::range(lower, DateRangeBound::Unbounded),
+            DateRange::range(DateRangeBound::Unbounded, upper),
+            DateRange::range(DateRangeBound::Unbounded, DateRangeBound::Unbounded),
+        ];
+
+        for range in ranges {
+            assert_eq!(round_trip(range), range);
+        }
+    }
+
+    #[test]
+    fn decode_date_range_invalid_test() {
+        assert!(decode_date_range(&[0x06]).is_err());
+        assert!(decode_date_range(&[0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x07]).is_err());
+        assert!(decode_date_range(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! `serde::Deserializer` implementation over rows and CQL values, so any type that
+//! implements `serde::Deserialize` could be populated from a result row. Columns are
+//! matched with struct fields by names and UDTs could be deserialized into nested structs.
+use std::fmt::Display;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{Error, Result};
+use crate::types::cql_value::CqlValue;
+use crate::types::rows::Row;
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
+/// Deserializes a row into any type which implements `serde::Deserialize`.
+pub fn from_row<T: DeserializeOwned>(row: Row) -> Result<T> {
+    T::deserialize(row)
+}
+
+impl Row {
+    /// Decodes all the columns of a row into pairs of column name and `CqlValue`.
+    fn into_cql_fields(self) -> Result<Vec<(String, CqlValue)>> {
+        self.columns()
+            .map(|(col_spec, cell)| {
+                CqlValue::decode(cell, &col_spec.col_type)
+                    .map(|value| (col_spec.name.as_plain(), value))
+            })
+            .collect()
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Row {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        CqlValue::Udt(self.into_cql_fields()?).deserialize_any(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> de::Deserializer<'de> for CqlValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            CqlValue::Null => visitor.visit_unit(),
+            CqlValue::Custom(bytes) => visitor.visit_byte_buf(bytes),
+            CqlValue::Blob(blob) => visitor.visit_byte_buf(blob.into_vec()),
+            CqlValue::Ascii(s) | CqlValue::Varchar(s) => visitor.visit_string(s),
+            CqlValue::Varint(v) => match v.to_i64() {
+                Some(i) => visitor.visit_i64(i),
+                None => visitor.visit_string(v.to_string()),
+            },
+            CqlValue::Bigint(i)
+            | CqlValue::Counter(i)
+            | CqlValue::Timestamp(i)
+            | CqlValue::Time(i) => visitor.visit_i64(i),
+            CqlValue::Int(i) | CqlValue::Date(i) => visitor.visit_i32(i),
+            CqlValue::Smallint(i) => visitor.visit_i16(i),
+            CqlValue::Tinyint(i) => visitor.visit_i8(i),
+            CqlValue::Boolean(b) => visitor.visit_bool(b),
+            CqlValue::Decimal(d) => visitor.visit_f64(d.as_plain()),
+            CqlValue::Double(f) => visitor.visit_f64(f),
+            CqlValue::Float(f) => visitor.visit_f32(f),
+            CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => {
+                visitor.visit_string(uuid.to_hyphenated().to_string())
+            }
+            CqlValue::Inet(ip) => visitor.visit_string(ip.to_string()),
+            CqlValue::List(items) | CqlValue::Set(items) | CqlValue::Tuple(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            CqlValue::Map(map) => {
+                let mut map = MapDeserializer::new(map.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            CqlValue::Udt(fields) => {
+                let mut map = MapDeserializer::new(fields.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            CqlValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            CqlValue::Ascii(s) | CqlValue::Varchar(s) => visitor.visit_enum(s.into_deserializer()),
+            value => Err(de::Error::custom(format!(
+                "Cannot deserialize {:?} into enum, only text values are supported",
+                value
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for CqlValue {
+    type Deserializer = CqlValue;
+
+    fn into_deserializer(self) -> CqlValue {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::frame::frame_result::{
+        BodyResResultRows, CUdt, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
+    };
+    use crate::types::value::Value;
+    use crate::types::{CBytes, CString};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Address {
+        street: String,
+        number: Option<i32>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: i64,
+        name: Option<String>,
+        tags: Vec<String>,
+        scores: HashMap<String, i32>,
+        address: Address,
+    }
+
+    fn cell(value: CqlValue) -> CBytes {
+        CBytes::new_shared(Value::from(value).body)
+    }
+
+    #[test]
+    fn deserialize_row() {
+        let address_type = ColTypeOption {
+            id: ColType::Udt,
+            value: Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: CString::new("ks".into()),
+                udt_name: CString::new("address".into()),
+                descriptions: vec![
+                    (
+                        CString::new("street".into()),
+                        ColTypeOption::simple(ColType::Varchar),
+                    ),
+                    (
+                        CString::new("number".into()),
+                        ColTypeOption::simple(ColType::Int),
+                    ),
+                ],
+            })),
+        };
+        let metadata = RowsMetadata::for_test(vec![
+            ("id", ColTypeOption::simple(ColType::Bigint)),
+            ("name", ColTypeOption::simple(ColType::Varchar)),
+            (
+                "tags",
+                ColTypeOption {
+                    id: ColType::Set,
+                    value: Some(ColTypeOptionValue::CSet(Box::new(ColTypeOption::simple(
+                        ColType::Varchar,
+                    )))),
+                },
+            ),
+            (
+                "scores",
+                ColTypeOption {
+                    id: ColType::Map,
+                    value: Some(ColTypeOptionValue::CMap((
+                        Box::new(ColTypeOption::simple(ColType::Varchar)),
+                        Box::new(ColTypeOption::simple(ColType::Int)),
+                    ))),
+                },
+            ),
+            ("address", address_type),
+        ]);
+        let row = vec![
+            cell(CqlValue::Bigint(1)),
+            CBytes::new_empty(),
+            cell(CqlValue::Set(vec![CqlValue::Varchar("admin".into())])),
+            cell(CqlValue::Map(vec![(
+                CqlValue::Varchar("math".into()),
+                CqlValue::Int(5),
+            )])),
+            cell(CqlValue::Udt(vec![
+                ("street".into(), CqlValue::Varchar("Main".into())),
+                ("number".into(), CqlValue::Null),
+            ])),
+        ];
+        let rows = Row::from_frame_body(BodyResResultRows {
+            metadata,
+            rows_count: 1,
+            rows_content: vec![row],
+        });
+
+        let user: User = from_row(rows[0].clone()).unwrap();
+        let mut scores = HashMap::new();
+        scores.insert("math".to_string(), 5);
+        assert_eq!(
+            user,
+            User {
+                id: 1,
+                name: None,
+                tags: vec!["admin".into()],
+                scores,
+                address: Address {
+                    street: "Main".into(),
+                    number: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_type_mismatch() {
+        let value = CqlValue::Varchar("not a number".into());
+        assert!(i32::deserialize(value).is_err());
+    }
+}
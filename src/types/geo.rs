@@ -0,0 +1,276 @@
+//! DSE geospatial types which are sent by a server as custom types and
+//! encoded in [WKB](https://en.wikipedia.org/wiki/Well-known_text_representation_of_geometry)
+//! format.
+use std::io;
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::types::value::Bytes;
+
+/// Class name of DSE `PointType` custom type.
+pub const POINT_TYPE: &str = "org.apache.cassandra.db.marshal.PointType";
+/// Class name of DSE `LineStringType` custom type.
+pub const LINE_STRING_TYPE: &str = "org.apache.cassandra.db.marshal.LineStringType";
+/// Class name of DSE `PolygonType` custom type.
+pub const POLYGON_TYPE: &str = "org.apache.cassandra.db.marshal.PolygonType";
+
+const WKB_BIG_ENDIAN: u8 = 0x00;
+const WKB_LITTLE_ENDIAN: u8 = 0x01;
+
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+
+/// DSE `PointType` value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+}
+
+/// DSE `LineStringType` value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineString {
+    pub points: Vec<Point>,
+}
+
+impl LineString {
+    pub fn new(points: Vec<Point>) -> LineString {
+        LineString { points }
+    }
+}
+
+/// DSE `PolygonType` value. The first ring is an exterior one,
+/// the rest are interior rings (holes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub rings: Vec<Vec<Point>>,
+}
+
+impl Polygon {
+    pub fn new(rings: Vec<Vec<Point>>) -> Polygon {
+        Polygon { rings }
+    }
+}
+
+// Decodes DSE `PointType` data (WKB bytes) into Rust's `Result<Point, io::Error>`.
+pub fn decode_point(bytes: &[u8]) -> Result<Point, io::Error> {
+    let mut cursor = Cursor::new(bytes);
+    match read_wkb_header(&mut cursor, WKB_POINT)? {
+        WKB_BIG_ENDIAN => read_point::<BigEndian>(&mut cursor),
+        _ => read_point::<LittleEndian>(&mut cursor),
+    }
+}
+
+// Decodes DSE `LineStringType` data (WKB bytes) into Rust's `Result<LineString, io::Error>`.
+pub fn decode_line_string(bytes: &[u8]) -> Result<LineString, io::Error> {
+    let mut cursor = Cursor::new(bytes);
+    let points = match read_wkb_header(&mut cursor, WKB_LINE_STRING)? {
+        WKB_BIG_ENDIAN => read_points::<BigEndian>(&mut cursor)?,
+        _ => read_points::<LittleEndian>(&mut cursor)?,
+    };
+
+    Ok(LineString::new(points))
+}
+
+// Decodes DSE `PolygonType` data (WKB bytes) into Rust's `Result<Polygon, io::Error>`.
+pub fn decode_polygon(bytes: &[u8]) -> Result<Polygon, io::Error> {
+    let mut cursor = Cursor::new(bytes);
+    let rings = match read_wkb_header(&mut cursor, WKB_POLYGON)? {
+        WKB_BIG_ENDIAN => read_rings::<BigEndian>(&mut cursor)?,
+        _ => read_rings::<LittleEndian>(&mut cursor)?,
+    };
+
+    Ok(Polygon::new(rings))
+}
+
+/// Reads WKB byte order and geometry type. Returns the byte order.
+fn read_wkb_header(cursor: &mut Cursor<&[u8]>, expected_type: u32) -> Result<u8, io::Error> {
+    let byte_order = cursor.read_u8()?;
+    let geometry_type = match byte_order {
+        WKB_BIG_ENDIAN => cursor.read_u32::<BigEndian>()?,
+        WKB_LITTLE_ENDIAN => cursor.read_u32::<LittleEndian>()?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected WKB byte order {}", byte_order),
+            ))
+        }
+    };
+
+    if geometry_type != expected_type {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Unexpected WKB geometry type {}, {} is expected",
+                geometry_type, expected_type
+            ),
+        ));
+    }
+
+    Ok(byte_order)
+}
+
+fn read_point<B: ByteOrder>(cursor: &mut Cursor<&[u8]>) -> Result<Point, io::Error> {
+    let x = cursor.read_f64::<B>()?;
+    let y = cursor.read_f64::<B>()?;
+
+    Ok(Point::new(x, y))
+}
+
+fn read_points<B: ByteOrder>(cursor: &mut Cursor<&[u8]>) -> Result<Vec<Point>, io::Error> {
+    let n = read_wkb_len::<B>(cursor, 16)?;
+    (0..n).map(|_| read_point::<B>(cursor)).collect()
+}
+
+fn read_rings<B: ByteOrder>(cursor: &mut Cursor<&[u8]>) -> Result<Vec<Vec<Point>>, io::Error> {
+    let n = read_wkb_len::<B>(cursor, 4)?;
+    (0..n).map(|_| read_points::<B>(cursor)).collect()
+}
+
+/// Reads number of WKB elements checking it against remaining bytes.
+fn read_wkb_len<B: ByteOrder>(
+    cursor: &mut Cursor<&[u8]>,
+    min_element_len: u64,
+) -> Result<u32, io::Error> {
+    let n = cursor.read_u32::<B>()?;
+    let remaining = cursor.get_ref().len() as u64 - cursor.position();
+    if u64::from(n) * min_element_len > remaining {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("WKB data is too short to contain {} elements", n),
+        ));
+    }
+
+    Ok(n)
+}
+
+// Geo values are encoded using little endian byte order as DSE drivers do.
+
+fn write_wkb_header(bytes: &mut Vec<u8>, geometry_type: u32) {
+    bytes.push(WKB_LITTLE_ENDIAN);
+    // writing into a vector does not fail
+    bytes.write_u32::<LittleEndian>(geometry_type).unwrap();
+}
+
+fn write_points(bytes: &mut Vec<u8>, points: &[Point]) {
+    bytes.write_u32::<LittleEndian>(points.len() as u32).unwrap();
+    for point in points {
+        bytes.write_f64::<LittleEndian>(point.x).unwrap();
+        bytes.write_f64::<LittleEndian>(point.y).unwrap();
+    }
+}
+
+impl From<Point> for Bytes {
+    fn from(point: Point) -> Bytes {
+        let mut bytes = Vec::with_capacity(21);
+        write_wkb_header(&mut bytes, WKB_POINT);
+        bytes.write_f64::<LittleEndian>(point.x).unwrap();
+        bytes.write_f64::<LittleEndian>(point.y).unwrap();
+        Bytes::new(bytes)
+    }
+}
+
+impl From<LineString> for Bytes {
+    fn from(line_string: LineString) -> Bytes {
+        let mut bytes = vec![];
+        write_wkb_header(&mut bytes, WKB_LINE_STRING);
+        write_points(&mut bytes, &line_string.points);
+        Bytes::new(bytes)
+    }
+}
+
+impl From<Polygon> for Bytes {
+    fn from(polygon: Polygon) -> Bytes {
+        let mut bytes = vec![];
+        write_wkb_header(&mut bytes, WKB_POLYGON);
+        bytes
+            .write_u32::<LittleEndian>(polygon.rings.len() as u32)
+            .unwrap();
+        for ring in &polygon.rings {
+            write_points(&mut bytes, ring);
+        }
+        Bytes::new(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
+    use crate::types::value::Value;
+    use crate::types::{CBytes, CString};
+
+    #[test]
+    fn decode_point_test() {
+        let le = &[1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xF0, 0x3F, 0, 0, 0, 0, 0, 0, 0, 0x40];
+        assert_eq!(decode_point(le).unwrap(), Point::new(1.0, 2.0));
+
+        let be = &[0, 0, 0, 0, 1, 0x3F, 0xF0, 0, 0, 0, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(decode_point(be).unwrap(), Point::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn decode_point_wrong_type_test() {
+        let line_string: Bytes = LineString::new(vec![]).into();
+        let value = Value::new_normal(line_string);
+        assert!(decode_point(value.body.as_ref()).is_err());
+    }
+
+    #[test]
+    fn line_string_round_trip_test() {
+        let line_string = LineString::new(vec![Point::new(1.0, 2.0), Point::new(3.0, 4.0)]);
+        let value = Value::new_normal(line_string.clone());
+        assert_eq!(decode_line_string(value.body.as_ref()).unwrap(), line_string);
+    }
+
+    #[test]
+    fn polygon_round_trip_test() {
+        let polygon = Polygon::new(vec![
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 0.0),
+                Point::new(10.0, 10.0),
+                Point::new(0.0, 0.0),
+            ],
+            vec![
+                Point::new(1.0, 1.0),
+                Point::new(2.0, 1.0),
+                Point::new(2.0, 2.0),
+                Point::new(1.0, 1.0),
+            ],
+        ]);
+        let value = Value::new_normal(polygon.clone());
+        assert_eq!(decode_polygon(value.body.as_ref()).unwrap(), polygon);
+    }
+
+    #[test]
+    fn decode_polygon_too_many_rings_test() {
+        let bytes = &[1, 3, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(decode_polygon(bytes).is_err());
+    }
+
+    #[test]
+    fn as_rust_point_test() {
+        let custom_type = |class_name: &str| ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString(CString::new(class_name.into()))),
+        };
+        let point_type = custom_type(POINT_TYPE);
+        let data = CBytes::new_shared(Value::new_normal(Point::new(1.0, 2.0)).body);
+        let point: Result<Option<Point>, Error> = as_rust_type!(point_type, data, Point);
+        assert_eq!(point.unwrap(), Some(Point::new(1.0, 2.0)));
+
+        let polygon_type = custom_type(POLYGON_TYPE);
+        let point: Result<Option<Point>, Error> = as_rust_type!(polygon_type, data, Point);
+        assert!(point.is_err());
+    }
+}
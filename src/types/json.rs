@@ -0,0 +1,191 @@
+//! Conversion of query results into `serde_json::Value` which could be used
+//! for building HTTP bridges on top of the protocol.
+use serde_json::{Map as JsonMap, Number, Value as JsonValue};
+use time::{at_utc, Timespec};
+
+use crate::error::Result;
+use crate::frame::frame_result::BodyResResultRows;
+use crate::types::cql_value::CqlValue;
+
+const DATE_EPOCH_OFFSET: i64 = 1 << 31;
+const SECONDS_PER_DAY: i64 = 86_400;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Converts rows into a JSON array of objects keyed by column names.
+pub fn rows_to_json(rows: &BodyResResultRows) -> Result<JsonValue> {
+    let col_specs = &rows.metadata.col_specs;
+    rows.rows_content
+        .iter()
+        .map(|row| {
+            let mut object = JsonMap::with_capacity(col_specs.len());
+            for (col_spec, cell) in col_specs.iter().zip(row.iter()) {
+                let value = CqlValue::decode(cell, &col_spec.col_type)?;
+                object.insert(col_spec.name.as_plain(), value.to_json());
+            }
+            Ok(JsonValue::Object(object))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(JsonValue::Array)
+}
+
+impl CqlValue {
+    /// Converts CQL value into JSON. Blobs are represented as `0x` prefixed hex strings,
+    /// timestamps, dates and times as ISO 8601 strings, UUIDs and inet addresses
+    /// as strings, maps as objects with stringified keys, UDTs as objects and
+    /// tuples, lists and sets as arrays. Non-finite floats are converted into `null`.
+    pub fn to_json(&self) -> JsonValue {
+        match *self {
+            CqlValue::Null => JsonValue::Null,
+            CqlValue::Custom(ref bytes) => JsonValue::String(to_hex(bytes)),
+            CqlValue::Blob(ref blob) => JsonValue::String(to_hex(blob.as_slice())),
+            CqlValue::Ascii(ref s) | CqlValue::Varchar(ref s) => JsonValue::String(s.clone()),
+            CqlValue::Bigint(i) | CqlValue::Counter(i) => i.into(),
+            CqlValue::Varint(ref v) => match v.to_i64() {
+                Some(i) => i.into(),
+                None => JsonValue::String(v.to_string()),
+            },
+            CqlValue::Int(i) => i.into(),
+            CqlValue::Smallint(i) => i.into(),
+            CqlValue::Tinyint(i) => i.into(),
+            CqlValue::Boolean(b) => JsonValue::Bool(b),
+            CqlValue::Decimal(ref d) => float_to_json(d.as_plain()),
+            CqlValue::Double(f) => float_to_json(f),
+            CqlValue::Float(f) => float_to_json(f64::from(f)),
+            CqlValue::Timestamp(ms) => JsonValue::String(format_timestamp(ms)),
+            CqlValue::Date(days) => JsonValue::String(format_date(days)),
+            CqlValue::Time(nanos) => JsonValue::String(format_time(nanos)),
+            CqlValue::Uuid(ref uuid) | CqlValue::Timeuuid(ref uuid) => {
+                JsonValue::String(uuid.to_hyphenated().to_string())
+            }
+            CqlValue::Inet(ref ip) => JsonValue::String(ip.to_string()),
+            CqlValue::List(ref items) | CqlValue::Set(ref items) | CqlValue::Tuple(ref items) => {
+                JsonValue::Array(items.iter().map(CqlValue::to_json).collect())
+            }
+            CqlValue::Map(ref map) => JsonValue::Object(
+                map.iter()
+                    .map(|(key, val)| {
+                        let key = match key.to_json() {
+                            JsonValue::String(s) => s,
+                            key => key.to_string(),
+                        };
+                        (key, val.to_json())
+                    })
+                    .collect(),
+            ),
+            CqlValue::Udt(ref fields) => JsonValue::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), field.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn float_to_json(f: f64) -> JsonValue {
+    Number::from_f64(f)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+fn format_timestamp(ms: i64) -> String {
+    let secs = ms.div_euclid(1_000);
+    let millis = ms.rem_euclid(1_000);
+    let tm = at_utc(Timespec::new(secs, 0));
+    format!("{}.{:03}Z", tm.strftime("%Y-%m-%dT%H:%M:%S").unwrap(), millis)
+}
+
+fn format_date(days: i32) -> String {
+    // `date` is an unsigned number of days with the epoch in the middle of the range
+    let days = i64::from(days as u32) - DATE_EPOCH_OFFSET;
+    let tm = at_utc(Timespec::new(days * SECONDS_PER_DAY, 0));
+    tm.strftime("%Y-%m-%d").unwrap().to_string()
+}
+
+fn format_time(nanos: i64) -> String {
+    let secs = nanos / NANOS_PER_SECOND;
+    format!(
+        "{:02}:{:02}:{:02}.{:09}",
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60,
+        nanos % NANOS_PER_SECOND
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::{ColType, ColTypeOption, RowsMetadata};
+    use crate::types::blob::Blob;
+    use crate::types::CBytes;
+    use uuid::Uuid;
+
+    #[test]
+    fn cql_value_to_json() {
+        assert_eq!(CqlValue::Int(1).to_json(), json_value("1"));
+        assert_eq!(CqlValue::Double(f64::NAN).to_json(), JsonValue::Null);
+        assert_eq!(CqlValue::Blob(Blob::new(vec![0, 255])).to_json(), json_value("\"0x00ff\""));
+        assert_eq!(
+            CqlValue::Timestamp(1_500_000_000_123).to_json(),
+            json_value("\"2017-07-14T02:40:00.123Z\"")
+        );
+        assert_eq!(
+            CqlValue::Date((DATE_EPOCH_OFFSET + 1) as u32 as i32).to_json(),
+            json_value("\"1970-01-02\"")
+        );
+        assert_eq!(
+            CqlValue::Time(3_723_000_000_004).to_json(),
+            json_value("\"01:02:03.000000004\"")
+        );
+        assert_eq!(
+            CqlValue::Uuid(Uuid::nil()).to_json(),
+            json_value("\"00000000-0000-0000-0000-000000000000\"")
+        );
+        assert_eq!(
+            CqlValue::Map(vec![
+                (CqlValue::Int(1), CqlValue::List(vec![CqlValue::Boolean(true)])),
+                (CqlValue::Varchar("a".into()), CqlValue::Null),
+            ])
+            .to_json(),
+            json_value(r#"{"1": [true], "a": null}"#)
+        );
+        assert_eq!(
+            CqlValue::Udt(vec![("street".into(), CqlValue::Varchar("Main".into()))]).to_json(),
+            json_value(r#"{"street": "Main"}"#)
+        );
+    }
+
+    #[test]
+    fn rows_into_json() {
+        let rows = BodyResResultRows {
+            metadata: RowsMetadata::for_test(vec![
+                ("id", ColTypeOption::simple(ColType::Int)),
+                ("name", ColTypeOption::simple(ColType::Varchar)),
+            ]),
+            rows_count: 2,
+            rows_content: vec![
+                vec![CBytes::new(vec![0, 0, 0, 1]), CBytes::new(b"John".to_vec())],
+                vec![CBytes::new(vec![0, 0, 0, 2]), CBytes::new_empty()],
+            ],
+        };
+
+        assert_eq!(
+            rows_to_json(&rows).unwrap(),
+            json_value(r#"[{"id": 1, "name": "John"}, {"id": 2, "name": null}]"#)
+        );
+    }
+
+    fn json_value(s: &str) -> JsonValue {
+        serde_json::from_str(s).unwrap()
+    }
+}
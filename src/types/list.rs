@@ -1,9 +1,14 @@
-use crate::error::{Error, Result};
+use crate::error::{null_item_err, Error, Result};
 use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use std::net::IpAddr;
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+#[cfg(feature = "dse")]
+use crate::types::geo::*;
+#[cfg(feature = "dse")]
+use crate::types::date_range::*;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
@@ -26,13 +31,6 @@ impl List {
             data: data,
         }
     }
-
-    fn map<T, F>(&self, f: F) -> Vec<T>
-    where
-        F: FnMut(&CBytes) -> T,
-    {
-        self.data.iter().map(f).collect()
-    }
 }
 
 impl AsRust for List {}
@@ -53,3 +51,33 @@ list_as_rust!(Map);
 list_as_rust!(UDT);
 list_as_rust!(Tuple);
 list_as_rust!(Decimal);
+list_as_rust!(CqlValue);
+#[cfg(feature = "dse")]
+list_as_rust!(Point);
+#[cfg(feature = "dse")]
+list_as_rust!(LineString);
+#[cfg(feature = "dse")]
+list_as_rust!(Polygon);
+#[cfg(feature = "dse")]
+list_as_rust!(DateRange);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::to_int;
+
+    #[test]
+    fn list_with_null_item() {
+        let item_type = ColTypeOption { id: ColType::Int,
+                                        value: None };
+        let item_type = ColTypeOptionValue::CList(Box::new(item_type));
+        let metadata = ColTypeOption { id: ColType::List,
+                                       value: Some(item_type) };
+        let list = List::new(vec![CBytes::new(to_int(1)), CBytes::new_empty()], metadata);
+
+        let items: Vec<Option<i32>> = list.as_r_rust().unwrap();
+        assert_eq!(items, vec![Some(1), None]);
+        let items: Result<Option<Vec<i32>>> = list.as_rust();
+        assert!(items.is_err());
+    }
+}
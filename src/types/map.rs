@@ -3,7 +3,7 @@ use std::net::IpAddr;
 use time::Timespec;
 use uuid::Uuid;
 
-use crate::error::{Error, Result};
+use crate::error::{null_item_err, Error, Result};
 use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
 use crate::types::data_serialization_types::*;
@@ -233,3 +233,26 @@ map_as_rust!({ Tuple }, { Map });
 map_as_rust!({ Tuple }, { UDT });
 map_as_rust!({ Tuple }, { Tuple });
 map_as_rust!({ Tuple }, { Decimal });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::to_int;
+
+    #[test]
+    fn map_with_null_value() {
+        let type_option = |id| Box::new(ColTypeOption { id, value: None });
+        let types = (type_option(ColType::Varchar), type_option(ColType::Int));
+        let metadata = ColTypeOption { id: ColType::Map,
+                                       value: Some(ColTypeOptionValue::CMap(types)) };
+        let data = vec![(CBytes::new(b"a".to_vec()), CBytes::new(to_int(1))),
+                        (CBytes::new(b"b".to_vec()), CBytes::new_empty())];
+        let map = Map::new(data, metadata);
+
+        let values: HashMap<String, Option<i32>> = map.as_r_rust().unwrap();
+        assert_eq!(values["a"], Some(1));
+        assert_eq!(values["b"], None);
+        let values: Result<Option<HashMap<String, i32>>> = map.as_rust();
+        assert!(values.is_err());
+    }
+}
@@ -1,42 +1,73 @@
 /// Cassandra types
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::io::{Cursor, Read};
 use std::net::SocketAddr;
+use std::str;
 
 use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
-use crate::error::{column_is_empty_err, Error as CDRSError, Result as CDRSResult};
-use crate::frame::traits::{FromBytes, FromCursor, IntoBytes};
-use crate::types::data_serialization_types::decode_inet;
+use bytes::{BufMut, Bytes};
+use crate::error::{column_is_empty_err, null_item_err, Error as CDRSError, Result as CDRSResult};
+use crate::frame::traits::{FromBytes, FromCursor, IntoBytes, TryIntoBytes};
+use crate::types::data_serialization_types::{decode_inet, Utf8Policy};
+
+/// Maximal value of a `[short]` length or number of items, which is unsigned.
+pub const SHORT_MAX: usize = u16::MAX as usize;
+/// Maximal value of an `[int]` length, e.g. of `[bytes]` or `[long string]`.
+pub const INT_MAX: usize = i32::MAX as usize;
 
 pub const LONG_STR_LEN: usize = 4;
 pub const SHORT_LEN: usize = 2;
 pub const INT_LEN: usize = 4;
+pub const LONG_LEN: usize = 8;
 pub const UUID_LEN: usize = 16;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
 #[macro_use]
 pub mod blob;
+pub mod cql_type;
+pub mod cql_value;
 pub mod data_serialization_types;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "dse")]
+pub mod date_range;
 pub mod decimal;
 pub mod from_cdrs;
+#[cfg(feature = "dse")]
+pub mod geo;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod list;
 pub mod map;
 pub mod rows;
 pub mod tuple;
 pub mod udt;
 pub mod value;
+pub mod varint;
 
 pub mod prelude {
     pub use crate::error::{Error, Result};
     pub use crate::frame::{TryFromRow, TryFromUDT};
+    #[cfg(feature = "derive")]
+    pub use cassandra_proto_derive::TryFromRow;
     pub use super::blob::Blob;
+    pub use super::cql_value::CqlValue;
     pub use super::decimal::Decimal;
+    #[cfg(feature = "dse")]
+    pub use super::date_range::{DateRange, DateRangeBound, DateRangePrecision};
+    #[cfg(feature = "dse")]
+    pub use super::geo::{LineString, Point, Polygon};
     pub use super::list::List;
     pub use super::map::Map;
     pub use super::rows::Row;
     pub use super::tuple::Tuple;
     pub use super::udt::UDT;
-    pub use super::value::{Bytes, Value};
-    pub use super::AsRustType;
+    pub use super::value::{Bind, Bytes, Value};
+    pub use super::varint::Varint;
+    pub use super::{AsRustType, ByColumn};
 }
 
 /// Should be used to represent a single column as a Rust value.
@@ -45,7 +76,17 @@ pub trait AsRustType<T> {
 
     fn as_r_type(&self) -> CDRSResult<T> {
         self.as_rust_type()
-            .and_then(|op| op.ok_or(CDRSError::from("Value is null or non-set")))
+            .and_then(|op| op.ok_or_else(|| null_item_err("Value")))
+    }
+}
+
+/// NULL is converted into `None` for any type which a value could be converted into.
+impl<T, R> AsRustType<Option<R>> for T
+where
+    T: AsRustType<R>,
+{
+    fn as_rust_type(&self) -> CDRSResult<Option<Option<R>>> {
+        self.as_rust_type().map(Some)
     }
 }
 
@@ -62,7 +103,7 @@ pub trait AsRust {
         Self: AsRustType<T>,
     {
         self.as_rust()
-            .and_then(|op| op.ok_or("Value is null or non-set".into()))
+            .and_then(|op| op.ok_or_else(|| null_item_err("Value")))
     }
 }
 
@@ -76,6 +117,17 @@ pub trait IntoRustByName<R> {
     }
 }
 
+/// NULL column is converted into `None`, so `get_r_by_name::<Option<R>>` fails
+/// only if a column doesn't exist or cannot be converted into `R`.
+impl<T, R> IntoRustByName<Option<R>> for T
+where
+    T: IntoRustByName<R>,
+{
+    fn get_by_name(&self, name: &str) -> CDRSResult<Option<Option<R>>> {
+        self.get_by_name(name).map(Some)
+    }
+}
+
 pub trait ByName {
     fn by_name<R>(&self, name: &str) -> CDRSResult<Option<R>>
     where
@@ -103,6 +155,16 @@ pub trait IntoRustByIndex<R> {
     }
 }
 
+/// NULL column is converted into `None`, see `IntoRustByName<Option<R>>`.
+impl<T, R> IntoRustByIndex<Option<R>> for T
+where
+    T: IntoRustByIndex<R>,
+{
+    fn get_by_index(&self, index: usize) -> CDRSResult<Option<Option<R>>> {
+        self.get_by_index(index).map(Some)
+    }
+}
+
 pub trait ByIndex {
     fn by_index<R>(&self, index: usize) -> CDRSResult<Option<R>>
     where
@@ -120,6 +182,20 @@ pub trait ByIndex {
     }
 }
 
+/// Should be used to return a single column of all rows as Rust values by its name.
+pub trait IntoRustColumn<R> {
+    fn get_column(&self, name: &str) -> CDRSResult<Vec<Option<R>>>;
+}
+
+pub trait ByColumn {
+    fn column<R>(&self, name: &str) -> CDRSResult<Vec<Option<R>>>
+    where
+        Self: IntoRustColumn<R>,
+    {
+        self.get_column(name)
+    }
+}
+
 /// Tries to converts u64 numerical value into array of n bytes.
 pub fn try_to_n_bytes(int: u64, n: usize) -> io::Result<Vec<u8>> {
     let mut bytes = vec![];
@@ -172,6 +248,12 @@ pub fn try_u16_from_bytes(bytes: &[u8]) -> Result<u16, io::Error> {
 /// Tries to decode bytes array into `i64`.
 pub fn try_i_from_bytes(bytes: &[u8]) -> Result<i64, io::Error> {
     let l = bytes.len();
+    if l == 0 || l > LONG_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("cannot decode {} bytes into i64", l),
+        ));
+    }
     let mut c = Cursor::new(bytes);
     c.read_int::<BigEndian>(l)
 }
@@ -227,6 +309,15 @@ pub fn from_u16_bytes(bytes: &[u8]) -> u16 {
     try_u16_from_bytes(bytes).unwrap()
 }
 
+/// Converts byte-array into i16
+///
+/// # Panics
+///
+/// It panics if given bytes could not be converted into `i16`
+pub fn from_i16_bytes(bytes: &[u8]) -> i16 {
+    try_i16_from_bytes(bytes).unwrap()
+}
+
 /// Converts number i16 into Cassandra's [short].
 ///
 /// # Panics
@@ -365,29 +456,38 @@ pub fn to_float_big(f: f64) -> Vec<u8> {
     bytes
 }
 
-#[derive(Debug, Clone)]
+/// Cassandra [string]. Strings decoded by `from_cursor_shared` reference a buffer
+/// they are decoded from instead of being copied.
+#[derive(Clone)]
 pub struct CString {
-    string: String,
+    // always valid UTF-8
+    string: Bytes,
 }
 
 impl CString {
     pub fn new(string: String) -> CString {
-        CString { string: string }
+        CString { string: Bytes::from(string) }
     }
 
     /// Converts internal value into pointer of `str`.
-    pub fn as_str<'a>(&'a self) -> &'a str {
-        self.string.as_str()
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.string).expect("CString is valid UTF-8")
     }
 
     /// Converts internal value into a plain `String`.
     pub fn into_plain(self) -> String {
-        self.string
+        String::from_utf8(Vec::from(self.string)).expect("CString is valid UTF-8")
     }
 
     /// Represents internal value as a `String`.
     pub fn as_plain(&self) -> String {
-        self.string.clone()
+        self.as_str().to_string()
+    }
+}
+
+impl fmt::Debug for CString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CString").field("string", &self.as_str()).finish()
     }
 }
 
@@ -396,25 +496,66 @@ impl CString {
 impl IntoBytes for CString {
     /// Converts into Cassandra byte representation of [string]
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = vec![];
-        let l = self.string.len() as i16;
-        v.extend_from_slice(to_short(l).as_slice());
-        v.extend_from_slice(self.string.as_bytes());
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
         v
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        put_string(buf, self.as_str());
+    }
+
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN + self.string.len()
+    }
+}
+
+impl TryIntoBytes for CString {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.string.len(), SHORT_MAX)
+    }
 }
 
 impl FromCursor for CString {
     /// from_cursor gets Cursor who's position is set such that it should be a start of a [string].
     /// It reads required number of bytes and returns a String
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> CDRSResult<CString> {
-        let len_bytes = cursor_next_value(&mut cursor, SHORT_LEN as u64)?;
-        let len: u64 = try_from_bytes(len_bytes.as_slice())?;
-        let body_bytes = cursor_next_value(&mut cursor, len)?;
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CString> {
+        CString::from_cursor_with_policy(cursor, Utf8Policy::Strict)
+    }
+}
 
-        String::from_utf8(body_bytes)
-            .map_err(Into::into)
-            .map(CString::new)
+impl CString {
+    /// The same as `from_cursor` but invalid UTF-8 is handled in accordance to a policy.
+    pub fn from_cursor_with_policy(cursor: &mut Cursor<&[u8]>,
+                                   policy: Utf8Policy)
+                                   -> CDRSResult<CString> {
+        CString::decode(cursor, policy, None)
+    }
+
+    /// Decodes [string] the same way `from_cursor` does, however if the cursor reads
+    /// from `buf` the string references `buf` instead of being copied.
+    pub fn from_cursor_shared(cursor: &mut Cursor<&[u8]>, buf: &Bytes) -> CDRSResult<CString> {
+        CString::decode(cursor, Utf8Policy::Strict, Some(buf))
+    }
+
+    fn decode(cursor: &mut Cursor<&[u8]>,
+              policy: Utf8Policy,
+              buf: Option<&Bytes>)
+              -> CDRSResult<CString> {
+        let len_bytes = cursor_next_value(cursor, SHORT_LEN as u64)?;
+        let len: u64 = try_from_bytes(len_bytes.as_slice())?;
+        let len = check_len(cursor, len as i64)?;
+        let start = cursor.position() as usize;
+        cursor.set_position((start + len) as u64);
+        let value = &cursor.get_ref()[start..start + len];
+
+        let string = match (str::from_utf8(value), buf) {
+            (Ok(_), Some(buf)) => share_or_copy(value, buf),
+            (Ok(_), None) => Bytes::copy_from_slice(value),
+            // an error is built from a copy since only `String` keeps invalid bytes
+            (Err(_), _) => Bytes::from(policy.decode(value.to_vec())?),
+        };
+        Ok(CString { string })
     }
 }
 
@@ -444,23 +585,45 @@ impl CStringLong {
 impl IntoBytes for CStringLong {
     /// Converts into Cassandra byte representation of [string]
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = vec![];
-        let l = self.string.len() as i32;
-        v.extend_from_slice(to_int(l).as_slice());
-        v.extend_from_slice(self.string.as_bytes());
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
         v
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_i32(self.string.len() as i32);
+        buf.put_slice(self.string.as_bytes());
+    }
+
+    fn serialized_len(&self) -> usize {
+        INT_LEN + self.string.len()
+    }
+}
+
+impl TryIntoBytes for CStringLong {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.string.len(), INT_MAX)
+    }
 }
 
 impl FromCursor for CStringLong {
     /// from_cursor gets Cursor who's position is set such that it should be a start of a [string].
     /// It reads required number of bytes and returns a String
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> CDRSResult<CStringLong> {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CStringLong> {
+        CStringLong::from_cursor_with_policy(cursor, Utf8Policy::Strict)
+    }
+}
+
+impl CStringLong {
+    /// The same as `from_cursor` but invalid UTF-8 is handled in accordance to a policy.
+    pub fn from_cursor_with_policy(mut cursor: &mut Cursor<&[u8]>,
+                                   policy: Utf8Policy)
+                                   -> CDRSResult<CStringLong> {
         let len_bytes = cursor_next_value(&mut cursor, INT_LEN as u64)?;
         let len: u64 = try_from_bytes(len_bytes.as_slice())?;
         let body_bytes = cursor_next_value(&mut cursor, len)?;
 
-        String::from_utf8(body_bytes)
+        policy.decode(body_bytes)
             .map_err(Into::into)
             .map(CStringLong::new)
     }
@@ -482,48 +645,161 @@ impl CStringList {
 
 impl IntoBytes for CStringList {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut bytes);
+        bytes
+    }
 
-        let l = to_short(self.list.len() as i16);
-        bytes.extend_from_slice(l.as_slice());
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_i16(self.list.len() as i16);
+        for cstring in &self.list {
+            cstring.serialize_into(buf);
+        }
+    }
 
-        bytes = self.list.iter().fold(bytes, |mut _bytes, cstring| {
-            _bytes.extend_from_slice(cstring.into_cbytes().as_slice());
-            _bytes
-        });
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN + self.list.iter().map(CString::serialized_len).sum::<usize>()
+    }
+}
 
-        bytes
+impl TryIntoBytes for CStringList {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.list.len(), SHORT_MAX)?;
+        self.list.iter().try_for_each(CString::check)
     }
 }
 
 impl FromCursor for CStringList {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> CDRSResult<CStringList> {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CStringList> {
+        CStringList::from_cursor_with_policy(cursor, Utf8Policy::Strict)
+    }
+}
+
+impl CStringList {
+    /// The same as `from_cursor` but invalid UTF-8 is handled in accordance to a policy.
+    pub fn from_cursor_with_policy(cursor: &mut Cursor<&[u8]>,
+                                   policy: Utf8Policy)
+                                   -> CDRSResult<CStringList> {
         // TODO: try to use slice instead
         let mut len_bytes = [0; SHORT_LEN];
         cursor.read_exact(&mut len_bytes)?;
-        let len = try_from_bytes(len_bytes.to_vec().as_slice())? as usize;
+        let len = try_from_bytes(len_bytes.to_vec().as_slice())?;
+        let len = check_count(cursor, len as i64, SHORT_LEN)?;
         let mut list = Vec::with_capacity(len);
         for _ in 0..len {
-            list.push(CString::from_cursor(&mut cursor)?);
+            list.push(CString::from_cursor_with_policy(cursor, policy)?);
         }
 
         Ok(CStringList { list: list })
     }
 }
 
+/// Cassandra [bytes map], e.g. a custom payload of a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CBytesMap {
+    pub map: HashMap<String, CBytes>,
+}
+
+impl CBytesMap {
+    pub fn new(map: HashMap<String, CBytes>) -> CBytesMap {
+        CBytesMap { map }
+    }
+
+    /// Converts the map into plain bytes. Null values are converted into empty ones.
+    pub fn into_plain(self) -> HashMap<String, Vec<u8>> {
+        self.map
+            .into_iter()
+            .map(|(key, value)| (key, value.into_plain().unwrap_or_default()))
+            .collect()
+    }
+}
+
+impl IntoBytes for CBytesMap {
+    fn into_cbytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut bytes);
+        bytes
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        buf.put_i16(self.map.len() as i16);
+        for (key, value) in &self.map {
+            put_string(buf, key);
+            value.serialize_into(buf);
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        SHORT_LEN
+        + self.map
+              .iter()
+              .map(|(key, value)| SHORT_LEN + key.len() + value.serialized_len())
+              .sum::<usize>()
+    }
+}
+
+impl TryIntoBytes for CBytesMap {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.map.len(), SHORT_MAX)?;
+        self.map.iter().try_for_each(|(key, value)| {
+                           check_max_len(key.len(), SHORT_MAX)?;
+                           value.check()
+                       })
+    }
+}
+
+impl FromCursor for CBytesMap {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CBytesMap> {
+        let len = CIntShort::from_cursor(cursor)?;
+        // every entry has at least a length of a key and a length of a value
+        let len = check_count(cursor, i64::from(len), SHORT_LEN + INT_LEN)?;
+        let mut map = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let key = CString::from_cursor(cursor)?.into_plain();
+            map.insert(key, CBytes::from_cursor(cursor)?);
+        }
+
+        Ok(CBytesMap { map })
+    }
+}
+
 //
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-/// The structure that represents Cassandra byte type.
+/// The structure that represents Cassandra byte type. Bytes are reference counted,
+/// so values decoded with `from_cursor_shared` point into a frame body instead of copying it.
 pub struct CBytes {
-    bytes: Option<Vec<u8>>,
+    bytes: Option<Bytes>,
 }
 
 impl CBytes {
     pub fn new(bytes: Vec<u8>) -> CBytes {
+        CBytes { bytes: Some(bytes.into()) }
+    }
+
+    /// Creates Cassandra bytes which share given buffer.
+    pub fn new_shared(bytes: Bytes) -> CBytes {
         CBytes { bytes: Some(bytes) }
     }
 
+    /// Decodes [bytes] the same way `from_cursor` does, however if the cursor reads
+    /// from `buf` the value references `buf` instead of being copied.
+    pub fn from_cursor_shared(cursor: &mut Cursor<&[u8]>, buf: &Bytes) -> CDRSResult<CBytes> {
+        let len = CInt::from_cursor(cursor)?;
+        // null or not set value
+        if len < 0 {
+            return Ok(CBytes { bytes: None });
+        }
+
+        let len = check_len(cursor, i64::from(len))?;
+        let data: &[u8] = cursor.get_ref();
+        let start = cursor.position() as usize;
+        let end = start + len;
+        cursor.set_position(end as u64);
+
+        Ok(CBytes { bytes: Some(share_or_copy(&data[start..end], buf)) })
+    }
+
     /// Creates Cassandra bytes that represent empty or null value
     pub fn new_empty() -> CBytes {
         CBytes { bytes: None }
@@ -531,19 +807,20 @@ impl CBytes {
 
     /// Converts `CBytes` into a plain array of bytes
     pub fn into_plain(self) -> Option<Vec<u8>> {
+        self.bytes.map(Vec::from)
+    }
+
+    /// Converts `CBytes` into reference counted bytes without copying.
+    pub fn into_bytes(self) -> Option<Bytes> {
         self.bytes
     }
 
-    // TODO: try to replace usage of `as_plain` by `as_slice`
+    /// Copies the value into a plain array of bytes. Prefer `as_slice` which doesn't copy.
     pub fn as_plain(&self) -> Option<Vec<u8>> {
-        self.bytes.clone()
+        self.bytes.as_ref().map(|bytes| bytes.to_vec())
     }
     pub fn as_slice(&self) -> Option<&[u8]> {
-        match self.bytes {
-            Some(ref v) => Some(v.as_slice()),
-            None => None,
-        }
-        // self.bytes.map(|v| v.as_slice())
+        self.bytes.as_deref()
     }
     pub fn is_empty(&self) -> bool {
         self.bytes.is_some()
@@ -567,17 +844,40 @@ impl FromCursor for CBytes {
 // Use extended Rust Vec<u8> as Cassandra [bytes]
 impl IntoBytes for CBytes {
     fn into_cbytes(&self) -> Vec<u8> {
-        match self.bytes {
-            Some(ref b) => {
-                let mut v: Vec<u8> = vec![];
-                let l = b.len() as i32;
-                v.extend_from_slice(to_int(l).as_slice());
-                v.extend_from_slice(b.as_slice());
-                v
-            }
-            None => vec![],
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
+        v
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        if let Some(ref b) = self.bytes {
+            buf.put_i32(b.len() as i32);
+            buf.put_slice(b);
         }
     }
+
+    fn serialized_len(&self) -> usize {
+        self.bytes.as_ref().map_or(0, |b| INT_LEN + b.len())
+    }
+}
+
+impl TryIntoBytes for CBytes {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.bytes.as_ref().map_or(0, Bytes::len), INT_MAX)
+    }
+}
+
+/// References `value` in `buf` if it's a part of the buffer and copies it otherwise.
+fn share_or_copy(value: &[u8], buf: &Bytes) -> Bytes {
+    let buf_start = buf.as_ptr() as usize;
+    let value_start = value.as_ptr() as usize;
+    let shared = value_start >= buf_start
+                 && value_start + value.len() <= buf_start + buf.len();
+    if shared {
+        buf.slice_ref(value)
+    } else {
+        Bytes::copy_from_slice(value)
+    }
 }
 
 /// Cassandra short bytes
@@ -594,6 +894,11 @@ impl CBytesShort {
     pub fn into_plain(self) -> Option<Vec<u8>> {
         self.bytes
     }
+
+    /// Returns a slice of bytes without consuming `CBytesShort`.
+    pub fn as_plain(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
 }
 
 impl FromCursor for CBytesShort {
@@ -615,17 +920,27 @@ impl FromCursor for CBytesShort {
 // Use extended Rust Vec<u8> as Cassandra [bytes]
 impl IntoBytes for CBytesShort {
     fn into_cbytes(&self) -> Vec<u8> {
-        match self.bytes {
-            Some(ref b) => {
-                let mut v: Vec<u8> = vec![];
-                let l = b.len() as i16;
-                v.extend_from_slice(to_short(l).as_slice());
-                v.extend_from_slice(b.as_slice());
-                v
-            }
-            None => vec![],
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
+        v
+    }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        if let Some(ref b) = self.bytes {
+            buf.put_i16(b.len() as i16);
+            buf.put_slice(b);
         }
     }
+
+    fn serialized_len(&self) -> usize {
+        self.bytes.as_ref().map_or(0, |b| SHORT_LEN + b.len())
+    }
+}
+
+impl TryIntoBytes for CBytesShort {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.bytes.as_ref().map_or(0, Vec::len), SHORT_MAX)
+    }
 }
 
 /// Cassandra int type.
@@ -638,6 +953,16 @@ impl FromCursor for CInt {
     }
 }
 
+/// Cassandra long type.
+pub type CLong = i64;
+
+impl FromCursor for CLong {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CLong> {
+        let bytes = cursor_next_value(cursor, LONG_LEN as u64)?;
+        try_i_from_bytes(bytes.as_slice()).map_err(Into::into)
+    }
+}
+
 /// Cassandra int short type.
 pub type CIntShort = i16;
 
@@ -677,18 +1002,57 @@ impl FromCursor for CInet {
     }
 }
 
+/// Reads `len` bytes. Negative lengths casted to `u64` as well as lengths which exceed
+/// the number of remaining bytes are rejected before anything is allocated.
 pub fn cursor_next_value(cursor: &mut Cursor<&[u8]>, len: u64) -> CDRSResult<Vec<u8>> {
-    let l = len as usize;
-    let current_position = cursor.position();
-    let mut buff: Vec<u8> = Vec::with_capacity(l);
-    unsafe {
-        buff.set_len(l);
-    }
-    cursor.read_exact(&mut buff)?;
-    cursor.set_position(current_position + len);
+    let len = check_len(cursor, len as i64)?;
+    let start = cursor.position() as usize;
+    let buff = cursor.get_ref()[start..start + len].to_vec();
+    cursor.set_position((start + len) as u64);
     Ok(buff)
 }
 
+/// Number of bytes which have not been read from a cursor yet.
+pub fn remaining_len(cursor: &Cursor<&[u8]>) -> usize {
+    (cursor.get_ref().len() as u64).saturating_sub(cursor.position()) as usize
+}
+
+/// Validates a length prefix read from a cursor: it should be non-negative and
+/// should not exceed the number of remaining bytes.
+pub fn check_len(cursor: &Cursor<&[u8]>, len: i64) -> CDRSResult<usize> {
+    check_count(cursor, len, 1)
+}
+
+/// Checks that a length or a number of items fits a length prefix with a `max` value
+/// before it's serialized.
+pub fn check_max_len(length: usize, max: usize) -> CDRSResult<()> {
+    if length > max {
+        return Err(CDRSError::LengthOverflow { length, max });
+    }
+
+    Ok(())
+}
+
+/// Writes a `str` as Cassandra `[string]` without creating an intermediate `CString`.
+pub fn put_string(buf: &mut impl BufMut, string: &str) {
+    buf.put_i16(string.len() as i16);
+    buf.put_slice(string.as_bytes());
+}
+
+/// Validates a number of items read from a cursor each of which takes at least
+/// `min_item_len` bytes, so a collection is never preallocated for a bogus number.
+pub fn check_count(cursor: &Cursor<&[u8]>, count: i64, min_item_len: usize) -> CDRSResult<usize> {
+    let remaining = remaining_len(cursor);
+    let fits = (count as u64).checked_mul(min_item_len as u64)
+                             .is_some_and(|len| len <= remaining as u64);
+    if count < 0 || !fits {
+        return Err(CDRSError::InvalidLength { length: count,
+                                              remaining });
+    }
+
+    Ok(count as usize)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -735,6 +1099,52 @@ mod tests {
         assert_eq!(cstring.as_str(), "foo");
     }
 
+    #[test]
+    fn test_try_into_cbytes() {
+        let cstring = CString::new("a".repeat(SHORT_MAX));
+        assert_eq!(cstring.try_into_cbytes().unwrap(), cstring.into_cbytes());
+        let cstring = CString::new("a".repeat(SHORT_MAX + 1));
+        assert!(matches!(cstring.try_into_cbytes(),
+                         Err(CDRSError::LengthOverflow { length, max: SHORT_MAX })
+                         if length == SHORT_MAX + 1));
+
+        let list = CStringList { list: vec![CString::new("a".into()); SHORT_MAX + 1] };
+        assert!(list.check().is_err());
+        assert!(CBytesShort::new(vec![0; SHORT_MAX + 1]).check().is_err());
+        assert!(CBytes::new_empty().check().is_ok());
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let mut map = HashMap::new();
+        map.insert("key".to_string(), CBytes::new(vec![1, 2]));
+        map.insert("null".to_string(), CBytes::new_empty());
+        let cases: Vec<(usize, Vec<u8>)> =
+            vec![(CString::new("abc".into()).serialized_len(),
+                  CString::new("abc".into()).into_cbytes()),
+                 (CStringLong::new("abc".into()).serialized_len(),
+                  CStringLong::new("abc".into()).into_cbytes()),
+                 (CStringList { list: vec![CString::new("a".into()); 3] }.serialized_len(),
+                  CStringList { list: vec![CString::new("a".into()); 3] }.into_cbytes()),
+                 (CBytesMap::new(map.clone()).serialized_len(),
+                  CBytesMap::new(map).into_cbytes()),
+                 (CBytes::new_empty().serialized_len(), CBytes::new_empty().into_cbytes()),
+                 (CBytesShort::new(vec![1]).serialized_len(),
+                  CBytesShort::new(vec![1]).into_cbytes())];
+        for (len, bytes) in cases {
+            assert_eq!(len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_cstring_from_cursor_with_policy() {
+        let a = &[0, 3, 102, 0xff, 111];
+        assert!(CString::from_cursor(&mut Cursor::new(a)).is_err());
+        let cstring = CString::from_cursor_with_policy(&mut Cursor::new(a), Utf8Policy::Lossy)
+            .unwrap();
+        assert_eq!(cstring.as_str(), "f\u{fffd}o");
+    }
+
     // CStringLong
     #[test]
     fn test_cstringlong_new() {
@@ -787,6 +1197,19 @@ mod tests {
         }
     }
 
+    // CBytesMap
+    #[test]
+    fn test_cbytesmap() {
+        let a = &[0, 1, 0, 3, 102, 111, 111, 0, 0, 0, 2, 1, 2];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        let map = CBytesMap::from_cursor(&mut cursor).unwrap();
+        assert_eq!(map.into_cbytes(), a.to_vec());
+        assert_eq!(map.into_plain().get("foo"), Some(&vec![1, 2]));
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&[0, 2, 0, 3, 102, 111, 111]);
+        assert!(CBytesMap::from_cursor(&mut cursor).is_err());
+    }
+
     // CBytes
     #[test]
     fn test_cbytes_new() {
@@ -808,6 +1231,41 @@ mod tests {
         assert_eq!(cbytes.into_plain().unwrap(), vec![1, 2, 3]);
     }
 
+    #[test]
+    fn test_cbytes_from_cursor_invalid_len() {
+        let a = &[0x7f, 0xff, 0xff, 0xff, 1, 2, 3];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        match CBytes::from_cursor(&mut cursor) {
+            Err(CDRSError::InvalidLength { length, remaining }) => {
+                assert_eq!(length, i64::from(i32::MAX));
+                assert_eq!(remaining, 3);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        assert!(CBytes::from_cursor_shared(&mut cursor, &Bytes::new()).is_err());
+
+        // negative length of inet address
+        let a = &[0xff, 0xfe, 1, 2, 3, 4];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        assert!(CInet::from_cursor(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_check_count() {
+        let a = &[0, 1, 2, 3];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        cursor.set_position(1);
+        assert_eq!(check_count(&cursor, 3, 1).unwrap(), 3);
+        assert!(check_count(&cursor, 2, 2).is_err());
+        assert!(check_count(&cursor, -1, 1).is_err());
+        assert!(check_count(&cursor, i64::MAX, 2).is_err());
+        cursor.set_position(10);
+        assert_eq!(remaining_len(&cursor), 0);
+        assert!(check_len(&cursor, 1).is_err());
+    }
+
     #[test]
     fn test_cbytes_into_cbytes() {
         let bytes_vec = vec![1, 2, 3];
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::Arc;
 use time::Timespec;
 use uuid::Uuid;
 
@@ -7,39 +9,159 @@ use crate::frame::frame_result::{
     BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
 };
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+#[cfg(feature = "dse")]
+use crate::types::geo::*;
+#[cfg(feature = "dse")]
+use crate::types::date_range::*;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
-use crate::types::{ByIndex, ByName, CBytes, IntoRustByIndex, IntoRustByName};
+use crate::types::{
+    ByColumn, ByIndex, ByName, CBytes, IntoRustByIndex, IntoRustByName, IntoRustColumn,
+};
+
+/// How a column name passed to `get_by_name` is matched against names of columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnCase {
+    /// A name should be equal to a column name, which is the default for rows.
+    Exact,
+    /// A name is compared with column names ignoring case. The first matching column
+    /// is used if several names differ only in case.
+    CaseInsensitive,
+    /// A name is treated as a CQL identifier: Cassandra lower-cases unquoted identifiers,
+    /// so `Name` matches `name` column, while a double quoted one such as `"Name"`
+    /// is matched exactly without quotes.
+    Identifier,
+}
+
+impl ColumnCase {
+    /// Shows if `name` refers to a column named `column`.
+    pub fn matches(self, column: &str, name: &str) -> bool {
+        match self {
+            ColumnCase::Exact => column == name,
+            ColumnCase::CaseInsensitive => lowercase_eq(column, name),
+            ColumnCase::Identifier => {
+                if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+                    column == name[1..name.len() - 1].replace("\"\"", "\"")
+                } else {
+                    column.chars().eq(name.chars().flat_map(char::to_lowercase))
+                }
+            }
+        }
+    }
+}
+
+fn lowercase_eq(left: &str, right: &str) -> bool {
+    left.chars()
+        .flat_map(char::to_lowercase)
+        .eq(right.chars().flat_map(char::to_lowercase))
+}
 
+/// A row of a rows result. Metadata is shared by all the rows of a result, so creating
+/// or cloning a row doesn't copy it.
 #[derive(Clone, Debug)]
 pub struct Row {
-    metadata: RowsMetadata,
+    metadata: Arc<RowsMetadata>,
     row_content: Vec<CBytes>,
+    utf8_policy: Utf8Policy,
+    column_case: ColumnCase,
 }
 
 impl Row {
+    /// Creates a row from rows metadata and a content of columns.
+    pub fn new<M: Into<Arc<RowsMetadata>>>(metadata: M, row_content: Vec<CBytes>) -> Row {
+        Row {
+            metadata: metadata.into(),
+            row_content,
+            utf8_policy: Utf8Policy::Lossy,
+            column_case: ColumnCase::Exact,
+        }
+    }
+
     pub fn from_frame_body(body: BodyResResultRows) -> Vec<Row> {
+        Row::from_frame_body_with(body, Utf8Policy::Lossy, ColumnCase::Exact)
+    }
+
+    /// The same as `from_frame_body` but applies UTF-8 policy and column case
+    /// to all the rows of a result.
+    pub fn from_frame_body_with(
+        body: BodyResResultRows,
+        utf8_policy: Utf8Policy,
+        column_case: ColumnCase,
+    ) -> Vec<Row> {
+        let metadata = Arc::new(body.metadata);
         body.rows_content
-            .iter()
+            .into_iter()
             .map(|row| Row {
-                metadata: body.metadata.clone(),
-                row_content: row.clone(),
-            }).collect()
+                metadata: metadata.clone(),
+                row_content: row,
+                utf8_policy,
+                column_case,
+            })
+            .collect()
     }
 
-    fn get_col_spec_by_name(&self, name: &str) -> Option<(&ColSpec, &CBytes)> {
-        self.metadata
-            .col_specs
-            .iter()
-            .position(|spec| spec.name.as_str() == name)
-            .map(|i| {
-                let ref col_spec = self.metadata.col_specs[i];
-                let ref data = self.row_content[i];
-                (col_spec, data)
+    /// Sets how invalid UTF-8 in `ascii`, `varchar` and custom columns is handled when they
+    /// are read as `String`. It's replaced with `U+FFFD` by default.
+    pub fn with_utf8_policy(mut self, policy: Utf8Policy) -> Row {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Sets how column names are matched by `get_by_name`, see `ColumnCase`.
+    pub fn with_column_case(mut self, column_case: ColumnCase) -> Row {
+        self.column_case = column_case;
+        self
+    }
+
+    fn decode_string(&self, col_spec: &ColSpec, cbytes: &CBytes) -> Result<Option<String>> {
+        let col_type = &col_spec.col_type;
+        match col_type.id {
+            ColType::Custom | ColType::Ascii | ColType::Varchar => match cbytes.as_slice() {
+                Some(bytes) => decode_text_with_policy(bytes, self.utf8_policy)
+                    .map(Some)
+                    .map_err(Into::into),
+                None => Ok(None),
+            },
+            _ => as_rust_type!(col_type, cbytes, String)
+                .map_err(|err| err.in_column(col_spec.name.as_str(), &col_type.id, "String")),
+        }
+    }
+
+    /// Returns an iterator over column specs and corresponding values of the row.
+    pub fn columns(&self) -> impl Iterator<Item = (&ColSpec, &CBytes)> {
+        self.metadata.col_specs.iter().zip(self.row_content.iter())
+    }
+
+    /// Decodes all the columns into a map of column names to dynamically typed values,
+    /// e.g. for logging rows of a schema which isn't known at compile time. NULL cells
+    /// are `CqlValue::Null`. Text values are decoded in accordance to the UTF-8 policy
+    /// of the row.
+    pub fn into_map(self) -> Result<HashMap<String, CqlValue>> {
+        self.columns()
+            .map(|(spec, cbytes)| {
+                let value =
+                    CqlValue::decode_with_utf8_policy(cbytes, &spec.col_type, self.utf8_policy)?;
+                Ok((spec.name.as_plain(), value))
+            })
+            .collect()
+    }
+
+    fn get_col_spec_by_name(&self, name: &str) -> Result<(&ColSpec, &CBytes)> {
+        self.columns()
+            .find(|(spec, _)| self.column_case.matches(spec.name.as_str(), name))
+            .ok_or_else(|| Error::NoSuchColumn {
+                name: name.to_string(),
+                available: self
+                    .metadata
+                    .col_specs
+                    .iter()
+                    .map(|spec| spec.name.as_str().to_string())
+                    .collect(),
             })
     }
 
@@ -53,7 +175,12 @@ impl Row {
 impl ByName for Row {}
 
 into_rust_by_name!(Row, Blob);
-into_rust_by_name!(Row, String);
+impl IntoRustByName<String> for Row {
+    fn get_by_name(&self, name: &str) -> Result<Option<String>> {
+        self.get_col_spec_by_name(name)
+            .and_then(|(col_spec, cbytes)| self.decode_string(col_spec, cbytes))
+    }
+}
 into_rust_by_name!(Row, bool);
 into_rust_by_name!(Row, i64);
 into_rust_by_name!(Row, i32);
@@ -69,11 +196,26 @@ into_rust_by_name!(Row, UDT);
 into_rust_by_name!(Row, Tuple);
 into_rust_by_name!(Row, Timespec);
 into_rust_by_name!(Row, Decimal);
+into_rust_by_name!(Row, CqlValue);
+#[cfg(feature = "dse")]
+into_rust_by_name!(Row, Point);
+#[cfg(feature = "dse")]
+into_rust_by_name!(Row, LineString);
+#[cfg(feature = "dse")]
+into_rust_by_name!(Row, Polygon);
+#[cfg(feature = "dse")]
+into_rust_by_name!(Row, DateRange);
 
 impl ByIndex for Row {}
 
 into_rust_by_index!(Row, Blob);
-into_rust_by_index!(Row, String);
+impl IntoRustByIndex<String> for Row {
+    fn get_by_index(&self, index: usize) -> Result<Option<String>> {
+        self.get_col_spec_by_index(index)
+            .ok_or(column_is_empty_err(index))
+            .and_then(|(col_spec, cbytes)| self.decode_string(col_spec, cbytes))
+    }
+}
 into_rust_by_index!(Row, bool);
 into_rust_by_index!(Row, i64);
 into_rust_by_index!(Row, i32);
@@ -89,3 +231,175 @@ into_rust_by_index!(Row, UDT);
 into_rust_by_index!(Row, Tuple);
 into_rust_by_index!(Row, Timespec);
 into_rust_by_index!(Row, Decimal);
+into_rust_by_index!(Row, CqlValue);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Row, Point);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Row, LineString);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Row, Polygon);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Row, DateRange);
+
+impl ByColumn for BodyResResultRows {}
+
+into_rust_column!(BodyResResultRows, Blob);
+into_rust_column!(BodyResResultRows, String);
+into_rust_column!(BodyResResultRows, bool);
+into_rust_column!(BodyResResultRows, i64);
+into_rust_column!(BodyResResultRows, i32);
+into_rust_column!(BodyResResultRows, i16);
+into_rust_column!(BodyResResultRows, i8);
+into_rust_column!(BodyResResultRows, f64);
+into_rust_column!(BodyResResultRows, f32);
+into_rust_column!(BodyResResultRows, IpAddr);
+into_rust_column!(BodyResResultRows, Uuid);
+into_rust_column!(BodyResResultRows, List);
+into_rust_column!(BodyResResultRows, Map);
+into_rust_column!(BodyResResultRows, UDT);
+into_rust_column!(BodyResResultRows, Tuple);
+into_rust_column!(BodyResResultRows, Timespec);
+into_rust_column!(BodyResResultRows, Decimal);
+into_rust_column!(BodyResResultRows, CqlValue);
+#[cfg(feature = "dse")]
+into_rust_column!(BodyResResultRows, Point);
+#[cfg(feature = "dse")]
+into_rust_column!(BodyResResultRows, LineString);
+#[cfg(feature = "dse")]
+into_rust_column!(BodyResResultRows, Polygon);
+#[cfg(feature = "dse")]
+into_rust_column!(BodyResResultRows, DateRange);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_body() -> BodyResResultRows {
+        BodyResResultRows {
+            metadata: RowsMetadata::for_test(vec![
+                ("id", ColTypeOption::simple(ColType::Bigint)),
+                ("name", ColTypeOption::simple(ColType::Varchar)),
+            ]),
+            rows_count: 2,
+            rows_content: vec![
+                vec![CBytes::new(vec![0, 0, 0, 0, 0, 0, 0, 1]), CBytes::new(b"John".to_vec())],
+                vec![CBytes::new(vec![0, 0, 0, 0, 0, 0, 0, 2]), CBytes::new_empty()],
+            ],
+        }
+    }
+
+    #[test]
+    fn column() {
+        let rows = rows_body();
+
+        assert_eq!(rows.column::<i64>("id").unwrap(), vec![Some(1), Some(2)]);
+        assert_eq!(
+            rows.column::<String>("name").unwrap(),
+            vec![Some("John".to_string()), None]
+        );
+        assert!(rows.column::<i64>("name").is_err());
+        assert!(rows.column::<i64>("unknown").is_err());
+    }
+
+    #[test]
+    fn row_columns() {
+        let rows = Row::from_frame_body(rows_body());
+        let names = rows[0]
+            .columns()
+            .map(|(col_spec, _)| col_spec.name.as_plain())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["id", "name"]);
+        assert!(Arc::ptr_eq(&rows[0].metadata, &rows[1].metadata));
+    }
+
+    #[test]
+    fn utf8_policy() {
+        let mut body = rows_body();
+        body.rows_content[0][1] = CBytes::new(b"J\xffhn".to_vec());
+        let row = Row::from_frame_body(body).remove(0);
+
+        let name: Option<String> = row.get_by_name("name").unwrap();
+        assert_eq!(name.unwrap(), "J\u{fffd}hn");
+
+        let row = row.with_utf8_policy(Utf8Policy::Strict);
+        assert!(IntoRustByName::<String>::get_by_name(&row, "name").is_err());
+        assert!(IntoRustByIndex::<String>::get_by_index(&row, 1).is_err());
+        assert!(IntoRustByIndex::<String>::get_by_index(&row, 0).is_err());
+        assert!(row.into_map().is_err());
+    }
+
+    #[test]
+    fn into_map() {
+        let mut rows = Row::from_frame_body(rows_body());
+        let map = rows.remove(1).into_map().unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["id"], CqlValue::Bigint(2));
+        assert_eq!(map["name"], CqlValue::Null);
+
+        let map = rows.remove(0).into_map().unwrap();
+        assert_eq!(map["name"], CqlValue::Varchar("John".into()));
+    }
+
+    #[test]
+    fn optional_columns() {
+        let row = Row::from_frame_body(rows_body()).remove(1);
+        let name: Option<String> = row.get_r_by_name("name").unwrap();
+        assert_eq!(name, None);
+        let id: Option<i64> = row.get_r_by_index(0).unwrap();
+        assert_eq!(id, Some(2));
+        assert!(IntoRustByName::<String>::get_r_by_name(&row, "name").is_err());
+        assert!(IntoRustByName::<Option<String>>::get_r_by_name(&row, "unknown").is_err());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        match rows_body().column::<i64>("name") {
+            Err(Error::ColumnTypeMismatch { column, col_type: ColType::Varchar, rust_type }) => {
+                assert_eq!(column, "name");
+                assert_eq!(rust_type, "i64");
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let row = Row::from_frame_body(rows_body()).remove(0);
+        assert!(matches!(IntoRustByName::<String>::get_by_name(&row, "id"),
+                         Err(Error::ColumnTypeMismatch { rust_type: "String", .. })));
+        assert!(matches!(IntoRustByIndex::<bool>::get_by_index(&row, 0),
+                         Err(Error::ColumnTypeMismatch { rust_type: "bool", .. })));
+    }
+
+    #[test]
+    fn column_case() {
+        assert!(ColumnCase::Exact.matches("name", "name"));
+        assert!(!ColumnCase::Exact.matches("name", "Name"));
+        assert!(ColumnCase::CaseInsensitive.matches("Name", "nAME"));
+        assert!(ColumnCase::Identifier.matches("name", "Name"));
+        assert!(!ColumnCase::Identifier.matches("Name", "Name"));
+        assert!(ColumnCase::Identifier.matches("Name", "\"Name\""));
+        assert!(!ColumnCase::Identifier.matches("name", "\"Name\""));
+        assert!(ColumnCase::Identifier.matches("a\"b", "\"a\"\"b\""));
+
+        let row = Row::from_frame_body(rows_body()).remove(0);
+        match IntoRustByName::<i64>::get_by_name(&row, "ID") {
+            Err(Error::NoSuchColumn { name, available }) => {
+                assert_eq!(name, "ID");
+                assert_eq!(available, vec!["id", "name"]);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let row = row.with_column_case(ColumnCase::Identifier);
+        let id: Option<i64> = row.get_by_name("ID").unwrap();
+        assert_eq!(id, Some(1));
+        assert!(IntoRustByName::<String>::get_by_name(&row, "\"NAME\"").is_err());
+
+        let rows =
+            Row::from_frame_body_with(rows_body(), Utf8Policy::Strict, ColumnCase::Identifier);
+        let ids = rows
+            .iter()
+            .map(|row| row.get_r_by_name("ID").unwrap())
+            .collect::<Vec<i64>>();
+        assert_eq!(ids, vec![1, 2]);
+    }
+}
@@ -5,12 +5,17 @@ use uuid::Uuid;
 use crate::error::{column_is_empty_err, Error, Result};
 use crate::frame::frame_result::{CTuple, ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+#[cfg(feature = "dse")]
+use crate::types::geo::*;
+#[cfg(feature = "dse")]
+use crate::types::date_range::*;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::udt::UDT;
-use crate::types::{ByIndex, CBytes, IntoRustByIndex};
+use crate::types::{AsRust, AsRustType, ByIndex, CBytes, IntoRustByIndex};
 
 use std::hash::{Hash, Hasher};
 
@@ -56,6 +61,16 @@ impl Tuple {
 
         Tuple { data: d }
     }
+
+    /// Returns number of elements in the tuple.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns `true` if the tuple does not contain any elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 }
 
 impl ByIndex for Tuple {}
@@ -77,3 +92,102 @@ into_rust_by_index!(Tuple, UDT);
 into_rust_by_index!(Tuple, Tuple);
 into_rust_by_index!(Tuple, Timespec);
 into_rust_by_index!(Tuple, Decimal);
+into_rust_by_index!(Tuple, CqlValue);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Tuple, Point);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Tuple, LineString);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Tuple, Polygon);
+#[cfg(feature = "dse")]
+into_rust_by_index!(Tuple, DateRange);
+
+impl AsRust for Tuple {}
+
+tuple_as_rust!(1; A: 0);
+tuple_as_rust!(2; A: 0, B: 1);
+tuple_as_rust!(3; A: 0, B: 1, C: 2);
+tuple_as_rust!(4; A: 0, B: 1, C: 2, D: 3);
+tuple_as_rust!(5; A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_as_rust!(6; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_as_rust!(7; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_as_rust!(8; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+tuple_as_rust!(9; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+tuple_as_rust!(10; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+tuple_as_rust!(11; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+tuple_as_rust!(12; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
+tuple_as_rust!(13; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11,
+               M: 12);
+tuple_as_rust!(14; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11,
+               M: 12, N: 13);
+tuple_as_rust!(15; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11,
+               M: 12, N: 13, O: 14);
+tuple_as_rust!(16; A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11,
+               M: 12, N: 13, O: 14, P: 15);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{to_int, to_short};
+
+    fn tuple_metadata(types: Vec<ColType>) -> CTuple {
+        CTuple { types: types.into_iter()
+                             .map(|id| ColTypeOption { id, value: None })
+                             .collect(), }
+    }
+
+    #[test]
+    fn tuple_as_rust_tuple() {
+        let metadata = tuple_metadata(vec![ColType::Int, ColType::Varchar, ColType::Smallint]);
+        let data = vec![CBytes::new(to_int(1)),
+                        CBytes::new(b"foo".to_vec()),
+                        CBytes::new(to_short(2))];
+        let tuple = Tuple::new(data, &metadata);
+
+        let (a, b, c): (i32, String, i16) = tuple.as_r_rust().unwrap();
+        assert_eq!(a, 1);
+        assert_eq!(b, "foo");
+        assert_eq!(c, 2);
+    }
+
+    #[test]
+    fn tuple_as_rust_tuple_wrong_arity() {
+        let metadata = tuple_metadata(vec![ColType::Int, ColType::Varchar]);
+        let data = vec![CBytes::new(to_int(1)), CBytes::new(b"foo".to_vec())];
+        let tuple = Tuple::new(data, &metadata);
+
+        let converted: Result<Option<(i32,)>> = tuple.as_rust();
+        assert!(converted.is_err());
+    }
+
+    #[test]
+    fn tuple_as_rust_tuple_wrong_element_type() {
+        let metadata = tuple_metadata(vec![ColType::Int, ColType::Varchar]);
+        let data = vec![CBytes::new(to_int(1)), CBytes::new(b"foo".to_vec())];
+        let tuple = Tuple::new(data, &metadata);
+
+        let converted: Result<Option<(i32, i64)>> = tuple.as_rust();
+        assert!(converted.is_err());
+    }
+
+    #[test]
+    fn tuple_as_rust_tuple_null_element() {
+        let metadata = tuple_metadata(vec![ColType::Int, ColType::Varchar]);
+        let data = vec![CBytes::new(to_int(1)), CBytes::new_empty()];
+        let tuple = Tuple::new(data, &metadata);
+
+        let converted: Result<Option<(i32, String)>> = tuple.as_rust();
+        assert!(converted.is_err());
+    }
+
+    #[test]
+    fn tuple_as_rust_tuple_optional_element() {
+        let metadata = tuple_metadata(vec![ColType::Int, ColType::Varchar]);
+        let data = vec![CBytes::new(to_int(1)), CBytes::new_empty()];
+        let tuple = Tuple::new(data, &metadata);
+
+        let (a, b): (i32, Option<String>) = tuple.as_r_rust().unwrap();
+        assert_eq!(a, 1);
+        assert_eq!(b, None);
+    }
+}
@@ -6,8 +6,13 @@ use uuid::Uuid;
 use crate::error::{column_is_empty_err, Error, Result};
 use crate::frame::frame_result::{CUdt, ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+#[cfg(feature = "dse")]
+use crate::types::geo::*;
+#[cfg(feature = "dse")]
+use crate::types::date_range::*;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
@@ -55,3 +60,12 @@ into_rust_by_name!(UDT, UDT);
 into_rust_by_name!(UDT, Tuple);
 into_rust_by_name!(UDT, Timespec);
 into_rust_by_name!(UDT, Decimal);
+into_rust_by_name!(UDT, CqlValue);
+#[cfg(feature = "dse")]
+into_rust_by_name!(UDT, Point);
+#[cfg(feature = "dse")]
+into_rust_by_name!(UDT, LineString);
+#[cfg(feature = "dse")]
+into_rust_by_name!(UDT, Polygon);
+#[cfg(feature = "dse")]
+into_rust_by_name!(UDT, DateRange);
@@ -5,7 +5,8 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::net::IpAddr;
 
-use crate::frame::IntoBytes;
+use bytes::BufMut;
+use crate::frame::{FromCursor, IntoBytes, TryIntoBytes};
 use time::Timespec;
 use uuid::Uuid;
 
@@ -29,12 +30,25 @@ impl IntoBytes for ValueType {
             ValueType::NotSet => i_to_n_bytes(-2, INT_LEN),
         }
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        match *self {
+            ValueType::Normal(n) => buf.put_i32(n),
+            ValueType::Null => buf.put_i32(-1),
+            ValueType::NotSet => buf.put_i32(-2),
+        }
+    }
+
+    fn serialized_len(&self) -> usize {
+        INT_LEN
+    }
 }
 
 /// Cassandra value which could be an array of bytes, null and non-set values.
+/// The body is reference counted, so cloning a value doesn't copy it.
 #[derive(Debug, Clone)]
 pub struct Value {
-    pub body: Vec<u8>,
+    pub body: bytes::Bytes,
     pub value_type: ValueType,
 }
 
@@ -47,7 +61,7 @@ impl Value {
         let bytes = v.into().0;
         let l = bytes.len() as i32;
         Value {
-            body: bytes,
+            body: bytes.into(),
             value_type: ValueType::Normal(l),
         }
     }
@@ -55,7 +69,7 @@ impl Value {
     /// The factory method which creates null Cassandra value.
     pub fn new_null() -> Value {
         Value {
-            body: vec![],
+            body: bytes::Bytes::new(),
             value_type: ValueType::Null,
         }
     }
@@ -63,7 +77,7 @@ impl Value {
     /// The factory method which creates non-set Cassandra value.
     pub fn new_not_set() -> Value {
         Value {
-            body: vec![],
+            body: bytes::Bytes::new(),
             value_type: ValueType::NotSet,
         }
     }
@@ -71,11 +85,61 @@ impl Value {
 
 impl IntoBytes for Value {
     fn into_cbytes(&self) -> Vec<u8> {
-        let mut v = Vec::with_capacity(INT_LEN + self.body.len());
-        v.extend_from_slice(self.value_type.into_cbytes().as_slice());
-        v.extend_from_slice(self.body.as_slice());
+        let mut v = Vec::with_capacity(self.serialized_len());
+        self.serialize_into(&mut v);
         v
     }
+
+    fn serialize_into(&self, buf: &mut impl BufMut) {
+        self.value_type.serialize_into(buf);
+        buf.put_slice(&self.body);
+    }
+
+    fn serialized_len(&self) -> usize {
+        INT_LEN + self.body.len()
+    }
+}
+
+impl TryIntoBytes for Value {
+    fn check(&self) -> CDRSResult<()> {
+        check_max_len(self.body.len(), INT_MAX)?;
+        if cfg!(feature = "v3") {
+            if let ValueType::NotSet = self.value_type {
+                let msg = "Not-set values are supported since protocol v4";
+                return Err(CDRSError::InvalidRequest(msg.into()));
+            }
+        }
+        let consistent = match self.value_type {
+            ValueType::Normal(len) => len as usize == self.body.len(),
+            ValueType::Null | ValueType::NotSet => self.body.is_empty(),
+        };
+        if !consistent {
+            return Err(CDRSError::InvalidRequest(format!(
+                "{:?} value has a body of {} bytes",
+                self.value_type,
+                self.body.len()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromCursor for Value {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<Value> {
+        let len = CInt::from_cursor(cursor)?;
+        match len {
+            -1 => Ok(Value::new_null()),
+            -2 => Ok(Value::new_not_set()),
+            len if len >= 0 => cursor_next_value(cursor, len as u64).map(|body| Value {
+                body: body.into(),
+                value_type: ValueType::Normal(len),
+            }),
+            len => Err(CDRSError::InvalidLength { length: i64::from(len),
+                                                  remaining: cursor.get_ref().len()
+                                                             - cursor.position() as usize }),
+        }
+    }
 }
 
 impl<T: Into<Bytes>> From<T> for Value {
@@ -93,6 +157,29 @@ impl<T: Into<Bytes>> From<Option<T>> for Value {
     }
 }
 
+/// Value of a bound variable which distinguishes writing NULL, i.e. a tombstone,
+/// from leaving a column untouched. `Option<T>` converts `None` into NULL as well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bind<T> {
+    /// A value which is written to a column.
+    Value(T),
+    /// NULL which deletes a value of a column.
+    Null,
+    /// Not-set value which leaves a column unchanged. Supported since protocol v4,
+    /// so `check` of such a value fails with `v3` feature.
+    Unset,
+}
+
+impl<T: Into<Bytes>> From<Bind<T>> for Value {
+    fn from(bind: Bind<T>) -> Value {
+        match bind {
+            Bind::Value(v) => Value::new_normal(v),
+            Bind::Null => Value::new_null(),
+            Bind::Unset => Value::new_not_set(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Bytes(Vec<u8>);
 
@@ -274,7 +361,7 @@ mod tests {
         let plain_value = "hello";
         let len = plain_value.len() as i32;
         let normal_value = Value::new_normal(plain_value);
-        assert_eq!(normal_value.body, b"hello");
+        assert_eq!(normal_value.body.as_ref(), b"hello");
         match normal_value.value_type {
             ValueType::Normal(l) => assert_eq!(l, len),
             _ => unreachable!(),
@@ -299,21 +386,32 @@ mod tests {
     #[test]
     fn test_new_null_value() {
         let null_value = Value::new_null();
-        assert_eq!(null_value.body, vec![]);
+        assert!(null_value.body.is_empty());
         match null_value.value_type {
             ValueType::Null => assert!(true),
             _ => unreachable!(),
         }
     }
 
+    #[test]
+    fn test_bind_into_value() {
+        let value: Value = Bind::Value(1i32).into();
+        assert_eq!(value.into_cbytes(), vec![0, 0, 0, 4, 0, 0, 0, 1]);
+        let value: Value = Bind::<i32>::Null.into();
+        assert_eq!(value.into_cbytes(), vec![255, 255, 255, 255]);
+        let value: Value = Bind::<i32>::Unset.into();
+        assert_eq!(value.into_cbytes(), vec![255, 255, 255, 254]);
+    }
+
     #[test]
     fn test_new_not_set_value() {
         let not_set_value = Value::new_not_set();
-        assert_eq!(not_set_value.body, vec![]);
+        assert!(not_set_value.body.is_empty());
         match not_set_value.value_type {
             ValueType::NotSet => assert!(true),
             _ => unreachable!(),
         }
+        assert_eq!(not_set_value.check().is_err(), cfg!(feature = "v3"));
     }
 
     #[test]
@@ -322,4 +420,15 @@ mod tests {
         assert_eq!(value.into_cbytes(), vec![0, 0, 0, 1, 1]);
     }
 
+    #[test]
+    fn test_value_from_cursor_invalid_len() {
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&[0xff, 0xff, 0xff, 0xfd, 1]);
+        match Value::from_cursor(&mut cursor) {
+            Err(CDRSError::InvalidLength { length, remaining }) => {
+                assert_eq!(length, -3);
+                assert_eq!(remaining, 1);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
 }
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::types::to_varint;
+
+/// Special type that represents Cassandra varint type. Values are kept as
+/// big-endian two's complement bytes, so integers of any size survive a round trip.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Varint(Vec<u8>);
+
+impl Varint {
+    /// Creates a varint from its big-endian two's complement representation.
+    /// Redundant leading sign bytes are stripped. Returns `None` if `bytes` is empty.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+
+        let mut start = 0;
+        while start + 1 < bytes.len() {
+            let (b, next) = (bytes[start], bytes[start + 1]);
+            if (b == 0x00 && next & 0x80 == 0) || (b == 0xFF && next & 0x80 != 0) {
+                start += 1;
+            } else {
+                break;
+            }
+        }
+
+        Some(Varint(bytes[start..].to_vec()))
+    }
+
+    /// Returns a reference to the underlying big-endian two's complement bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    /// Returns underlying vector of bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Returns the value as `i64` if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.0.len() > 8 {
+            return None;
+        }
+
+        let init = if self.is_negative() { -1 } else { 0 };
+        Some(self.0.iter().fold(init, |acc, b| (acc << 8) | i64::from(*b)))
+    }
+
+    fn is_negative(&self) -> bool {
+        self.0[0] & 0x80 != 0
+    }
+}
+
+impl From<i64> for Varint {
+    fn from(int: i64) -> Self {
+        Varint(to_varint(int))
+    }
+}
+
+impl fmt::Display for Varint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(int) = self.to_i64() {
+            return write!(f, "{}", int);
+        }
+
+        // magnitude as big-endian unsigned bytes
+        let mut magnitude = self.0.clone();
+        if self.is_negative() {
+            let mut carry = true;
+            for b in magnitude.iter_mut().rev() {
+                *b = !*b;
+                if carry {
+                    let (sum, overflow) = b.overflowing_add(1);
+                    *b = sum;
+                    carry = overflow;
+                }
+            }
+        }
+
+        let mut digits = Vec::new();
+        while magnitude.iter().any(|b| *b != 0) {
+            let mut rem = 0u32;
+            for b in magnitude.iter_mut() {
+                let cur = (rem << 8) | u32::from(*b);
+                *b = (cur / 10) as u8;
+                rem = cur % 10;
+            }
+            digits.push(b'0' + rem as u8);
+        }
+
+        if self.is_negative() {
+            digits.push(b'-');
+        }
+        digits.reverse();
+        f.write_str(&String::from_utf8_lossy(&digits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_strips_sign_bytes() {
+        assert_eq!(Varint::from_bytes(&[0x00, 0x00, 0x7F]).unwrap().as_bytes(), &[0x7F]);
+        assert_eq!(Varint::from_bytes(&[0xFF, 0xFF, 0x80]).unwrap().as_bytes(), &[0x80]);
+        assert_eq!(Varint::from_bytes(&[0x00, 0x80]).unwrap().as_bytes(), &[0x00, 0x80]);
+        assert!(Varint::from_bytes(&[]).is_none());
+    }
+
+    #[test]
+    fn to_i64_test() {
+        assert_eq!(Varint::from(-300).to_i64(), Some(-300));
+        assert_eq!(Varint::from(i64::MAX).to_i64(), Some(i64::MAX));
+        assert_eq!(Varint::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(Varint::from_bytes(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap().to_i64(), None);
+    }
+
+    #[test]
+    fn display_big_values() {
+        let big = Varint::from_bytes(&[0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(big.to_string(), "18446744073709551616");
+        let negative = Varint::from_bytes(&[0xFF, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(negative.to_string(), "-18446744073709551616");
+        assert_eq!(Varint::from(-300).to_string(), "-300");
+    }
+}